@@ -1,10 +1,12 @@
 use ansi_term::Colour;
 use compiler::compiler_interface;
-use compiler::compiler_interface::{Config, VCP};
+use compiler::compiler_interface::{Config, OptimizationLevel, VCP};
 use program_structure::error_definition::Report;
-use program_structure::error_code::ReportCode;
 use program_structure::file_definition::FileLibrary;
+use program_structure::output_directory::FileSystem;
+use program_structure::output_error::OutputError;
 use crate::VERSION;
+use circom::wat_to_wasm;
 
 
 pub struct CompilerConfig {
@@ -19,8 +21,11 @@ pub struct CompilerConfig {
     pub wat_flag: bool,
     pub wasm_flag: bool,
     pub c_flag: bool,
+    pub c_portable_flag: bool,
     pub debug_output: bool,
+    pub verify_ir: bool,
     pub produce_input_log: bool,
+    pub witness_optimization: OptimizationLevel,
     pub vcp: VCP,
 }
 
@@ -30,12 +35,42 @@ pub fn compile(config: CompilerConfig) -> Result<(), ()> {
     if config.c_flag || config.wat_flag || config.wasm_flag{
         let circuit = compiler_interface::run_compiler(
             config.vcp,
-            Config { debug_output: config.debug_output, produce_input_log: config.produce_input_log, wat_flag: config.wat_flag },
-            VERSION
-        )?;
+            Config {
+                debug_output: config.debug_output,
+                verify_ir: config.verify_ir,
+                produce_input_log: config.produce_input_log,
+                wat_flag: config.wat_flag,
+                c_portable: config.c_portable_flag,
+                optimization: config.witness_optimization,
+            },
+            VERSION,
+            &mut FileSystem,
+        ).map_err(|reports| Report::print_reports(&reports, &FileLibrary::new()))?;
+
+        let saved = circuit.saved_var_slots();
+        if saved > 0 {
+            let largest = circuit.stack_allocations.iter().max_by_key(|allocation| allocation.declared).unwrap();
+            println!(
+                "{} {} (largest stack: {} slots in {}, {} before)",
+                Colour::Green.paint("Var stack slots saved:"),
+                saved,
+                largest.allocated,
+                largest.header,
+                largest.declared
+            );
+        }
+        let shared = circuit.shared_instances();
+        if shared > 0 {
+            println!(
+                "{} {} (in {} shared templates)",
+                Colour::Green.paint("Template instances sharing code:"),
+                shared,
+                circuit.shared_templates.len()
+            );
+        }
     
         if config.c_flag {
-            compiler_interface::write_c(&circuit, &config.c_folder, &config.c_run_name, &config.c_file, &config.dat_file)?;
+            compiler_interface::write_c(&circuit, &mut FileSystem, &config.c_folder, &config.c_run_name, &config.c_file, &config.dat_file).map_err(report_output_error)?;
             println!(
                 "{} {} and {}",
                 Colour::Green.paint("Written successfully:"),
@@ -52,19 +87,19 @@ pub fn compile(config: CompilerConfig) -> Result<(), ()> {
                 "calcwit.cpp".to_string(),
                 "fr.hpp".to_string(),
                 "fr.cpp".to_string(),
-                "fr.asm".to_string(),
+                if circuit.c_producer.get_portable_flag() { "fr_portable.cpp" } else { "fr.asm" },
                 "Makefile".to_string()
             );
         }
     
         match (config.wat_flag, config.wasm_flag) {
             (true, true) => {
-                compiler_interface::write_wasm(&circuit, &config.js_folder, &config.wasm_name, &config.wat_file)?;
+                compiler_interface::write_wasm(&circuit, &mut FileSystem, &config.js_folder, &config.wasm_name, &config.wat_file).map_err(report_output_error)?;
                 println!("{} {}", Colour::Green.paint("Written successfully:"), config.wat_file);
                 let result = wat_to_wasm(&config.wat_file, &config.wasm_file);
                 match result {
                     Result::Err(report) => {
-                        Report::print_reports(&[report], &FileLibrary::new());
+                        Report::print_reports(&[*report], &FileLibrary::new());
                         return Err(());
                     }
                     Result::Ok(()) => {
@@ -73,12 +108,12 @@ pub fn compile(config: CompilerConfig) -> Result<(), ()> {
                 }
             }
             (false, true) => {
-                compiler_interface::write_wasm(&circuit,  &config.js_folder, &config.wasm_name, &config.wat_file)?;
+                compiler_interface::write_wasm(&circuit, &mut FileSystem, &config.js_folder, &config.wasm_name, &config.wat_file).map_err(report_output_error)?;
                 let result = wat_to_wasm(&config.wat_file, &config.wasm_file);
                 std::fs::remove_file(&config.wat_file).unwrap();
                 match result {
                     Result::Err(report) => {
-                        Report::print_reports(&[report], &FileLibrary::new());
+                        Report::print_reports(&[*report], &FileLibrary::new());
                         return Err(());
                     }
                     Result::Ok(()) => {
@@ -87,7 +122,7 @@ pub fn compile(config: CompilerConfig) -> Result<(), ()> {
                 }
             }
             (true, false) => {
-                compiler_interface::write_wasm(&circuit,  &config.js_folder, &config.wasm_name, &config.wat_file)?;
+                compiler_interface::write_wasm(&circuit, &mut FileSystem, &config.js_folder, &config.wasm_name, &config.wat_file).map_err(report_output_error)?;
                 println!("{} {}", Colour::Green.paint("Written successfully:"), config.wat_file);
             }
             (false, false) => {}
@@ -98,48 +133,6 @@ pub fn compile(config: CompilerConfig) -> Result<(), ()> {
     Ok(())
 }
 
-
-fn wat_to_wasm(wat_file: &str, wasm_file: &str) -> Result<(), Report> {
-    use std::fs::read_to_string;
-    use std::fs::File;
-    use std::io::BufWriter;
-    use std::io::Write;
-    use wast::Wat;
-    use wast::parser::{self, ParseBuffer};
-
-    let wat_contents = read_to_string(wat_file).unwrap();
-    let buf = ParseBuffer::new(&wat_contents).unwrap();
-    let result_wasm_contents = parser::parse::<Wat>(&buf);
-    match result_wasm_contents {
-        Result::Err(error) => {
-            Result::Err(Report::error(
-                format!("Error translating the circuit from wat to wasm.\n\nException encountered when parsing WAT: {}", error),
-                ReportCode::ErrorWat2Wasm,
-            ))
-        }
-        Result::Ok(mut wat) => {
-            let wasm_contents = wat.module.encode();
-            match wasm_contents {
-                Result::Err(error) => {
-                    Result::Err(Report::error(
-                        format!("Error translating the circuit from wat to wasm.\n\nException encountered when encoding WASM: {}", error),
-                        ReportCode::ErrorWat2Wasm,
-                    ))
-                }
-                Result::Ok(wasm_contents) => {
-                    let file = File::create(wasm_file).unwrap();
-                    let mut writer = BufWriter::new(file);
-                    writer.write_all(&wasm_contents).map_err(|_err| Report::error(
-                        format!("Error writing the circuit. Exception generated: {}", _err),
-                        ReportCode::ErrorWat2Wasm,
-                    ))?;
-                    writer.flush().map_err(|_err| Report::error(
-                        format!("Error writing the circuit. Exception generated: {}", _err),
-                        ReportCode::ErrorWat2Wasm,
-                    ))?;
-                    Ok(())
-                }
-            }
-        }
-    }
+fn report_output_error(err: OutputError) {
+    Report::print_reports(&[err.into_report()], &FileLibrary::new());
 }
@@ -0,0 +1,522 @@
+use compiler::compiler_interface::{self, Config, OptimizationLevel, VCP};
+use constraint_generation::{build_circuit_with_reports, BuildConfig, BuildOutput, ConstraintSystem};
+use constraint_writers::debug_writer::DebugWriter;
+use constraint_writers::log_writer::Log;
+use program_structure::constants::parse_prime;
+use program_structure::error_code::ReportCode;
+use program_structure::error_definition::{Report, ReportCollection};
+use program_structure::file_definition::FileLibrary;
+use program_structure::output_directory::{MemoryDirectory, OutputDirectory};
+use program_structure::output_error::OutputError;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Simplification {
+    // --O0
+    None,
+    // --O1
+    Reduced,
+    // --O2 with a maximum number of rounds (--O2round)
+    Full(usize),
+}
+
+// Options of the compilation, equivalent to the ones of the command line
+#[derive(Clone)]
+pub struct Compiler {
+    main: Option<PathBuf>,
+    libraries: Vec<PathBuf>,
+    prime: String,
+    simplification: Simplification,
+    verbose: bool,
+    inspect: bool,
+    old_heuristics: bool,
+    // In megabytes, as --memory-budget
+    memory_budget: Option<usize>,
+    // Signals the constraint list can explain, see ConstraintList::explain
+    explain: Vec<String>,
+    r1cs: bool,
+    sym: bool,
+    json: bool,
+    wasm: bool,
+    wat: bool,
+    c: bool,
+    c_portable: bool,
+    witness_optimization: OptimizationLevel,
+    verify_ir: bool,
+}
+
+impl Default for Compiler {
+    fn default() -> Self {
+        Compiler {
+            main: None,
+            libraries: Vec::new(),
+            prime: "bn128".to_string(),
+            simplification: Simplification::Full(usize::MAX),
+            verbose: false,
+            inspect: false,
+            old_heuristics: false,
+            memory_budget: None,
+            explain: Vec::new(),
+            r1cs: false,
+            sym: false,
+            json: false,
+            wasm: false,
+            wat: false,
+            c: false,
+            c_portable: false,
+            witness_optimization: OptimizationLevel::default(),
+            verify_ir: false,
+        }
+    }
+}
+
+// A file produced by the compilation. The path is relative to the output
+// folder, for instance circuit_js/circuit.wasm
+pub struct Artefact {
+    pub path: PathBuf,
+    pub contents: Vec<u8>,
+}
+
+// Nothing is printed during the compilation, the information the command line
+// shows is returned here instead
+pub struct Compilation {
+    pub file_library: FileLibrary,
+    pub warnings: ReportCollection,
+    pub template_instances: usize,
+    // Statistics of the constraints, only when the r1cs file is produced
+    pub log: Option<Log>,
+    pub constraints: ConstraintSystem,
+    pub vcp: VCP,
+    pub artefacts: Vec<Artefact>,
+}
+
+impl Compilation {
+    pub fn get_artefact(&self, path: &str) -> Option<&Artefact> {
+        self.artefacts.iter().find(|artefact| artefact.path == Path::new(path))
+    }
+}
+
+// Errors found during the compilation together with the warnings produced
+// before them. The file library allows to print them with Report::print_reports.
+pub struct CompilationError {
+    pub file_library: FileLibrary,
+    pub reports: ReportCollection,
+}
+
+impl CompilationError {
+    fn new(file_library: FileLibrary, mut warnings: ReportCollection, mut errors: ReportCollection) -> CompilationError {
+        warnings.append(&mut errors);
+        CompilationError { file_library, reports: warnings }
+    }
+
+    pub fn print_reports(&self) {
+        Report::print_reports(&self.reports, &self.file_library);
+    }
+}
+
+impl Compiler {
+    pub fn new() -> Compiler {
+        Compiler::default()
+    }
+
+    pub fn main<P: Into<PathBuf>>(mut self, main: P) -> Compiler {
+        self.main = Some(main.into());
+        self
+    }
+    pub fn library<P: Into<PathBuf>>(mut self, library: P) -> Compiler {
+        self.libraries.push(library.into());
+        self
+    }
+    pub fn prime(mut self, prime: &str) -> Compiler {
+        self.prime = prime.to_string();
+        self
+    }
+    pub fn simplification(mut self, simplification: Simplification) -> Compiler {
+        self.simplification = simplification;
+        self
+    }
+    pub fn verbose(mut self, verbose: bool) -> Compiler {
+        self.verbose = verbose;
+        self
+    }
+    pub fn inspect(mut self, inspect: bool) -> Compiler {
+        self.inspect = inspect;
+        self
+    }
+    pub fn old_heuristics(mut self, old_heuristics: bool) -> Compiler {
+        self.old_heuristics = old_heuristics;
+        self
+    }
+    pub fn memory_budget(mut self, memory_budget: Option<usize>) -> Compiler {
+        self.memory_budget = memory_budget;
+        self
+    }
+    pub fn explain(mut self, signal: &str) -> Compiler {
+        self.explain.push(signal.to_string());
+        self
+    }
+    pub fn r1cs(mut self, r1cs: bool) -> Compiler {
+        self.r1cs = r1cs;
+        self
+    }
+    pub fn sym(mut self, sym: bool) -> Compiler {
+        self.sym = sym;
+        self
+    }
+    pub fn json(mut self, json: bool) -> Compiler {
+        self.json = json;
+        self
+    }
+    pub fn wasm(mut self, wasm: bool) -> Compiler {
+        self.wasm = wasm;
+        self
+    }
+    pub fn wat(mut self, wat: bool) -> Compiler {
+        self.wat = wat;
+        self
+    }
+    pub fn witness_optimization(mut self, level: OptimizationLevel) -> Compiler {
+        self.witness_optimization = level;
+        self
+    }
+    // Checks the code of the witness calculator after every optimization, as
+    // --verify_ir
+    pub fn verify_ir(mut self, verify_ir: bool) -> Compiler {
+        self.verify_ir = verify_ir;
+        self
+    }
+    pub fn c(mut self, c: bool) -> Compiler {
+        self.c = c;
+        self
+    }
+    // The C++ output uses a field implementation in plain C++ instead of
+    // x86-64 assembly
+    pub fn c_portable(mut self, portable: bool) -> Compiler {
+        self.c_portable = portable;
+        self
+    }
+
+    pub fn run(&self) -> Result<Compilation, CompilationError> {
+        let main = match &self.main {
+            Some(main) => main.clone(),
+            None => return Err(option_error("No main file was given to the compiler".to_string())),
+        };
+        if let Err(reason) = parse_prime(&self.prime) {
+            return Err(option_error(format!("The prime {} is not supported: {}", self.prime, reason)));
+        }
+        let (mut program_archive, mut warnings) = parser::run_parser(
+            main.to_string_lossy().to_string(),
+            VERSION,
+            self.libraries.clone(),
+            None,
+        )
+        .map_err(|(file_library, reports)| CompilationError::new(file_library, vec![], reports))?;
+        match type_analysis::check_types::check_types(&mut program_archive) {
+            Err(errors) => {
+                let file_library = program_archive.get_file_library().clone();
+                return Err(CompilationError::new(file_library, warnings, errors));
+            }
+            Ok(mut type_warnings) => warnings.append(&mut type_warnings),
+        }
+        let file_library = program_archive.get_file_library().clone();
+        let custom_gates = program_archive.custom_gates;
+        let output = match build_circuit_with_reports(program_archive, &self.build_config()) {
+            Err(errors) => return Err(CompilationError::new(file_library, warnings, errors)),
+            Ok(mut output) => {
+                warnings.append(&mut output.warnings);
+                output
+            }
+        };
+        let name = main.file_stem().map_or("circuit".to_string(), |stem| stem.to_string_lossy().to_string());
+        let artefacts = self.produce_artefacts(&name, &output, custom_gates);
+        let (artefacts, log) = match artefacts {
+            Err(errors) => return Err(CompilationError::new(file_library, warnings, errors)),
+            Ok(produced) => produced,
+        };
+        Ok(Compilation {
+            file_library,
+            warnings,
+            template_instances: output.template_instances,
+            log,
+            constraints: output.constraints,
+            vcp: output.vcp,
+            artefacts,
+        })
+    }
+
+    fn build_config(&self) -> BuildConfig {
+        BuildConfig {
+            no_rounds: if let Simplification::Full(rounds) = self.simplification { rounds } else { 0 },
+            memory_budget: self.memory_budget,
+            flag_json_sub: false,
+            json_substitutions: String::new(),
+            explain: self.explain.clone(),
+            flag_s: self.simplification == Simplification::Reduced,
+            flag_f: self.simplification == Simplification::None,
+            flag_p: false,
+            flag_verbose: self.verbose,
+            flag_old_heuristics: self.old_heuristics,
+            inspect_constraints: self.inspect,
+            prime: self.prime.clone(),
+            flag_hierarchy: false,
+            hierarchy_dot: String::new(),
+            hierarchy_json: String::new(),
+        }
+    }
+
+    // The artefacts are produced in a memory directory, so the compilation
+    // does not touch the file system
+    fn produce_artefacts(
+        &self,
+        name: &str,
+        output: &BuildOutput,
+        custom_gates: bool,
+    ) -> Result<(Vec<Artefact>, Option<Log>), ReportCollection> {
+        let mut directory = MemoryDirectory::new();
+        let log = self.write_artefacts(&mut directory, name, output, custom_gates)?;
+        let artefacts = directory
+            .into_files()
+            .into_iter()
+            .map(|(path, contents)| Artefact { path: PathBuf::from(path), contents })
+            .collect();
+        Ok((artefacts, log))
+    }
+
+    fn write_artefacts(
+        &self,
+        directory: &mut MemoryDirectory,
+        name: &str,
+        output: &BuildOutput,
+        custom_gates: bool,
+    ) -> Result<Option<Log>, ReportCollection> {
+        let exporter = output.constraints.exporter();
+        let mut log = None;
+        if self.r1cs {
+            let file = format!("{}.r1cs", name);
+            log = Some(exporter.r1cs(directory, &file, custom_gates).map_err(into_reports)?);
+        }
+        if self.sym {
+            let file = format!("{}.sym", name);
+            exporter.sym(directory, &file).map_err(into_reports)?;
+        }
+        if self.json {
+            let debug = DebugWriter::new(format!("{}_constraints.json", name)).unwrap();
+            exporter.json_constraints(directory, &debug).map_err(into_reports)?;
+        }
+        if !(self.c || self.wat || self.wasm) {
+            return Ok(log);
+        }
+        let config = Config {
+            debug_output: false,
+            produce_input_log: false,
+            wat_flag: self.wat,
+            c_portable: self.c_portable,
+            optimization: self.witness_optimization,
+            verify_ir: self.verify_ir,
+        };
+        let circuit = compiler_interface::run_compiler(output.vcp.clone(), config, VERSION, directory)?;
+        if self.c {
+            let c_name = if name == "main" || name == "fr" || name == "calcwit" {
+                format!("{}_c", name)
+            } else {
+                name.to_string()
+            };
+            let c_folder = format!("{}_cpp", c_name);
+            let c_file = format!("{}/{}.cpp", c_folder, c_name);
+            let dat_file = format!("{}/{}.dat", c_folder, c_name);
+            compiler_interface::write_c(&circuit, directory, &c_folder, &c_name, &c_file, &dat_file)
+                .map_err(into_reports)?;
+        }
+        if self.wat || self.wasm {
+            let js_folder = format!("{}_js", name);
+            let wat_file = format!("{}/{}.wat", js_folder, name);
+            let wasm_file = format!("{}/{}.wasm", js_folder, name);
+            compiler_interface::write_wasm(&circuit, directory, &js_folder, name, &wat_file)
+                .map_err(into_reports)?;
+            if self.wasm {
+                let wat_contents = String::from_utf8_lossy(directory.get_file(&wat_file).unwrap()).to_string();
+                let wasm_contents = wat_contents_to_wasm(&wat_contents).map_err(|report| vec![*report])?;
+                directory.create_file(&wasm_file)
+                    .and_then(|mut writer| writer.write_all(&wasm_contents))
+                    .map_err(|err| into_reports(OutputError::new(&wasm_file, err)))?;
+            }
+            if !self.wat {
+                directory.remove_file(&wat_file);
+            }
+        }
+        Ok(log)
+    }
+}
+
+fn into_reports(error: OutputError) -> ReportCollection {
+    vec![error.into_report()]
+}
+
+fn option_error_report(message: String) -> Report {
+    Report::error(message, ReportCode::InvalidCompilerOption)
+}
+
+fn option_error(message: String) -> CompilationError {
+    CompilationError::new(FileLibrary::new(), vec![], vec![option_error_report(message)])
+}
+
+// The report is boxed to keep the result small
+pub fn wat_to_wasm(wat_file: &str, wasm_file: &str) -> Result<(), Box<Report>> {
+    use std::fs::read_to_string;
+    use std::fs::File;
+    use std::io::BufWriter;
+
+    let wat_contents = read_to_string(wat_file).unwrap();
+    let wasm_contents = wat_contents_to_wasm(&wat_contents)?;
+    let file = File::create(wasm_file).unwrap();
+    let mut writer = BufWriter::new(file);
+    writer.write_all(&wasm_contents).map_err(|_err| Box::new(Report::error(
+        format!("Error writing the circuit. Exception generated: {}", _err),
+        ReportCode::ErrorWat2Wasm,
+    )))?;
+    writer.flush().map_err(|_err| Box::new(Report::error(
+        format!("Error writing the circuit. Exception generated: {}", _err),
+        ReportCode::ErrorWat2Wasm,
+    )))?;
+    Ok(())
+}
+
+pub fn wat_contents_to_wasm(wat_contents: &str) -> Result<Vec<u8>, Box<Report>> {
+    use wast::Wat;
+    use wast::parser::{self, ParseBuffer};
+
+    let buf = ParseBuffer::new(wat_contents).unwrap();
+    let result_wasm_contents = parser::parse::<Wat>(&buf);
+    match result_wasm_contents {
+        Result::Err(error) => {
+            Result::Err(Box::new(Report::error(
+                format!("Error translating the circuit from wat to wasm.\n\nException encountered when parsing WAT: {}", error),
+                ReportCode::ErrorWat2Wasm,
+            )))
+        }
+        Result::Ok(mut wat) => {
+            wat.module.encode().map_err(|error| Box::new(Report::error(
+                format!("Error translating the circuit from wat to wasm.\n\nException encountered when encoding WASM: {}", error),
+                ReportCode::ErrorWat2Wasm,
+            )))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use constraint_writers::r1cs_reader::read_r1cs_for_prime;
+    use constraint_writers::sym_reader::read_sym;
+    use parser::test_project::Project;
+    use program_structure::constants::parse_prime;
+
+    const CIRCUIT: &str = "
+pragma circom 2.0.0;
+template Square() { signal input in; signal output out; out <== in * in; }
+template Main(n) {
+    signal input a[n];
+    signal input b;
+    signal output c;
+    signal sum[n + 1];
+    component squares[n];
+    sum[0] <== b;
+    for (var i = 0; i < n; i++) {
+        squares[i] = Square();
+        squares[i].in <== a[i] + 2 * b;
+        sum[i + 1] <== sum[i] + squares[i].out;
+    }
+    c <== sum[n] * b;
+}
+component main {public [b]} = Main(3);
+";
+
+    // A project with the circuit in circuit.circom
+    fn circuit_project(name: &str) -> Project {
+        Project::new(name, &[("circuit.circom", CIRCUIT)])
+    }
+
+    // Compiles the circuit and checks that the files it returns agree with
+    // the statistics of the compilation
+    fn compile(simplification: Simplification, prime: &str) {
+        let project = circuit_project(&format!("compile_{:?}_{}", simplification, prime));
+        let compilation = Compiler::new().main(project.path("circuit.circom")).prime(prime).simplification(simplification).r1cs(true).sym(true).run();
+        let compilation = compilation.ok().unwrap();
+
+        let bytes = &compilation.get_artefact("circuit.r1cs").unwrap().contents;
+        let r1cs = read_r1cs_for_prime(bytes, "circuit.r1cs", &parse_prime(prime).unwrap()).unwrap();
+        assert_eq!(r1cs.header.public_inputs, 1);
+        assert_eq!(r1cs.header.private_inputs, 3);
+        assert_eq!(r1cs.header.public_outputs, 1);
+        assert_eq!(compilation.template_instances, 2);
+        let log = compilation.log.as_ref().unwrap();
+        assert_eq!(log.no_public_inputs, r1cs.header.public_inputs);
+        assert_eq!(log.no_wires, r1cs.header.total_wires);
+        assert_eq!(log.no_linear + log.no_non_linear, r1cs.constraints.len());
+
+        let sym = &compilation.get_artefact("circuit.sym").unwrap().contents;
+        let symbols = read_sym(sym, "circuit.sym").unwrap();
+        assert!(symbols.iter().any(|elem| elem.symbol == "main.c"));
+    }
+
+    #[test]
+    fn compilation_returns_its_files_and_statistics() {
+        compile(Simplification::None, "bn128");
+        compile(Simplification::Full(usize::MAX), "bn128");
+        compile(Simplification::Reduced, "goldilocks");
+    }
+
+    #[test]
+    fn the_logs_of_the_compiler_are_written_in_its_directory() {
+        let project = circuit_project("logs");
+        let compilation = Compiler::new().main(project.path("circuit.circom")).run().ok().unwrap();
+        let config = Config {
+            debug_output: true,
+            produce_input_log: true,
+            wat_flag: false,
+            c_portable: false,
+            optimization: OptimizationLevel::default(),
+            verify_ir: false,
+        };
+        let mut directory = MemoryDirectory::new();
+        compiler_interface::run_compiler(compilation.vcp, config, VERSION, &mut directory).ok().unwrap();
+        let inputs = String::from_utf8_lossy(directory.get_file("log_input_signals.txt").unwrap()).to_string();
+        assert_eq!(inputs, "main.b 1\nmain.a 3\n");
+        assert!(directory.get_file("ir_log/circuit.ir").is_some());
+    }
+
+    // Witness computed by the wasm code for the prime, None if node is not installed
+    fn wasm_witness(prime: &str, input: &str) -> Option<Vec<u8>> {
+        let project = circuit_project(&format!("witness_{}", prime));
+        let compilation = Compiler::new().main(project.path("circuit.circom")).prime(prime).wasm(true).run();
+        for artefact in &compilation.ok().unwrap().artefacts {
+            project.write(&artefact.path, &artefact.contents);
+        }
+        project.write("input.json", input.as_bytes());
+        let status = std::process::Command::new("node")
+            .current_dir(project.root())
+            .args(["circuit_js/generate_witness.js", "circuit_js/circuit.wasm", "input.json", "witness.wtns"])
+            .status()
+            .ok()?;
+        assert!(status.success());
+        Some(std::fs::read(project.path("witness.wtns")).unwrap())
+    }
+
+    #[test]
+    fn generic_field_code_computes_the_same_witness() {
+        // A prime given by value uses the generic Montgomery code of fr-code.wat
+        for name in ["bn128", "goldilocks"] {
+            let p = parse_prime(name).unwrap();
+            let minus_one = (&p - 1u32).to_string();
+            let input = format!(r#"{{"a": ["3", "{}", "123456789012345"], "b": "{}"}}"#, minus_one, minus_one);
+            let named = match wasm_witness(name, &input) {
+                Some(witness) => witness,
+                None => return eprintln!("node is not installed, the witnesses are not compared"),
+            };
+            assert!(wasm_witness(&p.to_string(), &input).unwrap() == named, "{}", name);
+        }
+    }
+}
@@ -0,0 +1,46 @@
+use super::input_user::DiffInput;
+use ansi_term::Colour;
+use constraint_list::{CanonicalSystem, SystemDiff};
+use constraint_writers::r1cs_reader::read_r1cs_file;
+use constraint_writers::sym_reader::read_sym_file;
+use program_structure::error_definition::Report;
+use program_structure::file_definition::FileLibrary;
+use std::path::{Path, PathBuf};
+
+// Compares the constraints of two r1cs files, with the signals named after
+// their sym files. Fails if they differ, so it can be used to check that a
+// change in a circuit does not change its constraints.
+pub fn diff(input: &DiffInput) -> Result<(), ()> {
+    let old = read_system(&input.old_r1cs, &input.old_sym)?;
+    let new = read_system(&input.new_r1cs, &input.new_sym)?;
+    if old.field != new.field {
+        eprintln!("{}", Colour::Red.paint("The r1cs files are defined over different primes"));
+        return Result::Err(());
+    }
+    let diff = SystemDiff::compare(&old, &new);
+    if diff.is_empty() {
+        println!("{}", Colour::Green.paint("The constraint systems are equivalent"));
+        Result::Ok(())
+    } else {
+        println!("{}", diff);
+        eprintln!("{}", Colour::Red.paint("The constraint systems differ"));
+        Result::Err(())
+    }
+}
+
+fn read_system(r1cs_file: &Path, sym_file: &Option<PathBuf>) -> Result<CanonicalSystem, ()> {
+    let print_error = |report: Report| Report::print_reports(&[report], &FileLibrary::new());
+    let r1cs_file = r1cs_file.to_string_lossy().to_string();
+    let r1cs = read_r1cs_file(&r1cs_file).map_err(|err| print_error(err.into_report()))?;
+    let symbols = match sym_file {
+        Some(sym_file) => {
+            let sym_file = sym_file.to_string_lossy().to_string();
+            read_sym_file(&sym_file).map_err(|err| print_error(err.into_report()))?
+        }
+        None => {
+            println!("{} {}", Colour::Yellow.paint("No sym file was found, the wires are not named in"), r1cs_file);
+            Vec::new()
+        }
+    };
+    Result::Ok(CanonicalSystem::from_r1cs(&r1cs, &symbols))
+}
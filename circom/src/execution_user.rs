@@ -1,7 +1,11 @@
 use ansi_term::Colour;
 use compiler::hir::very_concrete_program::VCP;
 use constraint_writers::debug_writer::DebugWriter;
+use constraint_writers::log_writer::Log;
 use constraint_writers::ConstraintExporter;
+use program_structure::error_definition::Report;
+use program_structure::file_definition::FileLibrary;
+use program_structure::output_directory::FileSystem;
 use program_structure::program_archive::ProgramArchive;
 
 
@@ -10,6 +14,7 @@ pub struct ExecutionConfig {
     pub sym: String,
     pub json_constraints: String,
     pub no_rounds: usize,
+    pub memory_budget: Option<usize>,
     pub flag_s: bool,
     pub flag_f: bool,
     pub flag_p: bool,
@@ -19,19 +24,27 @@ pub struct ExecutionConfig {
     pub sym_flag: bool,
     pub r1cs_flag: bool,
     pub json_substitution_flag: bool,
+    pub json_substitutions: String,
+    pub explain: Vec<String>,
     pub json_constraint_flag: bool,
     pub prime: String,
+    pub hierarchy_flag: bool,
+    pub hierarchy_dot: String,
+    pub hierarchy_json: String,
 }
 
 pub fn execute_project(
     program_archive: ProgramArchive,
     config: ExecutionConfig,
 ) -> Result<VCP, ()> {
-    use constraint_generation::{build_circuit, BuildConfig};
+    use constraint_generation::{build_circuit_with_reports, BuildConfig, ConstraintSystem};
     let debug = DebugWriter::new(config.json_constraints).unwrap();
     let build_config = BuildConfig {
         no_rounds: config.no_rounds,
+        memory_budget: config.memory_budget,
         flag_json_sub: config.json_substitution_flag,
+        json_substitutions: config.json_substitutions,
+        explain: config.explain,
         flag_s: config.flag_s,
         flag_f: config.flag_f,
         flag_p: config.flag_p,
@@ -39,9 +52,36 @@ pub fn execute_project(
         inspect_constraints: config.inspect_constraints_flag,
         flag_old_heuristics: config.flag_old_heuristics,
         prime : config.prime,
+        flag_hierarchy: config.hierarchy_flag,
+        hierarchy_dot: config.hierarchy_dot,
+        hierarchy_json: config.hierarchy_json,
     };
     let custom_gates = program_archive.custom_gates;
-    let (exporter, vcp) = build_circuit(program_archive, build_config)?;
+    let files = program_archive.file_library.clone();
+    let output = build_circuit_with_reports(program_archive, &build_config)
+        .map_err(|reports| Report::print_reports(&reports, &files))?;
+    println!("{}: {}", Colour::Green.paint("template instances"), output.template_instances);
+    Report::print_reports(&output.warnings, &files);
+    if let ConstraintSystem::Simplified(list) = &output.constraints {
+        if !build_config.explain.is_empty() {
+            for explanation in list.explain(&build_config.explain, &files) {
+                println!("{}", explanation);
+            }
+        }
+        if build_config.flag_json_sub {
+            println!("{} {}", Colour::Green.paint("Substitutions written in:"), build_config.json_substitutions);
+        }
+    }
+    if build_config.flag_hierarchy {
+        println!(
+            "{} {} and {}",
+            Colour::Green.paint("Written successfully:"),
+            build_config.hierarchy_dot,
+            build_config.hierarchy_json
+        );
+    }
+    let exporter = output.constraints.into_exporter();
+    let vcp = output.vcp;
     if config.r1cs_flag {
         generate_output_r1cs(&config.r1cs, exporter.as_ref(), custom_gates)?;
     }
@@ -55,22 +95,29 @@ pub fn execute_project(
 }
 
 fn generate_output_r1cs(file: &str, exporter: &dyn ConstraintExporter, custom_gates: bool) -> Result<(), ()> {
-    if let Result::Ok(()) = exporter.r1cs(file, custom_gates) {
-        println!("{} {}", Colour::Green.paint("Written successfully:"), file);
-        Result::Ok(())
-    } else {
-        eprintln!("{}", Colour::Red.paint("Could not write the output in the given path"));
-        Result::Err(())
+    match exporter.r1cs(&mut FileSystem, file, custom_gates) {
+        Result::Ok(log) => {
+            Log::print(&log);
+            println!("{} {}", Colour::Green.paint("Written successfully:"), file);
+            Result::Ok(())
+        }
+        Result::Err(err) => {
+            Report::print_reports(&[err.into_report()], &FileLibrary::new());
+            Result::Err(())
+        }
     }
 }
 
 fn generate_output_sym(file: &str, exporter: &dyn ConstraintExporter) -> Result<(), ()> {
-    if let Result::Ok(()) = exporter.sym(file) {
-        println!("{} {}", Colour::Green.paint("Written successfully:"), file);
-        Result::Ok(())
-    } else {
-        eprintln!("{}", Colour::Red.paint("Could not write the output in the given path"));
-        Result::Err(())
+    match exporter.sym(&mut FileSystem, file) {
+        Result::Ok(()) => {
+            println!("{} {}", Colour::Green.paint("Written successfully:"), file);
+            Result::Ok(())
+        }
+        Result::Err(err) => {
+            Report::print_reports(&[err.into_report()], &FileLibrary::new());
+            Result::Err(())
+        }
     }
 }
 
@@ -78,11 +125,14 @@ fn generate_json_constraints(
     debug: &DebugWriter,
     exporter: &dyn ConstraintExporter,
 ) -> Result<(), ()> {
-    if let Ok(()) = exporter.json_constraints(&debug) {
-        println!("{} {}", Colour::Green.paint("Constraints written in:"), debug.json_constraints);
-        Result::Ok(())
-    } else {
-        eprintln!("{}", Colour::Red.paint("Could not write the output in the given path"));
-        Result::Err(())
+    match exporter.json_constraints(&mut FileSystem, &debug) {
+        Result::Ok(()) => {
+            println!("{} {}", Colour::Green.paint("Constraints written in:"), debug.json_constraints);
+            Result::Ok(())
+        }
+        Result::Err(err) => {
+            Report::print_reports(&[err.into_report()], &FileLibrary::new());
+            Result::Err(())
+        }
     }
 }
@@ -1,9 +1,13 @@
 use std::path::PathBuf;
+use super::manifest_user::{Manifest, TargetSettings};
+use compiler::compiler_interface::OptimizationLevel;
 
 pub struct Input {
+    pub target: Option<String>,
     pub input_program: PathBuf,
     pub out_r1cs: PathBuf,
     pub out_json_constraints: PathBuf,
+    pub out_json_substitutions: PathBuf,
     pub out_wat_code: PathBuf,
     pub out_wasm_code: PathBuf,
     pub out_wasm_name: String,
@@ -13,24 +17,35 @@ pub struct Input {
     pub out_c_code: PathBuf,
     pub out_c_dat: PathBuf,
     pub out_sym: PathBuf,
+    pub out_hierarchy_dot: PathBuf,
+    pub out_hierarchy_json: PathBuf,
+    pub out_includes_dot: PathBuf,
+    pub out_includes_json: PathBuf,
     //pub field: &'static str,
     pub c_flag: bool,
+    pub c_portable_flag: bool,
     pub wasm_flag: bool,
     pub wat_flag: bool,
     pub r1cs_flag: bool,
     pub sym_flag: bool,
     pub json_constraint_flag: bool,
     pub json_substitution_flag: bool,
+    pub explain: Vec<String>,
+    pub hierarchy_flag: bool,
+    pub includes_flag: bool,
     pub main_inputs_flag: bool,
     pub print_ir_flag: bool,
+    pub verify_ir_flag: bool,
     pub fast_flag: bool,
     pub reduced_simplification_flag: bool,
     pub parallel_simplification_flag: bool,
     pub flag_old_heuristics: bool,
     pub inspect_constraints_flag: bool,
     pub no_rounds: usize,
+    pub memory_budget: Option<usize>,
     pub flag_verbose: bool,
     pub prime: String,
+    pub witness_optimization: OptimizationLevel,
     pub link_libraries : Vec<PathBuf>
 }
 
@@ -43,18 +58,58 @@ const JS: &'static str = "js";
 const DAT: &'static str = "dat";
 const SYM: &'static str = "sym";
 const JSON: &'static str = "json";
+const DOT: &'static str = "dot";
 
 
 impl Input {
-    pub fn new() -> Result<Input, ()> {
+    // Builds the inputs of every target of the manifest, or of a single one if
+    // the project does not have a manifest or the input file is given in the
+    // command line without selecting targets. Command line options override the
+    // values of the manifest. A target has an input for each of its primes;
+    // they only differ in the prime and in the output folder, which is a
+    // subfolder named after the prime when there are several of them.
+    pub fn collect(matches: &clap::ArgMatches) -> Result<Vec<Vec<Input>>, ()> {
+        let command_line = input_processing::get_settings(matches)?;
+        let manifest = match input_processing::get_manifest(matches)? {
+            Some(file) => Manifest::load(&file)?,
+            None => Manifest::default(),
+        };
+        let selected = input_processing::get_selected_targets(matches);
+        let mut inputs = vec![];
+        for (target, settings) in manifest.get_targets(&selected, command_line.main.is_some())? {
+            let settings = settings.override_with(&command_line);
+            let primes = input_processing::get_primes(&settings)?;
+            let mut target_inputs = vec![];
+            for prime in &primes {
+                let prime_folder = if primes.len() > 1 { Some(prime.as_str()) } else { None };
+                target_inputs.push(Input::new(target.clone(), &settings, matches, prime, prime_folder)?);
+            }
+            inputs.push(target_inputs);
+        }
+        Result::Ok(inputs)
+    }
+
+    fn new(
+        target: Option<String>,
+        settings: &TargetSettings,
+        matches: &clap::ArgMatches,
+        prime: &str,
+        prime_folder: Option<&str>,
+    ) -> Result<Input, ()> {
         use ansi_term::Colour;
         use input_processing::SimplificationStyle;
-        let matches = input_processing::view();
-        let input = input_processing::get_input(&matches)?;
+        let input = input_processing::get_input(settings)?;
         let mut file_name = input.file_stem().unwrap().to_str().unwrap().to_string();
-        let output_path = input_processing::get_output_path(&matches)?;
+        // The include graph does not depend on the prime
+        let includes_path = input_processing::get_output_path(settings)?;
+        let output_path = match prime_folder {
+            Some(folder) => input_processing::get_prime_output_path(&includes_path, folder)?,
+            None => includes_path.clone(),
+        };
+        let outputs = input_processing::get_outputs(settings)?;
 
-        let c_flag = input_processing::get_c(&matches);
+        let c_portable_flag = settings.c_portable.unwrap_or(false);
+        let c_flag = outputs.contains(&"c") || c_portable_flag;
 
         if c_flag && (file_name == "main" || file_name == "fr" || file_name == "calcwit"){
             println!("{}", Colour::Yellow.paint(format!("The name {} is reserved in Circom when using de --c flag. The files generated for your circuit will use the name {}_c instead of {}.", file_name, file_name, file_name)));
@@ -62,10 +117,16 @@ impl Input {
         };
         let output_c_path = Input::build_folder(&output_path, &file_name, CPP);
         let output_js_path = Input::build_folder(&output_path, &file_name, JS);
-        let o_style = input_processing::get_simplification_style(&matches)?;
-        let link_libraries = input_processing::get_link_libraries(&matches);
+        let o_style = input_processing::get_simplification_style(settings)?;
+        let explain = input_processing::get_explain(matches);
+        if !explain.is_empty() && o_style == SimplificationStyle::O0 {
+            eprintln!("{}", Colour::Red.paint("--explain needs the simplification, it cannot be used with --O0"));
+            return Result::Err(());
+        }
+        let link_libraries = settings.libraries.clone().unwrap_or_default();
         Result::Ok(Input {
             //field: P_BN128,
+            target,
             input_program: input,
             out_r1cs: Input::build_output(&output_path, &file_name, R1CS),
             out_wat_code: Input::build_output(&output_js_path, &file_name, WAT),
@@ -82,23 +143,55 @@ impl Input {
                 &format!("{}_constraints", file_name),
                 JSON,
             ),
-            wat_flag:input_processing::get_wat(&matches),
-            wasm_flag: input_processing::get_wasm(&matches),
+            out_json_substitutions: Input::build_output(
+                &output_path,
+                &format!("{}_substitutions", file_name),
+                JSON,
+            ),
+            out_hierarchy_dot: Input::build_output(
+                &output_path,
+                &format!("{}_hierarchy", file_name),
+                DOT,
+            ),
+            out_hierarchy_json: Input::build_output(
+                &output_path,
+                &format!("{}_hierarchy", file_name),
+                JSON,
+            ),
+            out_includes_dot: Input::build_output(
+                &includes_path,
+                &format!("{}_includes", file_name),
+                DOT,
+            ),
+            out_includes_json: Input::build_output(
+                &includes_path,
+                &format!("{}_includes", file_name),
+                JSON,
+            ),
+            wat_flag: outputs.contains(&"wat"),
+            wasm_flag: outputs.contains(&"wasm"),
             c_flag: c_flag,
-            r1cs_flag: input_processing::get_r1cs(&matches),
-            sym_flag: input_processing::get_sym(&matches),
+            c_portable_flag,
+            r1cs_flag: outputs.contains(&"r1cs"),
+            sym_flag: outputs.contains(&"sym"),
             main_inputs_flag: input_processing::get_main_inputs_log(&matches),
-            json_constraint_flag: input_processing::get_json_constraints(&matches),
+            json_constraint_flag: outputs.contains(&"json"),
             json_substitution_flag: input_processing::get_json_substitutions(&matches),
+            explain,
+            hierarchy_flag: outputs.contains(&"hierarchy"),
+            includes_flag: outputs.contains(&"includes"),
             print_ir_flag: input_processing::get_ir(&matches),
+            verify_ir_flag: input_processing::get_verify_ir(matches),
             no_rounds: if let SimplificationStyle::O2(r) = o_style { r } else { 0 },
             fast_flag: o_style == SimplificationStyle::O0,
             reduced_simplification_flag: o_style == SimplificationStyle::O1,
             parallel_simplification_flag: input_processing::get_parallel_simplification(&matches),
-            inspect_constraints_flag: input_processing::get_inspect_constraints(&matches),
-            flag_old_heuristics: input_processing::get_flag_old_heuristics(&matches),
-            flag_verbose: input_processing::get_flag_verbose(&matches), 
-            prime: input_processing::get_prime(&matches)?,
+            inspect_constraints_flag: settings.inspect.unwrap_or(false),
+            flag_old_heuristics: settings.old_heuristics.unwrap_or(false),
+            memory_budget: settings.memory_budget,
+            flag_verbose: settings.verbose.unwrap_or(false),
+            prime: prime.to_string(),
+            witness_optimization: input_processing::get_witness_optimization(settings)?,
             link_libraries
         })
     }
@@ -116,6 +209,10 @@ impl Input {
         file
     }
 
+    pub fn target(&self) -> Option<&str> {
+        self.target.as_deref()
+    }
+
     pub fn get_link_libraries(&self) -> &Vec<PathBuf> {
         &self.link_libraries
     }
@@ -158,6 +255,21 @@ impl Input {
     pub fn json_constraints_file(&self) -> &str {
         self.out_json_constraints.to_str().unwrap()
     }
+    pub fn json_substitutions_file(&self) -> &str {
+        self.out_json_substitutions.to_str().unwrap()
+    }
+    pub fn hierarchy_dot_file(&self) -> &str {
+        self.out_hierarchy_dot.to_str().unwrap()
+    }
+    pub fn hierarchy_json_file(&self) -> &str {
+        self.out_hierarchy_json.to_str().unwrap()
+    }
+    pub fn includes_dot_file(&self) -> &str {
+        self.out_includes_dot.to_str().unwrap()
+    }
+    pub fn includes_json_file(&self) -> &str {
+        self.out_includes_json.to_str().unwrap()
+    }
     pub fn wasm_flag(&self) -> bool {
         self.wasm_flag
     }
@@ -167,6 +279,9 @@ impl Input {
     pub fn c_flag(&self) -> bool {
         self.c_flag
     }
+    pub fn c_portable_flag(&self) -> bool {
+        self.c_portable_flag
+    }
     pub fn unsimplified_flag(&self) -> bool {
         self.fast_flag
     }
@@ -179,6 +294,15 @@ impl Input {
     pub fn json_substitutions_flag(&self) -> bool {
         self.json_substitution_flag
     }
+    pub fn explain(&self) -> &[String] {
+        &self.explain
+    }
+    pub fn hierarchy_flag(&self) -> bool {
+        self.hierarchy_flag
+    }
+    pub fn includes_flag(&self) -> bool {
+        self.includes_flag
+    }
     pub fn main_inputs_flag(&self) -> bool {
         self.main_inputs_flag
     }
@@ -188,6 +312,9 @@ impl Input {
     pub fn print_ir_flag(&self) -> bool {
         self.print_ir_flag
     }
+    pub fn verify_ir_flag(&self) -> bool {
+        self.verify_ir_flag
+    }
     pub fn inspect_constraints_flag(&self) -> bool {
         self.inspect_constraints_flag
     }
@@ -206,18 +333,107 @@ impl Input {
     pub fn no_rounds(&self) -> usize {
         self.no_rounds
     }
+    pub fn memory_budget(&self) -> Option<usize> {
+        self.memory_budget
+    }
     pub fn prime(&self) -> String{
         self.prime.clone()
     }
+    pub fn witness_optimization(&self) -> OptimizationLevel {
+        self.witness_optimization
+    }
+}
+pub fn command_line() -> clap::ArgMatches<'static> {
+    input_processing::view()
+}
+
+// Options of the simplify subcommand, which simplifies again the constraints
+// of an r1cs file. The outputs are named after the input with _simplified.
+pub struct SimplificationInput {
+    pub input_r1cs: PathBuf,
+    pub input_sym: Option<PathBuf>,
+    pub out_r1cs: PathBuf,
+    pub out_sym: PathBuf,
+    pub out_substitutions: PathBuf,
+    pub no_rounds: usize,
+    pub reduced_simplification_flag: bool,
+    pub flag_old_heuristics: bool,
+    pub memory_budget: Option<usize>,
+}
+
+impl SimplificationInput {
+    pub fn collect(matches: &clap::ArgMatches) -> Result<SimplificationInput, ()> {
+        use input_processing::SimplificationStyle;
+        let settings = input_processing::get_settings(matches)?;
+        let input_r1cs = input_processing::get_input(&settings)?;
+        let input_sym = input_processing::get_sym_file(matches, "sym_file", &input_r1cs)?;
+        let output_path = input_processing::get_output_path(&settings)?;
+        let file_name = format!("{}_simplified", input_r1cs.file_stem().unwrap().to_str().unwrap());
+        let (no_rounds, reduced_simplification_flag) = match input_processing::get_simplification_style(&settings)? {
+            // --O0 is not an option of the subcommand
+            SimplificationStyle::O0 => unreachable!(),
+            SimplificationStyle::O1 => (0, true),
+            SimplificationStyle::O2(no_rounds) => (no_rounds, false),
+        };
+        Result::Ok(SimplificationInput {
+            input_r1cs,
+            input_sym,
+            out_r1cs: Input::build_output(&output_path, &file_name, R1CS),
+            out_sym: Input::build_output(&output_path, &file_name, SYM),
+            out_substitutions: Input::build_output(&output_path, &format!("{}_substitutions", file_name), JSON),
+            no_rounds,
+            reduced_simplification_flag,
+            flag_old_heuristics: settings.old_heuristics.unwrap_or(false),
+            memory_budget: settings.memory_budget,
+        })
+    }
 }
+
+// Options of the diff subcommand, which compares the constraints of two
+// r1cs files
+pub struct DiffInput {
+    pub old_r1cs: PathBuf,
+    pub old_sym: Option<PathBuf>,
+    pub new_r1cs: PathBuf,
+    pub new_sym: Option<PathBuf>,
+}
+
+impl DiffInput {
+    pub fn collect(matches: &clap::ArgMatches) -> Result<DiffInput, ()> {
+        let old_r1cs = input_processing::get_r1cs_file(matches, "old")?;
+        let new_r1cs = input_processing::get_r1cs_file(matches, "new")?;
+        Result::Ok(DiffInput {
+            old_sym: input_processing::get_sym_file(matches, "old_sym", &old_r1cs)?,
+            new_sym: input_processing::get_sym_file(matches, "new_sym", &new_r1cs)?,
+            old_r1cs,
+            new_r1cs,
+        })
+    }
+}
+
 mod input_processing {
     use ansi_term::Colour;
-    use clap::{App, Arg, ArgMatches};
+    use clap::{App, Arg, ArgMatches, SubCommand};
     use std::path::{Path, PathBuf};
+    use crate::manifest_user::{TargetSettings, MANIFEST_FILE};
+    use compiler::compiler_interface::OptimizationLevel;
+    use program_structure::constants::parse_prime;
     use crate::VERSION;
 
-    pub fn get_input(matches: &ArgMatches) -> Result<PathBuf, ()> {
-        let route = Path::new(matches.value_of("input").unwrap()).to_path_buf();
+    // Outputs that can be selected in the manifest and the flag that selects them
+    const OUTPUTS: [(&str, &str); 8] = [
+        ("r1cs", "print_r1cs"),
+        ("sym", "print_sym"),
+        ("wasm", "print_wasm"),
+        ("wat", "print_wat"),
+        ("c", "print_c"),
+        ("json", "print_json_c"),
+        ("hierarchy", "print_hierarchy"),
+        ("includes", "print_includes"),
+    ];
+
+    pub fn get_input(settings: &TargetSettings) -> Result<PathBuf, ()> {
+        let route = settings.main.clone().unwrap_or(PathBuf::from("./circuit.circom"));
         if route.is_file() {
             Result::Ok(route)
         } else {
@@ -226,8 +442,31 @@ mod input_processing {
         }
     }
 
-    pub fn get_output_path(matches: &ArgMatches) -> Result<PathBuf, ()> {
-        let route = Path::new(matches.value_of("output").unwrap()).to_path_buf();
+    pub fn get_r1cs_file(matches: &ArgMatches, option: &str) -> Result<PathBuf, ()> {
+        let route = PathBuf::from(matches.value_of(option).unwrap());
+        if route.is_file() {
+            Result::Ok(route)
+        } else {
+            eprintln!("{}", Colour::Red.paint(format!("Input file does not exist: {}", route.display())));
+            Result::Err(())
+        }
+    }
+
+    // The sym file given in the option or, without it, the one next to the
+    // r1cs file if there is one
+    pub fn get_sym_file(matches: &ArgMatches, option: &str, r1cs: &Path) -> Result<Option<PathBuf>, ()> {
+        match matches.value_of(option) {
+            Some(file) if !Path::new(file).is_file() => {
+                eprintln!("{}", Colour::Red.paint("Sym file does not exist: ".to_owned() + file));
+                Result::Err(())
+            }
+            Some(file) => Result::Ok(Some(PathBuf::from(file))),
+            None => Result::Ok(Some(r1cs.with_extension(super::SYM)).filter(|sym| sym.is_file())),
+        }
+    }
+
+    pub fn get_output_path(settings: &TargetSettings) -> Result<PathBuf, ()> {
+        let route = settings.output.clone().unwrap_or(PathBuf::from("."));
         if route.is_dir() {
             Result::Ok(route)
         } else {
@@ -235,57 +474,159 @@ mod input_processing {
         }
     }
 
-    #[derive(Copy, Clone, Eq, PartialEq)]
-    pub enum SimplificationStyle { O0, O1, O2(usize) }
-    pub fn get_simplification_style(matches: &ArgMatches) -> Result<SimplificationStyle, ()> {
-
-        let o_0 = matches.is_present("no_simplification");
-        let o_1 = matches.is_present("reduced_simplification");
-        let o_2 = matches.is_present("full_simplification");
-        let o_2round = matches.is_present("simplification_rounds");
-        match (o_0, o_1, o_2round, o_2) {
-            (true, _, _, _) => Ok(SimplificationStyle::O0),
-            (_, true, _, _) => Ok(SimplificationStyle::O1),
-            (_, _, true,  _) => {
-                let o_2_argument = matches.value_of("simplification_rounds").unwrap();
-                let rounds_r = usize::from_str_radix(o_2_argument, 10);
-                if let Result::Ok(no_rounds) = rounds_r { 
-                    if no_rounds == 0 { Ok(SimplificationStyle::O1) }
-                    else {Ok(SimplificationStyle::O2(no_rounds))}} 
-                else { Result::Err(eprintln!("{}", Colour::Red.paint("invalid number of rounds"))) }
-            },
-            
-            (false, false, false, true) => Ok(SimplificationStyle::O2(usize::MAX)),
-            (false, false, false, false) => Ok(SimplificationStyle::O2(usize::MAX)),
+    pub fn get_prime_output_path(output_path: &Path, prime: &str) -> Result<PathBuf, ()> {
+        let route = output_path.join(prime);
+        if route.is_dir() || std::fs::create_dir(&route).is_ok() {
+            Result::Ok(route)
+        } else {
+            Result::Err(eprintln!("{} {}", Colour::Red.paint("could not create the output folder"), route.display()))
         }
     }
 
-    pub fn get_json_constraints(matches: &ArgMatches) -> bool {
-        matches.is_present("print_json_c")
+    pub fn get_outputs(settings: &TargetSettings) -> Result<Vec<&'static str>, ()> {
+        let mut outputs = vec![];
+        for output in settings.outputs.iter().flatten() {
+            match OUTPUTS.iter().find(|(name, _)| name == output) {
+                Some((name, _)) => outputs.push(*name),
+                None => {
+                    eprintln!("{} {}", Colour::Red.paint("invalid output"), output);
+                    return Result::Err(());
+                }
+            }
+        }
+        Result::Ok(outputs)
     }
 
-    pub fn get_json_substitutions(matches: &ArgMatches) -> bool {
-        matches.is_present("print_json_sub")
+    // The manifest given with --manifest or the one in the current folder
+    pub fn get_manifest(matches: &ArgMatches) -> Result<Option<PathBuf>, ()> {
+        match matches.value_of("manifest") {
+            Some(file) => {
+                let route = Path::new(file).to_path_buf();
+                if route.is_file() {
+                    Result::Ok(Some(route))
+                } else {
+                    eprintln!("{}", Colour::Red.paint("Manifest file does not exist: ".to_owned() + file));
+                    Result::Err(())
+                }
+            }
+            None => {
+                let route = Path::new(MANIFEST_FILE).to_path_buf();
+                Result::Ok(if route.is_file() { Some(route) } else { None })
+            }
+        }
     }
 
-    pub fn get_sym(matches: &ArgMatches) -> bool {
-        matches.is_present("print_sym")
+    pub fn get_selected_targets(matches: &ArgMatches) -> Vec<String> {
+        match matches.values_of("target") {
+            Some(targets) => targets.map(String::from).collect(),
+            None => Vec::new(),
+        }
     }
 
-    pub fn get_r1cs(matches: &ArgMatches) -> bool {
-        matches.is_present("print_r1cs")
+    // Only the options actually written in the command line are returned, so
+    // that the default values of clap do not override the manifest
+    pub fn get_settings(matches: &ArgMatches) -> Result<TargetSettings, ()> {
+        let given = |name: &str| matches.occurrences_of(name) > 0;
+        let mut settings = TargetSettings::default();
+        if given("input") {
+            settings.main = Some(Path::new(matches.value_of("input").unwrap()).to_path_buf());
+        }
+        if given("output") {
+            settings.output = Some(Path::new(matches.value_of("output").unwrap()).to_path_buf());
+        }
+        if given("link_libraries") {
+            settings.libraries = Some(get_link_libraries(matches));
+        }
+        if given("prime") {
+            settings.prime = Some(matches.values_of("prime").unwrap().map(String::from).collect());
+        }
+        if given("no_simplification") {
+            settings.simplification = Some("O0".to_string());
+        } else if given("reduced_simplification") {
+            settings.simplification = Some("O1".to_string());
+        } else if given("simplification_rounds") {
+            let o_2_argument = matches.value_of("simplification_rounds").unwrap();
+            let rounds_r = usize::from_str_radix(o_2_argument, 10);
+            if let Result::Ok(no_rounds) = rounds_r {
+                settings.simplification = Some("O2".to_string());
+                settings.rounds = Some(no_rounds);
+            } else {
+                return Result::Err(eprintln!("{}", Colour::Red.paint("invalid number of rounds")));
+            }
+        } else if given("full_simplification") {
+            settings.simplification = Some("O2".to_string());
+        }
+        // The output flags replace the outputs of the manifest, they are not added to them
+        let outputs: Vec<String> = OUTPUTS
+            .iter()
+            .filter(|(_, flag)| given(flag))
+            .map(|(name, _)| name.to_string())
+            .collect();
+        if !outputs.is_empty() {
+            settings.outputs = Some(outputs);
+        }
+        if given("flag_verbose") {
+            settings.verbose = Some(true);
+        }
+        if given("inspect_constraints") {
+            settings.inspect = Some(true);
+        }
+        if given("flag_old_heuristics") {
+            settings.old_heuristics = Some(true);
+        }
+        if given("c_portable") {
+            settings.c_portable = Some(true);
+        }
+        if given("memory_budget") {
+            let budget = matches.value_of("memory_budget").unwrap();
+            match budget.parse::<usize>() {
+                Result::Ok(budget) => settings.memory_budget = Some(budget),
+                Result::Err(_) => {
+                    return Result::Err(eprintln!("{}", Colour::Red.paint("invalid memory budget")));
+                }
+            }
+        }
+        if given("witness_optimization") {
+            let level = matches.value_of("witness_optimization").unwrap();
+            match usize::from_str_radix(level, 10) {
+                Result::Ok(level) => settings.witness_optimization = Some(level),
+                Result::Err(_) => {
+                    return Result::Err(eprintln!("{}", Colour::Red.paint("invalid witness optimization level")));
+                }
+            }
+        }
+        Result::Ok(settings)
     }
 
-    pub fn get_wasm(matches: &ArgMatches) -> bool {
-        matches.is_present("print_wasm")
+    #[derive(Copy, Clone, Eq, PartialEq)]
+    pub enum SimplificationStyle { O0, O1, O2(usize) }
+    pub fn get_simplification_style(settings: &TargetSettings) -> Result<SimplificationStyle, ()> {
+        match (settings.simplification.as_deref(), settings.rounds) {
+            (Some("O0"), _) => Ok(SimplificationStyle::O0),
+            (Some("O1"), _) => Ok(SimplificationStyle::O1),
+            (None, Some(0)) | (Some("O2"), Some(0)) => Ok(SimplificationStyle::O1),
+            (None, Some(no_rounds)) | (Some("O2"), Some(no_rounds)) => Ok(SimplificationStyle::O2(no_rounds)),
+            (None, None) | (Some("O2"), None) => Ok(SimplificationStyle::O2(usize::MAX)),
+            (Some(_), _) => Result::Err(eprintln!("{}", Colour::Red.paint("invalid simplification level"))),
+        }
     }
 
-    pub fn get_wat(matches: &ArgMatches) -> bool {
-        matches.is_present("print_wat")
+    pub fn get_witness_optimization(settings: &TargetSettings) -> Result<OptimizationLevel, ()> {
+        match settings.witness_optimization {
+            None => Ok(OptimizationLevel::default()),
+            Some(0) => Ok(OptimizationLevel::O0),
+            Some(1) => Ok(OptimizationLevel::O1),
+            Some(2) => Ok(OptimizationLevel::O2),
+            Some(_) => Result::Err(eprintln!("{}", Colour::Red.paint("invalid witness optimization level"))),
+        }
     }
 
-    pub fn get_c(matches: &ArgMatches) -> bool {
-        matches.is_present("print_c")
+    pub fn get_json_substitutions(matches: &ArgMatches) -> bool {
+        matches.is_present("print_json_sub")
+    }
+
+    pub fn get_explain(matches: &ArgMatches) -> Vec<String> {
+        matches.values_of("explain").into_iter().flatten().map(String::from).collect()
     }
 
     pub fn get_main_inputs_log(matches: &ArgMatches) -> bool {
@@ -299,39 +640,74 @@ mod input_processing {
     pub fn get_ir(matches: &ArgMatches) -> bool {
         matches.is_present("print_ir")
     }
-    pub fn get_inspect_constraints(matches: &ArgMatches) -> bool {
-        matches.is_present("inspect_constraints")
-    }
 
-    pub fn get_flag_verbose(matches: &ArgMatches) -> bool {
-        matches.is_present("flag_verbose")
+    pub fn get_verify_ir(matches: &ArgMatches) -> bool {
+        matches.is_present("verify_ir")
     }
 
-    pub fn get_flag_old_heuristics(matches: &ArgMatches) -> bool {
-        matches.is_present("flag_old_heuristics")
-    }
-    pub fn get_prime(matches: &ArgMatches) -> Result<String, ()> {
-        
-        match matches.is_present("prime"){
-            true => 
-               {
-                   let prime_value = matches.value_of("prime").unwrap();
-                   if prime_value == "bn128"
-                      || prime_value == "bls12381"
-                      || prime_value == "goldilocks"
-                      || prime_value == "grumpkin"
-                      || prime_value == "pallas"
-                      || prime_value == "vesta"
-                      {
-                        Ok(String::from(matches.value_of("prime").unwrap()))
-                    }
-                    else{
-                        Result::Err(eprintln!("{}", Colour::Red.paint("invalid prime number")))
+
+    // The primes of a target without repetitions, a prime given both by name
+    // and by value counts once
+    pub fn get_primes(settings: &TargetSettings) -> Result<Vec<String>, ()> {
+        let primes = match &settings.prime {
+            Some(primes) => primes.clone(),
+            None => vec![String::from("bn128")],
+        };
+        if primes.is_empty() {
+            return Result::Err(eprintln!("{}", Colour::Red.paint("no prime was given")));
+        }
+        let mut values = Vec::new();
+        let mut selected = Vec::new();
+        for prime in primes {
+            match parse_prime(&prime) {
+                Ok(value) => {
+                    if !values.contains(&value) {
+                        values.push(value);
+                        selected.push(prime);
                     }
-               }
-               
-            false => Ok(String::from("bn128")),
+                }
+                Err(reason) => {
+                    return Result::Err(eprintln!("{}", Colour::Red.paint(format!("invalid prime number: {}", reason))));
+                }
+            }
         }
+        Ok(selected)
+    }
+
+    // Options of the simplification, shared by the compilation and by the
+    // simplify subcommand
+    fn simplification_args() -> Vec<Arg<'static, 'static>> {
+        vec![
+            Arg::with_name("reduced_simplification")
+                .long("O1")
+                .hidden(false)
+                .takes_value(false)
+                .help("Only applies var to var and var to constant simplification")
+                .display_order(460),
+            Arg::with_name("full_simplification")
+                .long("O2")
+                .takes_value(false)
+                .hidden(false)
+                .help("Full constraint simplification")
+                .display_order(480),
+            Arg::with_name("simplification_rounds")
+                .long("O2round")
+                .takes_value(true)
+                .hidden(false)
+                .help("Maximum number of rounds of the simplification process")
+                .display_order(500),
+            Arg::with_name("memory_budget")
+                .long("memory-budget")
+                .takes_value(true)
+                .value_name("MB")
+                .display_order(505)
+                .help("Keeps the non-linear constraints in a temporary file during the simplification, with at most this many megabytes of them in memory"),
+            Arg::with_name("flag_old_heuristics")
+                .long("use_old_simplification_heuristics")
+                .takes_value(false)
+                .display_order(980)
+                .help("Applies the old version of the heuristics when performing linear simplification"),
+        ]
     }
 
     pub fn view() -> ArgMatches<'static> {
@@ -353,29 +729,21 @@ mod input_processing {
                     .help("No simplification is applied")
                     .display_order(420)
             )
+            .args(&simplification_args())
             .arg(
-                Arg::with_name("reduced_simplification")
-                    .long("O1")
-                    .hidden(false)
-                    .takes_value(false)
-                    .help("Only applies var to var and var to constant simplification")
-                    .display_order(460)
+                Arg::with_name("witness_optimization")
+                    .long("witness_opt")
+                    .takes_value(true)
+                    .default_value("1")
+                    .display_order(510)
+                    .help("Optimization level of the code of the witness calculator (0, 1 or 2)")
             )
             .arg(
-                Arg::with_name("full_simplification")
-                    .long("O2")
+                Arg::with_name("verify_ir")
+                    .long("verify_ir")
                     .takes_value(false)
-                    .hidden(false)
-                    .help("Full constraint simplification")
-                    .display_order(480)
-            )
-            .arg(
-                Arg::with_name("simplification_rounds")
-                    .long("O2round")
-                    .takes_value(true)
-                    .hidden(false)
-                    .help("Maximum number of rounds of the simplification process")
-                    .display_order(500)
+                    .display_order(520)
+                    .help("Checks that the code of the witness calculator is well formed after every optimization")
             )
             .arg(
                 Arg::with_name("output")
@@ -416,6 +784,30 @@ mod input_processing {
                     .display_order(100)
                     .help("Outputs the substitution in json format"),
             )
+            .arg(
+                Arg::with_name("explain")
+                    .long("explain")
+                    .takes_value(true)
+                    .multiple(true)
+                    .number_of_values(1)
+                    .value_name("SIGNAL")
+                    .display_order(510)
+                    .help("Explains what the simplification did to the signal, given by its full name as in the sym file"),
+            )
+            .arg(
+                Arg::with_name("print_hierarchy")
+                    .long("hierarchy")
+                    .takes_value(false)
+                    .display_order(130)
+                    .help("Outputs the component hierarchy in dot and json format"),
+            )
+            .arg(
+                Arg::with_name("print_includes")
+                    .long("includes")
+                    .takes_value(false)
+                    .display_order(140)
+                    .help("Outputs the include graph and the origin of every template and function in dot and json format"),
+            )
             .arg(
                 Arg::with_name("print_sym")
                     .long("sym")
@@ -461,6 +853,13 @@ mod input_processing {
                     .display_order(150)
                     .help("Compiles the circuit to c"),
             )
+            .arg(
+                Arg::with_name("c_portable")
+                    .long("c-portable")
+                    .takes_value(false)
+                    .display_order(160)
+                    .help("Compiles the circuit to c with a field implementation in plain C++ instead of x86-64 assembly"),
+            )
             .arg(
                 Arg::with_name("parallel_simplification")
                     .long("parallel")
@@ -485,20 +884,85 @@ mod input_processing {
                     .help("Shows logs during compilation"),
             )
             .arg(
-                Arg::with_name("flag_old_heuristics")
-                    .long("use_old_simplification_heuristics")
-                    .takes_value(false)
-                    .display_order(980)
-                    .help("Applies the old version of the heuristics when performing linear simplification"),
+                Arg::with_name("manifest")
+                    .long("manifest")
+                    .takes_value(true)
+                    .display_order(2)
+                    .help("Path to the project manifest (by default ./circom.toml if it exists)"),
+            )
+            .arg(
+                Arg::with_name("target")
+                    .long("target")
+                    .takes_value(true)
+                    .multiple(true)
+                    .number_of_values(1)
+                    .display_order(3)
+                    .help("Target of the manifest to compile (by default all of them)"),
             )
             .arg (
                 Arg::with_name("prime")
                     .short("prime")
                     .long("prime")
                     .takes_value(true)
+                    .multiple(true)
+                    .require_delimiter(true)
                     .default_value("bn128")
                     .display_order(300)
-                    .help("To choose the prime number to use to generate the circuit. Receives the name of the curve (bn128, bls12381, goldilocks, grumpkin, pallas, vesta)"),
+                    .help("To choose the prime number to use to generate the circuit. Receives the name of the curve (bn128, bls12381, goldilocks, grumpkin, pallas, vesta) or the prime itself, in decimal or in hexadecimal with the 0x prefix. Several primes separated by commas compile the circuit for each of them in a subfolder of the output named after the prime"),
+            )
+            .subcommand(
+                SubCommand::with_name("simplify")
+                    .about("Simplifies the constraints of an r1cs file produced before, with --O2 unless --O1 is given")
+                    .arg(
+                        Arg::with_name("input")
+                            .required(true)
+                            .help("Path to the r1cs file"),
+                    )
+                    .arg(
+                        Arg::with_name("sym_file")
+                            .long("sym")
+                            .takes_value(true)
+                            .display_order(10)
+                            .help("Path to the sym file of the r1cs (by default the one next to it, if it exists)"),
+                    )
+                    .arg(
+                        Arg::with_name("output")
+                            .short("o")
+                            .long("output")
+                            .takes_value(true)
+                            .default_value(".")
+                            .display_order(1)
+                            .help("Path to the directory where the simplified r1cs and sym files will be written"),
+                    )
+                    .args(&simplification_args()),
+            )
+            .subcommand(
+                SubCommand::with_name("diff")
+                    .about("Compares the constraints of two r1cs files with the names of the sym files, fails if they differ")
+                    .arg(
+                        Arg::with_name("old")
+                            .required(true)
+                            .help("Path to the r1cs file to compare with"),
+                    )
+                    .arg(
+                        Arg::with_name("new")
+                            .required(true)
+                            .help("Path to the r1cs file to compare"),
+                    )
+                    .arg(
+                        Arg::with_name("old_sym")
+                            .long("old-sym")
+                            .takes_value(true)
+                            .display_order(10)
+                            .help("Path to the sym file of the old r1cs (by default the one next to it, if it exists)"),
+                    )
+                    .arg(
+                        Arg::with_name("new_sym")
+                            .long("new-sym")
+                            .takes_value(true)
+                            .display_order(20)
+                            .help("Path to the sym file of the new r1cs (by default the one next to it, if it exists)"),
+                    ),
             )
             .get_matches()
     }
@@ -0,0 +1,25 @@
+// Library interface of the compiler. It runs the same pipeline as the
+// command line (parsing, type analysis, constraint generation and code
+// generation) but returns the reports and the generated files instead of
+// printing and writing them.
+//
+//     let compilation = Compiler::new()
+//         .main("circuit.circom")
+//         .prime("bn128")
+//         .simplification(Simplification::Full(usize::MAX))
+//         .r1cs(true)
+//         .run();
+
+mod compiler_api;
+
+pub use compiler_api::{
+    wat_to_wasm, Artefact, Compilation, CompilationError, Compiler, Simplification, VERSION,
+};
+pub use compiler::compiler_interface::{OptimizationLevel, VCP};
+pub use constraint_generation::ConstraintSystem;
+pub use constraint_list::ConstraintList;
+pub use constraint_writers::ConstraintExporter;
+pub use dag::DAG;
+pub use program_structure::error_definition::{Report, ReportCollection};
+pub use program_structure::file_definition::FileLibrary;
+pub use program_structure::output_directory::{FileSystem, MemoryDirectory, OutputDirectory, OutputSink};
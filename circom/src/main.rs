@@ -1,7 +1,10 @@
 mod compilation_user;
+mod diff_user;
 mod execution_user;
 mod input_user;
+mod manifest_user;
 mod parser_user;
+mod simplification_user;
 mod type_analysis_user;
 
 const VERSION: &'static str = env!("CARGO_PKG_VERSION");
@@ -9,6 +12,7 @@ const VERSION: &'static str = env!("CARGO_PKG_VERSION");
 
 use ansi_term::Colour;
 use input_user::Input;
+use program_structure::program_archive::ProgramArchive;
 fn main() {
     let result = start();
     if result.is_err() {
@@ -21,19 +25,54 @@ fn main() {
 }
 
 fn start() -> Result<(), ()> {
-    use compilation_user::CompilerConfig;
-    use execution_user::ExecutionConfig;
-    let user_input = Input::new()?;
-    let mut program_archive = parser_user::parse_project(&user_input)?;
+    let matches = input_user::command_line();
+    if let Some(matches) = matches.subcommand_matches("simplify") {
+        let input = input_user::SimplificationInput::collect(matches)?;
+        return simplification_user::simplify(&input);
+    }
+    if let Some(matches) = matches.subcommand_matches("diff") {
+        let input = input_user::DiffInput::collect(matches)?;
+        return diff_user::diff(&input);
+    }
+    let user_inputs = Input::collect(&matches)?;
+    for target_inputs in &user_inputs {
+        if let Some(target) = target_inputs[0].target() {
+            println!("{} {}", Colour::Green.paint("Compiling target:"), target);
+        }
+        compile_target(target_inputs)?;
+    }
+    Result::Ok(())
+}
+
+// The inputs of a target only differ in the prime, so the program is parsed
+// and analysed once and then built for each of the primes
+fn compile_target(user_inputs: &[Input]) -> Result<(), ()> {
+    let mut program_archive = parser_user::parse_project(&user_inputs[0])?;
     type_analysis_user::analyse_project(&mut program_archive)?;
     // DEBUG: Only for benchmarking assignment analysis
     use program_structure::ast_shortcuts;
     use std::sync::atomic::{Ordering};
     let artificial_assig = ast_shortcuts::ARTIFICIAL_VAR_ASSIGNMENTS.load(Ordering::Relaxed);
     println!("TotalArtificial: {}", artificial_assig);
-    
+
+    if user_inputs.len() == 1 {
+        return compile_input(program_archive, &user_inputs[0]);
+    }
+    let primes: Vec<String> = user_inputs.iter().map(|input| input.prime()).collect();
+    type_analysis_user::analyse_prime_dependence(&program_archive, &primes);
+    for user_input in user_inputs {
+        println!("{} {}", Colour::Green.paint("Compiling for prime:"), user_input.prime());
+        compile_input(program_archive.clone(), user_input)?;
+    }
+    Result::Ok(())
+}
+
+fn compile_input(program_archive: ProgramArchive, user_input: &Input) -> Result<(), ()> {
+    use compilation_user::CompilerConfig;
+    use execution_user::ExecutionConfig;
     let config = ExecutionConfig {
         no_rounds: user_input.no_rounds(),
+        memory_budget: user_input.memory_budget(),
         flag_p: user_input.parallel_simplification_flag(),
         flag_s: user_input.reduced_simplification_flag(),
         flag_f: user_input.unsimplified_flag(),
@@ -43,17 +82,24 @@ fn start() -> Result<(), ()> {
         r1cs_flag: user_input.r1cs_flag(),
         json_constraint_flag: user_input.json_constraints_flag(),
         json_substitution_flag: user_input.json_substitutions_flag(),
+        explain: user_input.explain().to_vec(),
         sym_flag: user_input.sym_flag(),
         sym: user_input.sym_file().to_string(),
         r1cs: user_input.r1cs_file().to_string(),
         json_constraints: user_input.json_constraints_file().to_string(),
-        prime: user_input.prime(),        
+        json_substitutions: user_input.json_substitutions_file().to_string(),
+        prime: user_input.prime(),
+        hierarchy_flag: user_input.hierarchy_flag(),
+        hierarchy_dot: user_input.hierarchy_dot_file().to_string(),
+        hierarchy_json: user_input.hierarchy_json_file().to_string(),
     };
     let circuit = execution_user::execute_project(program_archive, config)?;
     let compilation_config = CompilerConfig {
         vcp: circuit,
         debug_output: user_input.print_ir_flag(),
+        verify_ir: user_input.verify_ir_flag(),
         c_flag: user_input.c_flag(),
+        c_portable_flag: user_input.c_portable_flag(),
         wasm_flag: user_input.wasm_flag(),
         wat_flag: user_input.wat_flag(),
 	    js_folder: user_input.js_folder().to_string(),
@@ -65,6 +111,7 @@ fn start() -> Result<(), ()> {
         wat_file: user_input.wat_file().to_string(),
         wasm_file: user_input.wasm_file().to_string(),
         produce_input_log: user_input.main_inputs_flag(),
+        witness_optimization: user_input.witness_optimization(),
     };
     compilation_user::compile(compilation_config)?;
     Result::Ok(())
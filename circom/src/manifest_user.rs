@@ -0,0 +1,175 @@
+use ansi_term::Colour;
+use serde::{Deserialize, Deserializer};
+use serde_derive::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const MANIFEST_FILE: &str = "circom.toml";
+
+// Every field is optional so that a section only overrides the values it
+// contains: a target overrides [project] and the command line overrides both.
+// Lists are overridden as a whole, so the outputs of a target or the output
+// flags of the command line replace the outputs given before.
+#[derive(Deserialize, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct TargetSettings {
+    pub main: Option<PathBuf>,
+    pub output: Option<PathBuf>,
+    pub libraries: Option<Vec<PathBuf>>,
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub prime: Option<Vec<String>>,
+    pub simplification: Option<String>,
+    pub rounds: Option<usize>,
+    pub outputs: Option<Vec<String>>,
+    pub verbose: Option<bool>,
+    pub inspect: Option<bool>,
+    pub old_heuristics: Option<bool>,
+    pub witness_optimization: Option<usize>,
+    pub c_portable: Option<bool>,
+    pub memory_budget: Option<usize>,
+}
+
+// The prime of a target is a single value or a list of them
+fn one_or_many<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Vec<String>>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+    match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(prime) => Ok(Some(vec![prime])),
+        OneOrMany::Many(primes) => Ok(Some(primes)),
+    }
+}
+
+impl TargetSettings {
+    pub fn override_with(&self, other: &TargetSettings) -> TargetSettings {
+        TargetSettings {
+            main: other.main.clone().or_else(|| self.main.clone()),
+            output: other.output.clone().or_else(|| self.output.clone()),
+            libraries: other.libraries.clone().or_else(|| self.libraries.clone()),
+            prime: other.prime.clone().or_else(|| self.prime.clone()),
+            simplification: other.simplification.clone().or_else(|| self.simplification.clone()),
+            rounds: other.rounds.or(self.rounds),
+            outputs: other.outputs.clone().or_else(|| self.outputs.clone()),
+            verbose: other.verbose.or(self.verbose),
+            inspect: other.inspect.or(self.inspect),
+            old_heuristics: other.old_heuristics.or(self.old_heuristics),
+            witness_optimization: other.witness_optimization.or(self.witness_optimization),
+            c_portable: other.c_portable.or(self.c_portable),
+            memory_budget: other.memory_budget.or(self.memory_budget),
+        }
+    }
+
+    // Paths of the manifest are relative to the folder that contains it
+    fn make_relative_to(&mut self, folder: &Path) {
+        let join = |path: &PathBuf| folder.join(path);
+        self.main = self.main.as_ref().map(join);
+        self.output = self.output.as_ref().map(join);
+        self.libraries = self.libraries.as_ref().map(|libs| libs.iter().map(join).collect());
+    }
+}
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+    #[serde(default)]
+    pub project: TargetSettings,
+    #[serde(default)]
+    pub targets: BTreeMap<String, TargetSettings>,
+}
+
+impl Manifest {
+    pub fn load(file: &Path) -> Result<Manifest, ()> {
+        let contents = fs::read_to_string(file).map_err(|_err| {
+            eprintln!("{} {}", Colour::Red.paint("Could not read the manifest"), file.display())
+        })?;
+        let mut manifest: Manifest = toml::from_str(&contents).map_err(|err| {
+            eprintln!("{} {}: {}", Colour::Red.paint("Invalid manifest"), file.display(), err)
+        })?;
+        let folder = file.parent().unwrap_or(Path::new("."));
+        manifest.project.make_relative_to(folder);
+        for target in manifest.targets.values_mut() {
+            target.make_relative_to(folder);
+        }
+        Ok(manifest)
+    }
+
+    // Returns the settings of the selected targets (all of them if none is
+    // selected) after applying the [project] defaults. A manifest without
+    // targets describes a single unnamed one, and so does an input file given
+    // in the command line when no target is selected: it is compiled once with
+    // the [project] settings instead of once for each target.
+    pub fn get_targets(
+        &self,
+        selected: &[String],
+        input_given: bool,
+    ) -> Result<Vec<(Option<String>, TargetSettings)>, ()> {
+        if self.targets.is_empty() || (input_given && selected.is_empty()) {
+            if !selected.is_empty() {
+                eprintln!("{}", Colour::Red.paint("The manifest does not declare any target"));
+                return Result::Err(());
+            }
+            return Ok(vec![(None, self.project.clone())]);
+        }
+        let mut targets = vec![];
+        for name in selected {
+            if !self.targets.contains_key(name) {
+                eprintln!("{} {}", Colour::Red.paint("Unknown target in the manifest:"), name);
+                return Result::Err(());
+            }
+        }
+        for (name, settings) in &self.targets {
+            if selected.is_empty() || selected.contains(name) {
+                targets.push((Some(name.clone()), self.project.override_with(settings)));
+            }
+        }
+        Ok(targets)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MANIFEST: &str = "
+        [project]
+        output = \"build\"
+        prime = \"bn128\"
+
+        [targets.multiplier]
+        main = \"circuits/multiplier.circom\"
+
+        [targets.hasher]
+        main = \"circuits/hasher.circom\"
+        prime = \"goldilocks\"
+    ";
+
+    fn names(targets: &[(Option<String>, TargetSettings)]) -> Vec<Option<&str>> {
+        targets.iter().map(|(name, _)| name.as_deref()).collect()
+    }
+
+    #[test]
+    fn every_target_is_compiled_by_default() {
+        let manifest: Manifest = toml::from_str(MANIFEST).unwrap();
+        let targets = manifest.get_targets(&[], false).unwrap();
+        assert_eq!(names(&targets), vec![Some("hasher"), Some("multiplier")]);
+        assert_eq!(targets[0].1.prime, Some(vec!["goldilocks".to_string()]));
+    }
+
+    #[test]
+    fn an_input_in_the_command_line_ignores_the_targets() {
+        let manifest: Manifest = toml::from_str(MANIFEST).unwrap();
+        let targets = manifest.get_targets(&[], true).unwrap();
+        assert_eq!(names(&targets), vec![None]);
+        assert_eq!(targets[0].1.main, None);
+        assert_eq!(targets[0].1.output, Some(PathBuf::from("build")));
+        assert_eq!(targets[0].1.prime, Some(vec!["bn128".to_string()]));
+
+        // Unless the targets are selected
+        let targets = manifest.get_targets(&["hasher".to_string()], true).unwrap();
+        assert_eq!(names(&targets), vec![Some("hasher")]);
+    }
+}
@@ -5,8 +5,23 @@ use crate::VERSION;
 
 
 pub fn parse_project(input_info: &Input) -> Result<ProgramArchive, ()> {
+    use ansi_term::Colour;
+    use parser::IncludesOutput;
     let initial_file = input_info.input_file().to_string();
-    let result_program_archive = parser::run_parser(initial_file, VERSION, input_info.get_link_libraries().to_vec());
+    let includes_output = if input_info.includes_flag() {
+        Option::Some(IncludesOutput {
+            dot: input_info.includes_dot_file().to_string(),
+            json: input_info.includes_json_file().to_string(),
+        })
+    } else {
+        Option::None
+    };
+    let result_program_archive = parser::run_parser(
+        initial_file,
+        VERSION,
+        input_info.get_link_libraries().to_vec(),
+        includes_output,
+    );
     match result_program_archive {
         Result::Err((file_library, report_collection)) => {
             Report::print_reports(&report_collection, &file_library);
@@ -14,6 +29,14 @@ pub fn parse_project(input_info: &Input) -> Result<ProgramArchive, ()> {
         }
         Result::Ok((program_archive, warnings)) => {
             Report::print_reports(&warnings, &program_archive.file_library);
+            if input_info.includes_flag() {
+                println!(
+                    "{} {} and {}",
+                    Colour::Green.paint("Includes graph written in:"),
+                    input_info.includes_dot_file(),
+                    input_info.includes_json_file()
+                );
+            }
             Result::Ok(program_archive)
         }
     }
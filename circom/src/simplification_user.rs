@@ -0,0 +1,64 @@
+use super::input_user::SimplificationInput;
+use ansi_term::Colour;
+use constraint_list::{storage_error_report, Simplifier};
+use constraint_writers::log_writer::Log;
+use constraint_writers::r1cs_reader::read_r1cs_file;
+use constraint_writers::sym_reader::read_sym_file;
+use constraint_writers::sym_writer::{SymElem, SymFile};
+use constraint_writers::ConstraintExporter;
+use program_structure::error_definition::Report;
+use program_structure::file_definition::FileLibrary;
+use program_structure::output_directory::{FileSystem, OutputDirectory};
+use program_structure::output_error::OutputError;
+
+// Simplifies the constraints of an r1cs file as the compilation does. The
+// substitutions applied are written in <file>_simplified_substitutions.json, as
+// with --jsons, in terms of the labels of the signals (the first column of the sym file).
+pub fn simplify(input: &SimplificationInput) -> Result<(), ()> {
+    let print_error = |report: Report| Report::print_reports(&[report], &FileLibrary::new());
+    let r1cs_file = input.input_r1cs.to_string_lossy().to_string();
+    let r1cs = read_r1cs_file(&r1cs_file).map_err(|err| print_error(err.into_report()))?;
+    let symbols = match &input.input_sym {
+        Some(sym_file) => {
+            let sym_file = sym_file.to_string_lossy().to_string();
+            Some(read_sym_file(&sym_file).map_err(|err| print_error(err.into_report()))?)
+        }
+        None => {
+            println!("{}", Colour::Yellow.paint("No sym file was found, only the r1cs file will be written"));
+            None
+        }
+    };
+    let custom_gates = r1cs.custom_gates_used.is_some();
+
+    let mut simplifier = Simplifier::from_r1cs(&r1cs);
+    simplifier.no_rounds = input.no_rounds;
+    simplifier.flag_s = input.reduced_simplification_flag;
+    simplifier.flag_old_heuristics = input.flag_old_heuristics;
+    simplifier.memory_budget = input.memory_budget;
+    let out_substitutions = input.out_substitutions.to_string_lossy().to_string();
+    simplifier.port_substitution = Some(out_substitutions.clone());
+    drop(r1cs);
+    let list = simplifier.simplify_constraints().map_err(|err| print_error(storage_error_report(err)))?;
+
+    let out_r1cs = input.out_r1cs.to_string_lossy().to_string();
+    let log = list.r1cs(&mut FileSystem, &out_r1cs, custom_gates).map_err(|err| print_error(err.into_report()))?;
+    Log::print(&log);
+    println!("{} {}", Colour::Green.paint("Written successfully:"), out_r1cs);
+    if let Some(symbols) = symbols {
+        let out_sym = input.out_sym.to_string_lossy().to_string();
+        write_symbols(&out_sym, list.port_symbols(&symbols)).map_err(|err| print_error(err.into_report()))?;
+        println!("{} {}", Colour::Green.paint("Written successfully:"), out_sym);
+    }
+    println!("{} {}", Colour::Green.paint("Substitutions written in:"), out_substitutions);
+    Result::Ok(())
+}
+
+fn write_symbols(file: &str, symbols: Vec<SymElem>) -> Result<(), OutputError> {
+    let mut file_system = FileSystem;
+    let writer = file_system.create_file(file).map_err(|err| OutputError::new(file, err))?;
+    let mut sym = SymFile::new(writer, file);
+    for elem in symbols {
+        SymFile::write_sym_elem(&mut sym, elem)?;
+    }
+    SymFile::finish_writing(sym)
+}
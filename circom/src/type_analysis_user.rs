@@ -1,6 +1,6 @@
 use program_structure::error_definition::Report;
 use program_structure::program_archive::ProgramArchive;
-use type_analysis::check_types::check_types;
+use type_analysis::check_types::{check_prime_dependence, check_types};
 
 pub fn analyse_project(program_archive: &mut ProgramArchive) -> Result<(), ()> {
     let analysis_result = check_types(program_archive);
@@ -15,3 +15,8 @@ pub fn analyse_project(program_archive: &mut ProgramArchive) -> Result<(), ()> {
         }
     }
 }
+
+pub fn analyse_prime_dependence(program_archive: &ProgramArchive, primes: &[String]) {
+    let warns = check_prime_dependence(program_archive, primes);
+    Report::print_reports(&warns, program_archive.get_file_library());
+}
@@ -1,7 +1,10 @@
 use super::modular_arithmetic;
 pub use super::modular_arithmetic::ArithmeticError;
+use crate::field::{Field, FieldElement};
+use crate::linear_combination::LinearCombination;
 use num_bigint::BigInt;
-use num_traits::{ToPrimitive, Zero};
+use num_traits::ToPrimitive;
+use std::cmp::Ordering;
 use std::collections::{HashMap, HashSet, BTreeSet};
 use std::fmt::{Display, Formatter};
 use std::hash::Hash;
@@ -20,19 +23,19 @@ where
         // Represents the expression: c1*s1 + .. + cn*sn + C
         // where c1..cn are integers modulo a prime and
         // s1..sn are signals. C is a constant value
-        coefficients: HashMap<C, BigInt>,
+        coefficients: LinearCombination<C>,
     },
     Quadratic {
         // Is a quadratic expression of the form:
         //              a*b + c
         // Where a,b and c are linear expression
-        a: HashMap<C, BigInt>,
-        b: HashMap<C, BigInt>,
-        c: HashMap<C, BigInt>,
+        a: LinearCombination<C>,
+        b: LinearCombination<C>,
+        c: LinearCombination<C>,
     },
     NonQuadratic,
 }
-impl<C: Default + Clone + Display + Hash + Eq> Display for ArithmeticExpression<C> {
+impl<C: Default + Clone + Display + Hash + Ord> Display for ArithmeticExpression<C> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         use ArithmeticExpression::*;
         let msg = match self {
@@ -51,7 +54,7 @@ impl<C: Default + Clone + Display + Hash + Eq> Display for ArithmeticExpression<
     }
 }
 
-impl<C: Default + Clone + Display + Hash + Eq> Clone for ArithmeticExpression<C> {
+impl<C: Default + Clone + Display + Hash + Ord> Clone for ArithmeticExpression<C> {
     fn clone(&self) -> Self {
         use ArithmeticExpression::*;
         match self {
@@ -64,8 +67,8 @@ impl<C: Default + Clone + Display + Hash + Eq> Clone for ArithmeticExpression<C>
     }
 }
 
-impl<C: Default + Clone + Display + Hash + Eq> Eq for ArithmeticExpression<C> {}
-impl<C: Default + Clone + Display + Hash + Eq> PartialEq for ArithmeticExpression<C> {
+impl<C: Default + Clone + Display + Hash + Ord> Eq for ArithmeticExpression<C> {}
+impl<C: Default + Clone + Display + Hash + Ord> PartialEq for ArithmeticExpression<C> {
     fn eq(&self, other: &Self) -> bool {
         use ArithmeticExpression::*;
         match (self, other) {
@@ -80,27 +83,27 @@ impl<C: Default + Clone + Display + Hash + Eq> PartialEq for ArithmeticExpressio
     }
 }
 
-impl<C: Default + Clone + Display + Hash + Eq> Default for ArithmeticExpression<C> {
+impl<C: Default + Clone + Display + Hash + Ord> Default for ArithmeticExpression<C> {
     fn default() -> Self {
         ArithmeticExpression::NonQuadratic
     }
 }
 
-impl<C: Default + Clone + Display + Hash + Eq> ArithmeticExpression<C> {
+impl<C: Default + Clone + Display + Hash + Ord> ArithmeticExpression<C> {
     pub fn new() -> ArithmeticExpression<C> {
         ArithmeticExpression::default()
     }
 
     // printing utils
-    fn string_from_coefficients(coefficients: &HashMap<C, BigInt>) -> String {
+    fn string_from_coefficients(coefficients: &LinearCombination<C>) -> String {
         let mut string_coefficients = "".to_string();
         for (signal, value) in coefficients {
             let component_string = if value.is_zero() {
                 "".to_string()
             } else if signal.eq(&ArithmeticExpression::constant_coefficient()) {
-                format!("{}+", value.to_str_radix(10))
+                format!("{}+", value)
             } else {
-                format!("{}*{}+", signal, value.to_str_radix(10))
+                format!("{}*{}+", signal, value)
             };
             string_coefficients.push_str(component_string.as_str());
         }
@@ -112,12 +115,12 @@ impl<C: Default + Clone + Display + Hash + Eq> ArithmeticExpression<C> {
     // transforms constraints into a constraint, None if the expression was non-quadratic
     pub fn transform_expression_to_constraint_form(
         arithmetic_expression: ArithmeticExpression<C>,
-        field: &BigInt,
+        field: &Field,
     ) -> Option<Constraint<C>> {
         use ArithmeticExpression::*;
-        let mut a = HashMap::new();
-        let mut b = HashMap::new();
-        let mut c = HashMap::new();
+        let mut a = LinearCombination::new();
+        let mut b = LinearCombination::new();
+        let mut c = LinearCombination::new();
         ArithmeticExpression::initialize_hashmap_for_expression(&mut a);
         ArithmeticExpression::initialize_hashmap_for_expression(&mut b);
         ArithmeticExpression::initialize_hashmap_for_expression(&mut c);
@@ -131,16 +134,16 @@ impl<C: Default + Clone + Display + Hash + Eq> ArithmeticExpression<C> {
                 c = old_c;
             }
             Number { value } => {
-                c.insert(ArithmeticExpression::constant_coefficient(), value);
+                c.insert(ArithmeticExpression::constant_coefficient(), field.from_bigint(&value));
             }
             Signal { symbol } => {
-                c.insert(symbol, BigInt::from(1));
+                c.insert(symbol, field.one());
             }
             Linear { coefficients } => {
                 c = coefficients;
             }
         }
-        ArithmeticExpression::multiply_coefficients_by_constant(&BigInt::from(-1), &mut c, field);
+        ArithmeticExpression::negate_coefficients(&mut c, field);
         Option::Some(Constraint::new(a, b, c))
     }
 
@@ -155,90 +158,68 @@ impl<C: Default + Clone + Display + Hash + Eq> ArithmeticExpression<C> {
     fn constant_coefficient() -> C {
         C::default()
     }
-    fn initialize_hashmap_for_expression(initial: &mut HashMap<C, BigInt>) {
-        initial
-            .entry(ArithmeticExpression::constant_coefficient())
-            .or_insert_with(|| BigInt::from(0));
+    fn initialize_hashmap_for_expression(initial: &mut LinearCombination<C>) {
+        initial.coefficient_mut(&ArithmeticExpression::constant_coefficient());
         debug_assert!(ArithmeticExpression::valid_hashmap_for_expression(initial));
     }
-    fn valid_hashmap_for_expression(h: &HashMap<C, BigInt>) -> bool {
+    fn valid_hashmap_for_expression(h: &LinearCombination<C>) -> bool {
         let cc = ArithmeticExpression::constant_coefficient();
         h.contains_key(&cc)
     }
-    fn initialize_symbol_in_coefficients(symbol: &C, coefficients: &mut HashMap<C, BigInt>) {
-        debug_assert!(ArithmeticExpression::valid_hashmap_for_expression(coefficients));
-        if !coefficients.contains_key(symbol) {
-            coefficients.insert(symbol.clone(), BigInt::from(0));
-        }
-        debug_assert!(ArithmeticExpression::valid_hashmap_for_expression(coefficients));
-    }
     fn add_constant_to_coefficients(
         value: &BigInt,
-        coefficients: &mut HashMap<C, BigInt>,
-        field: &BigInt,
+        coefficients: &mut LinearCombination<C>,
+        field: &Field,
     ) {
         debug_assert!(ArithmeticExpression::valid_hashmap_for_expression(coefficients));
         let cc: C = ArithmeticExpression::constant_coefficient();
-        coefficients.insert(
-            cc.clone(),
-            modular_arithmetic::add(coefficients.get(&cc).unwrap(), value, field),
-        );
+        let value = field.from_bigint(value);
+        ArithmeticExpression::add_symbol_to_coefficients(&cc, &value, coefficients, field);
         debug_assert!(ArithmeticExpression::valid_hashmap_for_expression(coefficients));
     }
     fn add_symbol_to_coefficients(
         symbol: &C,
-        coefficient: &BigInt,
-        coefficients: &mut HashMap<C, BigInt>,
-        field: &BigInt,
+        coefficient: &FieldElement,
+        coefficients: &mut LinearCombination<C>,
+        field: &Field,
     ) {
         debug_assert!(ArithmeticExpression::valid_hashmap_for_expression(coefficients));
-        ArithmeticExpression::initialize_symbol_in_coefficients(symbol, coefficients);
-        coefficients.insert(
-            symbol.clone(),
-            modular_arithmetic::add(coefficients.get(symbol).unwrap(), coefficient, field),
-        );
+        let value = coefficients.coefficient_mut(symbol);
+        *value = field.add(value, coefficient);
         debug_assert!(ArithmeticExpression::valid_hashmap_for_expression(coefficients));
     }
     fn add_coefficients_to_coefficients(
-        coefficients_0: &HashMap<C, BigInt>,
-        coefficients_1: &mut HashMap<C, BigInt>,
-        field: &BigInt,
+        coefficients_0: &LinearCombination<C>,
+        coefficients_1: &mut LinearCombination<C>,
+        field: &Field,
     ) {
         debug_assert!(ArithmeticExpression::valid_hashmap_for_expression(coefficients_0));
         debug_assert!(ArithmeticExpression::valid_hashmap_for_expression(coefficients_1));
-        for (symbol, coefficient) in coefficients_0 {
-            ArithmeticExpression::add_symbol_to_coefficients(
-                symbol,
-                coefficient,
-                coefficients_1,
-                field,
-            );
-        }
+        coefficients_1.add(coefficients_0, field);
         debug_assert!(ArithmeticExpression::valid_hashmap_for_expression(coefficients_0));
         debug_assert!(ArithmeticExpression::valid_hashmap_for_expression(coefficients_1));
     }
     fn multiply_coefficients_by_constant(
-        constant: &BigInt,
-        coefficients: &mut HashMap<C, BigInt>,
-        field: &BigInt,
+        constant: &FieldElement,
+        coefficients: &mut LinearCombination<C>,
+        field: &Field,
     ) {
         debug_assert!(ArithmeticExpression::valid_hashmap_for_expression(coefficients));
-        for value in coefficients.values_mut() {
-            *value = modular_arithmetic::mul(value, constant, field);
-        }
+        coefficients.scale(constant, field);
+        debug_assert!(ArithmeticExpression::valid_hashmap_for_expression(coefficients));
+    }
+    fn negate_coefficients(coefficients: &mut LinearCombination<C>, field: &Field) {
+        debug_assert!(ArithmeticExpression::valid_hashmap_for_expression(coefficients));
+        coefficients.negate(field);
         debug_assert!(ArithmeticExpression::valid_hashmap_for_expression(coefficients));
     }
     fn divide_coefficients_by_constant(
-        constant: &BigInt,
-        coefficients: &mut HashMap<C, BigInt>,
-        field: &BigInt,
+        constant: &FieldElement,
+        coefficients: &mut LinearCombination<C>,
+        field: &Field,
     ) -> Result<(), ArithmeticError> {
         debug_assert!(ArithmeticExpression::valid_hashmap_for_expression(coefficients));
-        let inverse_constant = modular_arithmetic::div(
-            &BigInt::from(1),
-            constant,
-            &field
-        )?;
+        let inverse_constant = field.inverse(constant).ok_or(ArithmeticError::DivisionByZero)?;
         ArithmeticExpression::multiply_coefficients_by_constant(&inverse_constant, coefficients, field);
         debug_assert!(ArithmeticExpression::valid_hashmap_for_expression(coefficients));
         Result::Ok(())
@@ -247,7 +228,7 @@ impl<C: Default + Clone + Display + Hash + Eq> ArithmeticExpression<C> {
     pub fn add(
         left: &ArithmeticExpression<C>,
         right: &ArithmeticExpression<C>,
-        field: &BigInt,
+        field: &Field,
     ) -> ArithmeticExpression<C> {
         use ArithmeticExpression::*;
         match (left, right) {
@@ -255,15 +236,15 @@ impl<C: Default + Clone + Display + Hash + Eq> ArithmeticExpression<C> {
                 NonQuadratic
             }
             (Number { value: v_0 }, Number { value: v_1 }) => {
-                Number { value: modular_arithmetic::add(v_0, v_1, field) }
+                Number { value: modular_arithmetic::add(v_0, v_1, field.prime()) }
             }
             (Number { value }, Signal { symbol }) | (Signal { symbol }, Number { value }) => {
-                let mut coefficients = HashMap::new();
+                let mut coefficients = LinearCombination::new();
                 ArithmeticExpression::initialize_hashmap_for_expression(&mut coefficients);
                 ArithmeticExpression::add_constant_to_coefficients(value, &mut coefficients, field);
                 ArithmeticExpression::add_symbol_to_coefficients(
                     symbol,
-                    &BigInt::from(1),
+                    &field.one(),
                     &mut coefficients,
                     field,
                 );
@@ -286,17 +267,17 @@ impl<C: Default + Clone + Display + Hash + Eq> ArithmeticExpression<C> {
                 Quadratic { a: a.clone(), b: b.clone(), c: n_c }
             }
             (Signal { symbol: symbol_0 }, Signal { symbol: symbol_1 }) => {
-                let mut coefficients = HashMap::new();
+                let mut coefficients = LinearCombination::new();
                 ArithmeticExpression::initialize_hashmap_for_expression(&mut coefficients);
                 ArithmeticExpression::add_symbol_to_coefficients(
                     symbol_0,
-                    &BigInt::from(1),
+                    &field.one(),
                     &mut coefficients,
                     field,
                 );
                 ArithmeticExpression::add_symbol_to_coefficients(
                     symbol_1,
-                    &BigInt::from(1),
+                    &field.one(),
                     &mut coefficients,
                     field,
                 );
@@ -307,7 +288,7 @@ impl<C: Default + Clone + Display + Hash + Eq> ArithmeticExpression<C> {
                 let mut n_coefficients = coefficients.clone();
                 ArithmeticExpression::add_symbol_to_coefficients(
                     symbol,
-                    &BigInt::from(1),
+                    &field.one(),
                     &mut n_coefficients,
                     field,
                 );
@@ -318,7 +299,7 @@ impl<C: Default + Clone + Display + Hash + Eq> ArithmeticExpression<C> {
                 let mut coefficients = c.clone();
                 ArithmeticExpression::add_symbol_to_coefficients(
                     symbol,
-                    &BigInt::from(1),
+                    &field.one(),
                     &mut coefficients,
                     field,
                 );
@@ -349,7 +330,7 @@ impl<C: Default + Clone + Display + Hash + Eq> ArithmeticExpression<C> {
     pub fn mul(
         left: &ArithmeticExpression<C>,
         right: &ArithmeticExpression<C>,
-        field: &BigInt,
+        field: &Field,
     ) -> ArithmeticExpression<C> {
         use ArithmeticExpression::*;
         match (left, right) {
@@ -361,14 +342,14 @@ impl<C: Default + Clone + Display + Hash + Eq> ArithmeticExpression<C> {
             | (Quadratic { .. }, Signal { .. })
             | (Signal { .. }, Quadratic { .. }) => NonQuadratic,
             (Number { value: value_0 }, Number { value: value_1 }) => {
-                Number { value: modular_arithmetic::mul(value_0, value_1, field) }
+                Number { value: modular_arithmetic::mul(value_0, value_1, field.prime()) }
             }
             (Number { value }, Signal { symbol }) | (Signal { symbol }, Number { value }) => {
-                let mut coefficients = HashMap::new();
+                let mut coefficients = LinearCombination::new();
                 ArithmeticExpression::initialize_hashmap_for_expression(&mut coefficients);
                 ArithmeticExpression::add_symbol_to_coefficients(
                     symbol,
-                    value,
+                    &field.from_bigint(value),
                     &mut coefficients,
                     field,
                 );
@@ -378,7 +359,7 @@ impl<C: Default + Clone + Display + Hash + Eq> ArithmeticExpression<C> {
             | (Linear { coefficients }, Number { value }) => {
                 let mut n_coefficients = coefficients.clone();
                 ArithmeticExpression::multiply_coefficients_by_constant(
-                    value,
+                    &field.from_bigint(value),
                     &mut n_coefficients,
                     field,
                 );
@@ -389,26 +370,27 @@ impl<C: Default + Clone + Display + Hash + Eq> ArithmeticExpression<C> {
                 let mut n_a = a.clone();
                 let n_b = b.clone();
                 let mut n_c = c.clone();
-                ArithmeticExpression::multiply_coefficients_by_constant(value, &mut n_a, field);
-                ArithmeticExpression::multiply_coefficients_by_constant(value, &mut n_c, field);
+                let value = field.from_bigint(value);
+                ArithmeticExpression::multiply_coefficients_by_constant(&value, &mut n_a, field);
+                ArithmeticExpression::multiply_coefficients_by_constant(&value, &mut n_c, field);
                 Quadratic { a: n_a, b: n_b, c: n_c }
             }
             (Signal { symbol: symbol_0 }, Signal { symbol: symbol_1 }) => {
-                let mut a = HashMap::new();
-                let mut b = HashMap::new();
-                let mut c = HashMap::new();
+                let mut a = LinearCombination::new();
+                let mut b = LinearCombination::new();
+                let mut c = LinearCombination::new();
                 ArithmeticExpression::initialize_hashmap_for_expression(&mut a);
                 ArithmeticExpression::initialize_hashmap_for_expression(&mut b);
                 ArithmeticExpression::initialize_hashmap_for_expression(&mut c);
                 ArithmeticExpression::add_symbol_to_coefficients(
                     symbol_0,
-                    &BigInt::from(1),
+                    &field.one(),
                     &mut a,
                     field,
                 );
                 ArithmeticExpression::add_symbol_to_coefficients(
                     symbol_1,
-                    &BigInt::from(1),
+                    &field.one(),
                     &mut b,
                     field,
                 );
@@ -417,13 +399,13 @@ impl<C: Default + Clone + Display + Hash + Eq> ArithmeticExpression<C> {
             (Signal { symbol }, Linear { coefficients })
             | (Linear { coefficients }, Signal { symbol }) => {
                 let a = coefficients.clone();
-                let mut b = HashMap::new();
-                let mut c = HashMap::new();
+                let mut b = LinearCombination::new();
+                let mut c = LinearCombination::new();
                 ArithmeticExpression::initialize_hashmap_for_expression(&mut b);
                 ArithmeticExpression::initialize_hashmap_for_expression(&mut c);
                 ArithmeticExpression::add_symbol_to_coefficients(
                     symbol,
-                    &BigInt::from(1),
+                    &field.one(),
                     &mut b,
                     field,
                 );
@@ -432,7 +414,7 @@ impl<C: Default + Clone + Display + Hash + Eq> ArithmeticExpression<C> {
             (Linear { coefficients: coefficients_0 }, Linear { coefficients: coefficients_1 }) => {
                 let a = coefficients_0.clone();
                 let b = coefficients_1.clone();
-                let mut c = HashMap::new();
+                let mut c = LinearCombination::new();
                 ArithmeticExpression::initialize_hashmap_for_expression(&mut c);
                 Quadratic { a, b, c }
             }
@@ -441,7 +423,7 @@ impl<C: Default + Clone + Display + Hash + Eq> ArithmeticExpression<C> {
     pub fn sub(
         left: &ArithmeticExpression<C>,
         right: &ArithmeticExpression<C>,
-        field: &BigInt,
+        field: &Field,
     ) -> ArithmeticExpression<C> {
         use ArithmeticExpression::*;
         let minus_one = Number { value: BigInt::from(-1) };
@@ -452,25 +434,25 @@ impl<C: Default + Clone + Display + Hash + Eq> ArithmeticExpression<C> {
     pub fn div(
         left: &ArithmeticExpression<C>,
         right: &ArithmeticExpression<C>,
-        field: &BigInt,
+        field: &Field,
     ) -> Result<ArithmeticExpression<C>, ArithmeticError> {
         use ArithmeticExpression::*;
         match (left, right) {
             (Number { value: value_0 }, Number { value: value_1 }) => {
-                let value = modular_arithmetic::div(value_0, value_1, field)?;
+                let value = modular_arithmetic::div(value_0, value_1, field.prime())?;
                 Result::Ok(Number { value })
             }
             (Signal { symbol }, Number { value }) => {
-                let mut coefficients = HashMap::new();
+                let mut coefficients = LinearCombination::new();
                 ArithmeticExpression::initialize_hashmap_for_expression(&mut coefficients);
                 ArithmeticExpression::add_symbol_to_coefficients(
                     symbol,
-                    &BigInt::from(1),
+                    &field.one(),
                     &mut coefficients,
                     field,
                 );
                 ArithmeticExpression::divide_coefficients_by_constant(
-                    value,
+                    &field.from_bigint(value),
                     &mut coefficients,
                     field,
                 )?;
@@ -479,7 +461,7 @@ impl<C: Default + Clone + Display + Hash + Eq> ArithmeticExpression<C> {
             (Linear { coefficients }, Number { value }) => {
                 let mut coefficients = coefficients.clone();
                 ArithmeticExpression::divide_coefficients_by_constant(
-                    value,
+                    &field.from_bigint(value),
                     &mut coefficients,
                     field,
                 )?;
@@ -489,8 +471,9 @@ impl<C: Default + Clone + Display + Hash + Eq> ArithmeticExpression<C> {
                 let mut a = a.clone();
                 let b = b.clone();
                 let mut c = c.clone();
-                ArithmeticExpression::divide_coefficients_by_constant(value, &mut a, field)?;
-                ArithmeticExpression::divide_coefficients_by_constant(value, &mut c, field)?;
+                let value = field.from_bigint(value);
+                ArithmeticExpression::divide_coefficients_by_constant(&value, &mut a, field)?;
+                ArithmeticExpression::divide_coefficients_by_constant(&value, &mut c, field)?;
                 Result::Ok(Quadratic { a, b, c })
             }
             _ => Result::Ok(NonQuadratic),
@@ -499,12 +482,12 @@ impl<C: Default + Clone + Display + Hash + Eq> ArithmeticExpression<C> {
     pub fn idiv(
         left: &ArithmeticExpression<C>,
         right: &ArithmeticExpression<C>,
-        field: &BigInt,
+        field: &Field,
     ) -> Result<ArithmeticExpression<C>, ArithmeticError> {
         use ArithmeticExpression::*;
         match (left, right) {
             (Number { value: value_0 }, Number { value: value_1 }) => {
-                let value = modular_arithmetic::idiv(value_0, value_1, field)?;
+                let value = modular_arithmetic::idiv(value_0, value_1, field.prime())?;
                 Result::Ok(Number { value })
             }
             _ => Result::Ok(NonQuadratic),
@@ -513,11 +496,11 @@ impl<C: Default + Clone + Display + Hash + Eq> ArithmeticExpression<C> {
     pub fn mod_op(
         left: &ArithmeticExpression<C>,
         right: &ArithmeticExpression<C>,
-        field: &BigInt,
+        field: &Field,
     ) -> Result<ArithmeticExpression<C>, ArithmeticError> {
         use ArithmeticExpression::*;
         if let (Number { value: value_0 }, Number { value: value_1 }) = (left, right) {
-            let value = modular_arithmetic::mod_op(value_0, value_1, field)?;
+            let value = modular_arithmetic::mod_op(value_0, value_1, field.prime())?;
             Result::Ok(Number { value })
         } else {
             Result::Ok(NonQuadratic)
@@ -526,12 +509,12 @@ impl<C: Default + Clone + Display + Hash + Eq> ArithmeticExpression<C> {
     pub fn pow(
         left: &ArithmeticExpression<C>,
         right: &ArithmeticExpression<C>,
-        field: &BigInt,
+        field: &Field,
     ) -> ArithmeticExpression<C> {
         use ArithmeticExpression::*;
         match (left, right) {
             (Number { value: value_0 }, Number { value: value_1 }) => {
-                let value = modular_arithmetic::pow(value_0, value_1, field);
+                let value = modular_arithmetic::pow(value_0, value_1, field.prime());
                 Number { value }
             }
             (Signal { symbol }, Number { value }) if *value == BigInt::from(2) => {      
@@ -547,7 +530,7 @@ impl<C: Default + Clone + Display + Hash + Eq> ArithmeticExpression<C> {
             _ => NonQuadratic,
         }
     }
-    pub fn prefix_sub(elem: &ArithmeticExpression<C>, field: &BigInt) -> ArithmeticExpression<C> {
+    pub fn prefix_sub(elem: &ArithmeticExpression<C>, field: &Field) -> ArithmeticExpression<C> {
         use ArithmeticExpression::*;
         let minus_one = Number { value: BigInt::from(-1) };
         ArithmeticExpression::mul(elem, &minus_one, field)
@@ -556,11 +539,11 @@ impl<C: Default + Clone + Display + Hash + Eq> ArithmeticExpression<C> {
     // Bit operations
     pub fn complement_256(
         elem: &ArithmeticExpression<C>,
-        field: &BigInt,
+        field: &Field,
     ) -> ArithmeticExpression<C> {
         use ArithmeticExpression::*;
         if let Number { value } = elem {
-            Number { value: modular_arithmetic::complement_256(value, field) }
+            Number { value: modular_arithmetic::complement_256(value, field.prime()) }
         } else {
             NonQuadratic
         }
@@ -568,11 +551,11 @@ impl<C: Default + Clone + Display + Hash + Eq> ArithmeticExpression<C> {
     pub fn shift_l(
         left: &ArithmeticExpression<C>,
         right: &ArithmeticExpression<C>,
-        field: &BigInt,
+        field: &Field,
     ) -> Result<ArithmeticExpression<C>, ArithmeticError> {
         use ArithmeticExpression::*;
         if let (Number { value: value_0 }, Number { value: value_1 }) = (left, right) {
-            let shifted_elem = modular_arithmetic::shift_l(value_0, value_1, field)?;
+            let shifted_elem = modular_arithmetic::shift_l(value_0, value_1, field.prime())?;
             Result::Ok(Number { value: shifted_elem })
         } else {
             Result::Ok(NonQuadratic)
@@ -581,11 +564,11 @@ impl<C: Default + Clone + Display + Hash + Eq> ArithmeticExpression<C> {
     pub fn shift_r(
         left: &ArithmeticExpression<C>,
         right: &ArithmeticExpression<C>,
-        field: &BigInt,
+        field: &Field,
     ) -> Result<ArithmeticExpression<C>, ArithmeticError> {
         use ArithmeticExpression::*;
         if let (Number { value: value_0 }, Number { value: value_1 }) = (left, right) {
-            let shifted_elem = modular_arithmetic::shift_r(value_0, value_1, field)?;
+            let shifted_elem = modular_arithmetic::shift_r(value_0, value_1, field.prime())?;
             Result::Ok(Number { value: shifted_elem })
         } else {
             Result::Ok(NonQuadratic)
@@ -594,11 +577,11 @@ impl<C: Default + Clone + Display + Hash + Eq> ArithmeticExpression<C> {
     pub fn bit_or(
         left: &ArithmeticExpression<C>,
         right: &ArithmeticExpression<C>,
-        field: &BigInt,
+        field: &Field,
     ) -> ArithmeticExpression<C> {
         use ArithmeticExpression::*;
         if let (Number { value: value_0 }, Number { value: value_1 }) = (left, right) {
-            let value = modular_arithmetic::bit_or(value_0, value_1, field);
+            let value = modular_arithmetic::bit_or(value_0, value_1, field.prime());
             Number { value }
         } else {
             NonQuadratic
@@ -607,11 +590,11 @@ impl<C: Default + Clone + Display + Hash + Eq> ArithmeticExpression<C> {
     pub fn bit_and(
         left: &ArithmeticExpression<C>,
         right: &ArithmeticExpression<C>,
-        field: &BigInt,
+        field: &Field,
     ) -> ArithmeticExpression<C> {
         use ArithmeticExpression::*;
         if let (Number { value: value_0 }, Number { value: value_1 }) = (left, right) {
-            let value = modular_arithmetic::bit_and(value_0, value_1, field);
+            let value = modular_arithmetic::bit_and(value_0, value_1, field.prime());
             Number { value }
         } else {
             NonQuadratic
@@ -620,11 +603,11 @@ impl<C: Default + Clone + Display + Hash + Eq> ArithmeticExpression<C> {
     pub fn bit_xor(
         left: &ArithmeticExpression<C>,
         right: &ArithmeticExpression<C>,
-        field: &BigInt,
+        field: &Field,
     ) -> ArithmeticExpression<C> {
         use ArithmeticExpression::*;
         if let (Number { value: value_0 }, Number { value: value_1 }) = (left, right) {
-            let value = modular_arithmetic::bit_xor(value_0, value_1, field);
+            let value = modular_arithmetic::bit_xor(value_0, value_1, field.prime());
             Number { value }
         } else {
             NonQuadratic
@@ -632,18 +615,18 @@ impl<C: Default + Clone + Display + Hash + Eq> ArithmeticExpression<C> {
     }
 
     // Boolean operations
-    pub fn get_boolean_equivalence(elem: &ArithmeticExpression<C>, field: &BigInt) -> Option<bool> {
+    pub fn get_boolean_equivalence(elem: &ArithmeticExpression<C>, field: &Field) -> Option<bool> {
         use ArithmeticExpression::*;
         if let Number { value } = elem {
-            Option::Some(modular_arithmetic::as_bool(value, field))
+            Option::Some(modular_arithmetic::as_bool(value, field.prime()))
         } else {
             Option::None
         }
     }
-    pub fn not(elem: &ArithmeticExpression<C>, field: &BigInt) -> ArithmeticExpression<C> {
+    pub fn not(elem: &ArithmeticExpression<C>, field: &Field) -> ArithmeticExpression<C> {
         use ArithmeticExpression::*;
         if let Number { value } = elem {
-            let value = modular_arithmetic::not(value, field);
+            let value = modular_arithmetic::not(value, field.prime());
             Number { value }
         } else {
             NonQuadratic
@@ -652,11 +635,11 @@ impl<C: Default + Clone + Display + Hash + Eq> ArithmeticExpression<C> {
     pub fn bool_or(
         left: &ArithmeticExpression<C>,
         right: &ArithmeticExpression<C>,
-        field: &BigInt,
+        field: &Field,
     ) -> ArithmeticExpression<C> {
         use ArithmeticExpression::*;
         if let (Number { value: value_0 }, Number { value: value_1 }) = (left, right) {
-            let value = modular_arithmetic::bool_or(value_0, value_1, field);
+            let value = modular_arithmetic::bool_or(value_0, value_1, field.prime());
             Number { value }
         } else {
             NonQuadratic
@@ -665,11 +648,11 @@ impl<C: Default + Clone + Display + Hash + Eq> ArithmeticExpression<C> {
     pub fn bool_and(
         left: &ArithmeticExpression<C>,
         right: &ArithmeticExpression<C>,
-        field: &BigInt,
+        field: &Field,
     ) -> ArithmeticExpression<C> {
         use ArithmeticExpression::*;
         if let (Number { value: value_0 }, Number { value: value_1 }) = (left, right) {
-            let value = modular_arithmetic::bool_and(value_0, value_1, field);
+            let value = modular_arithmetic::bool_and(value_0, value_1, field.prime());
             Number { value }
         } else {
             NonQuadratic
@@ -678,11 +661,11 @@ impl<C: Default + Clone + Display + Hash + Eq> ArithmeticExpression<C> {
     pub fn eq(
         left: &ArithmeticExpression<C>,
         right: &ArithmeticExpression<C>,
-        field: &BigInt,
+        field: &Field,
     ) -> ArithmeticExpression<C> {
         use ArithmeticExpression::*;
         if let (Number { value: value_0 }, Number { value: value_1 }) = (left, right) {
-            let value = modular_arithmetic::eq(value_0, value_1, field);
+            let value = modular_arithmetic::eq(value_0, value_1, field.prime());
             Number { value }
         } else {
             NonQuadratic
@@ -691,11 +674,11 @@ impl<C: Default + Clone + Display + Hash + Eq> ArithmeticExpression<C> {
     pub fn not_eq(
         left: &ArithmeticExpression<C>,
         right: &ArithmeticExpression<C>,
-        field: &BigInt,
+        field: &Field,
     ) -> ArithmeticExpression<C> {
         use ArithmeticExpression::*;
         if let (Number { value: value_0 }, Number { value: value_1 }) = (left, right) {
-            let value = modular_arithmetic::not_eq(value_0, value_1, field);
+            let value = modular_arithmetic::not_eq(value_0, value_1, field.prime());
             Number { value }
         } else {
             NonQuadratic
@@ -704,11 +687,11 @@ impl<C: Default + Clone + Display + Hash + Eq> ArithmeticExpression<C> {
     pub fn lesser(
         left: &ArithmeticExpression<C>,
         right: &ArithmeticExpression<C>,
-        field: &BigInt,
+        field: &Field,
     ) -> ArithmeticExpression<C> {
         use ArithmeticExpression::*;
         if let (Number { value: value_0 }, Number { value: value_1 }) = (left, right) {
-            let value = modular_arithmetic::lesser(value_0, value_1, field);
+            let value = modular_arithmetic::lesser(value_0, value_1, field.prime());
             Number { value }
         } else {
             NonQuadratic
@@ -717,11 +700,11 @@ impl<C: Default + Clone + Display + Hash + Eq> ArithmeticExpression<C> {
     pub fn lesser_eq(
         left: &ArithmeticExpression<C>,
         right: &ArithmeticExpression<C>,
-        field: &BigInt,
+        field: &Field,
     ) -> ArithmeticExpression<C> {
         use ArithmeticExpression::*;
         if let (Number { value: value_0 }, Number { value: value_1 }) = (left, right) {
-            let value = modular_arithmetic::lesser_eq(value_0, value_1, field);
+            let value = modular_arithmetic::lesser_eq(value_0, value_1, field.prime());
             Number { value }
         } else {
             NonQuadratic
@@ -730,11 +713,11 @@ impl<C: Default + Clone + Display + Hash + Eq> ArithmeticExpression<C> {
     pub fn greater(
         left: &ArithmeticExpression<C>,
         right: &ArithmeticExpression<C>,
-        field: &BigInt,
+        field: &Field,
     ) -> ArithmeticExpression<C> {
         use ArithmeticExpression::*;
         if let (Number { value: value_0 }, Number { value: value_1 }) = (left, right) {
-            let value = modular_arithmetic::greater(value_0, value_1, field);
+            let value = modular_arithmetic::greater(value_0, value_1, field.prime());
             Number { value }
         } else {
             NonQuadratic
@@ -743,11 +726,11 @@ impl<C: Default + Clone + Display + Hash + Eq> ArithmeticExpression<C> {
     pub fn greater_eq(
         left: &ArithmeticExpression<C>,
         right: &ArithmeticExpression<C>,
-        field: &BigInt,
+        field: &Field,
     ) -> ArithmeticExpression<C> {
         use ArithmeticExpression::*;
         if let (Number { value: value_0 }, Number { value: value_1 }) = (left, right) {
-            let value = modular_arithmetic::greater_eq(value_0, value_1, field);
+            let value = modular_arithmetic::greater_eq(value_0, value_1, field.prime());
             Number { value }
         } else {
             NonQuadratic
@@ -758,7 +741,7 @@ impl<C: Default + Clone + Display + Hash + Eq> ArithmeticExpression<C> {
     pub fn apply_substitutions(
         expr: &mut ArithmeticExpression<C>,
         substitution: &Substitution<C>,
-        field: &BigInt,
+        field: &Field,
     ) {
         use ArithmeticExpression::*;
         match expr {
@@ -801,17 +784,17 @@ impl<C: Default + Clone + Display + Hash + Eq> ArithmeticExpression<C> {
         matches!(self, ArithmeticExpression::Linear { .. })
     }
 
-    pub fn hashmap_into_arith(mut map: HashMap<C, BigInt>) -> ArithmeticExpression<C> {
+    pub fn hashmap_into_arith(mut map: LinearCombination<C>, field: &Field) -> ArithmeticExpression<C> {
         let c: C = ArithmeticExpression::constant_coefficient();
-        let expr = if HashMap::len(&map) == 1 && HashMap::contains_key(&map, &c) {
-            let value = HashMap::remove(&mut map, &c).unwrap();
+        let expr = if LinearCombination::len(&map) == 1 && LinearCombination::contains_key(&map, &c) {
+            let value = field.to_bigint(&LinearCombination::remove(&mut map, &c).unwrap());
             ArithmeticExpression::Number { value }
-        } else if HashMap::len(&map) == 1 {
+        } else if LinearCombination::len(&map) == 1 {
             let mut values: Vec<_> = map.values().cloned().collect();
             let mut symbols: Vec<_> = map.keys().cloned().collect();
             let symbol = symbols.pop().unwrap();
             let value = values.pop().unwrap();
-            if value == BigInt::from(1) {
+            if value == field.one() {
                 ArithmeticExpression::Signal { symbol }
             } else {
                 ArithmeticExpression::initialize_hashmap_for_expression(&mut map);
@@ -837,21 +820,21 @@ where
     C: Hash + Eq,
 {
     pub(crate) from: C,
-    pub(crate) to: HashMap<C, BigInt>,
+    pub(crate) to: LinearCombination<C>,
 }
-impl<C: Default + Clone + Display + Hash + Eq> Substitution<C> {
+impl<C: Default + Clone + Display + Hash + Ord> Substitution<C> {
     // Substitution public utils
-    pub fn new(from: C, to: ArithmeticExpression<C>) -> Option<Substitution<C>> {
+    pub fn new(from: C, to: ArithmeticExpression<C>, field: &Field) -> Option<Substitution<C>> {
         use ArithmeticExpression::*;
         match to {
             Number { value } => {
-                let mut to = HashMap::new();
-                to.insert(ArithmeticExpression::constant_coefficient(), value);
+                let mut to = LinearCombination::new();
+                to.insert(ArithmeticExpression::constant_coefficient(), field.from_bigint(&value));
                 Option::Some(Substitution { from, to })
             }
             Signal { symbol } => {
-                let mut to = HashMap::new();
-                to.insert(symbol, BigInt::from(1));
+                let mut to = LinearCombination::new();
+                to.insert(symbol, field.one());
                 Option::Some(Substitution { from, to })
             }
             Linear { coefficients: to } if !to.contains_key(&from) => {
@@ -866,7 +849,7 @@ impl<C: Default + Clone + Display + Hash + Eq> Substitution<C> {
         symbol_correspondence: &HashMap<C, K>,
     ) -> Substitution<K>
     where
-        K: Default + Clone + Display + Hash + Eq,
+        K: Default + Clone + Display + Hash + Ord,
     {
         Substitution::apply_correspondence(&substitution, symbol_correspondence)
     }
@@ -880,41 +863,41 @@ impl<C: Default + Clone + Display + Hash + Eq> Substitution<C> {
         symbol_correspondence: &HashMap<C, K>,
     ) -> Substitution<K>
     where
-        K: Default + Clone + Display + Hash + Eq,
+        K: Default + Clone + Display + Hash + Ord,
     {
         let from = symbol_correspondence.get(&substitution.from).unwrap().clone();
         let to = apply_raw_correspondence(&substitution.to, symbol_correspondence);
         Substitution { to, from }
     }
 
-    pub fn apply_substitution(src: &mut Substitution<C>, change: &Substitution<C>, field: &BigInt) {
+    pub fn apply_substitution(src: &mut Substitution<C>, change: &Substitution<C>, field: &Field) {
         raw_substitution(&mut src.to, change, field);
     }
 
     pub fn substitution_into_constraint(
         substitution: Substitution<C>,
-        field: &BigInt,
+        field: &Field,
     ) -> Constraint<C> {
         let symbol = substitution.from;
         let mut coefficients = substitution.to;
         ArithmeticExpression::initialize_hashmap_for_expression(&mut coefficients);
-        coefficients.insert(symbol, BigInt::from(-1 % field));
+        coefficients.insert(symbol, field.neg(&field.one()));
         let arith = ArithmeticExpression::Linear { coefficients };
         ArithmeticExpression::transform_expression_to_constraint_form(arith, field).unwrap()
     }
 
-    pub fn decompose(substitution: Substitution<C>) -> (C, ArithmeticExpression<C>) {
+    pub fn decompose(substitution: Substitution<C>, field: &Field) -> (C, ArithmeticExpression<C>) {
         let c: C = ArithmeticExpression::constant_coefficient();
         let mut to = substitution.to;
-        let right = if HashMap::len(&to) == 1 && HashMap::contains_key(&to, &c) {
-            let value = HashMap::remove(&mut to, &c).unwrap();
+        let right = if LinearCombination::len(&to) == 1 && LinearCombination::contains_key(&to, &c) {
+            let value = field.to_bigint(&LinearCombination::remove(&mut to, &c).unwrap());
             ArithmeticExpression::Number { value }
-        } else if HashMap::len(&to) == 1 {
+        } else if LinearCombination::len(&to) == 1 {
             let mut values: Vec<_> = to.values().cloned().collect();
             let mut symbols: Vec<_> = to.keys().cloned().collect();
             let symbol = symbols.pop().unwrap();
             let value = values.pop().unwrap();
-            if value == BigInt::from(1) {
+            if value == field.one() {
                 ArithmeticExpression::Signal { symbol }
             } else {
                 ArithmeticExpression::initialize_hashmap_for_expression(&mut to);
@@ -929,9 +912,9 @@ impl<C: Default + Clone + Display + Hash + Eq> Substitution<C> {
 
     pub fn map_into_arith_expr(
         substitution: Substitution<C>,
-        field: &BigInt,
+        field: &Field,
     ) -> ArithmeticExpression<C> {
-        let (left, right) = Substitution::decompose(substitution);
+        let (left, right) = Substitution::decompose(substitution, field);
         let left = ArithmeticExpression::Signal { symbol: left };
         ArithmeticExpression::sub(&right, &left, field)
     }
@@ -940,7 +923,7 @@ impl<C: Default + Clone + Display + Hash + Eq> Substitution<C> {
         &self.from
     }
 
-    pub fn to(&self) -> &HashMap<C, BigInt> {
+    pub fn to(&self) -> &LinearCombination<C> {
         &self.to
     }
 
@@ -971,7 +954,7 @@ impl<C: Default + Clone + Display + Hash + Eq> Substitution<C> {
     }
 }
 
-impl<C: Default + Clone + Display + Hash + Eq + std::cmp::Ord> Substitution<C> {
+impl<C: Default + Clone + Display + Hash + Ord> Substitution<C> {
     pub fn take_cloned_signals_ordered(&self) -> BTreeSet<C> {
         let cq: C = ArithmeticExpression::constant_coefficient();
         let mut signals = BTreeSet::new();
@@ -998,26 +981,26 @@ impl Substitution<usize> {
     Represents a constraint of the form: A*B - C = 0
     where A,B and C are linear expression.
 */
-#[derive(Clone)]
+#[derive(Clone, PartialEq, Eq, Hash)]
 pub struct Constraint<C>
 where
     C: Hash + Eq,
 {
-    pub(crate) a: HashMap<C, BigInt>,
-    pub(crate) b: HashMap<C, BigInt>,
-    pub(crate) c: HashMap<C, BigInt>,
+    pub(crate) a: LinearCombination<C>,
+    pub(crate) b: LinearCombination<C>,
+    pub(crate) c: LinearCombination<C>,
 }
 
-impl<C: Default + Clone + Display + Hash + Eq> Constraint<C> {
-    fn new(a: HashMap<C, BigInt>, b: HashMap<C, BigInt>, c: HashMap<C, BigInt>) -> Constraint<C> {
+impl<C: Default + Clone + Display + Hash + Ord> Constraint<C> {
+    pub fn new(a: LinearCombination<C>, b: LinearCombination<C>, c: LinearCombination<C>) -> Constraint<C> {
         Constraint { a, b, c }
     }
 
     pub fn empty() -> Constraint<C> {
         Constraint::new(
-            HashMap::with_capacity(0),
-            HashMap::with_capacity(0),
-            HashMap::with_capacity(0),
+            LinearCombination::new(),
+            LinearCombination::new(),
+            LinearCombination::new(),
         )
     }
 
@@ -1029,7 +1012,7 @@ impl<C: Default + Clone + Display + Hash + Eq> Constraint<C> {
         symbol_correspondence: &HashMap<C, K>,
     ) -> Constraint<K>
     where
-        K: Default + Clone + Display + Hash + Eq,
+        K: Default + Clone + Display + Hash + Ord,
     {
         Constraint::apply_correspondence(&constraint, symbol_correspondence)
     }
@@ -1039,7 +1022,7 @@ impl<C: Default + Clone + Display + Hash + Eq> Constraint<C> {
         symbol_correspondence: &HashMap<C, K>,
     ) -> Constraint<K>
     where
-        K: Default + Clone + Display + Hash + Eq,
+        K: Default + Clone + Display + Hash + Ord,
     {
         let a = apply_raw_correspondence(&constraint.a, symbol_correspondence);
         let b = apply_raw_correspondence(&constraint.b, symbol_correspondence);
@@ -1056,7 +1039,7 @@ impl<C: Default + Clone + Display + Hash + Eq> Constraint<C> {
     pub fn clear_signal_from_linear(
         constraint: Constraint<C>,
         signal: &C,
-        field: &BigInt,
+        field: &Field,
     ) -> Substitution<C> {
         debug_assert!(Constraint::is_linear(&constraint));
         debug_assert!(constraint.c.contains_key(signal));
@@ -1067,8 +1050,8 @@ impl<C: Default + Clone + Display + Hash + Eq> Constraint<C> {
     pub fn clear_signal_from_linear_not_normalized(
         constraint: Constraint<C>,
         signal: &C,
-        field: &BigInt,
-    ) -> (BigInt, Substitution<C>) {
+        field: &Field,
+    ) -> (FieldElement, Substitution<C>) {
         debug_assert!(Constraint::is_linear(&constraint));
         debug_assert!(constraint.c.contains_key(signal));
         let (coefficient, raw_expression) = Constraint::clear_signal_not_normalized(constraint.c, &signal, field);
@@ -1106,13 +1089,13 @@ impl<C: Default + Clone + Display + Hash + Eq> Constraint<C> {
     }
 
     fn clear_signal(
-        mut symbols: HashMap<C, BigInt>,
+        mut symbols: LinearCombination<C>,
         key: &C,
-        field: &BigInt,
-    ) -> HashMap<C, BigInt> {
+        field: &Field,
+    ) -> LinearCombination<C> {
         let key_value = symbols.remove(&key).unwrap();
         assert!(!key_value.is_zero());
-        let value_to_the_right = modular_arithmetic::mul(&key_value, &BigInt::from(-1), field);
+        let value_to_the_right = field.neg(&key_value);
         ArithmeticExpression::initialize_hashmap_for_expression(&mut symbols);
         let arithmetic_result = ArithmeticExpression::divide_coefficients_by_constant(
             &value_to_the_right,
@@ -1124,13 +1107,13 @@ impl<C: Default + Clone + Display + Hash + Eq> Constraint<C> {
     }
 
     fn clear_signal_not_normalized(
-        mut symbols: HashMap<C, BigInt>,
+        mut symbols: LinearCombination<C>,
         key: &C,
-        field: &BigInt,
-    ) -> (BigInt, HashMap<C, BigInt>) {
+        field: &Field,
+    ) -> (FieldElement, LinearCombination<C>) {
         let key_value = symbols.remove(&key).unwrap();
         assert!(!key_value.is_zero());
-        let value_to_the_right = modular_arithmetic::mul(&key_value, &BigInt::from(-1), field);
+        let value_to_the_right = field.neg(&key_value);
         ArithmeticExpression::initialize_hashmap_for_expression(&mut symbols);
         (value_to_the_right, symbols)
     }
@@ -1138,7 +1121,7 @@ impl<C: Default + Clone + Display + Hash + Eq> Constraint<C> {
     pub fn apply_substitution(
         constraint: &mut Constraint<C>,
         substitution: &Substitution<C>,
-        field: &BigInt,
+        field: &Field,
     ) {
         raw_substitution(&mut constraint.a, substitution, field);
         raw_substitution(&mut constraint.b, substitution, field);
@@ -1152,10 +1135,53 @@ impl<C: Default + Clone + Display + Hash + Eq> Constraint<C> {
         constraint.c = remove_zero_value_coefficients(std::mem::take(&mut constraint.c));
     }
 
-    pub fn fix_constraint(constraint: &mut Constraint<C>, field: &BigInt) {
+    pub fn fix_constraint(constraint: &mut Constraint<C>, field: &Field) {
         fix_raw_constraint(&mut constraint.a, &mut constraint.b, &mut constraint.c, field);
     }
 
+    // Scalar multiples of a constraint share its normal form: a constant
+    // factor is folded into C, the first coefficient of A, B and, for linear
+    // constraints, C becomes 1, and the factors A and B are ordered.
+    pub fn normalize(constraint: &mut Constraint<C>, field: &Field) {
+        Constraint::fix_constraint(constraint, field);
+        let leading = constraint.leading_coefficients();
+        let inverses: Vec<_> = leading.iter().map(|value| leading_inverse(value, field)).collect();
+        Constraint::normalize_fixed(constraint, &inverses, field);
+    }
+
+    // The coefficients normalize divides by in a fixed constraint: the first
+    // ones of A and B, or the first one of C if it is linear
+    pub fn leading_coefficients(&self) -> Vec<FieldElement> {
+        let first = |expr: &RawExpr<C>| expr.values().next().cloned();
+        if self.a.is_empty() {
+            first(&self.c).into_iter().collect()
+        } else {
+            vec![first(&self.a).unwrap(), first(&self.b).unwrap()]
+        }
+    }
+
+    // Normalizes a fixed constraint given the inverses of its leading
+    // coefficients, which can be computed together for many constraints
+    pub fn normalize_fixed(
+        constraint: &mut Constraint<C>,
+        inverses: &[FieldElement],
+        field: &Field,
+    ) {
+        if constraint.a.is_empty() {
+            if let Some(inverse) = inverses.first() {
+                constraint.c.scale(inverse, field);
+            }
+            return;
+        }
+        let (inverse_a, inverse_b) = (&inverses[0], &inverses[1]);
+        constraint.a.scale(inverse_a, field);
+        constraint.b.scale(inverse_b, field);
+        constraint.c.scale(&field.mul(inverse_a, inverse_b), field);
+        if compare_raw(&constraint.b, &constraint.a, field) == Ordering::Less {
+            std::mem::swap(&mut constraint.a, &mut constraint.b);
+        }
+    }
+
     pub fn is_empty(&self) -> bool {
         self.a.is_empty() && self.b.is_empty() && self.c.is_empty()
     }
@@ -1166,18 +1192,18 @@ impl<C: Default + Clone + Display + Hash + Eq> Constraint<C> {
             || self.a.contains_key(&Constraint::constant_coefficient())
     }
 
-    pub fn a(&self) -> &HashMap<C, BigInt> {
+    pub fn a(&self) -> &LinearCombination<C> {
         &self.a
     }
-    pub fn b(&self) -> &HashMap<C, BigInt> {
+    pub fn b(&self) -> &LinearCombination<C> {
         &self.b
     }
 
-    pub fn c(&self) -> &HashMap<C, BigInt> {
+    pub fn c(&self) -> &LinearCombination<C> {
         &self.c
     }
 
-    pub fn is_equality(&self, field: &BigInt) -> bool {
+    pub fn is_equality(&self, field: &Field) -> bool {
         signal_equals_signal(&self.a, &self.b, &self.c, field)
     }
 
@@ -1195,7 +1221,7 @@ impl<C: Default + Clone + Display + Hash + Eq> Constraint<C> {
 
 }
 
-impl<C: Default + Clone + Display + Hash + Eq + std::cmp::Ord> Constraint<C> {
+impl<C: Default + Clone + Display + Hash + Ord> Constraint<C> {
     pub fn take_cloned_signals_ordered(&self) -> BTreeSet<C> {
         let mut signals = BTreeSet::new();
         for signal in self.a().keys() {
@@ -1229,93 +1255,61 @@ impl Constraint<usize> {
 }
 
 // model utils
-type RawExpr<C> = HashMap<C, BigInt>;
+type RawExpr<C> = LinearCombination<C>;
 
 fn apply_vectored_correspondence(
-    symbols: &HashMap<usize, BigInt>,
+    symbols: &LinearCombination<usize>,
     map: &Vec<usize>,
-) -> HashMap<usize, BigInt> {
-    let mut mapped = HashMap::new();
-    for (s, v) in symbols {
-        mapped.insert(map[*s], v.clone());
-    }
-    mapped
+) -> LinearCombination<usize> {
+    symbols.iter().map(|(s, v)| (map[*s], v.clone())).collect()
 }
 
 fn apply_raw_correspondence<C, K>(
-    symbols: &HashMap<C, BigInt>,
+    symbols: &LinearCombination<C>,
     map: &HashMap<C, K>,
-) -> HashMap<K, BigInt>
+) -> LinearCombination<K>
 where
-    K: Default + Clone + Display + Hash + Eq,
-    C: Default + Clone + Display + Hash + Eq,
+    K: Default + Clone + Display + Hash + Ord,
+    C: Default + Clone + Display + Hash + Ord,
 {
-    let constant_coefficient: C = ArithmeticExpression::constant_coefficient();
-    let mut coefficients_as_correspondence = HashMap::new();
-    for (key, value) in symbols {
-        let id = if key.eq(&constant_coefficient) {
-            ArithmeticExpression::constant_coefficient()
-        } else {
-            map.get(&key).expect(&format!("Unknown signal: {}", key)).clone()
-        };
-        coefficients_as_correspondence.insert(id, value.clone());
-    }
-    coefficients_as_correspondence
+    symbols.map_symbols(|key| map.get(key).expect(&format!("Unknown signal: {}", key)).clone())
 }
 
-fn apply_raw_offset(h: &HashMap<usize, BigInt>, offset: usize) -> HashMap<usize, BigInt> {
-    let mut new = HashMap::new();
-    let constant: usize = Constraint::constant_coefficient();
-    for (k, v) in h {
-        if *k == constant {
-            new.insert(*k, v.clone());
-        } else {
-            new.insert(*k + offset, v.clone());
-        }
-    }
-    new
+fn apply_raw_offset(h: &LinearCombination<usize>, offset: usize) -> LinearCombination<usize> {
+    h.map_symbols(|k| *k + offset)
 }
 
 fn raw_substitution<C>(
-    change: &mut HashMap<C, BigInt>,
+    change: &mut LinearCombination<C>,
     substitution: &Substitution<C>,
-    field: &BigInt,
+    field: &Field,
 ) where
-    C: Default + Clone + Display + Hash + Eq,
+    C: Default + Clone + Display + Hash + Ord,
 {
     ArithmeticExpression::initialize_hashmap_for_expression(change);
     if let Option::Some(val) = change.remove(&substitution.from) {
-        let mut coefficients = substitution.to.clone();
-        ArithmeticExpression::initialize_hashmap_for_expression(&mut coefficients);
-        ArithmeticExpression::multiply_coefficients_by_constant(&val, &mut coefficients, field);
-        ArithmeticExpression::add_coefficients_to_coefficients(&coefficients, change, field);
+        change.add_scaled(&substitution.to, &val, field);
     }
-    //*change = remove_zero_value_coefficients(std::mem::take(change));
 }
 
-fn remove_zero_value_coefficients<C>(raw_expression: HashMap<C, BigInt>) -> HashMap<C, BigInt>
+fn remove_zero_value_coefficients<C>(mut raw_expression: LinearCombination<C>) -> LinearCombination<C>
 where
-    C: Default + Clone + Display + Hash + Eq,
+    C: Default + Clone + Display + Hash + Ord,
 {
-    let mut clean_raw = HashMap::new();
-    for (key, val) in raw_expression {
-        if !val.is_zero() {
-            clean_raw.insert(key, val);
-        }
-    }
-    clean_raw
+    raw_expression.remove_zeros();
+    raw_expression
 }
 
-fn fix_raw_constraint<C>(a: &mut RawExpr<C>, b: &mut RawExpr<C>, c: &mut RawExpr<C>, field: &BigInt)
+fn fix_raw_constraint<C>(a: &mut RawExpr<C>, b: &mut RawExpr<C>, c: &mut RawExpr<C>, field: &Field)
 where
-    C: Default + Clone + Display + Hash + Eq,
+    C: Default + Clone + Display + Hash + Ord,
 {
     *a = remove_zero_value_coefficients(std::mem::take(a));
     *b = remove_zero_value_coefficients(std::mem::take(b));
     *c = remove_zero_value_coefficients(std::mem::take(c));
-    if HashMap::is_empty(a) || HashMap::is_empty(b) {
-        HashMap::clear(a);
-        HashMap::clear(b);
+    if LinearCombination::is_empty(a) || LinearCombination::is_empty(b) {
+        LinearCombination::clear(a);
+        LinearCombination::clear(b);
     } else if is_constant_expression(a) {
         constant_linear_linear_reduction(a, b, c, field);
     } else if is_constant_expression(b) {
@@ -1327,32 +1321,32 @@ fn constant_linear_linear_reduction<C>(
     a: &mut RawExpr<C>,
     b: &mut RawExpr<C>,
     c: &mut RawExpr<C>,
-    field: &BigInt,
+    field: &Field,
 ) where
-    C: Default + Clone + Display + Hash + Eq,
+    C: Default + Clone + Display + Hash + Ord,
 {
     let cq: C = ArithmeticExpression::constant_coefficient();
     ArithmeticExpression::initialize_hashmap_for_expression(c);
     ArithmeticExpression::initialize_hashmap_for_expression(b);
-    let constant = HashMap::remove(a, &cq).unwrap();
+    let constant = LinearCombination::remove(a, &cq).unwrap();
     ArithmeticExpression::multiply_coefficients_by_constant(&constant, b, field);
-    ArithmeticExpression::multiply_coefficients_by_constant(&BigInt::from(-1), b, field);
+    ArithmeticExpression::negate_coefficients(b, field);
     ArithmeticExpression::add_coefficients_to_coefficients(b, c, field);
     *c = remove_zero_value_coefficients(std::mem::take(c));
-    HashMap::clear(a);
-    HashMap::clear(b);
+    LinearCombination::clear(a);
+    LinearCombination::clear(b);
 }
 
-fn signal_equals_signal<C>(a: &RawExpr<C>, b: &RawExpr<C>, c: &RawExpr<C>, field: &BigInt) -> bool
+fn signal_equals_signal<C>(a: &RawExpr<C>, b: &RawExpr<C>, c: &RawExpr<C>, field: &Field) -> bool
 where
-    C: Default + Clone + Display + Hash + Eq,
+    C: Default + Clone + Display + Hash + Ord,
 {
     let cq: C = ArithmeticExpression::constant_coefficient();
-    if a.is_empty() && b.is_empty() && !HashMap::contains_key(c, &cq) && c.len() == 2 {
+    if a.is_empty() && b.is_empty() && !LinearCombination::contains_key(c, &cq) && c.len() == 2 {
         let signals: Vec<_> = c.keys().cloned().collect();
-        let c0 = HashMap::get(c, &signals[0]).unwrap();
-        let c1 = HashMap::get(c, &signals[1]).unwrap();
-        let c1_p = modular_arithmetic::mul(&BigInt::from(-1), c1, field);
+        let c0 = LinearCombination::get(c, &signals[0]).unwrap();
+        let c1 = LinearCombination::get(c, &signals[1]).unwrap();
+        let c1_p = field.neg(c1);
         c1_p == *c0
     } else {
         false
@@ -1361,38 +1355,48 @@ where
 
 fn signal_equals_constant<C>(a: &RawExpr<C>, b: &RawExpr<C>, c: &RawExpr<C>) -> bool
 where
-    C: Default + Clone + Display + Hash + Eq,
+    C: Default + Clone + Display + Hash + Ord,
 {
     let cq: C = ArithmeticExpression::constant_coefficient();
-    HashMap::is_empty(a)
-        && HashMap::is_empty(b)
+    LinearCombination::is_empty(a)
+        && LinearCombination::is_empty(b)
         && 
-        	((HashMap::contains_key(c, &cq) && HashMap::len(c) == 2) ||
-        	(!HashMap::contains_key(c, &cq) && HashMap::len(c) == 1))
+        	((LinearCombination::contains_key(c, &cq) && LinearCombination::len(c) == 2) ||
+        	(!LinearCombination::contains_key(c, &cq) && LinearCombination::len(c) == 1))
 }
 
 fn is_constant_expression<C>(expr: &RawExpr<C>) -> bool
 where
-    C: Default + Clone + Display + Hash + Eq,
+    C: Default + Clone + Display + Hash + Ord,
 {
     let cq: C = ArithmeticExpression::constant_coefficient();
-    HashMap::contains_key(expr, &cq) && HashMap::len(expr) == 1
+    LinearCombination::contains_key(expr, &cq) && LinearCombination::len(expr) == 1
+}
+
+// The coefficients 1 and -1, by far the most common, are their own inverse
+fn leading_inverse(value: &FieldElement, field: &Field) -> FieldElement {
+    let one = field.one();
+    if *value == one || *value == field.neg(&one) {
+        value.clone()
+    } else {
+        field.inverse(value).expect("zero coefficient in a constraint")
+    }
 }
 
-pub fn normalize(c: Constraint<usize>, _field: &BigInt) -> Constraint<usize> {
-    use std::collections::LinkedList;
-    let _a: LinkedList<_> = c.a.iter().clone().collect();
-    let _b: LinkedList<_> = c.b.iter().clone().collect();
-    let _c: LinkedList<_> = c.c.iter().clone().collect();
-    todo!()
+// Orders by the signals and then by the values of the coefficients
+fn compare_raw<C: Ord + Clone>(left: &RawExpr<C>, right: &RawExpr<C>, field: &Field) -> Ordering {
+    let left = left.iter().map(|(symbol, value)| (symbol, field.to_bigint(value)));
+    let right = right.iter().map(|(symbol, value)| (symbol, field.to_bigint(value)));
+    left.cmp(right)
 }
 
 #[cfg(test)]
 mod test {
     use crate::algebra::{ArithmeticExpression, Constraint, Substitution};
+    use crate::field::Field;
     use crate::modular_arithmetic;
     use num_bigint::BigInt;
-    use std::collections::HashMap;
+    use crate::linear_combination::LinearCombination;
     const FIELD: &str = "257";
     type C = Constraint<usize>;
     type S = Substitution<usize>;
@@ -1400,13 +1404,14 @@ mod test {
 
     #[test]
     fn algebra_constraint_offset() {
+        let field = Field::new(&BigInt::parse_bytes(FIELD.as_bytes(), 10).unwrap());
         let offset = 7;
         let x = 1;
-        let a = HashMap::new();
-        let b = HashMap::new();
-        let mut c = HashMap::new();
-        c.insert(C::constant_coefficient(), BigInt::from(12));
-        c.insert(x, BigInt::from(3));
+        let a = LinearCombination::new();
+        let b = LinearCombination::new();
+        let mut c = LinearCombination::new();
+        c.insert(C::constant_coefficient(), field.from_bigint(&BigInt::from(12)));
+        c.insert(x, field.from_bigint(&BigInt::from(3)));
         let constraint = C::new(a, b, c);
         let constraint_with_offset = constraint.apply_offset(offset);
         assert!(constraint_with_offset.a.is_empty());
@@ -1416,43 +1421,44 @@ mod test {
         let new_x = x + offset;
         assert!(constraint_with_offset.c.contains_key(&new_x));
         let value = constraint_with_offset.c.get(&new_x).unwrap();
-        assert!(value.eq(&BigInt::from(3)));
+        assert!(field.to_bigint(value).eq(&BigInt::from(3)));
     }
 
     #[test]
     fn algebra_constraint_clear_signal() {
-        let field = BigInt::parse_bytes(FIELD.as_bytes(), 10)
+        let prime = BigInt::parse_bytes(FIELD.as_bytes(), 10)
             .expect("generating the big int was not possible");
+        let field = Field::new(&prime);
         let constant = C::constant_coefficient();
         let c_coefficient = BigInt::from(3);
         let x = 1;
         let x_coefficient = BigInt::from(1);
         let y = 2;
         let y_coefficient = BigInt::from(1);
-        let a = HashMap::new();
-        let b = HashMap::new();
-        let mut c = HashMap::new();
-        c.insert(x, x_coefficient);
-        c.insert(y, y_coefficient);
-        c.insert(constant, c_coefficient);
+        let a = LinearCombination::new();
+        let b = LinearCombination::new();
+        let mut c = LinearCombination::new();
+        c.insert(x, field.from_bigint(&x_coefficient));
+        c.insert(y, field.from_bigint(&y_coefficient));
+        c.insert(constant, field.from_bigint(&c_coefficient));
         // constraint: x + y + 3 = 0
         let constraint = C::new(a, b, c);
         // sub: x = -y -3  ==> x = 256*y + 254
         let sub = C::clear_signal_from_linear(constraint, &x, &field);
         assert_eq!(sub.from, x);
-        let y_new_coefficient = modular_arithmetic::sub(&field, &BigInt::from(1), &field);
+        let y_new_coefficient = modular_arithmetic::sub(&prime, &BigInt::from(1), &prime);
         let sub_value = sub.to.get(&y).unwrap();
-        assert_eq!(*sub_value, y_new_coefficient);
+        assert_eq!(field.to_bigint(sub_value), y_new_coefficient);
 
         let constant_new_coefficient = BigInt::from(254);
         let sub_value = sub.to.get(&constant).unwrap();
-        assert_eq!(*sub_value, constant_new_coefficient);
+        assert_eq!(field.to_bigint(sub_value), constant_new_coefficient);
     }
 
     #[test]
     fn algebra_constraint_apply_substitution() {
-        let field = BigInt::parse_bytes(FIELD.as_bytes(), 10)
-            .expect("generating the big int was not possible");
+        let field = Field::new(&BigInt::parse_bytes(FIELD.as_bytes(), 10)
+            .expect("generating the big int was not possible"));
         // symbols
         let x = 1;
         let y = 2;
@@ -1462,23 +1468,23 @@ mod test {
         let x_c = BigInt::from(1);
         let y_c = BigInt::from(1);
         let constant_c = BigInt::from(4);
-        let a = HashMap::new();
-        let b = HashMap::new();
-        let mut c = HashMap::new();
-        c.insert(x, x_c);
-        c.insert(y, y_c);
-        c.insert(constant, constant_c);
+        let a = LinearCombination::new();
+        let b = LinearCombination::new();
+        let mut c = LinearCombination::new();
+        c.insert(x, field.from_bigint(&x_c));
+        c.insert(y, field.from_bigint(&y_c));
+        c.insert(constant, field.from_bigint(&constant_c));
         let mut constraint = C::new(a, b, c);
 
         // substitution: x = 2y + 3
         let y_c = BigInt::from(2);
         let constant_c = BigInt::from(3);
         let from = x;
-        let mut to_raw = HashMap::new();
-        to_raw.insert(y, y_c);
-        to_raw.insert(constant, constant_c);
+        let mut to_raw = LinearCombination::new();
+        to_raw.insert(y, field.from_bigint(&y_c));
+        to_raw.insert(constant, field.from_bigint(&constant_c));
         let to = A::Linear { coefficients: to_raw };
-        let substitution = S::new(from, to).unwrap();
+        let substitution = S::new(from, to, &field).unwrap();
 
         // result: 3y + 7 = 0
         let expected_y_c = BigInt::from(3);
@@ -1488,7 +1494,30 @@ mod test {
         let constant_c = constraint.c.get(&constant).unwrap();
         assert!(constraint.a.is_empty());
         assert!(constraint.b.is_empty());
-        assert_eq!(*y_c, expected_y_c);
-        assert_eq!(*constant_c, expected_constant_c);
+        assert_eq!(field.to_bigint(y_c), expected_y_c);
+        assert_eq!(field.to_bigint(constant_c), expected_constant_c);
+    }
+
+    #[test]
+    fn algebra_constraint_normalize() {
+        let field = Field::new(&BigInt::parse_bytes(FIELD.as_bytes(), 10).unwrap());
+        let lc = |terms: &[(usize, i64)]| -> LinearCombination<usize> {
+            terms.iter().map(|(s, v)| (*s, field.from_bigint(&BigInt::from(*v)))).collect()
+        };
+        let (x, y, z) = (1, 2, 3);
+        // (2x + y) * 3z = 6x and z * (2x + y) = 2x
+        let mut left = C::new(lc(&[(x, 2), (y, 1)]), lc(&[(z, 3)]), lc(&[(x, 6)]));
+        let mut right = C::new(lc(&[(z, 1)]), lc(&[(x, 2), (y, 1)]), lc(&[(x, 2)]));
+        C::normalize(&mut left, &field);
+        C::normalize(&mut right, &field);
+        assert!(left.a == right.a && left.b == right.b && left.c == right.c);
+        assert_eq!(field.to_bigint(left.a.get(&x).unwrap()), BigInt::from(1));
+        assert_eq!(field.to_bigint(left.a.get(&y).unwrap()), BigInt::from(129));
+
+        // 4 * (x + 2) = 8y is the linear 8y - 4x - 8 = 0, scaled to 1 + x/2 - y
+        let mut linear = C::new(lc(&[(0, 4)]), lc(&[(x, 1), (0, 2)]), lc(&[(y, 8)]));
+        C::normalize(&mut linear, &field);
+        assert!(linear.a.is_empty() && linear.b.is_empty());
+        assert!(linear.c == lc(&[(0, 1), (x, 129), (y, 256)]));
     }
 }
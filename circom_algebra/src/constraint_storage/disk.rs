@@ -0,0 +1,372 @@
+use super::{ConstraintID, C};
+use crate::field::FieldElement;
+use crate::linear_combination::LinearCombination;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap, LinkedList};
+use std::convert::TryInto;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// Records are written to the file once this many bytes are pending
+const PENDING_LIMIT: usize = 1 << 20;
+
+static NEXT_FILE: AtomicUsize = AtomicUsize::new(0);
+
+// Where the record of a constraint is, the length does not include the
+// four bytes that precede the record with it
+#[derive(Clone, Copy)]
+struct Record {
+    offset: u64,
+    length: u32,
+}
+
+// Constraints kept in a temporary file, with the most recently used ones
+// decoded in memory. Every constraint is a record appended to the file with
+// its coefficients written in place, so neither the constraints nor a table
+// of constants grow the memory with the size of the circuit: only the
+// position of each record does. A replaced constraint is appended again and
+// its old record is left unused until they take most of the file, then the
+// records in use are copied to a new one. The file is removed when the
+// storage is dropped.
+pub struct DiskConstraints {
+    path: PathBuf,
+    file: File,
+    // Length of the file, the pending records go after it
+    written: u64,
+    pending: Vec<u8>,
+    records: Vec<Record>,
+    // Bytes of the records that were replaced
+    unused: u64,
+    cache: RefCell<Cache>,
+}
+
+impl DiskConstraints {
+    pub fn new(memory_budget: usize) -> io::Result<DiskConstraints> {
+        let (path, file) = create_file()?;
+        Ok(DiskConstraints {
+            path,
+            file,
+            written: 0,
+            pending: Vec::new(),
+            records: Vec::new(),
+            unused: 0,
+            cache: RefCell::new(Cache::new(memory_budget)),
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn add(&mut self, constraint: C) -> io::Result<ConstraintID> {
+        let id = self.records.len();
+        let record = self.append(&constraint)?;
+        self.records.push(record);
+        self.cache.borrow_mut().insert(id, constraint);
+        Ok(id)
+    }
+
+    pub fn read(&self, id: ConstraintID) -> io::Result<Option<C>> {
+        let record = match self.records.get(id) {
+            Some(record) => *record,
+            None => return Ok(None),
+        };
+        if let Some(constraint) = self.cache.borrow_mut().get(id) {
+            return Ok(Some(constraint));
+        }
+        let constraint = decode(&self.load(record)?)?;
+        self.cache.borrow_mut().insert(id, constraint.clone());
+        Ok(Some(constraint))
+    }
+
+    pub fn replace(&mut self, id: ConstraintID, new: C) -> io::Result<()> {
+        if id >= self.records.len() {
+            return Ok(());
+        }
+        self.unused += 4 + self.records[id].length as u64;
+        self.records[id] = self.append(&new)?;
+        self.cache.borrow_mut().insert(id, new);
+        let size = self.written + self.pending.len() as u64;
+        if self.unused > PENDING_LIMIT as u64 && 2 * self.unused > size {
+            self.rewrite(&mut |_| Ok(true))?;
+        }
+        Ok(())
+    }
+
+    // The constraints are read straight from the file, without going through
+    // the cache, and the ones kept take consecutive ids as in memory
+    pub fn extract_with(&mut self, filter: &dyn Fn(&C) -> bool) -> io::Result<LinkedList<C>> {
+        let mut removed = LinkedList::new();
+        self.rewrite(&mut |bytes| {
+            let constraint = decode(bytes)?;
+            if filter(&constraint) {
+                removed.push_back(constraint);
+                Ok(false)
+            } else {
+                Ok(true)
+            }
+        })?;
+        self.cache.borrow_mut().clear();
+        Ok(removed)
+    }
+
+    fn append(&mut self, constraint: &C) -> io::Result<Record> {
+        let offset = self.written + self.pending.len() as u64;
+        let start = self.pending.len();
+        self.pending.extend_from_slice(&[0; 4]);
+        encode_constraint(constraint, &mut self.pending);
+        let length = (self.pending.len() - start - 4) as u32;
+        self.pending[start..start + 4].copy_from_slice(&length.to_le_bytes());
+        if self.pending.len() >= PENDING_LIMIT {
+            self.flush()?;
+        }
+        Ok(Record { offset, length })
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.seek(SeekFrom::Start(self.written))?;
+        self.file.write_all(&self.pending)?;
+        self.written += self.pending.len() as u64;
+        self.pending.clear();
+        Ok(())
+    }
+
+    fn load(&self, record: Record) -> io::Result<Vec<u8>> {
+        let length = record.length as usize;
+        if record.offset >= self.written {
+            let start = (record.offset - self.written) as usize + 4;
+            return Ok(self.pending[start..start + length].to_vec());
+        }
+        let mut file = &self.file;
+        file.seek(SeekFrom::Start(record.offset + 4))?;
+        let mut bytes = vec![0; length];
+        file.read_exact(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    // Copies the records that are kept to a new file, in the order of their
+    // ids, and removes the old one. The kept records take consecutive ids.
+    fn rewrite(&mut self, keep: &mut dyn FnMut(&[u8]) -> io::Result<bool>) -> io::Result<()> {
+        let (path, file) = create_file()?;
+        let copied = self.copy_records(&file, keep);
+        let (records, written) = match copied {
+            Ok(copied) => copied,
+            Err(error) => {
+                let _ = fs::remove_file(&path);
+                return Err(error);
+            }
+        };
+        let _ = fs::remove_file(&self.path);
+        self.path = path;
+        self.file = file;
+        self.written = written;
+        self.pending.clear();
+        self.records = records;
+        self.unused = 0;
+        Ok(())
+    }
+
+    fn copy_records(
+        &self,
+        file: &File,
+        keep: &mut dyn FnMut(&[u8]) -> io::Result<bool>,
+    ) -> io::Result<(Vec<Record>, u64)> {
+        let mut writer = BufWriter::new(file);
+        let mut records = Vec::new();
+        let mut written = 0;
+        for record in &self.records {
+            let bytes = self.load(*record)?;
+            if keep(&bytes)? {
+                writer.write_all(&record.length.to_le_bytes())?;
+                writer.write_all(&bytes)?;
+                records.push(Record { offset: written, length: record.length });
+                written += 4 + record.length as u64;
+            }
+        }
+        writer.flush()?;
+        Ok((records, written))
+    }
+}
+
+fn create_file() -> io::Result<(PathBuf, File)> {
+    let name = format!("circom_constraints_{}_{}", std::process::id(), NEXT_FILE.fetch_add(1, Ordering::Relaxed));
+    let path = std::env::temp_dir().join(name);
+    let file = OpenOptions::new().read(true).write(true).create_new(true).open(&path)?;
+    Ok((path, file))
+}
+
+fn decode(bytes: &[u8]) -> io::Result<C> {
+    decode_constraint(bytes).ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidData, "corrupted temporary file of the constraints")
+    })
+}
+
+impl Drop for DiskConstraints {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+// Least recently used constraints, up to a number of bytes
+struct Cache {
+    budget: usize,
+    used: usize,
+    clock: u64,
+    entries: HashMap<ConstraintID, (C, usize, u64)>,
+    by_use: BTreeMap<u64, ConstraintID>,
+}
+
+impl Cache {
+    fn new(budget: usize) -> Cache {
+        Cache { budget, used: 0, clock: 0, entries: HashMap::new(), by_use: BTreeMap::new() }
+    }
+
+    fn get(&mut self, id: ConstraintID) -> Option<C> {
+        let (constraint, _, last_use) = self.entries.get_mut(&id)?;
+        self.by_use.remove(last_use);
+        self.clock += 1;
+        *last_use = self.clock;
+        self.by_use.insert(self.clock, id);
+        Some(constraint.clone())
+    }
+
+    fn insert(&mut self, id: ConstraintID, constraint: C) {
+        self.remove(id);
+        let size = estimated_size(&constraint);
+        if size > self.budget {
+            return;
+        }
+        while self.used + size > self.budget {
+            let (_, oldest) = self.by_use.pop_first().unwrap();
+            let (_, oldest_size, _) = self.entries.remove(&oldest).unwrap();
+            self.used -= oldest_size;
+        }
+        self.clock += 1;
+        self.used += size;
+        self.entries.insert(id, (constraint, size, self.clock));
+        self.by_use.insert(self.clock, id);
+    }
+
+    fn remove(&mut self, id: ConstraintID) {
+        if let Some((_, size, last_use)) = self.entries.remove(&id) {
+            self.by_use.remove(&last_use);
+            self.used -= size;
+        }
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.by_use.clear();
+        self.used = 0;
+    }
+}
+
+fn estimated_size(constraint: &C) -> usize {
+    let terms = constraint.a.len() + constraint.b.len() + constraint.c.len();
+    let wide: usize = [&constraint.a, &constraint.b, &constraint.c]
+        .iter()
+        .flat_map(|expr| expr.values())
+        .map(|value| if let FieldElement::Wide(value) = value { value.bits() / 8 } else { 0 })
+        .sum();
+    std::mem::size_of::<(C, usize, u64)>()
+        + 2 * std::mem::size_of::<(u64, ConstraintID)>()
+        + terms * std::mem::size_of::<(usize, FieldElement)>()
+        + wide
+}
+
+fn encode_constraint(constraint: &C, out: &mut Vec<u8>) {
+    for expr in [&constraint.a, &constraint.b, &constraint.c] {
+        out.extend_from_slice(&(expr.len() as u32).to_le_bytes());
+        for (signal, value) in expr {
+            out.extend_from_slice(&(*signal as u64).to_le_bytes());
+            value.write_bytes(out);
+        }
+    }
+}
+
+fn decode_constraint(mut bytes: &[u8]) -> Option<C> {
+    let mut decode_expr = || {
+        let terms = read_u32(bytes.get(..4)?) as usize;
+        bytes = &bytes[4..];
+        let mut expr = Vec::with_capacity(terms);
+        for _ in 0..terms {
+            let signal = u64::from_le_bytes(bytes.get(..8)?.try_into().ok()?) as usize;
+            let (value, used) = FieldElement::read_bytes(&bytes[8..])?;
+            bytes = &bytes[8 + used..];
+            expr.push((signal, value));
+        }
+        Some(expr.into_iter().collect::<LinearCombination<usize>>())
+    };
+    let a = decode_expr()?;
+    let b = decode_expr()?;
+    let c = decode_expr()?;
+    Some(C { a, b, c })
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes(bytes[..4].try_into().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::field::Field;
+    use num_bigint::BigInt;
+
+    fn same(left: &io::Result<Option<C>>, right: &C) -> bool {
+        match left {
+            Ok(Some(left)) => left.a == right.a && left.b == right.b && left.c == right.c,
+            _ => false,
+        }
+    }
+
+    fn constraint(i: usize) -> C {
+        let field = Field::new(&BigInt::from(257));
+        let value = |v: usize| field.from_bigint(&BigInt::from(v));
+        let a: LinearCombination<usize> = vec![(i, value(i)), (i + 1, value(2))].into_iter().collect();
+        let c: LinearCombination<usize> = vec![(0, value(3)), (i + 2, value(1))].into_iter().collect();
+        C { a: a.clone(), b: a, c }
+    }
+
+    #[test]
+    fn constraints_survive_the_cache_and_the_file() {
+        // Room for a couple of constraints, so most of them are read from the file
+        let mut storage = DiskConstraints::new(2 * estimated_size(&constraint(0))).unwrap();
+        for i in 0..100 {
+            assert_eq!(storage.add(constraint(i)).unwrap(), i);
+        }
+        storage.flush().unwrap();
+        storage.replace(7, constraint(200)).unwrap();
+        for i in 0..100 {
+            let expected = if i == 7 { constraint(200) } else { constraint(i) };
+            assert!(same(&storage.read(i), &expected));
+        }
+        let removed = storage.extract_with(&|c| c.c.contains_key(&(200 + 2)) || c.c.contains_key(&(50 + 2))).unwrap();
+        assert_eq!(removed.len(), 2);
+        assert_eq!(storage.len(), 98);
+        assert!(same(&storage.read(50), &constraint(52)));
+        assert!(storage.read(98).unwrap().is_none());
+    }
+
+    #[test]
+    fn replaced_records_are_reclaimed() {
+        let mut storage = DiskConstraints::new(0).unwrap();
+        for i in 0..100 {
+            storage.add(constraint(i)).unwrap();
+        }
+        // Several times the limit is replaced, the file stays about twice it
+        for round in 0..1000 {
+            for i in 0..100 {
+                storage.replace(i, constraint(i + round)).unwrap();
+            }
+        }
+        let size = storage.written + storage.pending.len() as u64;
+        assert!(size < 3 * PENDING_LIMIT as u64);
+        assert_eq!(fs::metadata(&storage.path).unwrap().len(), storage.written);
+        for i in 0..100 {
+            assert!(same(&storage.read(i), &constraint(i + 999)));
+        }
+    }
+}
@@ -1,11 +1,10 @@
 use super::*;
-use std::collections::HashMap;
+use crate::linear_combination::LinearCombination;
 
-pub fn code_expression(expr: HashMap<S, BigInt>, tracker: &mut FieldTracker) -> CompressedExpr {
+pub fn code_expression(expr: LinearCombination<S>, tracker: &mut FieldTracker) -> CompressedExpr {
     let mut c_expr = CompressedExpr::new();
     for (var, coeff) in expr {
-        let raw_coeff = coeff.to_signed_bytes_le();
-        let coeff_id = tracker.insert(raw_coeff);
+        let coeff_id = tracker.insert(coeff);
         c_expr.push((coeff_id, var));
     }
     c_expr
@@ -18,17 +17,21 @@ pub fn code_constraint(constraint: C, tracker: &mut FieldTracker) -> CompressedC
     (a, b, c)
 }
 
-pub fn decode_expr(c_expr: &CompressedExpr, tracker: &FieldTracker) -> HashMap<S, BigInt> {
-    let mut decoded_expr = HashMap::new();
-    for (coeff_id, var) in c_expr {
-        let raw_coeff = tracker.get_constant(*coeff_id).unwrap();
-        let coeff = BigInt::from_signed_bytes_le(raw_coeff);
-        decoded_expr.insert(*var, coeff);
-    }
-    decoded_expr
+pub fn decode_expr(c_expr: &CompressedExpr, tracker: &FieldTracker) -> LinearCombination<S> {
+    c_expr.iter().map(|(coeff_id, var)| (*var, tracker.get_constant(*coeff_id).unwrap().clone())).collect()
 }
 
 pub fn decode_constraint(constraint: &CompressedConstraint, tracker: &FieldTracker) -> C {
     let (a, b, c) = constraint;
     C { a: decode_expr(a, tracker), b: decode_expr(b, tracker), c: decode_expr(c, tracker) }
 }
+
+pub fn remap_constants<F>(constraint: &mut CompressedConstraint, mut remap: F)
+where
+    F: FnMut(CID) -> CID,
+{
+    let (a, b, c) = constraint;
+    for (coeff_id, _) in a.iter_mut().chain(b.iter_mut()).chain(c.iter_mut()) {
+        *coeff_id = remap(*coeff_id);
+    }
+}
@@ -1,12 +1,14 @@
 use crate::algebra::Constraint;
-use crate::num_bigint::BigInt;
+use crate::field::FieldElement;
 use constant_tracking::{ConstantTracker, CID};
+use disk::DiskConstraints;
 use std::collections::LinkedList;
+use std::io;
 
+mod disk;
 mod logic;
 
-type RawField = Vec<u8>;
-type FieldTracker = ConstantTracker<RawField>;
+type FieldTracker = ConstantTracker<FieldElement>;
 type S = usize;
 type C = Constraint<usize>;
 
@@ -17,35 +19,59 @@ pub type ConstraintID = usize;
 pub struct ConstraintStorage {
     field_tracker: FieldTracker,
     constraints: Vec<CompressedConstraint>,
+    // When present the constraints are kept there instead
+    disk: Option<DiskConstraints>,
 }
 
 impl ConstraintStorage {
     pub fn new() -> ConstraintStorage {
-        ConstraintStorage { field_tracker: FieldTracker::new(), constraints: Vec::new() }
+        ConstraintStorage { field_tracker: FieldTracker::new(), constraints: Vec::new(), disk: None }
     }
 
-    pub fn add_constraint(&mut self, constraint: C) -> ConstraintID {
+    // A storage that keeps the constraints in a temporary file, with at most
+    // memory_budget bytes of them in memory
+    pub fn on_disk(memory_budget: usize) -> io::Result<ConstraintStorage> {
+        let disk = DiskConstraints::new(memory_budget)?;
+        Ok(ConstraintStorage { field_tracker: FieldTracker::new(), constraints: Vec::new(), disk: Some(disk) })
+    }
+
+    // Only the storages on disk fail, when the temporary file can not be
+    // read or written
+    pub fn add_constraint(&mut self, constraint: C) -> io::Result<ConstraintID> {
+        if let Some(disk) = &mut self.disk {
+            return disk.add(constraint);
+        }
         let id = self.constraints.len();
         let compressed = logic::code_constraint(constraint, &mut self.field_tracker);
         self.constraints.push(compressed);
-        id
+        Ok(id)
     }
 
-    pub fn read_constraint(&self, id: ConstraintID) -> Option<C> {
+    pub fn read_constraint(&self, id: ConstraintID) -> io::Result<Option<C>> {
+        if let Some(disk) = &self.disk {
+            return disk.read(id);
+        }
         if id < self.constraints.len() {
-            Some(logic::decode_constraint(&self.constraints[id], &self.field_tracker))
+            Ok(Some(logic::decode_constraint(&self.constraints[id], &self.field_tracker)))
         } else {
-            None
+            Ok(None)
         }
     }
 
-    pub fn replace(&mut self, id: ConstraintID, new: C) {
+    pub fn replace(&mut self, id: ConstraintID, new: C) -> io::Result<()> {
+        if let Some(disk) = &mut self.disk {
+            return disk.replace(id, new);
+        }
         if id < self.constraints.len() {
             self.constraints[id] = logic::code_constraint(new, &mut self.field_tracker);
         }
+        Ok(())
     }
 
-    pub fn extract_with(&mut self, filter: &dyn Fn(&C) -> bool) -> LinkedList<C> {
+    pub fn extract_with(&mut self, filter: &dyn Fn(&C) -> bool) -> io::Result<LinkedList<C>> {
+        if let Some(disk) = &mut self.disk {
+            return disk.extract_with(filter);
+        }
         let old = std::mem::take(&mut self.constraints);
         let mut removed = LinkedList::new();
         for c in old {
@@ -56,14 +82,108 @@ impl ConstraintStorage {
                 self.constraints.push(c);
             }
         }
-        removed
+        Ok(removed)
+    }
+
+    // Moves the constraints of other after the ones of this storage. Their
+    // constants are merged in the order of their ids, so storages built apart,
+    // as parallel workers do, always combine into the same one.
+    pub fn append(&mut self, other: ConstraintStorage) -> io::Result<()> {
+        if self.disk.is_some() || other.disk.is_some() {
+            for id in other.get_ids() {
+                if let Some(constraint) = other.read_constraint(id)? {
+                    self.add_constraint(constraint)?;
+                }
+            }
+            return Ok(());
+        }
+        let remap = self.field_tracker.merge(other.field_tracker);
+        for mut constraint in other.constraints {
+            logic::remap_constants(&mut constraint, |id| remap[id]);
+            self.constraints.push(constraint);
+        }
+        Ok(())
+    }
+
+    // Removes the constants that no constraint uses after replacing or
+    // extracting constraints
+    pub fn compact_constants(&mut self) {
+        let mut used = vec![false; self.field_tracker.next_id()];
+        for constraint in &mut self.constraints {
+            logic::remap_constants(constraint, |id| {
+                used[id] = true;
+                id
+            });
+        }
+        let remap = self.field_tracker.compact(|id| used[id]);
+        for constraint in &mut self.constraints {
+            logic::remap_constants(constraint, |id| remap[id].unwrap());
+        }
     }
 
     pub fn get_ids(&self) -> Vec<ConstraintID> {
-        (0..self.constraints.len()).collect()
+        let len = self.disk.as_ref().map_or(self.constraints.len(), DiskConstraints::len);
+        (0..len).collect()
     }
 
     pub fn no_constants(&self) -> CID {
         self.field_tracker.next_id()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::field::Field;
+    use crate::linear_combination::LinearCombination;
+    use num_bigint::BigInt;
+
+    // Constraints whose coefficients repeat within and across the storages
+    fn constraints(first: usize, field: &Field) -> Vec<C> {
+        let value = |v: usize| field.from_bigint(&BigInt::from(v));
+        (first..first + 20)
+            .map(|i| {
+                let a: LinearCombination<usize> =
+                    vec![(i, value(i % 7)), (i + 1, value(300))].into_iter().collect();
+                let c: LinearCombination<usize> =
+                    vec![(0, value(i % 3 + 1)), (i + 2, value(i))].into_iter().collect();
+                C { a: a.clone(), b: a, c }
+            })
+            .collect()
+    }
+
+    fn build(mut storage: ConstraintStorage, constraints: &[C]) -> ConstraintStorage {
+        for constraint in constraints {
+            storage.add_constraint(constraint.clone()).unwrap();
+        }
+        storage
+    }
+
+    fn read_all(storage: &ConstraintStorage) -> Vec<C> {
+        let read = |id| storage.read_constraint(id).unwrap().unwrap();
+        storage.get_ids().into_iter().map(read).collect()
+    }
+
+    #[test]
+    fn appended_storages_keep_their_constraints() {
+        let field = Field::new(&BigInt::from(65537));
+        let (first, second) = (constraints(0, &field), constraints(10, &field));
+        let expected: Vec<C> = first.iter().chain(second.iter()).cloned().collect();
+        let on_disk = || ConstraintStorage::on_disk(1 << 10).unwrap();
+        let pairs = vec![
+            (ConstraintStorage::new(), ConstraintStorage::new()),
+            (ConstraintStorage::new(), on_disk()),
+            (on_disk(), ConstraintStorage::new()),
+        ];
+        for (left, right) in pairs {
+            let mut merged = build(left, &first);
+            merged.append(build(right, &second)).unwrap();
+            assert!(read_all(&merged) == expected);
+        }
+        // The shared constants are not repeated
+        let mut merged = build(ConstraintStorage::new(), &first);
+        merged.append(build(ConstraintStorage::new(), &second)).unwrap();
+        let sequential = build(ConstraintStorage::new(), &expected);
+        assert_eq!(merged.no_constants(), sequential.no_constants());
+    }
+}
@@ -0,0 +1,439 @@
+use constant_tracking::BinaryConstant;
+use num_bigint::{BigInt, ModInverse, Sign};
+use num_traits::{One, Zero};
+use std::borrow::Cow;
+use std::convert::TryInto;
+use std::fmt::{Display, Formatter};
+
+// Primes of up to this number of 64 bit limbs keep their elements in fixed
+// size arrays, in Montgomery form
+const MAX_LIMBS: usize = 4;
+type Limbs = [u64; MAX_LIMBS];
+// First byte of a wide element in the byte form of write_bytes
+const WIDE_TAG: u8 = 0xff;
+
+// An element of the field of a prime. Elements do not know their field, the
+// operations are done through the Field they belong to. The elements of a
+// prime that does not fit in MAX_LIMBS limbs are kept as reduced BigInts.
+// Zero has the same representation in every field, so it can be built
+// without knowing the field.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub enum FieldElement {
+    Montgomery(Limbs),
+    Wide(Box<BigInt>),
+}
+
+impl FieldElement {
+    pub fn zero() -> FieldElement {
+        FieldElement::Montgomery([0; MAX_LIMBS])
+    }
+
+    pub fn is_zero(&self) -> bool {
+        match self {
+            FieldElement::Montgomery(limbs) => limbs.iter().all(|limb| *limb == 0),
+            FieldElement::Wide(value) => value.is_zero(),
+        }
+    }
+
+    // Appends the element to the buffer: a byte with the number of limbs up to
+    // the last non zero one followed by those limbs, or WIDE_TAG followed by
+    // the length and the bytes of a wide element
+    pub fn write_bytes(&self, out: &mut Vec<u8>) {
+        match self {
+            FieldElement::Montgomery(limbs) => {
+                let used = limbs.iter().rposition(|limb| *limb != 0).map_or(0, |last| last + 1);
+                out.push(used as u8);
+                for limb in &limbs[..used] {
+                    out.extend_from_slice(&limb.to_le_bytes());
+                }
+            }
+            FieldElement::Wide(value) => {
+                let (_, bytes) = value.to_bytes_le();
+                out.push(WIDE_TAG);
+                out.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+                out.extend_from_slice(&bytes);
+            }
+        }
+    }
+
+    // Reads an element written by write_bytes, together with the number of
+    // bytes it takes
+    pub fn read_bytes(bytes: &[u8]) -> Option<(FieldElement, usize)> {
+        let tag = *bytes.first()?;
+        if tag == WIDE_TAG {
+            let length = u32::from_le_bytes(bytes.get(1..5)?.try_into().ok()?) as usize;
+            let value = BigInt::from_bytes_le(Sign::Plus, bytes.get(5..5 + length)?);
+            Some((wide(value), 5 + length))
+        } else if (tag as usize) <= MAX_LIMBS {
+            let used = tag as usize;
+            let mut limbs = [0; MAX_LIMBS];
+            for (i, limb) in limbs.iter_mut().take(used).enumerate() {
+                let start = 1 + 8 * i;
+                *limb = u64::from_le_bytes(bytes.get(start..start + 8)?.try_into().ok()?);
+            }
+            Some((FieldElement::Montgomery(limbs), 1 + 8 * used))
+        } else {
+            None
+        }
+    }
+}
+
+impl BinaryConstant for FieldElement {
+    fn write_bytes(&self, out: &mut Vec<u8>) {
+        FieldElement::write_bytes(self, out)
+    }
+
+    fn read_bytes(bytes: &[u8]) -> Option<(Self, usize)> {
+        FieldElement::read_bytes(bytes)
+    }
+}
+
+impl Default for FieldElement {
+    fn default() -> Self {
+        FieldElement::zero()
+    }
+}
+
+// Without its field a Montgomery element can only be shown in its internal form
+impl Display for FieldElement {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FieldElement::Montgomery(limbs) => {
+                write!(f, "m0x")?;
+                for limb in limbs.iter().rev() {
+                    write!(f, "{:016x}", limb)?;
+                }
+                Ok(())
+            }
+            FieldElement::Wide(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+// The arithmetic of the field of a prime. The number of limbs of the
+// Montgomery form is the smallest one that holds the prime, so R = 2^(64*n).
+#[derive(Clone)]
+pub struct Field {
+    prime: BigInt,
+    // 0 when the prime does not fit in MAX_LIMBS limbs
+    limbs: usize,
+    modulus: Limbs,
+    // -prime^-1 mod 2^64
+    inv: u64,
+    // R^2 mod prime, to move values into Montgomery form
+    r2: Limbs,
+    // R mod prime, the Montgomery form of 1
+    one: Limbs,
+    // Exponent of the inverse
+    prime_minus_two: Limbs,
+}
+
+impl Field {
+    pub fn new(prime: &BigInt) -> Field {
+        let limbs = prime.bits().div_ceil(64);
+        let mut field = Field {
+            prime: prime.clone(),
+            limbs: if limbs <= MAX_LIMBS { limbs } else { 0 },
+            modulus: [0; MAX_LIMBS],
+            inv: 0,
+            r2: [0; MAX_LIMBS],
+            one: [0; MAX_LIMBS],
+            prime_minus_two: [0; MAX_LIMBS],
+        };
+        if field.limbs == 0 {
+            return field;
+        }
+        field.modulus = to_limbs(prime);
+        let mut inv: u64 = 1;
+        for _ in 0..6 {
+            inv = inv.wrapping_mul(2u64.wrapping_sub(field.modulus[0].wrapping_mul(inv)));
+        }
+        field.inv = inv.wrapping_neg();
+        let r = (BigInt::one() << (64 * limbs)) % prime;
+        field.one = to_limbs(&r);
+        field.r2 = to_limbs(&((&r * &r) % prime));
+        field.prime_minus_two = to_limbs(&(prime - 2));
+        field
+    }
+
+    pub fn prime(&self) -> &BigInt {
+        &self.prime
+    }
+
+    pub fn zero(&self) -> FieldElement {
+        FieldElement::zero()
+    }
+
+    pub fn one(&self) -> FieldElement {
+        if self.limbs == 0 {
+            FieldElement::Wide(Box::new(BigInt::one()))
+        } else {
+            FieldElement::Montgomery(self.one)
+        }
+    }
+
+    pub fn from_bigint(&self, value: &BigInt) -> FieldElement {
+        let mut reduced = value % &self.prime;
+        if reduced.sign() == Sign::Minus {
+            reduced += &self.prime;
+        }
+        if self.limbs == 0 {
+            wide(reduced)
+        } else {
+            FieldElement::Montgomery(self.mont_mul(&to_limbs(&reduced), &self.r2))
+        }
+    }
+
+    pub fn to_bigint(&self, element: &FieldElement) -> BigInt {
+        match element {
+            FieldElement::Montgomery(_) if self.limbs == 0 => BigInt::zero(),
+            FieldElement::Montgomery(limbs) => {
+                let mut unit = [0; MAX_LIMBS];
+                unit[0] = 1;
+                from_limbs(&self.mont_mul(limbs, &unit))
+            }
+            FieldElement::Wide(value) => value.as_ref().clone(),
+        }
+    }
+
+    pub fn add(&self, left: &FieldElement, right: &FieldElement) -> FieldElement {
+        use FieldElement::*;
+        match (left, right) {
+            (Montgomery(l), Montgomery(r)) => {
+                let n = self.limbs;
+                let mut result = [0; MAX_LIMBS];
+                let mut carry = 0u64;
+                for i in 0..n {
+                    let sum = l[i] as u128 + r[i] as u128 + carry as u128;
+                    result[i] = sum as u64;
+                    carry = (sum >> 64) as u64;
+                }
+                if carry != 0 || !self.below_modulus(&result) {
+                    self.sub_modulus(&mut result);
+                }
+                Montgomery(result)
+            }
+            _ => wide((self.wide_value(left).as_ref() + self.wide_value(right).as_ref()) % &self.prime),
+        }
+    }
+
+    pub fn sub(&self, left: &FieldElement, right: &FieldElement) -> FieldElement {
+        self.add(left, &self.neg(right))
+    }
+
+    pub fn neg(&self, element: &FieldElement) -> FieldElement {
+        use FieldElement::*;
+        if element.is_zero() {
+            return element.clone();
+        }
+        match element {
+            Montgomery(limbs) => {
+                let mut result = self.modulus;
+                let mut borrow = 0u64;
+                for i in 0..self.limbs {
+                    let (diff, b0) = result[i].overflowing_sub(limbs[i]);
+                    let (diff, b1) = diff.overflowing_sub(borrow);
+                    result[i] = diff;
+                    borrow = (b0 || b1) as u64;
+                }
+                Montgomery(result)
+            }
+            Wide(value) => wide(&self.prime - value.as_ref()),
+        }
+    }
+
+    pub fn mul(&self, left: &FieldElement, right: &FieldElement) -> FieldElement {
+        use FieldElement::*;
+        match (left, right) {
+            (Montgomery(l), Montgomery(r)) => Montgomery(self.mont_mul(l, r)),
+            _ => wide((self.wide_value(left).as_ref() * self.wide_value(right).as_ref()) % &self.prime),
+        }
+    }
+
+    // None for zero
+    pub fn inverse(&self, element: &FieldElement) -> Option<FieldElement> {
+        use FieldElement::*;
+        if element.is_zero() {
+            return None;
+        }
+        match element {
+            Montgomery(limbs) => {
+                let mut result = self.one;
+                for i in (0..self.limbs).rev() {
+                    for bit in (0..64).rev() {
+                        result = self.mont_mul(&result, &result);
+                        if (self.prime_minus_two[i] >> bit) & 1 == 1 {
+                            result = self.mont_mul(&result, limbs);
+                        }
+                    }
+                }
+                Some(Montgomery(result))
+            }
+            Wide(value) => value.as_ref().mod_inverse(&self.prime).map(wide),
+        }
+    }
+
+    // The inverses of all the elements with a single inversion, None if
+    // some element is zero
+    pub fn batch_inverse(&self, elements: &[FieldElement]) -> Option<Vec<FieldElement>> {
+        let mut prefix = Vec::with_capacity(elements.len());
+        let mut acc = self.one();
+        for element in elements {
+            prefix.push(acc.clone());
+            acc = self.mul(&acc, element);
+        }
+        let mut acc = self.inverse(&acc)?;
+        let mut inverses = vec![FieldElement::zero(); elements.len()];
+        for i in (0..elements.len()).rev() {
+            inverses[i] = self.mul(&acc, &prefix[i]);
+            acc = self.mul(&acc, &elements[i]);
+        }
+        Some(inverses)
+    }
+
+    // The value of an element of a wide field, zero is shared with the
+    // Montgomery elements
+    fn wide_value<'a>(&self, element: &'a FieldElement) -> Cow<'a, BigInt> {
+        match element {
+            FieldElement::Wide(value) => Cow::Borrowed(value.as_ref()),
+            FieldElement::Montgomery(_) => Cow::Owned(BigInt::zero()),
+        }
+    }
+
+    // a*b*R^-1 mod prime, with the CIOS method
+    fn mont_mul(&self, a: &Limbs, b: &Limbs) -> Limbs {
+        let n = self.limbs;
+        let mut t = [0u64; MAX_LIMBS + 2];
+        for i in 0..n {
+            let mut carry = 0u64;
+            for j in 0..n {
+                let acc = t[j] as u128 + a[j] as u128 * b[i] as u128 + carry as u128;
+                t[j] = acc as u64;
+                carry = (acc >> 64) as u64;
+            }
+            let acc = t[n] as u128 + carry as u128;
+            t[n] = acc as u64;
+            t[n + 1] = (acc >> 64) as u64;
+            let m = t[0].wrapping_mul(self.inv);
+            let acc = t[0] as u128 + m as u128 * self.modulus[0] as u128;
+            let mut carry = (acc >> 64) as u64;
+            for j in 1..n {
+                let acc = t[j] as u128 + m as u128 * self.modulus[j] as u128 + carry as u128;
+                t[j - 1] = acc as u64;
+                carry = (acc >> 64) as u64;
+            }
+            let acc = t[n] as u128 + carry as u128;
+            t[n - 1] = acc as u64;
+            t[n] = t[n + 1] + (acc >> 64) as u64;
+        }
+        let mut result = [0; MAX_LIMBS];
+        result[..n].copy_from_slice(&t[..n]);
+        if t[n] != 0 || !self.below_modulus(&result) {
+            self.sub_modulus(&mut result);
+        }
+        result
+    }
+
+    fn below_modulus(&self, value: &Limbs) -> bool {
+        for i in (0..self.limbs).rev() {
+            if value[i] != self.modulus[i] {
+                return value[i] < self.modulus[i];
+            }
+        }
+        false
+    }
+
+    fn sub_modulus(&self, value: &mut Limbs) {
+        let mut borrow = 0u64;
+        for i in 0..self.limbs {
+            let (diff, b0) = value[i].overflowing_sub(self.modulus[i]);
+            let (diff, b1) = diff.overflowing_sub(borrow);
+            value[i] = diff;
+            borrow = (b0 || b1) as u64;
+        }
+    }
+}
+
+fn wide(value: BigInt) -> FieldElement {
+    if value.is_zero() {
+        FieldElement::zero()
+    } else {
+        FieldElement::Wide(Box::new(value))
+    }
+}
+
+// value must be non negative and fit in MAX_LIMBS limbs
+fn to_limbs(value: &BigInt) -> Limbs {
+    let (_, bytes) = value.to_bytes_le();
+    let mut limbs = [0; MAX_LIMBS];
+    for (i, byte) in bytes.iter().enumerate() {
+        limbs[i / 8] |= (*byte as u64) << (8 * (i % 8));
+    }
+    limbs
+}
+
+fn from_limbs(limbs: &Limbs) -> BigInt {
+    let mut bytes = Vec::with_capacity(8 * MAX_LIMBS);
+    for limb in limbs {
+        bytes.extend_from_slice(&limb.to_le_bytes());
+    }
+    BigInt::from_bytes_le(Sign::Plus, &bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modular_arithmetic;
+
+    // Primes of 1, 2, 3 and 4 limbs, and one that does not fit in 4 limbs
+    const PRIMES: [&str; 5] = [
+        "18446744069414584321",
+        "170141183460469231731687303715884105727",
+        "1361129467683753853853498429727072845819",
+        "21888242871839275222246405745257275088548364400416034343698204186575808495617",
+        "6864797660130609714981900799081393217269435300143305409394463459185543183397656052122559640661454554977296311391480858037121987999716643812574028291115057151",
+    ];
+
+    fn samples(prime: &BigInt) -> Vec<BigInt> {
+        let mut samples = vec![BigInt::zero(), BigInt::one(), BigInt::from(-1), BigInt::from(2), prime - 1, prime / 2];
+        let mut value = BigInt::from(0x1234_5678_9abc_def1u64);
+        for _ in 0..8 {
+            value = (&value * &value + 7) % prime;
+            samples.push(value.clone());
+        }
+        samples
+    }
+
+    #[test]
+    fn operations_agree_with_big_integers() {
+        for prime in PRIMES.iter() {
+            let prime = BigInt::parse_bytes(prime.as_bytes(), 10).unwrap();
+            let field = Field::new(&prime);
+            let samples = samples(&prime);
+            for a in &samples {
+                let fa = field.from_bigint(a);
+                assert_eq!(field.to_bigint(&fa), modular_arithmetic::add(a, &BigInt::zero(), &prime));
+                assert_eq!(field.to_bigint(&field.neg(&fa)), modular_arithmetic::prefix_sub(a, &prime));
+                match field.inverse(&fa) {
+                    Some(inverse) => assert!(field.mul(&fa, &inverse) == field.one()),
+                    None => assert!(fa.is_zero()),
+                }
+                assert!(field.from_bigint(&(a + &prime)) == fa);
+                let mut bytes = vec![];
+                fa.write_bytes(&mut bytes);
+                assert!(FieldElement::read_bytes(&bytes) == Some((fa.clone(), bytes.len())));
+                for b in &samples {
+                    let fb = field.from_bigint(b);
+                    assert_eq!(field.to_bigint(&field.add(&fa, &fb)), modular_arithmetic::add(a, b, &prime));
+                    assert_eq!(field.to_bigint(&field.sub(&fa, &fb)), modular_arithmetic::sub(a, b, &prime));
+                    assert_eq!(field.to_bigint(&field.mul(&fa, &fb)), modular_arithmetic::mul(a, b, &prime));
+                }
+            }
+            let elements: Vec<_> = samples.iter().skip(1).map(|a| field.from_bigint(a)).collect();
+            let inverses = field.batch_inverse(&elements).unwrap();
+            for (element, inverse) in elements.iter().zip(&inverses) {
+                assert!(Some(inverse.clone()) == field.inverse(element));
+            }
+        }
+    }
+}
@@ -0,0 +1,216 @@
+use crate::field::{Field, FieldElement};
+use std::cmp::Ordering;
+use std::iter::FromIterator;
+
+// Sparse linear combination c1*s1 + .. + cn*sn, kept as a vector of terms
+// sorted by symbol without repetitions. The constant term uses the default
+// symbol, the smallest one, so it is always the first term when present.
+// Terms with a zero coefficient are kept until they are removed explicitly.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct LinearCombination<C> {
+    terms: Vec<(C, FieldElement)>,
+}
+
+impl<C> Default for LinearCombination<C> {
+    fn default() -> Self {
+        LinearCombination { terms: Vec::new() }
+    }
+}
+
+impl<C: Ord + Clone> LinearCombination<C> {
+    pub fn new() -> LinearCombination<C> {
+        LinearCombination::default()
+    }
+
+    pub fn with_capacity(capacity: usize) -> LinearCombination<C> {
+        LinearCombination { terms: Vec::with_capacity(capacity) }
+    }
+
+    pub fn len(&self) -> usize {
+        self.terms.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.terms.clear();
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, (C, FieldElement)> {
+        self.terms.iter()
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &C> {
+        self.terms.iter().map(|(symbol, _)| symbol)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &FieldElement> {
+        self.terms.iter().map(|(_, value)| value)
+    }
+
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut FieldElement> {
+        self.terms.iter_mut().map(|(_, value)| value)
+    }
+
+    fn position(&self, symbol: &C) -> Result<usize, usize> {
+        self.terms.binary_search_by(|(s, _)| s.cmp(symbol))
+    }
+
+    pub fn contains_key(&self, symbol: &C) -> bool {
+        self.position(symbol).is_ok()
+    }
+
+    pub fn get(&self, symbol: &C) -> Option<&FieldElement> {
+        self.position(symbol).ok().map(|pos| &self.terms[pos].1)
+    }
+
+    pub fn insert(&mut self, symbol: C, value: FieldElement) -> Option<FieldElement> {
+        match self.position(&symbol) {
+            Ok(pos) => Some(std::mem::replace(&mut self.terms[pos].1, value)),
+            Err(pos) => {
+                self.terms.insert(pos, (symbol, value));
+                None
+            }
+        }
+    }
+
+    pub fn remove(&mut self, symbol: &C) -> Option<FieldElement> {
+        self.position(symbol).ok().map(|pos| self.terms.remove(pos).1)
+    }
+
+    // The coefficient of the symbol, added with value zero if it is missing
+    pub fn coefficient_mut(&mut self, symbol: &C) -> &mut FieldElement {
+        let pos = match self.position(symbol) {
+            Ok(pos) => pos,
+            Err(pos) => {
+                self.terms.insert(pos, (symbol.clone(), FieldElement::zero()));
+                pos
+            }
+        };
+        &mut self.terms[pos].1
+    }
+
+    pub fn scale(&mut self, constant: &FieldElement, field: &Field) {
+        for (_, value) in &mut self.terms {
+            *value = field.mul(value, constant);
+        }
+    }
+
+    pub fn negate(&mut self, field: &Field) {
+        for (_, value) in &mut self.terms {
+            *value = field.neg(value);
+        }
+    }
+
+    // self + factor*other
+    pub fn add_scaled(&mut self, other: &LinearCombination<C>, factor: &FieldElement, field: &Field) {
+        self.merge(other, field, |value| field.mul(factor, value));
+    }
+
+    pub fn add(&mut self, other: &LinearCombination<C>, field: &Field) {
+        self.merge(other, field, |value| value.clone());
+    }
+
+    // Adds the terms of other, transformed, walking both vectors in order
+    fn merge<F>(&mut self, other: &LinearCombination<C>, field: &Field, transform: F)
+    where
+        F: Fn(&FieldElement) -> FieldElement,
+    {
+        let old = std::mem::take(&mut self.terms);
+        let mut terms = Vec::with_capacity(old.len() + other.len());
+        let mut left = old.into_iter().peekable();
+        let mut right = other.terms.iter().peekable();
+        loop {
+            let order = match (left.peek(), right.peek()) {
+                (Some((l, _)), Some((r, _))) => l.cmp(r),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => break,
+            };
+            match order {
+                Ordering::Less => terms.push(left.next().unwrap()),
+                Ordering::Greater => {
+                    let (symbol, value) = right.next().unwrap();
+                    terms.push((symbol.clone(), transform(value)));
+                }
+                Ordering::Equal => {
+                    let (symbol, value) = left.next().unwrap();
+                    let (_, other_value) = right.next().unwrap();
+                    terms.push((symbol, field.add(&value, &transform(other_value))));
+                }
+            }
+        }
+        self.terms = terms;
+    }
+
+    pub fn remove_zeros(&mut self) {
+        self.terms.retain(|(_, value)| !value.is_zero());
+    }
+
+    // Renames the symbols, the constant term keeps its symbol
+    pub fn map_symbols<K, F>(&self, mut rename: F) -> LinearCombination<K>
+    where
+        C: Default,
+        K: Ord + Clone + Default,
+        F: FnMut(&C) -> K,
+    {
+        let constant = C::default();
+        self.terms
+            .iter()
+            .map(|(symbol, value)| {
+                let new = if *symbol == constant { K::default() } else { rename(symbol) };
+                (new, value.clone())
+            })
+            .collect()
+    }
+}
+
+// Repeated symbols keep the last value, as an insertion in a map would do
+impl<C: Ord + Clone> FromIterator<(C, FieldElement)> for LinearCombination<C> {
+    fn from_iter<I: IntoIterator<Item = (C, FieldElement)>>(iter: I) -> Self {
+        let mut terms: Vec<(C, FieldElement)> = iter.into_iter().collect();
+        terms.reverse();
+        terms.sort_by(|(l, _), (r, _)| l.cmp(r));
+        terms.dedup_by(|(next, _), (first, _)| next == first);
+        LinearCombination { terms }
+    }
+}
+
+impl<C> IntoIterator for LinearCombination<C> {
+    type Item = (C, FieldElement);
+    type IntoIter = std::vec::IntoIter<(C, FieldElement)>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.terms.into_iter()
+    }
+}
+
+impl<'a, C> IntoIterator for &'a LinearCombination<C> {
+    type Item = &'a (C, FieldElement);
+    type IntoIter = std::slice::Iter<'a, (C, FieldElement)>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.terms.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num_bigint::BigInt;
+
+    #[test]
+    fn merges_keep_the_terms_sorted() {
+        let field = Field::new(&BigInt::from(257));
+        let value = |v: i32| field.from_bigint(&BigInt::from(v));
+        let mut left: LinearCombination<usize> = vec![(5, value(1)), (0, value(3)), (2, value(4))].into_iter().collect();
+        let right: LinearCombination<usize> = vec![(2, value(-2)), (7, value(1))].into_iter().collect();
+        left.add_scaled(&right, &value(2), &field);
+        let terms: Vec<_> = left.iter().map(|(s, v)| (*s, field.to_bigint(v))).collect();
+        let expected: Vec<_> = vec![(0, 3), (2, 0), (5, 1), (7, 2)].into_iter().map(|(s, v)| (s, BigInt::from(v))).collect();
+        assert_eq!(terms, expected);
+        left.remove_zeros();
+        assert!(!left.contains_key(&2));
+        assert_eq!(left.keys().cloned().collect::<Vec<_>>(), vec![0, 5, 7]);
+    }
+}
@@ -1,6 +1,6 @@
-use super::num_bigint::BigInt;
+use crate::field::{Field, FieldElement};
+use crate::linear_combination::LinearCombination;
 use crate::num_traits::ToPrimitive;
-use crate::modular_arithmetic;
 use std::collections::{BTreeMap, HashMap, HashSet, LinkedList};
 use std::mem::replace;
 
@@ -8,7 +8,7 @@ type C = crate::algebra::Constraint<usize>;
 type S = crate::algebra::Substitution<usize>;
 type A = crate::algebra::ArithmeticExpression<usize>;
 type SH = BTreeMap<usize, S>;
-type SHNotNormalized = BTreeMap<usize, (BigInt, S)>;
+type SHNotNormalized = BTreeMap<usize, (FieldElement, S)>;
 
 #[allow(dead_code)]
 fn debug_check_keys_in_order(tree: &BTreeMap<usize, S>) -> bool {
@@ -117,7 +117,7 @@ fn substitution_process_1(
     signals: &mut SignalDefinition,
     constraints: &mut LinkedList<C>,
     substitutions: &mut SH,
-    field: &BigInt,
+    field: &Field,
 ) {
     let mut lconst = LinkedList::new();
     while let Option::Some(actual_constraint) = LinkedList::pop_back(constraints) {
@@ -131,7 +131,7 @@ fn substitution_process_2(
     signals: &mut SignalDefinition,
     constraints: &mut LinkedList<C>,
     substitutions: &mut SHNotNormalized,
-    field: &BigInt,
+    field: &Field,
 ) {
     let mut lconst = LinkedList::new();
     while let Option::Some(actual_constraint) = LinkedList::pop_back(constraints) {
@@ -144,7 +144,7 @@ fn substitution_process_3(
     signals: &mut SignalDefinition,
     constraints: &mut LinkedList<C>,
     substitutions: &mut SHNotNormalized,
-    field: &BigInt,
+    field: &Field,
 ) {
     let mut lconst = LinkedList::new();
     while let Option::Some(actual_constraint) = LinkedList::pop_back(constraints) {
@@ -158,7 +158,7 @@ fn substitution_process_4(
     constraints: &mut LinkedList<C>,
     substitutions: &mut SHNotNormalized,
     num_signals: usize,
-    field: &BigInt,
+    field: &Field,
 ) {
     let mut lconst = LinkedList::new();
     let mut vec_constraints = Vec::new();
@@ -190,7 +190,7 @@ fn treat_constraint_1(
     substitutions: &mut SH,
     lconst: &mut LinkedList<C>,
     mut work: C,
-    field: &BigInt,
+    field: &Field,
 ) {
     loop {
         if C::is_empty(&work) {
@@ -210,11 +210,7 @@ fn treat_constraint_1(
             break;
         }
         let in_conflict = in_conflict.unwrap();
-        let right = S::decompose(in_conflict).1;
-        let left = S::decompose(substitution).1;
-        let merge = A::sub(&left, &right, field);
-        work = A::transform_expression_to_constraint_form(merge, field).unwrap();
-        C::remove_zero_value_coefficients(&mut work);
+        work = merge_substitutions(&field.one(), substitution, &field.one(), in_conflict, field);
     }
 }
 
@@ -224,7 +220,7 @@ fn treat_constraint_2(
     substitutions: &mut SHNotNormalized,
     lconst: &mut LinkedList<C>,
     mut work: C,
-    field: &BigInt,
+    field: &Field,
 ) {
     loop {
         if C::is_empty(&work) {
@@ -244,15 +240,7 @@ fn treat_constraint_2(
             break;
         }
         let (in_conflict_coef, in_conflict_subs) = in_conflict.unwrap();
-        let right = S::decompose(in_conflict_subs).1;
-        let left = S::decompose(substitution).1;
-        let exp_coef_right = A::Number {value : in_conflict_coef};
-        let exp_coef_left = A::Number {value : coefficient};
-        let new_left  = A::mul(&exp_coef_right,&left,field);
-        let new_right  = A::mul(&exp_coef_left,&right,field);
-        let merge = A::sub(&new_left, &new_right, field);
-        work = A::transform_expression_to_constraint_form(merge, field).unwrap();
-        C::remove_zero_value_coefficients(&mut work);
+        work = merge_substitutions(&coefficient, substitution, &in_conflict_coef, in_conflict_subs, field);
     }
 }
 
@@ -261,7 +249,7 @@ fn treat_constraint_3(
     substitutions: &mut SHNotNormalized,
     lconst: &mut LinkedList<C>,
     mut work: C,
-    field: &BigInt,
+    field: &Field,
 ) {
     loop {
         if C::is_empty(&work) {
@@ -281,15 +269,7 @@ fn treat_constraint_3(
             break;
         }
         let (in_conflict_coef, in_conflict_subs) = in_conflict.unwrap();
-        let right = S::decompose(in_conflict_subs).1;
-        let left = S::decompose(substitution).1;
-        let exp_coef_right = A::Number {value : in_conflict_coef};
-        let exp_coef_left = A::Number {value : coefficient};
-        let new_left  = A::mul(&exp_coef_right,&left,field);
-        let new_right  = A::mul(&exp_coef_left,&right,field);
-        let merge = A::sub(&new_left, &new_right, field);
-        work = A::transform_expression_to_constraint_form(merge, field).unwrap();
-        C::remove_zero_value_coefficients(&mut work);
+        work = merge_substitutions(&coefficient, substitution, &in_conflict_coef, in_conflict_subs, field);
     }
 }
 
@@ -300,7 +280,7 @@ fn treat_unique_constraint_4(
     work: C,
     info_ocurrences: &mut SignalsInformation,
     signal: usize,
-    field: &BigInt,
+    field: &Field,
 ) {
 
     let (coefficient, substitution) = C::clear_signal_from_linear_not_normalized(work, &signal, field);
@@ -315,7 +295,7 @@ fn treat_constraint_4(
     lconst: &mut LinkedList<C>,
     mut work: C,
     info_ocurrences: &mut SignalsInformation,
-    field: &BigInt,
+    field: &Field,
 ) {
     loop {
         if C::is_empty(&work) {
@@ -336,18 +316,27 @@ fn treat_constraint_4(
             break;
         }
         let (in_conflict_coef, in_conflict_subs) = in_conflict.unwrap();
-        let right = S::decompose(in_conflict_subs).1;
-        let left = S::decompose(substitution).1;
-        let exp_coef_right = A::Number {value : in_conflict_coef};
-        let exp_coef_left = A::Number {value : coefficient};
-        let new_left  = A::mul(&exp_coef_right,&left,field);
-        let new_right  = A::mul(&exp_coef_left,&right,field);
-        let merge = A::sub(&new_left, &new_right, field);
-        work = A::transform_expression_to_constraint_form(merge, field).unwrap();
-        C::remove_zero_value_coefficients(&mut work);
+        work = merge_substitutions(&coefficient, substitution, &in_conflict_coef, in_conflict_subs, field);
     }
 }
 
+// Two substitutions c_0*s = e_0 and c_1*s = e_1 of the same signal give the
+// linear constraint c_0*e_1 - c_1*e_0 = 0
+fn merge_substitutions(
+    coefficient: &FieldElement,
+    substitution: S,
+    in_conflict_coef: &FieldElement,
+    in_conflict: S,
+    field: &Field,
+) -> C {
+    let mut c = in_conflict.to;
+    c.scale(coefficient, field);
+    c.add_scaled(&substitution.to, &field.neg(in_conflict_coef), field);
+    let mut work = C { a: LinearCombination::new(), b: LinearCombination::new(), c };
+    C::remove_zero_value_coefficients(&mut work);
+    work
+}
+
 #[allow(dead_code)]
 fn take_signal_1(signals: &SignalDefinition, constraint: &C) -> Option<usize> {
     let mut ret = Option::None;
@@ -411,27 +400,14 @@ fn take_signal_4(signals: &SignalDefinition4, info_ocurrences: &SignalsInformati
 }
 
 
-fn normalize_substitutions(substitutions: SHNotNormalized, field: &BigInt) -> SH{
-    let mut coeffs : Vec<BigInt> = Vec::new();
-
-    for (_signal, (coeff, _sub)) in &substitutions{
-        coeffs.push(coeff.clone());
-    }
-    
-    let inverses = modular_arithmetic::multi_inv(&coeffs, field);
+fn normalize_substitutions(substitutions: SHNotNormalized, field: &Field) -> SH{
+    let coeffs: Vec<FieldElement> = substitutions.values().map(|(coeff, _)| coeff.clone()).collect();
+    let inverses = field.batch_inverse(&coeffs).unwrap();
     let mut tree : BTreeMap<usize,S> = BTreeMap::new();
-    let mut i = 0;
-    for (signal, (_coeff, sub)) in substitutions{
-        let inv = inverses.get(i).unwrap();
-        let arith_sub = A::hashmap_into_arith(sub.to().clone());
-        let mult_by_inverse = A::mul(
-            &arith_sub, 
-            &A::Number {value : inv.clone()}, 
-            field
-        );
-        let new_sub = S::new(signal.clone(), mult_by_inverse).unwrap(); 
-        tree.insert(signal, new_sub);
-        i = i + 1;
+    for ((signal, (_coeff, mut sub)), inv) in substitutions.into_iter().zip(inverses) {
+        sub.to.scale(&inv, field);
+        sub.to.coefficient_mut(&S::constant_coefficient());
+        tree.insert(signal, sub);
     }
     tree
 }
@@ -448,7 +424,7 @@ fn take_substitutions_to_be_applied<'a>(sh: &'a HashMap<usize, S>, subs: &S) ->
 }
 
 #[allow(dead_code)]
-fn create_nonoverlapping_substitutions(possible_overlap: SH, field: &BigInt) -> HashMap<usize, S> {
+fn create_nonoverlapping_substitutions(possible_overlap: SH, field: &Field) -> HashMap<usize, S> {
     debug_assert!(debug_check_keys_in_order(&possible_overlap));
     let mut no_overlap = HashMap::with_capacity(possible_overlap.len());
     for (s, mut substitution) in possible_overlap {
@@ -462,7 +438,7 @@ fn create_nonoverlapping_substitutions(possible_overlap: SH, field: &BigInt) ->
     no_overlap
 }
 
-fn create_nonoverlapping_substitutions_4(mut possible_overlap: SH, signals: &SignalDefinition4,field: &BigInt) -> HashMap<usize, S> {
+fn create_nonoverlapping_substitutions_4(mut possible_overlap: SH, signals: &SignalDefinition4,field: &Field) -> HashMap<usize, S> {
     debug_assert!(debug_check_keys_in_order(&possible_overlap));
 
     let mut no_overlap = HashMap::with_capacity(possible_overlap.len());
@@ -493,12 +469,12 @@ pub fn debug_substitution_check(substitutions: &HashMap<usize, S>) -> bool {
     result
 }
 
-pub fn fast_encoded_constraint_substitution(c: &mut C, enc: &HashMap<usize, A>, field: &BigInt)-> bool {
+pub fn fast_encoded_constraint_substitution(c: &mut C, enc: &HashMap<usize, A>, field: &Field)-> bool {
     let signals = C::take_cloned_signals(c);
     let mut applied_substitution = false;
     for signal in signals {
         if let Some(expr) = HashMap::get(enc, &signal) {
-            let sub = S::new(signal, expr.clone()).unwrap();
+            let sub = S::new(signal, expr.clone(), field).unwrap();
             C::apply_substitution(c, &sub, field);
             applied_substitution = true;
         }
@@ -506,28 +482,28 @@ pub fn fast_encoded_constraint_substitution(c: &mut C, enc: &HashMap<usize, A>,
     applied_substitution
 }
 
-pub fn fast_encoded_substitution_substitution(s: &mut S, enc: &HashMap<usize, A>, field: &BigInt) {
+pub fn fast_encoded_substitution_substitution(s: &mut S, enc: &HashMap<usize, A>, field: &Field) {
     let signals = S::take_cloned_signals(s);
     for signal in signals {
         if let Some(expr) = HashMap::get(enc, &signal) {
-            let sub = S::new(signal, expr.clone()).unwrap();
+            let sub = S::new(signal, expr.clone(), field).unwrap();
             S::apply_substitution(s, &sub, field);
         }
     }
     S::rmv_zero_coefficients(s)
 }
 
-pub fn build_encoded_fast_substitutions(fast_sub: LinkedList<S>) -> HashMap<usize, A> {
+pub fn build_encoded_fast_substitutions(fast_sub: LinkedList<S>, field: &Field) -> HashMap<usize, A> {
     let mut encoded = HashMap::with_capacity(LinkedList::len(&fast_sub));
     for sub in fast_sub {
-        let (from, to) = S::decompose(sub);
+        let (from, to) = S::decompose(sub, field);
         HashMap::insert(&mut encoded, from, to);
     }
     encoded
 }
 
 pub struct Config<T> {
-    pub field: BigInt,
+    pub field: Field,
     pub constraints: LinkedList<C>,
     pub forbidden: T,
     pub num_signals: usize,
@@ -653,7 +629,7 @@ where
 pub fn check_substitutions(
     subs_1: &LinkedList<S>, 
     subs_2: &LinkedList<S>,
-    field: &BigInt,
+    field: &Field,
 ){
     // First consider the constraints of the first substitution and apply on them the second substitution.
     // The result should be the identity
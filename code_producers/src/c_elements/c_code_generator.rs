@@ -3,7 +3,6 @@ use num_bigint_dig::{BigInt, Sign};
 use serde_json::json;
 use std::fs::File;
 use std::io::prelude::*;
-use std::path::PathBuf;
 
 // Types
 const T_U64: &str = "u64";
@@ -316,6 +315,14 @@ pub fn circuit_constants(at: CInstruction) -> CInstruction {
 pub fn store_circuit_constants(at: CInstruction, value: CInstruction) -> CInstruction {
     format!("{} = {}", circuit_constants(at), value)
 }
+// First constant of the instance running a shared template body
+pub const INSTANCE_CONSTANTS: &str = "instanceConstants";
+pub fn declare_instance_constants() -> CInstruction {
+    format!("uint {}", INSTANCE_CONSTANTS)
+}
+pub fn instance_constant(at: CInstruction) -> CInstruction {
+    circuit_constants(format!("{} + {}", INSTANCE_CONSTANTS, at))
+}
 pub const FREE_IN_COMPONENT_MEM_MUTEX: &str = "freePositionInComponentMemoryMutex"; // type u32
 pub const FREE_IN_COMPONENT_MEM: &str = "freePositionInComponentMemory"; // type u32
 pub fn declare_free_position_in_component_memory() -> CInstruction {
@@ -766,13 +773,7 @@ pub fn generate_function_release_memory_circuit() -> Vec<String>{
     instructions
   }
 
-pub fn generate_main_cpp_file(c_folder: &PathBuf) -> std::io::Result<()> {
-    use std::io::BufWriter;
-    let mut file_path = c_folder.clone();
-    file_path.push("main");
-    file_path.set_extension("cpp");
-    let file_name = file_path.to_str().unwrap();
-    let mut c_file = BufWriter::new(File::create(file_name).unwrap());
+pub fn generate_main_cpp_file(c_file: &mut dyn Write) -> std::io::Result<()> {
     let mut code = "".to_string();
     let file = include_str!("common/main.cpp");
     for line in file.lines() {
@@ -783,13 +784,7 @@ pub fn generate_main_cpp_file(c_folder: &PathBuf) -> std::io::Result<()> {
     Ok(())
 }
 
-pub fn generate_circom_hpp_file(c_folder: &PathBuf) -> std::io::Result<()> {
-    use std::io::BufWriter;
-    let mut file_path = c_folder.clone();
-    file_path.push("circom");
-    file_path.set_extension("hpp");
-    let file_name = file_path.to_str().unwrap();
-    let mut c_file = BufWriter::new(File::create(file_name).unwrap());
+pub fn generate_circom_hpp_file(c_file: &mut dyn Write) -> std::io::Result<()> {
     let mut code = "".to_string();
     let file = include_str!("common/circom.hpp");
     for line in file.lines() {
@@ -800,13 +795,7 @@ pub fn generate_circom_hpp_file(c_folder: &PathBuf) -> std::io::Result<()> {
     Ok(())
 }
 
-pub fn generate_fr_hpp_file(c_folder: &PathBuf, prime: &String) -> std::io::Result<()> {
-    use std::io::BufWriter;
-    let mut file_path = c_folder.clone();
-    file_path.push("fr");
-    file_path.set_extension("hpp");
-    let file_name = file_path.to_str().unwrap();
-    let mut c_file = BufWriter::new(File::create(file_name).unwrap());
+pub fn generate_fr_hpp_file(c_file: &mut dyn Write, prime: &String) -> std::io::Result<()> {
     let mut code = "".to_string();
     let file = match prime.as_ref(){
         "bn128" => include_str!("bn128/fr.hpp"),
@@ -825,13 +814,7 @@ pub fn generate_fr_hpp_file(c_folder: &PathBuf, prime: &String) -> std::io::Resu
     Ok(())
 }
 
-pub fn generate_calcwit_hpp_file(c_folder: &PathBuf) -> std::io::Result<()> {
-    use std::io::BufWriter;
-    let mut file_path = c_folder.clone();
-    file_path.push("calcwit");
-    file_path.set_extension("hpp");
-    let file_name = file_path.to_str().unwrap();
-    let mut c_file = BufWriter::new(File::create(file_name).unwrap());
+pub fn generate_calcwit_hpp_file(c_file: &mut dyn Write) -> std::io::Result<()> {
     let mut code = "".to_string();
     let file = include_str!("common/calcwit.hpp");
     for line in file.lines() {
@@ -842,13 +825,7 @@ pub fn generate_calcwit_hpp_file(c_folder: &PathBuf) -> std::io::Result<()> {
     Ok(())
 }
 
-pub fn generate_fr_cpp_file(c_folder: &PathBuf, prime: &String) -> std::io::Result<()> {
-    use std::io::BufWriter;
-    let mut file_path = c_folder.clone();
-    file_path.push("fr");
-    file_path.set_extension("cpp");
-    let file_name = file_path.to_str().unwrap();
-    let mut c_file = BufWriter::new(File::create(file_name).unwrap());
+pub fn generate_fr_cpp_file(c_file: &mut dyn Write, prime: &String) -> std::io::Result<()> {
     let mut code = "".to_string();
     let file = match prime.as_ref(){
         "bn128" => include_str!("bn128/fr.cpp"),
@@ -857,8 +834,8 @@ pub fn generate_fr_cpp_file(c_folder: &PathBuf, prime: &String) -> std::io::Resu
         "grumpkin" => include_str!("grumpkin/fr.cpp"),
         "pallas" => include_str!("pallas/fr.cpp"),
         "vesta" => include_str!("vesta/fr.cpp"),
-        
-        _ => unreachable!(),
+        // it only relies on fr.hpp, so any other prime takes the same file
+        _ => include_str!("bn128/fr.cpp"),
     };
     for line in file.lines() {
         code = format!("{}{}\n", code, line);
@@ -868,13 +845,7 @@ pub fn generate_fr_cpp_file(c_folder: &PathBuf, prime: &String) -> std::io::Resu
     Ok(())
 }
 
-pub fn generate_calcwit_cpp_file(c_folder: &PathBuf) -> std::io::Result<()> {
-    use std::io::BufWriter;
-    let mut file_path = c_folder.clone();
-    file_path.push("calcwit");
-    file_path.set_extension("cpp");
-    let file_name = file_path.to_str().unwrap();
-    let mut c_file = BufWriter::new(File::create(file_name).unwrap());
+pub fn generate_calcwit_cpp_file(c_file: &mut dyn Write) -> std::io::Result<()> {
     let mut code = "".to_string();
     let file = include_str!("common/calcwit.cpp");
     for line in file.lines() {
@@ -885,13 +856,7 @@ pub fn generate_calcwit_cpp_file(c_folder: &PathBuf) -> std::io::Result<()> {
     Ok(())
 }
 
-pub fn generate_fr_asm_file(c_folder: &PathBuf, prime: &String) -> std::io::Result<()> {
-    use std::io::BufWriter;
-    let mut file_path = c_folder.clone();
-    file_path.push("fr");
-    file_path.set_extension("asm");
-    let file_name = file_path.to_str().unwrap();
-    let mut c_file = BufWriter::new(File::create(file_name).unwrap());
+pub fn generate_fr_asm_file(c_file: &mut dyn Write, prime: &String) -> std::io::Result<()> {
     let mut code = "".to_string();
     let file = match prime.as_ref(){
         "bn128" => include_str!("bn128/fr.asm"),
@@ -910,13 +875,97 @@ pub fn generate_fr_asm_file(c_folder: &PathBuf, prime: &String) -> std::io::Resu
     Ok(())
 }
 
+// Constants of the field of the prime, with every number written as the
+// list of its 64 bit limbs, least significant first
+struct FieldDescription {
+    n64: usize,
+    n_bits: usize,
+    q: String,
+    half: String,
+    r2: String,
+    r3: String,
+    np: u64,
+    lbo_mask: u64,
+}
+
+fn field_description(prime: &str) -> FieldDescription {
+    let q = prime.parse::<BigInt>().unwrap();
+    let n_bits = q.bits();
+    let n64 = n_bits.div_ceil(64);
+    let r = BigInt::from(1) << (64 * n64);
+    let limbs = |n: &BigInt| -> Vec<u64> {
+        let (_, mut bytes) = n.to_bytes_le();
+        bytes.resize(8 * n64, 0);
+        bytes.chunks(8).map(|limb| limb.iter().rev().fold(0, |acc, &byte| (acc << 8) | byte as u64)).collect()
+    };
+    let hex = |n: &BigInt| {
+        let digits: Vec<String> = limbs(n).iter().map(|limb| format!("0x{:016x}", limb)).collect();
+        digits.join(", ")
+    };
+    // np = -q^-1 mod 2^64, by Newton's iteration over the inverse of the lowest limb
+    let q0 = limbs(&q)[0];
+    let mut inverse: u64 = 1;
+    for _ in 0..6 {
+        inverse = inverse.wrapping_mul(2u64.wrapping_sub(q0.wrapping_mul(inverse)));
+    }
+    FieldDescription {
+        n64,
+        n_bits,
+        q: hex(&q),
+        half: hex(&((&q - 1) / 2)),
+        r2: hex(&((&r * &r) % &q)),
+        r3: hex(&((&r * &r * &r) % &q)),
+        np: inverse.wrapping_neg(),
+        lbo_mask: if n_bits % 64 == 0 { u64::MAX } else { (1u64 << (n_bits % 64)) - 1 },
+    }
+}
+
+// The header of the field of the given prime, written in decimal
+pub fn generate_portable_fr_hpp_file(c_file: &mut dyn Write, prime: &str) -> std::io::Result<()> {
+    let field = field_description(prime);
+    let template = handlebars::Handlebars::new();
+    let code = template
+        .render_template(
+            include_str!("common/fr.hpp"),
+            &json!({
+                "n64": field.n64,
+                "max_bits": field.n_bits,
+            }),
+        )
+        .expect("must render");
+    c_file.write_all(code.as_bytes())?;
+    c_file.flush()?;
+    Ok(())
+}
+
+// The C++ implementation of the field of the given prime that replaces fr.asm
+pub fn generate_fr_portable_cpp_file(c_file: &mut dyn Write, prime: &str) -> std::io::Result<()> {
+    let field = field_description(prime);
+    let template = handlebars::Handlebars::new();
+    let code = template
+        .render_template(
+            include_str!("common/fr_portable.cpp"),
+            &json!({
+                "n_bits": field.n_bits,
+                "np": format!("0x{:016x}", field.np),
+                "lbo_mask": format!("0x{:016x}", field.lbo_mask),
+                "q": field.q,
+                "half": field.half,
+                "r2": field.r2,
+                "r3": field.r3,
+            }),
+        )
+        .expect("must render");
+    c_file.write_all(code.as_bytes())?;
+    c_file.flush()?;
+    Ok(())
+}
+
 pub fn generate_make_file(
-    c_folder: &PathBuf,
+    c_file: &mut dyn Write,
     run_name: &str,
     producer: &CProducer,
 ) -> std::io::Result<()> {
-    use std::io::BufWriter;
-
     let makefile_template: &str = include_str!("common/makefile");
 
     let template = handlebars::Handlebars::new();
@@ -926,14 +975,11 @@ pub fn generate_make_file(
             &json!({
                 "run_name": run_name,
                 "has_parallelism": producer.has_parallelism,
+                "portable": producer.portable_flag,
             }),
         )
         .expect("must render");
 
-    let mut file_path = c_folder.clone();
-    file_path.push("Makefile");
-    let file_name = file_path.to_str().unwrap();
-    let mut c_file = BufWriter::new(File::create(file_name).unwrap());
     c_file.write_all(code.as_bytes())?;
     c_file.flush()?;
     Ok(())
@@ -1013,4 +1059,30 @@ mod tests {
         let _rc = generate_c_file(pathc, &producer);
         assert!(true);
     }
+
+    #[test]
+    fn portable_field_constants_match_the_assembly() {
+        let fields = [
+            (include_str!("bn128/fr.asm"), "21888242871839275222246405745257275088548364400416034343698204186575808495617"),
+            (include_str!("bls12381/fr.asm"), "52435875175126190479447740508185965837690552500527637822603658699938581184513"),
+            (include_str!("goldilocks/fr.asm"), "18446744069414584321"),
+            (include_str!("grumpkin/fr.asm"), "21888242871839275222246405745257275088696311157297823662689037894645226208583"),
+            (include_str!("pallas/fr.asm"), "28948022309329048855892746252171976963363056481941560715954676764349967630337"),
+            (include_str!("vesta/fr.asm"), "28948022309329048855892746252171976963363056481941647379679742748393362948097"),
+        ];
+        for (asm, prime) in fields.iter() {
+            let data = |name: &str| {
+                let line = asm.lines().find(|line| line.starts_with(&format!("{} ", name))).unwrap();
+                let values: Vec<&str> = line.split("dq").nth(1).unwrap().split(',').map(|v| v.trim()).collect();
+                values.join(", ")
+            };
+            let field = field_description(prime);
+            assert_eq!(field.q, data("q"));
+            assert_eq!(field.half, data("half"));
+            assert_eq!(field.r2, data("R2"));
+            assert_eq!(field.r3, data("R3"));
+            assert_eq!(format!("0x{:016x}", field.lbo_mask), data("lboMask"));
+            assert_eq!(format!("0x{:016x}", field.np), data("np"));
+        }
+    }
 }
@@ -0,0 +1,558 @@
+#include "fr.hpp"
+
+// Field arithmetic in plain C++, used instead of fr.asm by --c-portable.
+// Long values are kept in Fr_N64 limbs of 64 bits, least significant first.
+
+#define Fr_MONTGOMERY 0x40000000
+
+// Constants of the prime field
+static const int Fr_nBits = {{n_bits}};
+static const uint64_t Fr_np = {{np}};
+static const uint64_t Fr_lboMask = {{lbo_mask}};
+static const FrRawElement Fr_rawHalf = { {{half}} };
+static const FrRawElement Fr_rawR2 = { {{r2}} };
+static const FrRawElement Fr_rawOne = { 1 };
+FrRawElement Fr_rawq = { {{q}} };
+FrRawElement Fr_rawR3 = { {{r3}} };
+FrElement Fr_q = { 0, Fr_LONG, { {{q}} } };
+FrElement Fr_R3 = { 0, Fr_LONG, { {{r3}} } };
+
+// Limb arithmetic
+
+// a*b + c + d, which always fits in 128 bits
+static inline uint64_t mulAdd(uint64_t a, uint64_t b, uint64_t c, uint64_t d, uint64_t *hi) {
+#ifdef __SIZEOF_INT128__
+    unsigned __int128 r = (unsigned __int128)a * b + c + d;
+    *hi = (uint64_t)(r >> 64);
+    return (uint64_t)r;
+#else
+    uint64_t aL = a & 0xFFFFFFFF, aH = a >> 32;
+    uint64_t bL = b & 0xFFFFFFFF, bH = b >> 32;
+    uint64_t ll = aL * bL, lh = aL * bH, hl = aH * bL, hh = aH * bH;
+    uint64_t mid = (ll >> 32) + (lh & 0xFFFFFFFF) + (hl & 0xFFFFFFFF);
+    uint64_t lo = (ll & 0xFFFFFFFF) | (mid << 32);
+    uint64_t h = hh + (lh >> 32) + (hl >> 32) + (mid >> 32);
+    lo += c;
+    h += lo < c;
+    lo += d;
+    h += lo < d;
+    *hi = h;
+    return lo;
+#endif
+}
+
+static inline uint64_t addCarry(uint64_t a, uint64_t b, uint64_t *carry) {
+    uint64_t s = a + *carry;
+    uint64_t c = s < a;
+    s += b;
+    c += s < b;
+    *carry = c;
+    return s;
+}
+
+static inline uint64_t subBorrow(uint64_t a, uint64_t b, uint64_t *borrow) {
+    uint64_t d = a - b;
+    uint64_t c = a < b;
+    uint64_t r = d - *borrow;
+    c += d < *borrow;
+    *borrow = c;
+    return r;
+}
+
+// r = a + b, returning the carry
+static uint64_t rawAddLimbs(uint64_t *r, const uint64_t *a, const uint64_t *b) {
+    uint64_t carry = 0;
+    for (int i = 0; i < Fr_N64; i++) r[i] = addCarry(a[i], b[i], &carry);
+    return carry;
+}
+
+// r = a - b, returning the borrow
+static uint64_t rawSubLimbs(uint64_t *r, const uint64_t *a, const uint64_t *b) {
+    uint64_t borrow = 0;
+    for (int i = 0; i < Fr_N64; i++) r[i] = subBorrow(a[i], b[i], &borrow);
+    return borrow;
+}
+
+static int rawCmp(const uint64_t *a, const uint64_t *b) {
+    for (int i = Fr_N64 - 1; i >= 0; i--) {
+        if (a[i] != b[i]) return a[i] < b[i] ? -1 : 1;
+    }
+    return 0;
+}
+
+// Whether a is below the given limit
+static bool rawBelow(const uint64_t *a, uint64_t limit) {
+    for (int i = 1; i < Fr_N64; i++) {
+        if (a[i] != 0) return false;
+    }
+    return a[0] < limit;
+}
+
+// r = a - q if the value of a, with top as an extra limb, is at least q,
+// and r = a otherwise
+static void rawReduce(uint64_t *r, const uint64_t *a, uint64_t top) {
+    FrRawElement s;
+    uint64_t borrow = rawSubLimbs(s, a, Fr_rawq);
+    const uint64_t *result = (top == 0 && borrow) ? a : s;
+    for (int i = 0; i < Fr_N64; i++) r[i] = result[i];
+}
+
+// r = a*b/R mod q, where R = 2^(64*Fr_N64), with the CIOS method
+static void rawMontgomeryMul(uint64_t *r, const uint64_t *a, const uint64_t *b) {
+    uint64_t t[Fr_N64 + 2];
+    for (int i = 0; i < Fr_N64 + 2; i++) t[i] = 0;
+    for (int i = 0; i < Fr_N64; i++) {
+        uint64_t carry = 0;
+        for (int j = 0; j < Fr_N64; j++) t[j] = mulAdd(a[j], b[i], t[j], carry, &carry);
+        uint64_t top = 0;
+        t[Fr_N64] = addCarry(t[Fr_N64], carry, &top);
+        t[Fr_N64 + 1] = top;
+
+        uint64_t m = t[0] * Fr_np;
+        mulAdd(m, Fr_rawq[0], t[0], 0, &carry);
+        for (int j = 1; j < Fr_N64; j++) t[j - 1] = mulAdd(m, Fr_rawq[j], t[j], carry, &carry);
+        top = 0;
+        t[Fr_N64 - 1] = addCarry(t[Fr_N64], carry, &top);
+        t[Fr_N64] = t[Fr_N64 + 1] + top;
+    }
+    rawReduce(r, t, t[Fr_N64]);
+}
+
+static void rawShr(uint64_t *r, const uint64_t *a, int n) {
+    int limbs = n / 64, bits = n % 64;
+    for (int i = 0; i < Fr_N64; i++) {
+        uint64_t low = i + limbs < Fr_N64 ? a[i + limbs] : 0;
+        uint64_t high = i + limbs + 1 < Fr_N64 ? a[i + limbs + 1] : 0;
+        r[i] = bits == 0 ? low : (low >> bits) | (high << (64 - bits));
+    }
+}
+
+static void rawShl(uint64_t *r, const uint64_t *a, int n) {
+    int limbs = n / 64, bits = n % 64;
+    for (int i = Fr_N64 - 1; i >= 0; i--) {
+        uint64_t high = i - limbs >= 0 ? a[i - limbs] : 0;
+        uint64_t low = i - limbs - 1 >= 0 ? a[i - limbs - 1] : 0;
+        r[i] = bits == 0 ? high : (high << bits) | (low >> (64 - bits));
+    }
+}
+
+// Drops the bits from nBits on and takes the result below q
+static void rawMaskReduce(uint64_t *a) {
+    a[Fr_N64 - 1] &= Fr_lboMask;
+    rawReduce(a, a, 0);
+}
+
+// Raw field elements
+
+void Fr_rawCopy(FrRawElement pRawResult, const FrRawElement pRawA) {
+    for (int i = 0; i < Fr_N64; i++) pRawResult[i] = pRawA[i];
+}
+
+void Fr_rawSwap(FrRawElement pRawResult, FrRawElement pRawA) {
+    for (int i = 0; i < Fr_N64; i++) {
+        uint64_t tmp = pRawResult[i];
+        pRawResult[i] = pRawA[i];
+        pRawA[i] = tmp;
+    }
+}
+
+void Fr_rawAdd(FrRawElement pRawResult, const FrRawElement pRawA, const FrRawElement pRawB) {
+    FrRawElement s;
+    uint64_t carry = rawAddLimbs(s, pRawA, pRawB);
+    rawReduce(pRawResult, s, carry);
+}
+
+void Fr_rawSub(FrRawElement pRawResult, const FrRawElement pRawA, const FrRawElement pRawB) {
+    FrRawElement s;
+    if (rawSubLimbs(s, pRawA, pRawB)) rawAddLimbs(s, s, Fr_rawq);
+    Fr_rawCopy(pRawResult, s);
+}
+
+void Fr_rawNeg(FrRawElement pRawResult, const FrRawElement pRawA) {
+    FrRawElement s;
+    if (Fr_rawIsZero(pRawA)) {
+        Fr_rawCopy(s, pRawA);
+    } else {
+        rawSubLimbs(s, Fr_rawq, pRawA);
+    }
+    Fr_rawCopy(pRawResult, s);
+}
+
+void Fr_rawMMul(FrRawElement pRawResult, const FrRawElement pRawA, const FrRawElement pRawB) {
+    rawMontgomeryMul(pRawResult, pRawA, pRawB);
+}
+
+void Fr_rawMSquare(FrRawElement pRawResult, const FrRawElement pRawA) {
+    rawMontgomeryMul(pRawResult, pRawA, pRawA);
+}
+
+void Fr_rawMMul1(FrRawElement pRawResult, const FrRawElement pRawA, uint64_t pRawB) {
+    FrRawElement b = { pRawB };
+    rawMontgomeryMul(pRawResult, pRawA, b);
+}
+
+void Fr_rawToMontgomery(FrRawElement pRawResult, const FrRawElement &pRawA) {
+    rawMontgomeryMul(pRawResult, pRawA, Fr_rawR2);
+}
+
+void Fr_rawFromMontgomery(FrRawElement pRawResult, const FrRawElement &pRawA) {
+    rawMontgomeryMul(pRawResult, pRawA, Fr_rawOne);
+}
+
+int Fr_rawIsEq(const FrRawElement pRawA, const FrRawElement pRawB) {
+    return rawCmp(pRawA, pRawB) == 0;
+}
+
+int Fr_rawIsZero(const FrRawElement pRawB) {
+    return rawBelow(pRawB, 1);
+}
+
+// Conversions between field elements and raw elements. A short value is
+// long when it does not fit in 32 bits, and it keeps its Montgomery form
+// in longVal when it has the Montgomery flag.
+
+static inline bool isLong(PFrElement a) {
+    return a->type & Fr_LONG;
+}
+
+static inline bool isMontgomery(PFrElement a) {
+    return a->type & Fr_MONTGOMERY;
+}
+
+// The value v mod q, where |v| may exceed a prime of a single limb
+static void rawFromInt(uint64_t *r, int64_t v) {
+    FrRawElement a = { v < 0 ? 0 - (uint64_t)v : (uint64_t)v };
+    if (Fr_N64 == 1) a[0] %= Fr_rawq[0];
+    if (v < 0 && !Fr_rawIsZero(a)) {
+        rawSubLimbs(r, Fr_rawq, a);
+    } else {
+        Fr_rawCopy(r, a);
+    }
+}
+
+static void rawLong(uint64_t *r, PFrElement a) {
+    for (int i = 0; i < Fr_N64; i++) r[i] = a->longVal[i];
+}
+
+static void rawNormal(uint64_t *r, PFrElement a) {
+    if (!isLong(a)) {
+        rawFromInt(r, a->shortVal);
+    } else {
+        rawLong(r, a);
+        if (isMontgomery(a)) rawMontgomeryMul(r, r, Fr_rawOne);
+    }
+}
+
+static void rawMontgomery(uint64_t *r, PFrElement a) {
+    if (isMontgomery(a)) {
+        rawLong(r, a);
+    } else {
+        rawNormal(r, a);
+        rawMontgomeryMul(r, r, Fr_rawR2);
+    }
+}
+
+// Both values in the same form, which is Montgomery if any of them is
+static uint32_t rawPair(uint64_t *x, uint64_t *y, PFrElement a, PFrElement b) {
+    if (isMontgomery(a) || isMontgomery(b)) {
+        rawMontgomery(x, a);
+        rawMontgomery(y, b);
+        return Fr_LONGMONTGOMERY;
+    }
+    rawNormal(x, a);
+    rawNormal(y, b);
+    return Fr_LONG;
+}
+
+static void setLong(PFrElement r, const uint64_t *v, uint32_t type) {
+    r->shortVal = 0;
+    r->type = type;
+    for (int i = 0; i < Fr_N64; i++) r->longVal[i] = v[i];
+}
+
+static void setInt(PFrElement r, int64_t v) {
+    if (v >= INT32_MIN && v <= INT32_MAX) {
+        r->shortVal = (int32_t)v;
+        r->type = Fr_SHORT;
+    } else {
+        FrRawElement x;
+        rawFromInt(x, v);
+        setLong(r, x, Fr_LONG);
+    }
+}
+
+// Values above (q-1)/2 stand for negative numbers in comparisons
+static bool lessThan(PFrElement a, PFrElement b) {
+    if (!isLong(a) && !isLong(b)) return a->shortVal < b->shortVal;
+    FrRawElement x, y;
+    rawNormal(x, a);
+    rawNormal(y, b);
+    bool xNegative = rawCmp(x, Fr_rawHalf) > 0;
+    bool yNegative = rawCmp(y, Fr_rawHalf) > 0;
+    if (xNegative != yNegative) return xNegative;
+    return rawCmp(x, y) < 0;
+}
+
+static bool equal(PFrElement a, PFrElement b) {
+    if (!isLong(a) && !isLong(b)) return a->shortVal == b->shortVal;
+    FrRawElement x, y;
+    rawNormal(x, a);
+    rawNormal(y, b);
+    return rawCmp(x, y) == 0;
+}
+
+// Field elements
+
+void Fr_copy(PFrElement r, PFrElement a) {
+    *r = *a;
+}
+
+void Fr_copyn(PFrElement r, PFrElement a, int n) {
+    for (int i = 0; i < n; i++) r[i] = a[i];
+}
+
+void Fr_add(PFrElement r, PFrElement a, PFrElement b) {
+    if (!isLong(a) && !isLong(b)) {
+        setInt(r, (int64_t)a->shortVal + b->shortVal);
+        return;
+    }
+    FrRawElement x, y;
+    uint32_t type = rawPair(x, y, a, b);
+    Fr_rawAdd(x, x, y);
+    setLong(r, x, type);
+}
+
+void Fr_sub(PFrElement r, PFrElement a, PFrElement b) {
+    if (!isLong(a) && !isLong(b)) {
+        setInt(r, (int64_t)a->shortVal - b->shortVal);
+        return;
+    }
+    FrRawElement x, y;
+    uint32_t type = rawPair(x, y, a, b);
+    Fr_rawSub(x, x, y);
+    setLong(r, x, type);
+}
+
+void Fr_neg(PFrElement r, PFrElement a) {
+    if (!isLong(a)) {
+        setInt(r, -(int64_t)a->shortVal);
+        return;
+    }
+    FrRawElement x;
+    rawLong(x, a);
+    Fr_rawNeg(x, x);
+    setLong(r, x, a->type);
+}
+
+// The product of two Montgomery forms is in Montgomery form, the product of
+// a Montgomery form and a normal one is normal, and the product of two
+// normal forms is taken to Montgomery form with R^3
+void Fr_mul(PFrElement r, PFrElement a, PFrElement b) {
+    if (!isLong(a) && !isLong(b)) {
+        setInt(r, (int64_t)a->shortVal * b->shortVal);
+        return;
+    }
+    bool aMontgomery = isMontgomery(a), bMontgomery = isMontgomery(b);
+    FrRawElement x, y;
+    if (aMontgomery) rawMontgomery(x, a); else rawNormal(x, a);
+    if (bMontgomery) rawMontgomery(y, b); else rawNormal(y, b);
+    rawMontgomeryMul(x, x, y);
+    if (!aMontgomery && !bMontgomery) {
+        rawMontgomeryMul(x, x, Fr_rawR3);
+        setLong(r, x, Fr_LONGMONTGOMERY);
+    } else {
+        setLong(r, x, aMontgomery && bMontgomery ? Fr_LONGMONTGOMERY : Fr_LONG);
+    }
+}
+
+void Fr_square(PFrElement r, PFrElement a) {
+    Fr_mul(r, a, a);
+}
+
+enum BitOperation { AND, OR, XOR };
+
+static void bitOperation(PFrElement r, PFrElement a, PFrElement b, BitOperation op) {
+    if (!isLong(a) && !isLong(b) && a->shortVal >= 0 && b->shortVal >= 0) {
+        int32_t x = a->shortVal, y = b->shortVal;
+        setInt(r, op == AND ? x & y : op == OR ? x | y : x ^ y);
+        return;
+    }
+    FrRawElement x, y;
+    rawNormal(x, a);
+    rawNormal(y, b);
+    for (int i = 0; i < Fr_N64; i++) {
+        x[i] = op == AND ? x[i] & y[i] : op == OR ? x[i] | y[i] : x[i] ^ y[i];
+    }
+    rawMaskReduce(x);
+    setLong(r, x, Fr_LONG);
+}
+
+void Fr_band(PFrElement r, PFrElement a, PFrElement b) {
+    bitOperation(r, a, b, AND);
+}
+
+void Fr_bor(PFrElement r, PFrElement a, PFrElement b) {
+    bitOperation(r, a, b, OR);
+}
+
+void Fr_bxor(PFrElement r, PFrElement a, PFrElement b) {
+    bitOperation(r, a, b, XOR);
+}
+
+void Fr_bnot(PFrElement r, PFrElement a) {
+    FrRawElement x;
+    rawNormal(x, a);
+    for (int i = 0; i < Fr_N64; i++) x[i] = ~x[i];
+    rawMaskReduce(x);
+    setLong(r, x, Fr_LONG);
+}
+
+// The positions a shift by b moves the bits, negative when it moves them
+// the other way. False when every bit is shifted out.
+static bool shiftPositions(PFrElement b, int *n) {
+    FrRawElement x, y;
+    rawNormal(x, b);
+    if (rawBelow(x, Fr_nBits)) {
+        *n = (int)x[0];
+        return true;
+    }
+    rawSubLimbs(y, Fr_rawq, x);
+    if (rawBelow(y, Fr_nBits)) {
+        *n = -(int)y[0];
+        return true;
+    }
+    return false;
+}
+
+static void shiftRight(PFrElement r, PFrElement a, int n) {
+    if (!isLong(a) && a->shortVal >= 0) {
+        setInt(r, n >= 31 ? 0 : a->shortVal >> n);
+        return;
+    }
+    FrRawElement x;
+    rawNormal(x, a);
+    rawShr(x, x, n);
+    setLong(r, x, Fr_LONG);
+}
+
+// A non negative short stays short while the result fits in 31 bits
+static void shiftLeft(PFrElement r, PFrElement a, int n) {
+    if (!isLong(a) && a->shortVal >= 0 && n < 31 && ((int64_t)a->shortVal << n) <= INT32_MAX) {
+        setInt(r, (int64_t)a->shortVal << n);
+        return;
+    }
+    FrRawElement x;
+    rawNormal(x, a);
+    rawShl(x, x, n);
+    rawMaskReduce(x);
+    setLong(r, x, Fr_LONG);
+}
+
+void Fr_shr(PFrElement r, PFrElement a, PFrElement b) {
+    int n;
+    if (!shiftPositions(b, &n)) {
+        setInt(r, 0);
+    } else if (n >= 0) {
+        shiftRight(r, a, n);
+    } else {
+        shiftLeft(r, a, -n);
+    }
+}
+
+void Fr_shl(PFrElement r, PFrElement a, PFrElement b) {
+    int n;
+    if (!shiftPositions(b, &n)) {
+        setInt(r, 0);
+    } else if (n >= 0) {
+        shiftLeft(r, a, n);
+    } else {
+        shiftRight(r, a, -n);
+    }
+}
+
+void Fr_eq(PFrElement r, PFrElement a, PFrElement b) {
+    setInt(r, equal(a, b));
+}
+
+void Fr_neq(PFrElement r, PFrElement a, PFrElement b) {
+    setInt(r, !equal(a, b));
+}
+
+void Fr_lt(PFrElement r, PFrElement a, PFrElement b) {
+    setInt(r, lessThan(a, b));
+}
+
+void Fr_gt(PFrElement r, PFrElement a, PFrElement b) {
+    setInt(r, lessThan(b, a));
+}
+
+void Fr_leq(PFrElement r, PFrElement a, PFrElement b) {
+    setInt(r, !lessThan(b, a));
+}
+
+void Fr_geq(PFrElement r, PFrElement a, PFrElement b) {
+    setInt(r, !lessThan(a, b));
+}
+
+void Fr_land(PFrElement r, PFrElement a, PFrElement b) {
+    setInt(r, Fr_isTrue(a) && Fr_isTrue(b));
+}
+
+void Fr_lor(PFrElement r, PFrElement a, PFrElement b) {
+    setInt(r, Fr_isTrue(a) || Fr_isTrue(b));
+}
+
+void Fr_lnot(PFrElement r, PFrElement a) {
+    setInt(r, !Fr_isTrue(a));
+}
+
+void Fr_toNormal(PFrElement r, PFrElement a) {
+    if (isLong(a) && isMontgomery(a)) {
+        FrRawElement x;
+        rawNormal(x, a);
+        setLong(r, x, Fr_LONG);
+    } else {
+        *r = *a;
+    }
+}
+
+void Fr_toLongNormal(PFrElement r, PFrElement a) {
+    FrRawElement x;
+    rawNormal(x, a);
+    setLong(r, x, Fr_LONG);
+}
+
+void Fr_toMontgomery(PFrElement r, PFrElement a) {
+    if (isMontgomery(a)) {
+        *r = *a;
+        return;
+    }
+    FrRawElement x;
+    rawMontgomery(x, a);
+    if (isLong(a)) {
+        setLong(r, x, Fr_LONGMONTGOMERY);
+    } else {
+        int32_t shortVal = a->shortVal;
+        setLong(r, x, Fr_MONTGOMERY);
+        r->shortVal = shortVal;
+    }
+}
+
+int Fr_isTrue(PFrElement pE) {
+    if (!isLong(pE)) return pE->shortVal != 0;
+    FrRawElement x;
+    rawLong(x, pE);
+    return !Fr_rawIsZero(x);
+}
+
+// Values above (q-1)/2 are negative, and the value must fit in 32 bits
+int Fr_toInt(PFrElement pE) {
+    if (!isLong(pE)) return pE->shortVal;
+    FrRawElement x, y;
+    rawNormal(x, pE);
+    if (rawBelow(x, 0x80000000)) return (int)x[0];
+    rawSubLimbs(y, Fr_rawq, x);
+    if (rawBelow(y, 0x80000001)) return (int)(-(int64_t)y[0]);
+    Fr_fail();
+    return 0;
+}
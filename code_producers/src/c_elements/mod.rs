@@ -8,6 +8,7 @@ pub struct CProducer {
     pub main_is_parallel: bool,
     //pub fr_memory_size: usize, // depending of the prime; missing in build.rs
     pub has_parallelism: bool,
+    pub portable_flag: bool,
     pub number_of_main_outputs: usize,
     pub main_signal_offset: usize,
     pub number_of_main_inputs: usize,
@@ -62,6 +63,7 @@ impl Default for CProducer {
             main_header: "Main_0".to_string(),
             main_is_parallel: false,
             has_parallelism: false,
+            portable_flag: false,
             main_signal_offset: 1,
             prime: "21888242871839275222246405745257275088548364400416034343698204186575808495617"
                 .to_string(),
@@ -119,6 +121,9 @@ impl CProducer {
     pub fn get_has_parallelism(&self) -> bool {
         self.has_parallelism
     }
+    pub fn get_portable_flag(&self) -> bool {
+        self.portable_flag
+    }
     pub fn get_main_signal_offset(&self) -> usize {
         self.main_signal_offset
     }
@@ -0,0 +1,892 @@
+;; Field code for primes without a hand-written implementation. Raw numbers
+;; are {{n32}} little endian words of 32 bits and the Montgomery form of x is
+;; x*2^{{r_bits}} mod q. Elements keep the layout of the other primes: the short
+;; value, the type (bit 31 long, bit 30 Montgomery) and the raw number.
+(func $Fr_int_copy
+ (param $px i32)
+ (param $pr i32)
+ (local $o i32)
+ block
+  loop
+   get_local $o i32.const {{n8}} i32.eq br_if 1
+   get_local $pr get_local $o i32.add
+   get_local $px get_local $o i32.add i32.load
+   i32.store
+   get_local $o i32.const 4 i32.add set_local $o
+   br 0
+  end
+ end
+)
+(func $Fr_int_zero
+ (param $pr i32)
+ (local $o i32)
+ block
+  loop
+   get_local $o i32.const {{n8}} i32.eq br_if 1
+   get_local $pr get_local $o i32.add i32.const 0 i32.store
+   get_local $o i32.const 4 i32.add set_local $o
+   br 0
+  end
+ end
+)
+;; -1, 0 or 1 as x is below, equal or above y
+(func $Fr_int_cmp
+ (param $px i32)
+ (param $py i32)
+ (result i32)
+ (local $o i32)
+ (local $x i32)
+ (local $y i32)
+ i32.const {{n8}} set_local $o
+ block
+  loop
+   get_local $o i32.eqz br_if 1
+   get_local $o i32.const 4 i32.sub set_local $o
+   get_local $px get_local $o i32.add i32.load set_local $x
+   get_local $py get_local $o i32.add i32.load set_local $y
+   get_local $x get_local $y i32.gt_u
+   if
+    i32.const 1 return
+   end
+   get_local $x get_local $y i32.lt_u
+   if
+    i32.const -1 return
+   end
+   br 0
+  end
+ end
+ i32.const 0
+)
+;; Whether x is below the 32 bit value v
+(func $Fr_int_below
+ (param $px i32)
+ (param $v i32)
+ (result i32)
+ (local $o i32)
+ i32.const 4 set_local $o
+ block
+  loop
+   get_local $o i32.const {{n8}} i32.eq br_if 1
+   get_local $px get_local $o i32.add i32.load
+   if
+    i32.const 0 return
+   end
+   get_local $o i32.const 4 i32.add set_local $o
+   br 0
+  end
+ end
+ get_local $px i32.load get_local $v i32.lt_u
+)
+(func $Fr_int_isZero
+ (param $px i32)
+ (result i32)
+ get_local $px i32.const 1 call $Fr_int_below
+)
+(func $Fr_int_eq
+ (param $px i32)
+ (param $py i32)
+ (result i32)
+ get_local $px get_local $py call $Fr_int_cmp i32.eqz
+)
+;; r = x + y, returning the carry
+(func $Fr_int_add
+ (param $px i32)
+ (param $py i32)
+ (param $pr i32)
+ (result i32)
+ (local $o i32)
+ (local $c i64)
+ block
+  loop
+   get_local $o i32.const {{n8}} i32.eq br_if 1
+   get_local $px get_local $o i32.add i64.load32_u
+   get_local $py get_local $o i32.add i64.load32_u
+   i64.add get_local $c i64.add set_local $c
+   get_local $pr get_local $o i32.add get_local $c i64.store32
+   get_local $c i64.const 32 i64.shr_u set_local $c
+   get_local $o i32.const 4 i32.add set_local $o
+   br 0
+  end
+ end
+ get_local $c i32.wrap/i64
+)
+;; r = x - y, returning the borrow
+(func $Fr_int_sub
+ (param $px i32)
+ (param $py i32)
+ (param $pr i32)
+ (result i32)
+ (local $o i32)
+ (local $c i64)
+ (local $d i64)
+ block
+  loop
+   get_local $o i32.const {{n8}} i32.eq br_if 1
+   get_local $px get_local $o i32.add i64.load32_u
+   get_local $py get_local $o i32.add i64.load32_u
+   i64.sub get_local $c i64.sub set_local $d
+   get_local $pr get_local $o i32.add get_local $d i64.store32
+   get_local $d i64.const 63 i64.shr_u set_local $c
+   get_local $o i32.const 4 i32.add set_local $o
+   br 0
+  end
+ end
+ get_local $c i32.wrap/i64
+)
+(func $Fr_int_bit
+ (param $px i32)
+ (param $i i32)
+ (result i32)
+ get_local $px get_local $i i32.const 5 i32.shr_u i32.const 2 i32.shl i32.add i32.load
+ get_local $i i32.const 31 i32.and i32.shr_u
+ i32.const 1 i32.and
+)
+;; The word k of x, zero outside the number
+(func $Fr_int_word
+ (param $px i32)
+ (param $k i32)
+ (result i64)
+ get_local $k i32.const {{n32}} i32.lt_u
+ if
+  get_local $px get_local $k i32.const 2 i32.shl i32.add i64.load32_u return
+ end
+ i64.const 0
+)
+;; r = x << n, dropping the bits beyond the size of the numbers
+(func $Fr_int_shl
+ (param $px i32)
+ (param $n i32)
+ (param $pr i32)
+ (local $i i32)
+ (local $k i32)
+ (local $s i64)
+ get_local $n i32.const 31 i32.and i64.extend_u/i32 set_local $s
+ i32.const {{n32}} set_local $i
+ block
+  loop
+   get_local $i i32.eqz br_if 1
+   get_local $i i32.const 1 i32.sub set_local $i
+   get_local $i get_local $n i32.const 5 i32.shr_u i32.sub set_local $k
+   get_local $pr get_local $i i32.const 2 i32.shl i32.add
+   get_local $px get_local $k call $Fr_int_word i64.const 32 i64.shl
+   get_local $px get_local $k i32.const 1 i32.sub call $Fr_int_word
+   i64.or get_local $s i64.shl i64.const 32 i64.shr_u
+   i64.store32
+   br 0
+  end
+ end
+)
+;; r = x >> n
+(func $Fr_int_shr
+ (param $px i32)
+ (param $n i32)
+ (param $pr i32)
+ (local $i i32)
+ (local $k i32)
+ (local $s i64)
+ get_local $n i32.const 31 i32.and i64.extend_u/i32 set_local $s
+ block
+  loop
+   get_local $i i32.const {{n32}} i32.eq br_if 1
+   get_local $i get_local $n i32.const 5 i32.shr_u i32.add set_local $k
+   get_local $pr get_local $i i32.const 2 i32.shl i32.add
+   get_local $px get_local $k i32.const 1 i32.add call $Fr_int_word i64.const 32 i64.shl
+   get_local $px get_local $k call $Fr_int_word
+   i64.or get_local $s i64.shr_u
+   i64.store32
+   get_local $i i32.const 1 i32.add set_local $i
+   br 0
+  end
+ end
+)
+;; Keeps the {{n_bits}} lower bits of r and subtracts q if it is still above it
+(func $Fr_int_maskReduce
+ (param $pr i32)
+ get_local $pr
+ get_local $pr i32.load offset={{top}} i32.const {{top_mask}} i32.and
+ i32.store offset={{top}}
+ get_local $pr i32.const {{q}} call $Fr_int_cmp i32.const 0 i32.ge_s
+ if
+  get_local $pr i32.const {{q}} get_local $pr call $Fr_int_sub drop
+ end
+)
+;; Quotient and remainder of a by b, left at {{div_q}} and {{div_r}}. Both are
+;; zero when b is zero.
+(func $Fr_int_div
+ (param $pa i32)
+ (param $pb i32)
+ (local $i i32)
+ i32.const {{div_q}} call $Fr_int_zero
+ i32.const {{div_r}} call $Fr_int_zero
+ get_local $pb call $Fr_int_isZero
+ if
+  return
+ end
+ i32.const {{n_bits}} set_local $i
+ block
+  loop
+   get_local $i i32.eqz br_if 1
+   get_local $i i32.const 1 i32.sub set_local $i
+   i32.const {{div_r}} i32.const {{div_r}} i32.const {{div_r}} call $Fr_int_add
+   i32.const {{div_r}}
+   i32.const {{div_r}} i32.load get_local $pa get_local $i call $Fr_int_bit i32.or
+   i32.store
+   i32.const {{div_r}} get_local $pb call $Fr_int_cmp i32.const 0 i32.ge_s
+   i32.or
+   if
+    i32.const {{div_r}} get_local $pb i32.const {{div_r}} call $Fr_int_sub drop
+    i32.const {{div_q}} get_local $i i32.const 5 i32.shr_u i32.const 2 i32.shl i32.add
+    i32.const {{div_q}} get_local $i i32.const 5 i32.shr_u i32.const 2 i32.shl i32.add i32.load
+    i32.const 1 get_local $i i32.const 31 i32.and i32.shl i32.or
+    i32.store
+   end
+   br 0
+  end
+ end
+)
+(func $Fr_F1m_add
+ (param $px i32)
+ (param $py i32)
+ (param $pr i32)
+ get_local $px get_local $py get_local $pr call $Fr_int_add
+ get_local $pr i32.const {{q}} call $Fr_int_cmp i32.const 0 i32.ge_s
+ i32.or
+ if
+  get_local $pr i32.const {{q}} get_local $pr call $Fr_int_sub drop
+ end
+)
+(func $Fr_F1m_sub
+ (param $px i32)
+ (param $py i32)
+ (param $pr i32)
+ get_local $px get_local $py get_local $pr call $Fr_int_sub
+ if
+  get_local $pr i32.const {{q}} get_local $pr call $Fr_int_add drop
+ end
+)
+(func $Fr_F1m_neg
+ (param $px i32)
+ (param $pr i32)
+ get_local $px call $Fr_int_isZero
+ if
+  get_local $pr call $Fr_int_zero
+ else
+  i32.const {{q}} get_local $px get_local $pr call $Fr_int_sub drop
+ end
+)
+;; r = x*y/2^{{r_bits}} mod q, with the CIOS method over the words at {{t}}
+(func $Fr_F1m_mul
+ (param $px i32)
+ (param $py i32)
+ (param $pr i32)
+ (local $i i32)
+ (local $j i32)
+ (local $c i64)
+ (local $y i64)
+ (local $m i64)
+ block
+  loop
+   get_local $j i32.const {{t_size}} i32.eq br_if 1
+   get_local $j i32.const 0 i32.store offset={{t}}
+   get_local $j i32.const 4 i32.add set_local $j
+   br 0
+  end
+ end
+ block
+  loop
+   get_local $i i32.const {{n8}} i32.eq br_if 1
+   get_local $py get_local $i i32.add i64.load32_u set_local $y
+   i64.const 0 set_local $c
+   i32.const 0 set_local $j
+   block
+    loop
+     get_local $j i32.const {{n8}} i32.eq br_if 1
+     get_local $j i64.load32_u offset={{t}}
+     get_local $px get_local $j i32.add i64.load32_u get_local $y i64.mul
+     i64.add get_local $c i64.add set_local $c
+     get_local $j get_local $c i64.store32 offset={{t}}
+     get_local $c i64.const 32 i64.shr_u set_local $c
+     get_local $j i32.const 4 i32.add set_local $j
+     br 0
+    end
+   end
+   i32.const 0 i64.load32_u offset={{t_n}} get_local $c i64.add set_local $c
+   i32.const 0 get_local $c i64.store32 offset={{t_n}}
+   i32.const 0 get_local $c i64.const 32 i64.shr_u i64.store32 offset={{t_n1}}
+   i32.const 0 i64.load32_u offset={{t}} i64.const {{np}} i64.mul
+   i64.const 4294967295 i64.and set_local $m
+   i32.const 0 i64.load32_u offset={{t}}
+   get_local $m i32.const 0 i64.load32_u offset={{q}} i64.mul
+   i64.add i64.const 32 i64.shr_u set_local $c
+   i32.const 4 set_local $j
+   block
+    loop
+     get_local $j i32.const {{n8}} i32.eq br_if 1
+     get_local $j i64.load32_u offset={{t}}
+     get_local $m get_local $j i64.load32_u offset={{q}} i64.mul
+     i64.add get_local $c i64.add set_local $c
+     get_local $j i32.const 4 i32.sub get_local $c i64.store32 offset={{t}}
+     get_local $c i64.const 32 i64.shr_u set_local $c
+     get_local $j i32.const 4 i32.add set_local $j
+     br 0
+    end
+   end
+   i32.const 0 i64.load32_u offset={{t_n}} get_local $c i64.add set_local $c
+   i32.const 0 get_local $c i64.store32 offset={{t_top}}
+   i32.const 0
+   i32.const 0 i64.load32_u offset={{t_n1}} get_local $c i64.const 32 i64.shr_u i64.add
+   i64.store32 offset={{t_n}}
+   get_local $i i32.const 4 i32.add set_local $i
+   br 0
+  end
+ end
+ i32.const 0 i32.load offset={{t_n}}
+ i32.const {{t}} i32.const {{q}} call $Fr_int_cmp i32.const 0 i32.ge_s
+ i32.or
+ if
+  i32.const {{t}} i32.const {{q}} get_local $pr call $Fr_int_sub drop
+ else
+  i32.const {{t}} get_local $pr call $Fr_int_copy
+ end
+)
+(func $Fr_F1m_toMontgomery
+ (param $px i32)
+ (param $pr i32)
+ get_local $px i32.const {{r2}} get_local $pr call $Fr_F1m_mul
+)
+(func $Fr_F1m_fromMontgomery
+ (param $px i32)
+ (param $pr i32)
+ get_local $px i32.const {{one}} get_local $pr call $Fr_F1m_mul
+)
+;; r = b^e, with b and r in Montgomery form and e a normal number
+(func $Fr_F1m_exp
+ (param $pb i32)
+ (param $pe i32)
+ (param $pr i32)
+ (local $i i32)
+ get_local $pb i32.const {{exp_b}} call $Fr_int_copy
+ i32.const {{one_m}} i32.const {{exp_a}} call $Fr_int_copy
+ i32.const {{n_bits}} set_local $i
+ block
+  loop
+   get_local $i i32.eqz br_if 1
+   get_local $i i32.const 1 i32.sub set_local $i
+   i32.const {{exp_a}} i32.const {{exp_a}} i32.const {{exp_a}} call $Fr_F1m_mul
+   get_local $pe get_local $i call $Fr_int_bit
+   if
+    i32.const {{exp_a}} i32.const {{exp_b}} i32.const {{exp_a}} call $Fr_F1m_mul
+   end
+   br 0
+  end
+ end
+ i32.const {{exp_a}} get_local $pr call $Fr_int_copy
+)
+(func $Fr_copy
+ (param $pr i32)
+ (param $px i32)
+ get_local $pr get_local $px i32.const 1 call $Fr_copyn
+)
+(func $Fr_copyn
+ (param $pr i32)
+ (param $px i32)
+ (param $n i32)
+ (local $o i32)
+ (local $last i32)
+ get_local $n i32.const {{e8}} i32.mul set_local $last
+ block
+  loop
+   get_local $o get_local $last i32.eq br_if 1
+   get_local $pr get_local $o i32.add
+   get_local $px get_local $o i32.add i32.load
+   i32.store
+   get_local $o i32.const 4 i32.add set_local $o
+   br 0
+  end
+ end
+)
+(func $Fr_isTrue
+ (param $px i32)
+ (result i32)
+ get_local $px i32.load8_u offset=7 i32.const 128 i32.and
+ if
+  get_local $px i32.const 8 i32.add call $Fr_int_isZero i32.eqz return
+ end
+ get_local $px i32.load i32.const 0 i32.ne
+)
+;; The raw number of v mod q
+(func $Fr_rawCopyS2L
+ (param $pR i32)
+ (param $v i64)
+ (local $neg i32)
+ get_local $v i64.const 0 i64.lt_s set_local $neg
+ get_local $neg
+ if
+  i64.const 0 get_local $v i64.sub set_local $v
+ end
+{{#if q64}}
+ get_local $v i64.const {{q64}} i64.rem_u set_local $v
+{{/if}}
+ get_local $pR call $Fr_int_zero
+ get_local $pR get_local $v i64.store
+ get_local $neg
+ if
+  get_local $pR get_local $pR call $Fr_F1m_neg
+ end
+)
+;; Stores v as a short when it fits in 32 bits and as a long normal otherwise
+(func $Fr_setInt
+ (param $pR i32)
+ (param $v i64)
+ get_local $v get_local $v i32.wrap/i64 i64.extend_s/i32 i64.eq
+ if
+  get_local $pR get_local $v i64.store32
+  get_local $pR i32.const 0 i32.store offset=4
+ else
+  get_local $pR i32.const -2147483648 i32.store offset=4
+  get_local $pR i32.const 8 i32.add get_local $v call $Fr_rawCopyS2L
+ end
+)
+(func $Fr_toMontgomery
+ (param $pR i32)
+ get_local $pR i32.load8_u offset=7 i32.const 64 i32.and
+ if
+  return
+ end
+ get_local $pR i32.load8_u offset=7 i32.const 128 i32.and i32.eqz
+ if
+  get_local $pR i32.const 8 i32.add get_local $pR i64.load32_s call $Fr_rawCopyS2L
+ end
+ get_local $pR i32.const -1073741824 i32.store offset=4
+ get_local $pR i32.const 8 i32.add get_local $pR i32.const 8 i32.add call $Fr_F1m_toMontgomery
+)
+(func $Fr_toNormal
+ (param $pR i32)
+ get_local $pR i32.load8_u offset=7 i32.const 192 i32.and i32.const 192 i32.eq
+ if
+  get_local $pR i32.const -2147483648 i32.store offset=4
+  get_local $pR i32.const 8 i32.add get_local $pR i32.const 8 i32.add call $Fr_F1m_fromMontgomery
+ end
+)
+(func $Fr_toLongNormal
+ (param $pR i32)
+ get_local $pR i32.load8_u offset=7 i32.const 128 i32.and
+ if
+  get_local $pR call $Fr_toNormal
+ else
+  get_local $pR i32.const 8 i32.add get_local $pR i64.load32_s call $Fr_rawCopyS2L
+  get_local $pR i32.const -2147483648 i32.store offset=4
+ end
+)
+(func $Fr_isNegative
+ (param $pA i32)
+ (result i32)
+ get_local $pA i32.load8_u offset=7 i32.const 128 i32.and
+ if
+  get_local $pA call $Fr_toNormal
+  get_local $pA i32.const 8 i32.add i32.const {{half}} call $Fr_int_cmp i32.const 0 i32.gt_s return
+ end
+ get_local $pA i32.load i32.const 0 i32.lt_s
+)
+(func $Fr_neg
+ (param $pR i32)
+ (param $pA i32)
+ get_local $pA i32.load8_u offset=7 i32.const 128 i32.and
+ if
+  get_local $pR get_local $pA i32.load offset=4 i32.store offset=4
+  get_local $pA i32.const 8 i32.add get_local $pR i32.const 8 i32.add call $Fr_F1m_neg
+ else
+  get_local $pR i64.const 0 get_local $pA i64.load32_s i64.sub call $Fr_setInt
+ end
+)
+(func $Fr_getLsb32
+ (param $pA i32)
+ (result i32)
+ get_local $pA i32.load8_u offset=7 i32.const 128 i32.and
+ if
+  get_local $pA call $Fr_toNormal
+  get_local $pA i32.load offset=8 return
+ end
+ get_local $pA i32.load
+)
+(func $Fr_toInt
+ (param $pA i32)
+ (result i32)
+ get_local $pA call $Fr_isNegative
+ if
+  i32.const {{e1}} get_local $pA call $Fr_neg
+  i32.const 0 i32.const {{e1}} call $Fr_getLsb32 i32.sub return
+ end
+ get_local $pA call $Fr_getLsb32
+)
+;; Whether any of a and b is long, and whether any of them is in Montgomery form
+(func $Fr_anyLong
+ (param $pA i32)
+ (param $pB i32)
+ (result i32)
+ get_local $pA i32.load8_u offset=7 get_local $pB i32.load8_u offset=7 i32.or i32.const 128 i32.and
+)
+(func $Fr_anyMontgomery
+ (param $pA i32)
+ (param $pB i32)
+ (result i32)
+ get_local $pA i32.load8_u offset=7 get_local $pB i32.load8_u offset=7 i32.or i32.const 64 i32.and
+)
+(func $Fr_add
+ (param $pR i32)
+ (param $pA i32)
+ (param $pB i32)
+ get_local $pA get_local $pB call $Fr_anyLong
+ if
+  get_local $pA get_local $pB call $Fr_anyMontgomery
+  if
+   get_local $pA call $Fr_toMontgomery
+   get_local $pB call $Fr_toMontgomery
+   get_local $pA i32.const 8 i32.add get_local $pB i32.const 8 i32.add get_local $pR i32.const 8 i32.add call $Fr_F1m_add
+   get_local $pR i32.const -1073741824 i32.store offset=4
+  else
+   get_local $pA call $Fr_toLongNormal
+   get_local $pB call $Fr_toLongNormal
+   get_local $pA i32.const 8 i32.add get_local $pB i32.const 8 i32.add get_local $pR i32.const 8 i32.add call $Fr_F1m_add
+   get_local $pR i32.const -2147483648 i32.store offset=4
+  end
+  return
+ end
+ get_local $pR get_local $pA i64.load32_s get_local $pB i64.load32_s i64.add call $Fr_setInt
+)
+(func $Fr_sub
+ (param $pR i32)
+ (param $pA i32)
+ (param $pB i32)
+ get_local $pA get_local $pB call $Fr_anyLong
+ if
+  get_local $pA get_local $pB call $Fr_anyMontgomery
+  if
+   get_local $pA call $Fr_toMontgomery
+   get_local $pB call $Fr_toMontgomery
+   get_local $pA i32.const 8 i32.add get_local $pB i32.const 8 i32.add get_local $pR i32.const 8 i32.add call $Fr_F1m_sub
+   get_local $pR i32.const -1073741824 i32.store offset=4
+  else
+   get_local $pA call $Fr_toLongNormal
+   get_local $pB call $Fr_toLongNormal
+   get_local $pA i32.const 8 i32.add get_local $pB i32.const 8 i32.add get_local $pR i32.const 8 i32.add call $Fr_F1m_sub
+   get_local $pR i32.const -2147483648 i32.store offset=4
+  end
+  return
+ end
+ get_local $pR get_local $pA i64.load32_s get_local $pB i64.load32_s i64.sub call $Fr_setInt
+)
+(func $Fr_mul
+ (param $pR i32)
+ (param $pA i32)
+ (param $pB i32)
+ get_local $pA get_local $pB call $Fr_anyLong
+ if
+  get_local $pA call $Fr_toMontgomery
+  get_local $pB call $Fr_toMontgomery
+  get_local $pA i32.const 8 i32.add get_local $pB i32.const 8 i32.add get_local $pR i32.const 8 i32.add call $Fr_F1m_mul
+  get_local $pR i32.const -1073741824 i32.store offset=4
+  return
+ end
+ get_local $pR get_local $pA i64.load32_s get_local $pB i64.load32_s i64.mul call $Fr_setInt
+)
+(func $Fr_eqR
+ (param $pA i32)
+ (param $pB i32)
+ (result i32)
+ get_local $pA get_local $pB call $Fr_anyLong
+ if
+  get_local $pA call $Fr_toLongNormal
+  get_local $pB call $Fr_toLongNormal
+  get_local $pA i32.const 8 i32.add get_local $pB i32.const 8 i32.add call $Fr_int_eq return
+ end
+ get_local $pA i32.load get_local $pB i32.load i32.eq
+)
+;; Values above half of q stand for negative numbers
+(func $Fr_gtR
+ (param $pA i32)
+ (param $pB i32)
+ (result i32)
+ (local $negative i32)
+ get_local $pA get_local $pB call $Fr_anyLong
+ if
+  get_local $pA call $Fr_toLongNormal
+  get_local $pB call $Fr_toLongNormal
+  get_local $pB call $Fr_isNegative set_local $negative
+  get_local $pA call $Fr_isNegative get_local $negative i32.ne
+  if
+   get_local $negative return
+  end
+  get_local $pA i32.const 8 i32.add get_local $pB i32.const 8 i32.add call $Fr_int_cmp i32.const 0 i32.gt_s return
+ end
+ get_local $pA i32.load get_local $pB i32.load i32.gt_s
+)
+(func $Fr_eq
+ (param $pR i32)
+ (param $pA i32)
+ (param $pB i32)
+ get_local $pR get_local $pA get_local $pB call $Fr_eqR i64.extend_u/i32 i64.store
+)
+(func $Fr_neq
+ (param $pR i32)
+ (param $pA i32)
+ (param $pB i32)
+ get_local $pR get_local $pA get_local $pB call $Fr_eqR i32.eqz i64.extend_u/i32 i64.store
+)
+(func $Fr_gt
+ (param $pR i32)
+ (param $pA i32)
+ (param $pB i32)
+ get_local $pR get_local $pA get_local $pB call $Fr_gtR i64.extend_u/i32 i64.store
+)
+(func $Fr_geq
+ (param $pR i32)
+ (param $pA i32)
+ (param $pB i32)
+ get_local $pR get_local $pB get_local $pA call $Fr_gtR i32.eqz i64.extend_u/i32 i64.store
+)
+(func $Fr_lt
+ (param $pR i32)
+ (param $pA i32)
+ (param $pB i32)
+ get_local $pR get_local $pB get_local $pA call $Fr_gtR i64.extend_u/i32 i64.store
+)
+(func $Fr_leq
+ (param $pR i32)
+ (param $pA i32)
+ (param $pB i32)
+ get_local $pR get_local $pA get_local $pB call $Fr_gtR i32.eqz i64.extend_u/i32 i64.store
+)
+(func $Fr_idiv
+ (param $pR i32)
+ (param $pA i32)
+ (param $pB i32)
+ get_local $pA call $Fr_toLongNormal
+ get_local $pB call $Fr_toLongNormal
+ get_local $pA i32.const 8 i32.add get_local $pB i32.const 8 i32.add call $Fr_int_div
+ i32.const {{div_q}} get_local $pR i32.const 8 i32.add call $Fr_int_copy
+ get_local $pR i32.const -2147483648 i32.store offset=4
+)
+(func $Fr_mod
+ (param $pR i32)
+ (param $pA i32)
+ (param $pB i32)
+ get_local $pA call $Fr_toLongNormal
+ get_local $pB call $Fr_toLongNormal
+ get_local $pA i32.const 8 i32.add get_local $pB i32.const 8 i32.add call $Fr_int_div
+ i32.const {{div_r}} get_local $pR i32.const 8 i32.add call $Fr_int_copy
+ get_local $pR i32.const -2147483648 i32.store offset=4
+)
+;; a^(q-2), which is zero for a zero
+(func $Fr_inv
+ (param $pR i32)
+ (param $pA i32)
+ get_local $pA call $Fr_toMontgomery
+ get_local $pA i32.const 8 i32.add i32.const {{qm2}} get_local $pR i32.const 8 i32.add call $Fr_F1m_exp
+ get_local $pR i32.const -1073741824 i32.store offset=4
+)
+(func $Fr_div
+ (param $pR i32)
+ (param $pA i32)
+ (param $pB i32)
+ i32.const {{e2}} get_local $pB call $Fr_inv
+ get_local $pR get_local $pA i32.const {{e2}} call $Fr_mul
+)
+(func $Fr_pow
+ (param $pR i32)
+ (param $pA i32)
+ (param $pB i32)
+ get_local $pA call $Fr_toMontgomery
+ get_local $pB call $Fr_toLongNormal
+ get_local $pA i32.const 8 i32.add get_local $pB i32.const 8 i32.add get_local $pR i32.const 8 i32.add call $Fr_F1m_exp
+ get_local $pR i32.const -1073741824 i32.store offset=4
+)
+;; A non negative short stays short while the result fits in 31 bits. Any
+;; other value is shifted as a normal number masked to {{n_bits}} bits.
+(func $Fr_rawshl
+ (param $pR i32)
+ (param $pA i32)
+ (param $n i32)
+ (local $r i64)
+ get_local $pA i32.load8_u offset=7 i32.const 128 i32.and i32.eqz
+ if
+  get_local $pA i32.load i32.const 0 i32.ge_s
+  get_local $n i32.const 31 i32.lt_u
+  i32.and
+  if
+   get_local $pA i64.load32_u get_local $n i64.extend_u/i32 i64.shl set_local $r
+   get_local $r i64.const 2147483647 i64.le_u
+   if
+    get_local $pR get_local $r i64.store return
+   end
+  end
+ end
+ get_local $pA call $Fr_toLongNormal
+ get_local $pA i32.const 8 i32.add get_local $n get_local $pR i32.const 8 i32.add call $Fr_int_shl
+ get_local $pR i32.const 8 i32.add call $Fr_int_maskReduce
+ get_local $pR i32.const -2147483648 i32.store offset=4
+)
+(func $Fr_rawshr
+ (param $pR i32)
+ (param $pA i32)
+ (param $n i32)
+ get_local $pA i32.load8_u offset=7 i32.const 128 i32.and i32.eqz
+ if
+  get_local $pA i32.load i32.const 0 i32.ge_s
+  if
+   get_local $n i32.const 31 i32.lt_u
+   if
+    get_local $pR get_local $pA i64.load32_u get_local $n i64.extend_u/i32 i64.shr_u i64.store
+   else
+    get_local $pR i64.const 0 i64.store
+   end
+   return
+  end
+ end
+ get_local $pA call $Fr_toLongNormal
+ get_local $pA i32.const 8 i32.add get_local $n get_local $pR i32.const 8 i32.add call $Fr_int_shr
+ get_local $pR i32.const -2147483648 i32.store offset=4
+)
+;; A negative b shifts the other way by -b. Every bit is shifted out when the
+;; number of positions is not below {{n_bits}}.
+(func $Fr_shl
+ (param $pR i32)
+ (param $pA i32)
+ (param $pB i32)
+ i32.const {{e2}} get_local $pB call $Fr_copy
+ i32.const {{e2}} call $Fr_toLongNormal
+ i32.const {{e2_raw}} i32.const {{n_bits}} call $Fr_int_below
+ if
+  get_local $pR get_local $pA i32.const {{e2_raw}} i32.load call $Fr_rawshl return
+ end
+ i32.const {{e2_raw}} i32.const {{e2_raw}} call $Fr_F1m_neg
+ i32.const {{e2_raw}} i32.const {{n_bits}} call $Fr_int_below
+ if
+  get_local $pR get_local $pA i32.const {{e2_raw}} i32.load call $Fr_rawshr return
+ end
+ get_local $pR i64.const 0 i64.store
+)
+(func $Fr_shr
+ (param $pR i32)
+ (param $pA i32)
+ (param $pB i32)
+ i32.const {{e2}} get_local $pB call $Fr_copy
+ i32.const {{e2}} call $Fr_toLongNormal
+ i32.const {{e2_raw}} i32.const {{n_bits}} call $Fr_int_below
+ if
+  get_local $pR get_local $pA i32.const {{e2_raw}} i32.load call $Fr_rawshr return
+ end
+ i32.const {{e2_raw}} i32.const {{e2_raw}} call $Fr_F1m_neg
+ i32.const {{e2_raw}} i32.const {{n_bits}} call $Fr_int_below
+ if
+  get_local $pR get_local $pA i32.const {{e2_raw}} i32.load call $Fr_rawshl return
+ end
+ get_local $pR i64.const 0 i64.store
+)
+;; x and y, x or y or x xor y as op is 0, 1 or 2
+(func $Fr_bitOp32
+ (param $x i32)
+ (param $y i32)
+ (param $op i32)
+ (result i32)
+ get_local $op i32.eqz
+ if
+  get_local $x get_local $y i32.and return
+ end
+ get_local $op i32.const 1 i32.eq
+ if
+  get_local $x get_local $y i32.or return
+ end
+ get_local $x get_local $y i32.xor
+)
+;; Non negative shorts give a short, any other value is operated as a normal
+;; number masked to {{n_bits}} bits
+(func $Fr_bitOp
+ (param $pR i32)
+ (param $pA i32)
+ (param $pB i32)
+ (param $op i32)
+ (local $o i32)
+ get_local $pA get_local $pB call $Fr_anyLong i32.eqz
+ get_local $pA i32.load get_local $pB i32.load i32.or i32.const 0 i32.ge_s
+ i32.and
+ if
+  get_local $pR
+  get_local $pA i32.load get_local $pB i32.load get_local $op call $Fr_bitOp32
+  i64.extend_u/i32 i64.store
+  return
+ end
+ get_local $pA call $Fr_toLongNormal
+ get_local $pB call $Fr_toLongNormal
+ block
+  loop
+   get_local $o i32.const {{n8}} i32.eq br_if 1
+   get_local $pR get_local $o i32.add
+   get_local $pA get_local $o i32.add i32.load offset=8
+   get_local $pB get_local $o i32.add i32.load offset=8
+   get_local $op call $Fr_bitOp32
+   i32.store offset=8
+   get_local $o i32.const 4 i32.add set_local $o
+   br 0
+  end
+ end
+ get_local $pR i32.const 8 i32.add call $Fr_int_maskReduce
+ get_local $pR i32.const -2147483648 i32.store offset=4
+)
+(func $Fr_band
+ (param $pR i32)
+ (param $pA i32)
+ (param $pB i32)
+ get_local $pR get_local $pA get_local $pB i32.const 0 call $Fr_bitOp
+)
+(func $Fr_bor
+ (param $pR i32)
+ (param $pA i32)
+ (param $pB i32)
+ get_local $pR get_local $pA get_local $pB i32.const 1 call $Fr_bitOp
+)
+(func $Fr_bxor
+ (param $pR i32)
+ (param $pA i32)
+ (param $pB i32)
+ get_local $pR get_local $pA get_local $pB i32.const 2 call $Fr_bitOp
+)
+(func $Fr_bnot
+ (param $pR i32)
+ (param $pA i32)
+ (local $o i32)
+ get_local $pA call $Fr_toLongNormal
+ block
+  loop
+   get_local $o i32.const {{n8}} i32.eq br_if 1
+   get_local $pR get_local $o i32.add
+   get_local $pA get_local $o i32.add i32.load offset=8 i32.const -1 i32.xor
+   i32.store offset=8
+   get_local $o i32.const 4 i32.add set_local $o
+   br 0
+  end
+ end
+ get_local $pR i32.const 8 i32.add call $Fr_int_maskReduce
+ get_local $pR i32.const -2147483648 i32.store offset=4
+)
+(func $Fr_land
+ (param $pR i32)
+ (param $pA i32)
+ (param $pB i32)
+ get_local $pR
+ get_local $pA call $Fr_isTrue get_local $pB call $Fr_isTrue i32.and
+ i64.extend_u/i32 i64.store
+)
+(func $Fr_lor
+ (param $pR i32)
+ (param $pA i32)
+ (param $pB i32)
+ get_local $pR
+ get_local $pA call $Fr_isTrue get_local $pB call $Fr_isTrue i32.or
+ i64.extend_u/i32 i64.store
+)
+(func $Fr_lnot
+ (param $pR i32)
+ (param $pA i32)
+ get_local $pR get_local $pA call $Fr_isTrue i32.eqz i64.extend_u/i32 i64.store
+)
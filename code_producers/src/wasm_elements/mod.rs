@@ -56,6 +56,7 @@ pub struct WASMProducer {
     create_loop_offset_tag: String,
     create_loop_counter_tag: String,
     merror_tag: String,
+    instance_constants_tag: String,
     string_table:  Vec<String>,
 }
 
@@ -118,6 +119,7 @@ impl Default for WASMProducer {
             create_loop_offset_tag: "$createloopoffset".to_string(),
             create_loop_counter_tag: "$createloopcounter".to_string(),
 	        merror_tag: "$merror".to_string(),
+            instance_constants_tag: "$instanceconstants".to_string(),
             string_table: Vec::new(),
         }
     }
@@ -403,6 +405,9 @@ impl WASMProducer {
     pub fn get_merror_tag(&self) -> &str {
 	&self.merror_tag
     }
+    pub fn get_instance_constants_tag(&self) -> &str {
+        &self.instance_constants_tag
+    }
     pub fn needs_comments(&self) -> bool{
         self.wat_flag
     }
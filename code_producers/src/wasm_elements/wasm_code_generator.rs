@@ -2,7 +2,6 @@ use super::*;
 use num_bigint_dig::BigInt;
 use std::fs::File;
 use std::io::prelude::*;
-use std::path::PathBuf;
 
 pub fn wasm_hexa(nbytes: usize, num: &BigInt) -> String {
     let inbytes = num.to_str_radix(16).to_string();
@@ -346,7 +345,8 @@ pub fn generate_data_constants(producer: &WASMProducer, constant_list: &Vec<Stri
         let min_int = BigInt::from(-2147483648);
         let max_int = BigInt::from(2147483647);
         let p = producer.get_prime().parse::<BigInt>().unwrap();
-        let b = ((p.bits() + 63) / 64) * 64;
+        // the Montgomery radix of the field code, 2^(32*n32)
+        let b = 32 * producer.get_size_32_bit();
         let mut r = BigInt::from(1);
         r = r << b;
         n = n % BigInt::clone(&p);
@@ -1129,6 +1129,13 @@ pub fn copy_32_in_shared_rw_memory_generator(producer: &WASMProducer) -> Vec<Was
 	instructions.push(set_constant_64("0"));
 	instructions.push(store64(Some(&pos.to_string())));
     }
+    if producer.get_size_32_bit() % 2 == 1 {
+        // the last word is left out by the 64 bit stores
+        let pos = 4 * (producer.get_size_32_bit() - 1);
+        instructions.push(set_constant(&producer.get_shared_rw_memory_start().to_string()));
+        instructions.push(set_constant("0"));
+        instructions.push(store32(Some(&pos.to_string())));
+    }
     instructions.push(")".to_string());
     instructions
 }
@@ -1557,16 +1564,18 @@ fn get_file_instructions(name: &str) -> Vec<WasmInstruction> {
     instructions
 }
 
-pub fn fr_types(prime: &String) -> Vec<WasmInstruction> {
+// The field code of the named primes is hand-written, any other prime uses
+// the generic code of common/fr-code.wat, which needs no extra types
+pub fn fr_types(producer: &WASMProducer) -> Vec<WasmInstruction> {
     let mut instructions = vec![];
-    let file = match prime.as_ref(){
+    let file = match producer.prime_str.as_ref(){
         "bn128" => include_str!("bn128/fr-types.wat"),
         "bls12381" => include_str!("bls12381/fr-types.wat"),
         "goldilocks" => include_str!("goldilocks/fr-types.wat"),
         "grumpkin" => include_str!("grumpkin/fr-types.wat"),
         "pallas" => include_str!("pallas/fr-types.wat"),
         "vesta" => include_str!("vesta/fr-types.wat"),
-        _ => unreachable!(),
+        _ => "",
     };    
     for line in file.lines() {
         instructions.push(line.to_string());
@@ -1574,32 +1583,32 @@ pub fn fr_types(prime: &String) -> Vec<WasmInstruction> {
     instructions
 }
 
-pub fn fr_data(prime: &String) -> Vec<WasmInstruction> {
+pub fn fr_data(producer: &WASMProducer) -> Vec<WasmInstruction> {
     let mut instructions = vec![];
-    let file = match prime.as_ref(){
+    let file = match producer.prime_str.as_ref(){
         "bn128" => include_str!("bn128/fr-data.wat"),
         "bls12381" => include_str!("bls12381/fr-data.wat"),
         "goldilocks" => include_str!("goldilocks/fr-data.wat"),
         "grumpkin" => include_str!("grumpkin/fr-data.wat"),
         "pallas" => include_str!("pallas/fr-data.wat"),
         "vesta" => include_str!("vesta/fr-data.wat"),
-        _ => unreachable!(),
+        _ => return generic_fr_data(producer),
     };    
     for line in file.lines() {
         instructions.push(line.to_string());
     }
     instructions
 }
-pub fn fr_code(prime: &String) -> Vec<WasmInstruction> {
+pub fn fr_code(producer: &WASMProducer) -> Vec<WasmInstruction> {
     let mut instructions = vec![];
-    let file = match prime.as_ref(){
+    let file = match producer.prime_str.as_ref(){
         "bn128" => include_str!("bn128/fr-code.wat"),
         "bls12381" => include_str!("bls12381/fr-code.wat"),
         "goldilocks" => include_str!("goldilocks/fr-code.wat"),
         "grumpkin" => include_str!("grumpkin/fr-code.wat"),
         "pallas" => include_str!("pallas/fr-code.wat"),
         "vesta" => include_str!("vesta/fr-code.wat"),
-        _ => unreachable!(),
+        _ => return generic_fr_code(producer),
     };    
     for line in file.lines() {
         instructions.push(line.to_string());
@@ -1607,6 +1616,127 @@ pub fn fr_code(prime: &String) -> Vec<WasmInstruction> {
     instructions
 }
 
+// Addresses of the constants and the scratch memory of the generic field
+// code, placed after the stack pointer kept at address 0
+struct FrLayout {
+    q: usize,
+    half: usize,
+    r2: usize,
+    one: usize,
+    one_m: usize,
+    qm2: usize,
+    e1: usize,
+    e2: usize,
+    t: usize,
+    div_q: usize,
+    div_r: usize,
+    exp_a: usize,
+    exp_b: usize,
+    end: usize,
+}
+
+fn fr_layout(size_32_bit: usize) -> FrLayout {
+    let raw = 4 * size_32_bit;
+    let element = raw + 8;
+    let q = 8;
+    let half = q + raw;
+    let r2 = half + raw;
+    let one = r2 + raw;
+    let one_m = one + raw;
+    let qm2 = one_m + raw;
+    let e1 = qm2 + raw;
+    let e2 = e1 + element;
+    // the Montgomery multiplication needs two more words
+    let t = e2 + element;
+    let div_q = t + raw + 8;
+    let div_r = div_q + raw;
+    let exp_a = div_r + raw;
+    let exp_b = exp_a + raw;
+    FrLayout { q, half, r2, one, one_m, qm2, e1, e2, t, div_q, div_r, exp_a, exp_b, end: exp_b + raw }
+}
+
+// Memory taken by the generic field code, the raw prime comes right after it
+pub fn generic_fr_memory_size(size_32_bit: usize) -> usize {
+    fr_layout(size_32_bit).end - 4
+}
+
+fn lowest_u64(n: &BigInt) -> u64 {
+    let (_, bytes) = n.to_bytes_le();
+    bytes.iter().take(8).rev().fold(0, |acc, &byte| (acc << 8) | byte as u64)
+}
+
+fn generic_fr_data(producer: &WASMProducer) -> Vec<WasmInstruction> {
+    let q = producer.get_prime().parse::<BigInt>().unwrap();
+    let n8 = 4 * producer.get_size_32_bit();
+    let layout = fr_layout(producer.get_size_32_bit());
+    let r = BigInt::from(1) << (8 * n8);
+    let constants = [
+        (layout.q, q.clone()),
+        (layout.half, (&q - 1) / 2),
+        (layout.r2, (&r * &r) % &q),
+        (layout.one, BigInt::from(1)),
+        (layout.one_m, &r % &q),
+        (layout.qm2, &q - 2),
+    ];
+    constants
+        .iter()
+        .map(|(address, value)| format!("(data (i32.const {}) \"{}\")", address, wasm_hexa(n8, value)))
+        .collect()
+}
+
+fn generic_fr_code(producer: &WASMProducer) -> Vec<WasmInstruction> {
+    let q = producer.get_prime().parse::<BigInt>().unwrap();
+    let n32 = producer.get_size_32_bit();
+    let n8 = 4 * n32;
+    let n_bits = q.bits();
+    let layout = fr_layout(n32);
+    // np = -q^-1 mod 2^32, by Newton's iteration over the inverse of the lowest word
+    let q0 = lowest_u64(&q) as u32;
+    let mut inverse: u32 = 1;
+    for _ in 0..5 {
+        inverse = inverse.wrapping_mul(2u32.wrapping_sub(q0.wrapping_mul(inverse)));
+    }
+    let top_mask = if n_bits % 32 == 0 { u32::MAX } else { (1u32 << (n_bits % 32)) - 1 };
+    // values of 64 bits may exceed a prime of two words
+    let q64 = if n32 == 2 { Some((lowest_u64(&q) as i64).to_string()) } else { None };
+    let template = handlebars::Handlebars::new();
+    let code = template
+        .render_template(
+            include_str!("common/fr-code.wat"),
+            &serde_json::json!({
+                "n32": n32,
+                "n8": n8,
+                "e8": n8 + 8,
+                "r_bits": 32 * n32,
+                "n_bits": n_bits,
+                "top": n8 - 4,
+                "top_mask": top_mask as i32,
+                "np": inverse.wrapping_neg(),
+                "q64": q64,
+                "q": layout.q,
+                "half": layout.half,
+                "r2": layout.r2,
+                "one": layout.one,
+                "one_m": layout.one_m,
+                "qm2": layout.qm2,
+                "e1": layout.e1,
+                "e2": layout.e2,
+                "e2_raw": layout.e2 + 8,
+                "t": layout.t,
+                "t_top": layout.t + n8 - 4,
+                "t_n": layout.t + n8,
+                "t_n1": layout.t + n8 + 4,
+                "t_size": n8 + 8,
+                "div_q": layout.div_q,
+                "div_r": layout.div_r,
+                "exp_a": layout.exp_a,
+                "exp_b": layout.exp_b,
+            }),
+        )
+        .expect("must render");
+    code.lines().map(|line| line.to_string()).collect()
+}
+
 /*
 pub fn generate_utils_js_file(js_folder: &PathBuf) -> std::io::Result<()> {
     use std::io::BufWriter;
@@ -1614,7 +1744,7 @@ pub fn generate_utils_js_file(js_folder: &PathBuf) -> std::io::Result<()> {
     file_path.push("utils");
     file_path.set_extension("js");
     let file_name = file_path.to_str().unwrap();
-    let mut js_file = BufWriter::new(File::create(file_name).unwrap());
+    let mut js_file = BufWriter::new(File::create(file_name)?);
     let mut code = "".to_string();
     let file = include_str!("utils.js");
     for line in file.lines() {
@@ -1626,13 +1756,7 @@ pub fn generate_utils_js_file(js_folder: &PathBuf) -> std::io::Result<()> {
 }
  */
 
-pub fn generate_generate_witness_js_file(js_folder: &PathBuf) -> std::io::Result<()> {
-    use std::io::BufWriter;
-    let mut file_path  = js_folder.clone();
-    file_path.push("generate_witness");
-    file_path.set_extension("js");
-    let file_name = file_path.to_str().unwrap();
-    let mut js_file = BufWriter::new(File::create(file_name).unwrap());
+pub fn generate_generate_witness_js_file(js_file: &mut dyn Write) -> std::io::Result<()> {
     let mut code = "".to_string();
     let file = include_str!("common/generate_witness.js");
     for line in file.lines() {
@@ -1643,13 +1767,7 @@ pub fn generate_generate_witness_js_file(js_folder: &PathBuf) -> std::io::Result
     Ok(())
 }
 
-pub fn generate_witness_calculator_js_file(js_folder: &PathBuf) -> std::io::Result<()> {
-    use std::io::BufWriter;
-    let mut file_path  = js_folder.clone();
-    file_path.push("witness_calculator");
-    file_path.set_extension("js");
-    let file_name = file_path.to_str().unwrap();
-    let mut js_file = BufWriter::new(File::create(file_name).unwrap());
+pub fn generate_witness_calculator_js_file(js_file: &mut dyn Write) -> std::io::Result<()> {
     let mut code = "".to_string();
     let file = include_str!("common/witness_calculator.js");
     for line in file.lines() {
@@ -1,11 +1,14 @@
 use crate::circuit_design::circuit::{Circuit, CompilationFlags};
 use crate::circuit_design::function::FunctionCodeInfo;
+use crate::circuit_design::shared_templates::share_template_code;
 use crate::circuit_design::template::TemplateCodeInfo;
 use crate::hir::very_concrete_program::*;
 use crate::intermediate_representation::translate;
 use crate::intermediate_representation::translate::{CodeInfo, FieldTracker, TemplateDB, ParallelClusters};
+use crate::ir_processing::{IllFormedIr, OptimizationLevel, StackAllocation};
 use code_producers::c_elements::*;
 use code_producers::wasm_elements::*;
+use num_bigint_dig::BigInt;
 use program_structure::file_definition::FileLibrary;
 use std::collections::{BTreeMap, HashMap};
 
@@ -25,6 +28,7 @@ fn build_template_instances(
     c_info: &CircuitInfo,
     ti: Vec<TemplateInstance>,
     mut field_tracker: FieldTracker,
+    ill_formed: &mut Vec<IllFormedIr>,
 ) -> (FieldTracker, HashMap<String,usize>) {
 
     fn compute_jump(lengths: &Vec<usize>, indexes: &[usize]) -> usize {
@@ -98,6 +102,9 @@ fn build_template_instances(
             template_database: &c_info.template_database,
             string_table : string_table,
             signals_to_tags: template.signals_to_tags,
+            prime: &c_info.prime,
+            optimization: c_info.optimization,
+            verify_ir: c_info.verify_ir,
         };
         let mut template_info = TemplateCodeInfo {
             name,
@@ -115,10 +122,16 @@ fn build_template_instances(
         };
         let code = template.code;
         let out = translate::translate_code(code, code_info);
+        ill_formed.extend(out.ill_formed);
         field_tracker = out.constant_tracker;
         template_info.body = out.code;
         template_info.expression_stack_depth = out.expression_depth;
         template_info.var_stack_depth = out.stack_depth;
+        circuit.stack_allocations.push(StackAllocation {
+            header: template_info.header.clone(),
+            declared: out.declared_stack_depth,
+            allocated: out.stack_depth,
+        });
         template_info.signal_stack_depth = out.signal_depth;
         string_table = out.string_table;
         cmp_id = out.next_cmp_id;
@@ -133,7 +146,8 @@ fn build_function_instances(
     c_info: &CircuitInfo,
     instances: Vec<VCF>,
     mut field_tracker: FieldTracker,
-    mut string_table : HashMap<String,usize>
+    mut string_table : HashMap<String,usize>,
+    ill_formed: &mut Vec<IllFormedIr>,
 ) -> (FieldTracker, HashMap<String, usize>, HashMap<String, usize>) {
     let mut function_to_arena_size = HashMap::new();
     for instance in instances {
@@ -162,6 +176,9 @@ fn build_function_instances(
             template_database: &c_info.template_database,
             string_table : string_table,
             signals_to_tags: BTreeMap::new(),
+            prime: &c_info.prime,
+            optimization: c_info.optimization,
+            verify_ir: c_info.verify_ir,
         };
         let mut function_info = FunctionCodeInfo {
             name,
@@ -172,11 +189,17 @@ fn build_function_instances(
         };
         let code = instance.body;
         let out = translate::translate_code(code, code_info);
+        ill_formed.extend(out.ill_formed);
         string_table = out.string_table;
         field_tracker = out.constant_tracker;
         function_info.body = out.code;
         function_info.max_number_of_ops_in_expression = out.expression_depth;
         function_info.max_number_of_vars = out.stack_depth;
+        circuit.stack_allocations.push(StackAllocation {
+            header: header.clone(),
+            declared: out.declared_stack_depth,
+            allocated: out.stack_depth,
+        });
         function_to_arena_size.insert(header, function_info.max_number_of_vars);
         circuit.add_function_code(function_info);
     }
@@ -194,6 +217,7 @@ fn initialize_wasm_producer(vcp: &VCP, database: &TemplateDB, wat_flag:bool, ver
     producer.main_signal_offset = 1;
     producer.prime = prime.to_str_radix(10);
     producer.prime_str = vcp.prime.clone();
+    producer.size_32_bit = prime.bits() / 32 + if prime.bits() % 32 != 0 { 1 } else { 0 };
     producer.fr_memory_size = match vcp.prime.as_str(){
         "goldilocks" => 412,
         "bn128" => 1948,
@@ -201,13 +225,12 @@ fn initialize_wasm_producer(vcp: &VCP, database: &TemplateDB, wat_flag:bool, ver
         "grumpkin" => 1948,
         "pallas" => 1948,
         "vesta" => 1948,
-        _ => unreachable!()
+        _ => wasm_code_generator::generic_fr_memory_size(producer.size_32_bit),
     };
     //producer.fr_memory_size = 412 if goldilocks and 1948 for bn128 and bls12381
     // for each created component we store three u32, for each son we store a u32 in its father
     producer.size_of_component_tree = stats.all_created_components * 3 + stats.all_needed_subcomponents_indexes;
     producer.total_number_of_signals = stats.all_signals + 1;
-    producer.size_32_bit = prime.bits() / 32 + if prime.bits() % 32 != 0 { 1 } else { 0 };
     producer.size_32_shift = 0;
     let mut pow = 1;
     while pow < producer.size_32_bit {
@@ -229,8 +252,8 @@ fn initialize_wasm_producer(vcp: &VCP, database: &TemplateDB, wat_flag:bool, ver
     producer
 }
 
-fn initialize_c_producer(vcp: &VCP, database: &TemplateDB, version: &str) -> CProducer {
-    use program_structure::utils::constants::UsefulConstants;
+fn initialize_c_producer(vcp: &VCP, database: &TemplateDB, portable_flag: bool, version: &str) -> CProducer {
+    use program_structure::utils::constants::{is_named_prime, UsefulConstants};
     let initial_node = vcp.get_main_id();
     let prime = UsefulConstants::new(&vcp.prime).get_p().clone();
     let mut producer = CProducer::default();
@@ -239,6 +262,8 @@ fn initialize_c_producer(vcp: &VCP, database: &TemplateDB, version: &str) -> CPr
     producer.main_signal_offset = 1;
     producer.prime = prime.to_str_radix(10);
     producer.prime_str = vcp.prime.clone();
+    // only the named primes have an assembly implementation of the field
+    producer.portable_flag = portable_flag || !is_named_prime(&vcp.prime);
     producer.size_of_component_tree = stats.all_created_components * 3 + stats.all_needed_subcomponents_indexes;
     producer.total_number_of_signals = stats.all_signals + 1;
     producer.size_32_bit = prime.bits() / 32 + if prime.bits() % 32 != 0 { 1 } else { 0 };
@@ -323,25 +348,6 @@ fn build_input_output_list(instance: &TemplateInstance, database: &TemplateDB) -
     io_list
 }
 
-fn write_main_inputs_log(vcp: &VCP) {
-    use program_structure::ast::SignalType::*;
-    use std::fs::File;
-    use std::io::{BufWriter, Write};
-
-    const INPUT_LOG: &str = "./log_input_signals.txt";
-    let main = vcp.get_main_instance().unwrap();
-    let mut writer = BufWriter::new(File::create(INPUT_LOG).unwrap());
-    for signal in &main.signals {
-        if signal.xtype == Input {
-            let name = format!("main.{}", &signal.name);
-            let length = signal.size();
-            let msg = format!("{} {}\n", name, length);
-            writer.write_all(msg.as_bytes()).unwrap();
-        }
-        writer.flush().unwrap();
-    }
-}
-
 fn get_number_version(version: &str) -> (usize, usize, usize) {
     use std::str::FromStr;
     let version_splitted: Vec<&str> = version.split(".").collect();
@@ -356,29 +362,41 @@ struct CircuitInfo {
     file_library: FileLibrary,
     functions: HashMap<String, Vec<usize>>,
     template_database: TemplateDB,
+    prime: BigInt,
+    optimization: OptimizationLevel,
+    verify_ir: bool,
 }
 
-pub fn build_circuit(vcp: VCP, flag: CompilationFlags, version: &str) -> Circuit {
+// Fails when the IR is verified and some transformation leaves it ill-formed
+pub fn build_circuit(vcp: VCP, flag: CompilationFlags, version: &str) -> Result<Circuit, Vec<IllFormedIr>> {
     use crate::ir_processing::set_arena_size_in_calls;
-    if flag.main_inputs_log {
-        write_main_inputs_log(&vcp);
-    }
+    use program_structure::utils::constants::UsefulConstants;
     let template_database = TemplateDB::build(&vcp.templates);
     let mut circuit = Circuit::default();
     circuit.wasm_producer = initialize_wasm_producer(&vcp, &template_database, flag.wat_flag, version);
-    circuit.c_producer = initialize_c_producer(&vcp, &template_database, version);
+    circuit.c_producer = initialize_c_producer(&vcp, &template_database, flag.c_portable, version);
 
     let field_tracker = FieldTracker::new();
     let circuit_info = CircuitInfo {
+        prime: UsefulConstants::new(&vcp.prime).get_p().clone(),
+        optimization: flag.optimization,
+        verify_ir: flag.verify_ir,
         template_database,
         file_library: vcp.file_library,
         functions: vcp.quick_knowledge,
     };
 
+    let mut ill_formed = Vec::new();
     let (field_tracker, string_table) =
-        build_template_instances(&mut circuit, &circuit_info, vcp.templates, field_tracker);
-    let (field_tracker, function_to_arena_size, table_string_to_usize) =
-        build_function_instances(&mut circuit, &circuit_info, vcp.functions, field_tracker,string_table);
+        build_template_instances(&mut circuit, &circuit_info, vcp.templates, field_tracker, &mut ill_formed);
+    let (field_tracker, function_to_arena_size, table_string_to_usize) = build_function_instances(
+        &mut circuit,
+        &circuit_info,
+        vcp.functions,
+        field_tracker,
+        string_table,
+        &mut ill_formed,
+    );
 
     let table_usize_to_string = create_table_usize_to_string(table_string_to_usize);
     circuit.wasm_producer.set_string_table(table_usize_to_string.clone());
@@ -394,8 +412,21 @@ pub fn build_circuit(vcp: VCP, flag: CompilationFlags, version: &str) -> Circuit
     for tem in &mut circuit.templates {
         set_arena_size_in_calls(&mut tem.body, &function_to_arena_size);
     }
+    if flag.optimization >= OptimizationLevel::O1 {
+        share_template_code(&mut circuit);
+    }
+    if flag.verify_ir && ill_formed.is_empty() {
+        if let Err(errors) = circuit.verify_ir() {
+            let pass = if flag.optimization >= OptimizationLevel::O1 { "share_template_code" } else { "set_arena_size_in_calls" };
+            ill_formed.push(IllFormedIr { pass: pass.to_string(), errors });
+        }
+    }
 
-    circuit
+    if ill_formed.is_empty() {
+        Ok(circuit)
+    } else {
+        Err(ill_formed)
+    }
 }
 
 pub fn create_table_usize_to_string( string_table : HashMap<String,usize>) -> Vec<String> {
@@ -2,14 +2,22 @@ use super::function::{FunctionCode, FunctionCodeInfo};
 use super::template::{TemplateCode, TemplateCodeInfo};
 use super::types::*;
 use crate::hir::very_concrete_program::VCP;
+use crate::ir_processing::{CircuitSummary, CodeKind, IllFormedIr, OptimizationLevel, StackAllocation, Verifier, VerifyError};
+use crate::ir_text::{self, ReadError};
 use crate::translating_traits::*;
 use code_producers::c_elements::*;
 use code_producers::wasm_elements::*;
+use program_structure::output_directory::OutputDirectory;
+use program_structure::output_error::{OutputError, OutputResult};
 use std::io::Write;
+use std::path::Path;
 
 pub struct CompilationFlags {
-    pub main_inputs_log: bool,
     pub wat_flag:bool,
+    pub c_portable: bool,
+    pub optimization: OptimizationLevel,
+    // Checks the IR after every transformation, always done in debug builds
+    pub verify_ir: bool,
 }
 
 pub struct Circuit {
@@ -17,6 +25,9 @@ pub struct Circuit {
     pub c_producer: CProducer,
     pub templates: Vec<TemplateCode>,
     pub functions: Vec<FunctionCode>,
+    pub stack_allocations: Vec<StackAllocation>,
+    // Bodies run by several instances of a template, see shared_templates.rs
+    pub shared_templates: Vec<TemplateCode>,
 }
 
 impl Default for Circuit {
@@ -26,6 +37,8 @@ impl Default for Circuit {
             wasm_producer: WASMProducer::default(),
             templates: Vec::new(),
             functions: Vec::new(),
+            stack_allocations: Vec::new(),
+            shared_templates: Vec::new(),
         }
     }
 }
@@ -40,7 +53,7 @@ impl WriteWasm for Circuit {
         code_aux = generate_memory_def_list(&producer);
         code.append(&mut code_aux);
 
-        code_aux = fr_types(&producer.prime_str);
+        code_aux = fr_types(producer);
         code.append(&mut code_aux);
 
         code_aux = generate_types_list();
@@ -48,7 +61,7 @@ impl WriteWasm for Circuit {
         code_aux = generate_exports_list();
         code.append(&mut code_aux);
 
-        code_aux = fr_code(&producer.prime_str);
+        code_aux = fr_code(producer);
         code.append(&mut code_aux);
 
         code_aux = desp_io_subcomponent_generator(&producer);
@@ -114,6 +127,10 @@ impl WriteWasm for Circuit {
             code.append(&mut f.produce_wasm(producer));
         }
 
+        for t in &self.shared_templates {
+            code.append(&mut t.produce_shared_wasm(producer));
+        }
+
         for t in &self.templates {
             code.append(&mut t.produce_wasm(producer));
         }
@@ -121,7 +138,7 @@ impl WriteWasm for Circuit {
         code_aux = generate_table_of_template_runs(&producer);
         code.append(&mut code_aux);
 
-        code_aux = fr_data(&producer.prime_str);
+        code_aux = fr_data(producer);
         code.append(&mut code_aux);
 
         code_aux = generate_data_list(&producer);
@@ -131,166 +148,171 @@ impl WriteWasm for Circuit {
         code
     }
 
-    fn write_wasm<T: Write>(&self, writer: &mut T, producer: &WASMProducer) -> Result<(), ()> {
+    fn write_wasm<T: Write>(&self, writer: &mut T, producer: &WASMProducer) -> std::io::Result<()> {
         use code_producers::wasm_elements::wasm_code_generator::*;
 
-        writer.write_all("(module".as_bytes()).map_err(|_| {})?;
-        //writer.flush().map_err(|_| {})?;
+        writer.write_all("(module".as_bytes())?;
+        //writer.flush()?;
 
         let mut code_aux = generate_imports_list();
         let mut code = merge_code(code_aux);
-        writer.write_all(code.as_bytes()).map_err(|_| {})?;
-        //writer.flush().map_err(|_| {})?;
+        writer.write_all(code.as_bytes())?;
+        //writer.flush()?;
 
         code_aux = generate_memory_def_list(&producer);
         code = merge_code(code_aux);
-        writer.write_all(code.as_bytes()).map_err(|_| {})?;
-        //writer.flush().map_err(|_| {})?;
+        writer.write_all(code.as_bytes())?;
+        //writer.flush()?;
 
-        code_aux = fr_types(&producer.prime_str);
+        code_aux = fr_types(producer);
         code = merge_code(code_aux);
-        writer.write_all(code.as_bytes()).map_err(|_| {})?;
-        //writer.flush().map_err(|_| {})?;
+        writer.write_all(code.as_bytes())?;
+        //writer.flush()?;
 
         code_aux = generate_types_list();
         code = merge_code(code_aux);
-        writer.write_all(code.as_bytes()).map_err(|_| {})?;
-        //writer.flush().map_err(|_| {})?;
+        writer.write_all(code.as_bytes())?;
+        //writer.flush()?;
 
         code_aux = generate_exports_list();
         code = merge_code(code_aux);
-        writer.write_all(code.as_bytes()).map_err(|_| {})?;
-        //writer.flush().map_err(|_| {})?;
+        writer.write_all(code.as_bytes())?;
+        //writer.flush()?;
 
-        code_aux = fr_code(&producer.prime_str);
+        code_aux = fr_code(producer);
         code = merge_code(code_aux);
-        writer.write_all(code.as_bytes()).map_err(|_| {})?;
-        //writer.flush().map_err(|_| {})?;
+        writer.write_all(code.as_bytes())?;
+        //writer.flush()?;
 
         code_aux = desp_io_subcomponent_generator(&producer);
         code = merge_code(code_aux);
-        writer.write_all(code.as_bytes()).map_err(|_| {})?;
-        //writer.flush().map_err(|_| {})?;
+        writer.write_all(code.as_bytes())?;
+        //writer.flush()?;
 
         code_aux = get_version_generator(&producer);
         code = merge_code(code_aux);
-        writer.write_all(code.as_bytes()).map_err(|_| {})?;
-        //writer.flush().map_err(|_| {})?;
+        writer.write_all(code.as_bytes())?;
+        //writer.flush()?;
 
         code_aux = get_shared_rw_memory_start_generator(&producer);
         code = merge_code(code_aux);
-        writer.write_all(code.as_bytes()).map_err(|_| {})?;
-        //writer.flush().map_err(|_| {})?;
+        writer.write_all(code.as_bytes())?;
+        //writer.flush()?;
 
         code_aux = read_shared_rw_memory_generator(&producer);
         code = merge_code(code_aux);
-        writer.write_all(code.as_bytes()).map_err(|_| {})?;
-        //writer.flush().map_err(|_| {})?;
+        writer.write_all(code.as_bytes())?;
+        //writer.flush()?;
 
         code_aux = write_shared_rw_memory_generator(&producer);
         code = merge_code(code_aux);
-        writer.write_all(code.as_bytes()).map_err(|_| {})?;
-        //writer.flush().map_err(|_| {})?;
+        writer.write_all(code.as_bytes())?;
+        //writer.flush()?;
 
         code_aux = reserve_stack_fr_function_generator();
         code = merge_code(code_aux);
-        writer.write_all(code.as_bytes()).map_err(|_| {})?;
-        //writer.flush().map_err(|_| {})?;
+        writer.write_all(code.as_bytes())?;
+        //writer.flush()?;
 
         code_aux = init_generator(&producer);
         code = merge_code(code_aux);
-        writer.write_all(code.as_bytes()).map_err(|_| {})?;
-        //writer.flush().map_err(|_| {})?;
+        writer.write_all(code.as_bytes())?;
+        //writer.flush()?;
 
         code_aux = set_input_signal_generator(&producer);
         code = merge_code(code_aux);
-        writer.write_all(code.as_bytes()).map_err(|_| {})?;
-        //writer.flush().map_err(|_| {})?;
+        writer.write_all(code.as_bytes())?;
+        //writer.flush()?;
 
         code_aux = get_input_signal_size_generator(&producer);
         code = merge_code(code_aux);
-        writer.write_all(code.as_bytes()).map_err(|_| {})?;
-        //writer.flush().map_err(|_| {})?;
+        writer.write_all(code.as_bytes())?;
+        //writer.flush()?;
 
         code_aux = get_raw_prime_generator(&producer);
         code = merge_code(code_aux);
-        writer.write_all(code.as_bytes()).map_err(|_| {})?;
-        //writer.flush().map_err(|_| {})?;
+        writer.write_all(code.as_bytes())?;
+        //writer.flush()?;
 
         code_aux = get_field_num_len32_generator(&producer);
         code = merge_code(code_aux);
-        writer.write_all(code.as_bytes()).map_err(|_| {})?;
-        //writer.flush().map_err(|_| {})?;
+        writer.write_all(code.as_bytes())?;
+        //writer.flush()?;
 
         code_aux = get_input_size_generator(&producer);
         code = merge_code(code_aux);
-        writer.write_all(code.as_bytes()).map_err(|_| {})?;
-        //writer.flush().map_err(|_| {})?;
+        writer.write_all(code.as_bytes())?;
+        //writer.flush()?;
 	
         code_aux = get_witness_size_generator(&producer);
         code = merge_code(code_aux);
-        writer.write_all(code.as_bytes()).map_err(|_| {})?;
-        //writer.flush().map_err(|_| {})?;
+        writer.write_all(code.as_bytes())?;
+        //writer.flush()?;
 
         code_aux = get_witness_generator(&producer);
         code = merge_code(code_aux);
-        writer.write_all(code.as_bytes()).map_err(|_| {})?;
-        //writer.flush().map_err(|_| {})?;
+        writer.write_all(code.as_bytes())?;
+        //writer.flush()?;
 
         code_aux = copy_32_in_shared_rw_memory_generator(&producer);
         code = merge_code(code_aux);
-        writer.write_all(code.as_bytes()).map_err(|_| {})?;
-        //writer.flush().map_err(|_| {})?;
+        writer.write_all(code.as_bytes())?;
+        //writer.flush()?;
 
         code_aux = copy_fr_in_shared_rw_memory_generator(&producer);
         code = merge_code(code_aux);
-        writer.write_all(code.as_bytes()).map_err(|_| {})?;
-        //writer.flush().map_err(|_| {})?;
+        writer.write_all(code.as_bytes())?;
+        //writer.flush()?;
 
         code_aux = get_message_char_generator(&producer);
         code = merge_code(code_aux);
-        writer.write_all(code.as_bytes()).map_err(|_| {})?;
-        //writer.flush().map_err(|_| {})?;
+        writer.write_all(code.as_bytes())?;
+        //writer.flush()?;
 
         code_aux = build_buffer_message_generator(&producer);
         code = merge_code(code_aux);
-        writer.write_all(code.as_bytes()).map_err(|_| {})?;
-        //writer.flush().map_err(|_| {})?;
+        writer.write_all(code.as_bytes())?;
+        //writer.flush()?;
 
         code_aux = build_log_message_generator(&producer);
         code = merge_code(code_aux);
-        writer.write_all(code.as_bytes()).map_err(|_| {})?;
-        //writer.flush().map_err(|_| {})?;
+        writer.write_all(code.as_bytes())?;
+        //writer.flush()?;
 
         // Actual code from the program
 
         for f in &self.functions {
             f.write_wasm(writer, producer)?;
-            //writer.flush().map_err(|_| {})?;
+            //writer.flush()?;
+        }
+
+        for t in &self.shared_templates {
+            code = merge_code(t.produce_shared_wasm(producer));
+            writer.write_all(code.as_bytes())?;
         }
 
         for t in &self.templates {
             t.write_wasm(writer, producer)?;
-            //writer.flush().map_err(|_| {})?;
+            //writer.flush()?;
         }
 
         code_aux = generate_table_of_template_runs(&producer);
         code = merge_code(code_aux);
-        writer.write_all(code.as_bytes()).map_err(|_| {})?;
-        //writer.flush().map_err(|_| {})?;
+        writer.write_all(code.as_bytes())?;
+        //writer.flush()?;
 
-        code_aux = fr_data(&producer.prime_str);
+        code_aux = fr_data(producer);
         code = merge_code(code_aux);
-        writer.write_all(code.as_bytes()).map_err(|_| {})?;
-        //writer.flush().map_err(|_| {})?;
+        writer.write_all(code.as_bytes())?;
+        //writer.flush()?;
 
         code_aux = generate_data_list(&producer);
         code = merge_code(code_aux);
-        writer.write_all(code.as_bytes()).map_err(|_| {})?;
-        //writer.flush().map_err(|_| {})?;
+        writer.write_all(code.as_bytes())?;
+        //writer.flush()?;
 
-        writer.write_all(")".as_bytes()).map_err(|_| {})?;
-        writer.flush().map_err(|_| {})
+        writer.write_all(")".as_bytes())?;
+        writer.flush()
     }
 }
 
@@ -372,6 +394,9 @@ impl WriteC for Circuit {
             code.append(&mut f_code);
         }
         code.push("// template declarations".to_string());
+        for t in &self.shared_templates {
+            code.append(&mut t.produce_shared_c(producer));
+        }
         for t in &self.templates {
             let (mut t_code, _) = t.produce_c(producer, None);
             code.append(&mut t_code);
@@ -410,7 +435,7 @@ impl WriteC for Circuit {
         (code, "".to_string())
     }
 
-    fn write_c<T: Write>(&self, writer: &mut T, producer: &CProducer) -> Result<(), ()> {
+    fn write_c<T: Write>(&self, writer: &mut T, producer: &CProducer) -> std::io::Result<()> {
 	use code_producers::wasm_elements::wasm_code_generator::merge_code;
         use c_code_generator::*;
         let mut code = vec![];
@@ -486,25 +511,30 @@ impl WriteC for Circuit {
         // Actual code of the circuit
 
 	code_write = merge_code(code);
-        writer.write_all(code_write.as_bytes()).map_err(|_| {})?;
+        writer.write_all(code_write.as_bytes())?;
 	
         code_write = "// function declarations\n".to_string();
-        writer.write_all(code_write.as_bytes()).map_err(|_| {})?;
+        writer.write_all(code_write.as_bytes())?;
 	
         for f in &self.functions {
             let (f_code, _) = f.produce_c(producer, None);
             //code.append(&mut f_code);
 	    code_write = merge_code(f_code);
-            writer.write_all(code_write.as_bytes()).map_err(|_| {})?;
+            writer.write_all(code_write.as_bytes())?;
         }
 	
 	code_write = "// template declarations\n".to_string();
-        writer.write_all(code_write.as_bytes()).map_err(|_| {})?;
+        writer.write_all(code_write.as_bytes())?;
+
+        for t in &self.shared_templates {
+            code_write = merge_code(t.produce_shared_c(producer));
+            writer.write_all(code_write.as_bytes())?;
+        }
 	
         for t in &self.templates {
             let (t_code, _) = t.produce_c(producer, None);
 	    code_write = merge_code(t_code);
-            writer.write_all(code_write.as_bytes()).map_err(|_| {})?;
+            writer.write_all(code_write.as_bytes())?;
             //code.append(&mut t_code);
         }
 
@@ -538,15 +568,15 @@ impl WriteC for Circuit {
 
         let main_run_body = vec![ctx_index, run_call];
 	code_write = build_callable(run_circuit, run_circuit_args, main_run_body) + "\n";
-        writer.write_all(code_write.as_bytes()).map_err(|_| {})?;
-        writer.flush().map_err(|_| {})
+        writer.write_all(code_write.as_bytes())?;
+        writer.flush()
 	    
     }
 
 }
 
 impl Circuit {
-    pub fn build(vcp: VCP, flags: CompilationFlags, version: &str) -> Self {
+    pub fn build(vcp: VCP, flags: CompilationFlags, version: &str) -> Result<Self, Vec<IllFormedIr>> {
         use super::build::build_circuit;
         build_circuit(vcp, flags, version)
     }
@@ -569,30 +599,136 @@ impl Circuit {
         self.templates[id].as_ref()
     }
     pub fn produce_ir_string_for_template(&self, id: ID) -> String {
-        self.templates[id].to_string()
+        ir_text::template_to_text(&self.templates[id])
     }
     pub fn produce_ir_string_for_function(&self, id: ID) -> String {
-        self.functions[id].to_string()
+        ir_text::function_to_text(&self.functions[id])
+    }
+    // Textual IR of every template, shared template and function, see ir_text
+    pub fn produce_ir_string(&self) -> String {
+        let templates: Vec<&TemplateCodeInfo> = self.templates.iter().map(|t| t.as_ref()).collect();
+        let shared: Vec<&TemplateCodeInfo> = self.shared_templates.iter().map(|t| t.as_ref()).collect();
+        let functions: Vec<&FunctionCodeInfo> = self.functions.iter().map(|f| f.as_ref()).collect();
+        ir_text::circuit_to_text(&templates, &shared, &functions)
+    }
+    // Replaces the templates, shared templates and functions of the circuit
+    // by the ones in the textual IR. The producers are left untouched.
+    pub fn read_ir_string(&mut self, text: &str) -> Result<(), ReadError> {
+        let code = ir_text::text_to_circuit(text)?;
+        self.templates = code.templates.into_iter().map(|template| template.wrap()).collect();
+        self.shared_templates = code.shared_templates.into_iter().map(|template| template.wrap()).collect();
+        self.functions = code.functions.into_iter().map(|function| function.wrap()).collect();
+        self.stack_allocations.clear();
+        Ok(())
     }
-    pub fn produce_c<W: Write>(&self, c_folder: &str, run_name: &str, c_circuit: &mut W, c_dat: &mut W) -> Result<(), ()> {
-	    use std::path::Path;
-	    let c_folder_path = Path::new(c_folder.clone()).to_path_buf();
-        c_code_generator::generate_main_cpp_file(&c_folder_path).map_err(|_err| {})?;
-        c_code_generator::generate_circom_hpp_file(&c_folder_path).map_err(|_err| {})?;
-        c_code_generator::generate_fr_hpp_file(&c_folder_path, &self.c_producer.prime_str).map_err(|_err| {})?;
-        c_code_generator::generate_calcwit_hpp_file(&c_folder_path).map_err(|_err| {})?;
-        c_code_generator::generate_fr_cpp_file(&c_folder_path, &self.c_producer.prime_str).map_err(|_err| {})?;
-        c_code_generator::generate_calcwit_cpp_file(&c_folder_path).map_err(|_err| {})?;
-        c_code_generator::generate_fr_asm_file(&c_folder_path, &self.c_producer.prime_str).map_err(|_err| {})?;
-        c_code_generator::generate_make_file(&c_folder_path,run_name,&self.c_producer).map_err(|_err| {})?;
-        c_code_generator::generate_dat_file(c_dat, &self.c_producer).map_err(|_err| {})?;
-        self.write_c(c_circuit, &self.c_producer)
+    // Slots of the var stacks saved by sharing them between variables
+    pub fn saved_var_slots(&self) -> usize {
+        self.stack_allocations.iter().map(|allocation| allocation.saved()).sum()
     }
-    pub fn produce_wasm<W: Write>(&self, js_folder: &str, _wasm_name: &str, writer: &mut W) -> Result<(), ()> {
-	use std::path::Path;
-	let js_folder_path = Path::new(js_folder.clone()).to_path_buf();
-        wasm_code_generator::generate_generate_witness_js_file(&js_folder_path).map_err(|_err| {})?;
-        wasm_code_generator::generate_witness_calculator_js_file(&js_folder_path).map_err(|_err| {})?;
-        self.write_wasm(writer, &self.wasm_producer)
+    // Instances of templates that run a shared body
+    pub fn shared_instances(&self) -> usize {
+        self.templates.iter().filter(|template| template.shared_run.is_some()).count()
     }
+    // Checks that the code of every template and function is well formed
+    pub fn verify_ir(&self) -> Result<(), Vec<VerifyError>> {
+        let summary = CircuitSummary {
+            templates: self.templates.iter().map(|template| template.header.clone()).collect(),
+            functions: self
+                .functions
+                .iter()
+                .map(|function| (function.header.clone(), (function.params.len(), function.max_number_of_vars)))
+                .collect(),
+        };
+        let mut errors = Vec::new();
+        for template in &self.templates {
+            let verifier = Verifier::new(&template.header)
+                .kind(CodeKind::Template)
+                .expression_stack_depth(template.expression_stack_depth)
+                .var_stack_depth(template.var_stack_depth)
+                .circuit(&summary);
+            errors.append(&mut verifier.verify(&template.body).err().unwrap_or_default());
+        }
+        for template in &self.shared_templates {
+            let verifier = Verifier::new(&template.header)
+                .kind(CodeKind::Template)
+                .expression_stack_depth(template.expression_stack_depth)
+                .var_stack_depth(template.var_stack_depth)
+                .circuit(&summary);
+            errors.append(&mut verifier.verify(&template.body).err().unwrap_or_default());
+        }
+        for function in &self.functions {
+            let verifier = Verifier::new(&function.header)
+                .kind(CodeKind::Function)
+                .expression_stack_depth(function.max_number_of_ops_in_expression)
+                .var_stack_depth(function.max_number_of_vars)
+                .circuit(&summary);
+            errors.append(&mut verifier.verify(&function.body).err().unwrap_or_default());
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+    // Writes the C++ project of the circuit in c_folder. Every file is
+    // created through the given directory, so it may not touch the disk.
+    pub fn produce_c(
+        &self,
+        directory: &mut dyn OutputDirectory,
+        c_folder: &str,
+        run_name: &str,
+        c_file: &str,
+        dat_file: &str,
+    ) -> OutputResult<()> {
+        let prime = &self.c_producer.prime_str;
+        let folder_file = |name: &str| Path::new(c_folder).join(name).to_string_lossy().to_string();
+        write_output(directory, &folder_file("main.cpp"), |w| c_code_generator::generate_main_cpp_file(w))?;
+        write_output(directory, &folder_file("circom.hpp"), |w| c_code_generator::generate_circom_hpp_file(w))?;
+        if self.c_producer.get_portable_flag() {
+            let field = self.c_producer.get_prime();
+            write_output(directory, &folder_file("fr.hpp"), |w| c_code_generator::generate_portable_fr_hpp_file(w, field))?;
+        } else {
+            write_output(directory, &folder_file("fr.hpp"), |w| c_code_generator::generate_fr_hpp_file(w, prime))?;
+        }
+        write_output(directory, &folder_file("calcwit.hpp"), |w| c_code_generator::generate_calcwit_hpp_file(w))?;
+        write_output(directory, &folder_file("fr.cpp"), |w| c_code_generator::generate_fr_cpp_file(w, prime))?;
+        write_output(directory, &folder_file("calcwit.cpp"), |w| c_code_generator::generate_calcwit_cpp_file(w))?;
+        if self.c_producer.get_portable_flag() {
+            let field = self.c_producer.get_prime();
+            write_output(directory, &folder_file("fr_portable.cpp"), |w| c_code_generator::generate_fr_portable_cpp_file(w, field))?;
+        } else {
+            write_output(directory, &folder_file("fr.asm"), |w| c_code_generator::generate_fr_asm_file(w, prime))?;
+        }
+        write_output(directory, &folder_file("Makefile"), |w| {
+            c_code_generator::generate_make_file(w, run_name, &self.c_producer)
+        })?;
+        write_output(directory, dat_file, |w| c_code_generator::generate_dat_file(w, &self.c_producer))?;
+        write_output(directory, c_file, |mut w| self.write_c(&mut w, &self.c_producer))
+    }
+    // Writes the witness generator of the circuit in js_folder and the
+    // textual wasm code in wat_file
+    pub fn produce_wasm(
+        &self,
+        directory: &mut dyn OutputDirectory,
+        js_folder: &str,
+        _wasm_name: &str,
+        wat_file: &str,
+    ) -> OutputResult<()> {
+        let folder_file = |name: &str| Path::new(js_folder).join(name).to_string_lossy().to_string();
+        write_output(directory, &folder_file("generate_witness.js"), |w| {
+            wasm_code_generator::generate_generate_witness_js_file(w)
+        })?;
+        write_output(directory, &folder_file("witness_calculator.js"), |w| {
+            wasm_code_generator::generate_witness_calculator_js_file(w)
+        })?;
+        write_output(directory, wat_file, |mut w| self.write_wasm(&mut w, &self.wasm_producer))
+    }
+}
+
+fn write_output<F>(directory: &mut dyn OutputDirectory, file: &str, produce: F) -> OutputResult<()>
+where
+    F: FnOnce(&mut dyn Write) -> std::io::Result<()>,
+{
+    let mut writer = directory.create_file(file).map_err(|err| OutputError::new(file, err))?;
+    produce(&mut writer).map_err(|err| OutputError::new(file, err))
 }
@@ -7,7 +7,7 @@ use code_producers::wasm_elements::*;
 //use std::io::Write;
 
 pub type FunctionCode = Box<FunctionCodeInfo>;
-#[derive(Default)]
+#[derive(Default, PartialEq)]
 pub struct FunctionCodeInfo {
     pub header: String,
     pub name: String,
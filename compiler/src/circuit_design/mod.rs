@@ -2,4 +2,5 @@ mod build;
 pub mod circuit;
 pub mod function;
 pub mod template;
+mod shared_templates;
 pub mod types;
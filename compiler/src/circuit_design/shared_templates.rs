@@ -0,0 +1,248 @@
+use super::circuit::Circuit;
+use super::template::{SharedRun, TemplateCodeInfo};
+use crate::ir_processing::{field_constants, parametrize_constants};
+use crate::ir_text;
+use std::collections::{HashMap, HashSet};
+
+// Instances of a template whose code only differs in some field constants
+// run a single body. Each instance gets a copy of the constants in which it
+// differs from the others, placed after the constants of the circuit, and
+// its run function calls the shared body with the position of that copy.
+pub fn share_template_code(circuit: &mut Circuit) {
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    let mut group_of_shape: HashMap<String, usize> = HashMap::new();
+    for (index, template) in circuit.templates.iter().enumerate() {
+        let shape = template_shape(template);
+        match group_of_shape.get(&shape) {
+            Some(&group) => groups[group].push(index),
+            None => {
+                group_of_shape.insert(shape, groups.len());
+                groups.push(vec![index]);
+            }
+        }
+    }
+    let mut headers: HashSet<String> = circuit.templates.iter().map(|t| t.header.clone()).collect();
+    headers.extend(circuit.functions.iter().map(|f| f.header.clone()));
+    for members in groups.iter().filter(|members| members.len() > 1) {
+        let shared = share_group(circuit, members, &mut headers);
+        circuit.shared_templates.push(shared.wrap());
+    }
+}
+
+// Everything the run function of an instance depends on, with the field
+// constants and the messages of its code erased
+fn template_shape(template: &TemplateCodeInfo) -> String {
+    let mut body = template.body.clone();
+    let parameters = vec![Some(0); field_constants(&body).len()];
+    parametrize_constants(&mut body, &parameters, 0);
+    let shape = TemplateCodeInfo {
+        name: template.name.clone(),
+        is_parallel: template.is_parallel,
+        is_parallel_component: template.is_parallel_component,
+        is_not_parallel_component: template.is_not_parallel_component,
+        has_parallel_sub_cmp: template.has_parallel_sub_cmp,
+        number_of_outputs: template.number_of_outputs,
+        number_of_components: template.number_of_components,
+        var_stack_depth: template.var_stack_depth,
+        expression_stack_depth: template.expression_stack_depth,
+        body,
+        ..TemplateCodeInfo::default()
+    };
+    ir_text::template_to_text(&shape)
+}
+
+fn share_group(circuit: &mut Circuit, members: &[usize], headers: &mut HashSet<String>) -> TemplateCodeInfo {
+    let constants: Vec<Vec<(usize, usize)>> =
+        members.iter().map(|&member| field_constants(&circuit.templates[member].body)).collect();
+    // The places with the same constant in every instance keep it. The
+    // others read constants of the instance, which are shared by the places
+    // that have the same constants in all the instances. A place that reads
+    // a block gets its own block of constants in every instance.
+    let mut parameters = Vec::with_capacity(constants[0].len());
+    let mut columns: Vec<(Vec<usize>, usize)> = Vec::new();
+    let mut column_offset = HashMap::new();
+    let mut next_offset = 0;
+    for place in 0..constants[0].len() {
+        let size = constants[0][place].1;
+        let column: Vec<usize> = constants.iter().map(|instance| instance[place].0).collect();
+        if column.iter().all(|&constant| constant == column[0]) {
+            parameters.push(None);
+        } else {
+            let offset = *column_offset.entry((column.clone(), size)).or_insert(next_offset);
+            if offset == next_offset {
+                columns.push((column, size));
+                next_offset += size;
+            }
+            parameters.push(Some(offset));
+        }
+    }
+
+    let representative = &circuit.templates[members[0]];
+    let header = unique_header(&representative.name, headers);
+    let message_id = circuit.wasm_producer.message_list.len();
+    circuit.wasm_producer.message_list.push(format!("Error in template {}", representative.name));
+    let mut body = representative.body.clone();
+    parametrize_constants(&mut body, &parameters, message_id);
+    let shared = TemplateCodeInfo {
+        id: representative.id,
+        header: header.clone(),
+        name: representative.name.clone(),
+        is_parallel: representative.is_parallel,
+        is_parallel_component: representative.is_parallel_component,
+        is_not_parallel_component: representative.is_not_parallel_component,
+        has_parallel_sub_cmp: representative.has_parallel_sub_cmp,
+        number_of_outputs: representative.number_of_outputs,
+        number_of_components: representative.number_of_components,
+        var_stack_depth: representative.var_stack_depth,
+        expression_stack_depth: representative.expression_stack_depth,
+        body,
+        ..TemplateCodeInfo::default()
+    };
+
+    for (instance, &member) in members.iter().enumerate() {
+        let first_constant = circuit.c_producer.field_tracking.len();
+        for (column, size) in &columns {
+            for id in column[instance]..column[instance] + size {
+                let constant = circuit.c_producer.field_tracking[id].clone();
+                circuit.wasm_producer.field_tracking.push(constant.clone());
+                circuit.c_producer.field_tracking.push(constant);
+            }
+        }
+        circuit.templates[member].shared_run = Some(SharedRun { header: header.clone(), first_constant });
+    }
+    shared
+}
+
+fn unique_header(name: &str, headers: &mut HashSet<String>) -> String {
+    let mut index = 0;
+    loop {
+        let header = format!("{}_shared_{}", name, index);
+        if headers.insert(header.clone()) {
+            return header;
+        }
+        index += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intermediate_representation::ir_interface::*;
+
+    fn value(parse_as: ValueType, value: usize) -> InstructionPointer {
+        ValueBucket { line: 0, message_id: 0, parse_as, op_aux_no: 0, value }.allocate()
+    }
+
+    fn store(slot: usize, src: InstructionPointer) -> InstructionPointer {
+        StoreBucket {
+            line: 0,
+            message_id: 0,
+            context: InstrContext { size: 1 },
+            dest_is_output: false,
+            dest_address_type: AddressType::Variable,
+            dest: LocationRule::Indexed { location: value(ValueType::U32, slot), template_header: None },
+            src,
+        }
+        .allocate()
+    }
+
+    // Copies size consecutive constants starting at first
+    fn block_store(size: usize, first: usize) -> InstructionPointer {
+        let mut instruction = store(0, value(ValueType::BigInt, first));
+        if let Instruction::Store(b) = &mut *instruction {
+            b.context.size = size;
+        }
+        instruction
+    }
+
+    fn template(id: usize, name: &str, constants: [usize; 2]) -> TemplateCodeInfo {
+        TemplateCodeInfo {
+            id,
+            header: format!("{}_{}", name, id),
+            name: name.to_string(),
+            body: vec![
+                store(0, value(ValueType::BigInt, constants[0])),
+                store(1, value(ValueType::BigInt, constants[1])),
+            ],
+            var_stack_depth: 2,
+            ..TemplateCodeInfo::default()
+        }
+    }
+
+    fn shared_circuit() -> Circuit {
+        let mut circuit = Circuit::default();
+        let constants: Vec<String> = ["1", "2", "3"].iter().map(|c| c.to_string()).collect();
+        circuit.c_producer.field_tracking = constants.clone();
+        circuit.wasm_producer.field_tracking = constants;
+        circuit.add_template_code(template(0, "A", [0, 1]));
+        circuit.add_template_code(template(1, "A", [0, 2]));
+        circuit.add_template_code(template(2, "B", [0, 2]));
+        share_template_code(&mut circuit);
+        circuit
+    }
+
+    #[test]
+    fn instances_that_differ_in_constants_share_their_body() {
+        let circuit = shared_circuit();
+
+        assert_eq!(circuit.shared_templates.len(), 1);
+        let shared = &circuit.shared_templates[0];
+        assert_eq!(shared.header, "A_shared_0");
+        // The first constant is the same in both instances, the second one
+        // is read from the constants of the instance
+        assert!(*shared.body[0] == *store(0, value(ValueType::BigInt, 0)));
+        assert!(matches!(&*shared.body[1], Instruction::Store(b)
+            if *b.src == *value(ValueType::InstanceConstant, 0)));
+        let first_constants: Vec<Option<usize>> = circuit
+            .templates
+            .iter()
+            .map(|template| template.shared_run.as_ref().map(|shared| shared.first_constant))
+            .collect();
+        assert_eq!(first_constants, vec![Some(3), Some(4), None]);
+        assert_eq!(circuit.c_producer.field_tracking[3..], ["2".to_string(), "3".to_string()]);
+    }
+
+    #[test]
+    fn instances_that_differ_in_a_block_copy_all_its_constants() {
+        let mut circuit = Circuit::default();
+        let constants: Vec<String> = ["1", "2", "3", "4", "5"].iter().map(|c| c.to_string()).collect();
+        circuit.c_producer.field_tracking = constants.clone();
+        circuit.wasm_producer.field_tracking = constants;
+        for (id, first) in [0, 2].iter().enumerate() {
+            circuit.add_template_code(TemplateCodeInfo {
+                id,
+                header: format!("A_{}", id),
+                name: "A".to_string(),
+                body: vec![block_store(2, *first), store(2, value(ValueType::BigInt, 4))],
+                var_stack_depth: 3,
+                ..TemplateCodeInfo::default()
+            });
+        }
+        share_template_code(&mut circuit);
+
+        let shared = &circuit.shared_templates[0];
+        assert!(matches!(&*shared.body[0], Instruction::Store(b)
+            if *b.src == *value(ValueType::InstanceConstant, 0)));
+        assert!(*shared.body[1] == *store(2, value(ValueType::BigInt, 4)));
+        let first_constants: Vec<usize> = circuit
+            .templates
+            .iter()
+            .map(|template| template.shared_run.as_ref().unwrap().first_constant)
+            .collect();
+        assert_eq!(first_constants, vec![5, 7]);
+        let copies: Vec<&str> = circuit.c_producer.field_tracking[5..].iter().map(|c| c.as_str()).collect();
+        assert_eq!(copies, ["1", "2", "3", "4"]);
+    }
+
+    #[test]
+    fn the_text_of_a_shared_circuit_is_read_back() {
+        let circuit = shared_circuit();
+        let text = circuit.produce_ir_string();
+        let mut read = Circuit::default();
+        read.read_ir_string(&text).unwrap();
+        assert!(read.templates == circuit.templates);
+        assert!(read.shared_templates == circuit.shared_templates);
+        assert_eq!(read.shared_instances(), 2);
+        assert_eq!(read.produce_ir_string(), text);
+    }
+}
@@ -6,7 +6,7 @@ use code_producers::wasm_elements::*;
 type TemplateID = usize;
 pub type TemplateCode = Box<TemplateCodeInfo>;
 
-#[derive(Default)]
+#[derive(Default, PartialEq)]
 pub struct TemplateCodeInfo {
     pub id: TemplateID,
     pub header: String,
@@ -23,7 +23,17 @@ pub struct TemplateCodeInfo {
     pub expression_stack_depth: usize,
     pub signal_stack_depth: usize, // Not used now
     pub number_of_components: usize,
+    pub shared_run: Option<SharedRun>,
 }
+
+// The instance runs the body of the shared template `header`, where its
+// constants start at position first_constant of the circuit constants
+#[derive(Clone, PartialEq)]
+pub struct SharedRun {
+    pub header: String,
+    pub first_constant: usize,
+}
+
 impl ToString for TemplateCodeInfo {
     fn to_string(&self) -> String {
         let mut body = "".to_string();
@@ -74,10 +84,54 @@ impl WriteWasm for TemplateCodeInfo {
         instructions.push(")".to_string());
 
         // run function code
+        match &self.shared_run {
+            Some(shared) => {
+                let mut run_code = self.produce_wasm_shared_call(producer, shared);
+                instructions.append(&mut run_code);
+            }
+            None => {
+                let mut run_code = self.produce_wasm_run(producer, false);
+                instructions.append(&mut run_code);
+            }
+        }
+        instructions
+    }
+}
 
-        let funcdef2 = format!("(func ${}_run (type $_t_i32ri32)", self.header);
-        instructions.push(funcdef2);
+impl TemplateCodeInfo {
+    // Run function of an instance that shares the body of another template
+    fn produce_wasm_shared_call(&self, producer: &WASMProducer, shared: &SharedRun) -> Vec<String> {
+        use code_producers::wasm_elements::wasm_code_generator::*;
+        let mut instructions = vec![];
+        instructions.push(format!("(func ${}_run (type $_t_i32ri32)", self.header));
         instructions.push(format!(" (param {} i32)", producer.get_offset_tag()));
+        instructions.push("(result i32)".to_string());
+        instructions.push(get_local(producer.get_offset_tag()));
+        let first_constant = producer.get_constant_numbers_start()
+            + shared.first_constant * (producer.get_size_32_bit() + 2) * 4;
+        instructions.push(set_constant(&first_constant.to_string()));
+        instructions.push(call(&format!("${}_run", shared.header)));
+        instructions.push(")".to_string());
+        instructions
+    }
+
+    // Run function of a shared template body, which also receives the
+    // address of the constants of the instance
+    pub fn produce_shared_wasm(&self, producer: &WASMProducer) -> Vec<String> {
+        self.produce_wasm_run(producer, true)
+    }
+
+    fn produce_wasm_run(&self, producer: &WASMProducer, shared: bool) -> Vec<String> {
+        use code_producers::wasm_elements::wasm_code_generator::*;
+        let mut instructions = vec![];
+        if shared {
+            instructions.push(format!("(func ${}_run", self.header));
+            instructions.push(format!(" (param {} i32)", producer.get_offset_tag()));
+            instructions.push(format!(" (param {} i32)", producer.get_instance_constants_tag()));
+        } else {
+            instructions.push(format!("(func ${}_run (type $_t_i32ri32)", self.header));
+            instructions.push(format!(" (param {} i32)", producer.get_offset_tag()));
+        }
 	instructions.push("(result i32)".to_string()); //state 0 = OK; > 0 error
         instructions.push(format!(" (local {} i32)", producer.get_cstack_tag()));
         instructions.push(format!(" (local {} i32)", producer.get_signal_start_tag()));
@@ -268,12 +322,48 @@ impl TemplateCodeInfo {
 	    create_body.push(format!("{};",build_call(cmp_call_name, cmp_call_arguments)));
         }
         let create_fun = build_callable(create_header, create_params, create_body);
+        let run_fun = match &self.shared_run {
+            Some(shared) => self.produce_c_shared_call(parallel, shared),
+            None => self.produce_c_run(producer, parallel, false),
+        };
+        vec![create_fun, run_fun]
+    }
+
+    // Run function of an instance that shares the body of another template
+    fn produce_c_shared_call(&self, parallel: bool, shared: &SharedRun) -> String {
+        use c_code_generator::*;
+        let suffix = if parallel { "_run_parallel" } else { "_run" };
+        let run_header = format!("void {}{}", self.header, suffix);
+        let run_params = vec![declare_ctx_index(), declare_circom_calc_wit()];
+        let call_arguments =
+            vec![ctx_index(), CIRCOM_CALC_WIT.to_string(), shared.first_constant.to_string()];
+        let call = build_call(format!("{}{}", shared.header, suffix), call_arguments);
+        build_callable(run_header, run_params, vec![format!("{};", call)])
+    }
 
+    // Run functions of a shared template body, which also receive the
+    // position of the constants of the instance
+    pub fn produce_shared_c(&self, producer: &CProducer) -> Vec<String> {
+        let mut produced_c = Vec::new();
+        if self.is_parallel || self.is_parallel_component {
+            produced_c.push(self.produce_c_run(producer, true, true));
+        }
+        if !self.is_parallel && self.is_not_parallel_component {
+            produced_c.push(self.produce_c_run(producer, false, true));
+        }
+        produced_c
+    }
+
+    fn produce_c_run(&self, producer: &CProducer, parallel: bool, shared: bool) -> String {
+        use c_code_generator::*;
         let run_header = if parallel {format!("void {}_run_parallel", self.header)}
             else{format!("void {}_run", self.header)} ;
         let mut run_params = vec![];
         run_params.push(declare_ctx_index());
         run_params.push(declare_circom_calc_wit());
+        if shared {
+            run_params.push(declare_instance_constants());
+        }
         let mut run_body = vec![];
         run_body.push(format!("{};", declare_signal_values()));
         run_body.push(format!("{};", declare_my_signal_start()));
@@ -335,8 +425,7 @@ impl TemplateCodeInfo {
             )));
         
         run_body.push(format!("}}"));
-        let run_fun = build_callable(run_header, run_params, run_body);
-        vec![create_fun, run_fun]
+        build_callable(run_header, run_params, run_body)
     }
 
     pub fn wrap(self) -> TemplateCode {
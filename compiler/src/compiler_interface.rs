@@ -1,70 +1,93 @@
 pub use crate::circuit_design::circuit::{Circuit, CompilationFlags};
 pub use crate::hir::very_concrete_program::VCP;
-use std::fs::File;
-use std::io::BufWriter;
+pub use crate::ir_processing::{OptimizationLevel, StackAllocation};
+pub use crate::ir_processing::VerifyError as IrVerifyError;
+pub use crate::ir_processing::IllFormedIr;
+pub use crate::ir_text::ReadError as IrReadError;
+use program_structure::error_definition::ReportCollection;
+use program_structure::output_directory::OutputDirectory;
+use program_structure::output_error::{OutputError, OutputResult};
 
 pub struct Config {
     pub debug_output: bool,
     pub produce_input_log: bool,
     pub wat_flag: bool,
+    pub c_portable: bool,
+    pub optimization: OptimizationLevel,
+    // Checks the IR after every transformation, debug builds always do it
+    pub verify_ir: bool,
 }
 
-pub fn run_compiler(vcp: VCP, config: Config, version: &str) -> Result<Circuit, ()> {
-    let flags = CompilationFlags { main_inputs_log: config.produce_input_log, wat_flag: config.wat_flag };
-    let circuit = Circuit::build(vcp, flags, version);
+// The errors are the ones of the IR verification or of the logs, which are
+// written in the directory
+pub fn run_compiler(
+    vcp: VCP,
+    config: Config,
+    version: &str,
+    directory: &mut dyn OutputDirectory,
+) -> Result<Circuit, ReportCollection> {
+    if config.produce_input_log {
+        write_main_inputs_log(&vcp, directory).map_err(|err| vec![err.into_report()])?;
+    }
+    let flags = CompilationFlags {
+        wat_flag: config.wat_flag,
+        c_portable: config.c_portable,
+        optimization: config.optimization,
+        verify_ir: config.verify_ir || cfg!(debug_assertions),
+    };
+    let circuit = Circuit::build(vcp, flags, version)
+        .map_err(|ill_formed| ill_formed.into_iter().map(IllFormedIr::into_report).collect::<ReportCollection>())?;
     if config.debug_output {
-        produce_debug_output(&circuit)?;
+        produce_debug_output(&circuit, directory).map_err(|err| vec![err.into_report()])?;
     }
     Ok(circuit)
 }
 
-pub fn write_wasm(circuit: &Circuit, js_folder: &str, wasm_name: &str, file: &str) -> Result<(), ()> {
-    use std::path::Path;
-    if Path::new(js_folder).is_dir() {
-        std::fs::remove_dir_all(js_folder).map_err(|_err| {})?;
-    }
-    std::fs::create_dir(js_folder).map_err(|_err| {})?;
-    let file = File::create(file).map_err(|_err| {})?;
-    let mut writer = BufWriter::new(file);
-    circuit.produce_wasm(js_folder, wasm_name, &mut writer)
+pub fn write_wasm(
+    circuit: &Circuit,
+    directory: &mut dyn OutputDirectory,
+    js_folder: &str,
+    wasm_name: &str,
+    wat_file: &str,
+) -> OutputResult<()> {
+    directory.create_folder(js_folder).map_err(|err| OutputError::new(js_folder, err))?;
+    circuit.produce_wasm(directory, js_folder, wasm_name, wat_file)
 }
 
-pub fn write_c(circuit: &Circuit, c_folder: &str, c_run_name: &str, c_file: &str, dat_file: &str) -> Result<(), ()> {
-    use std::path::Path;
-    if Path::new(c_folder).is_dir() {
-        std::fs::remove_dir_all(c_folder).map_err(|_err| {})?;
-    }
-    std::fs::create_dir(c_folder).map_err(|_err| {})?;
-    let dat_file = File::create(dat_file).map_err(|_err| {})?;
-    let c_file = File::create(c_file).map_err(|_err| {})?;
-    let mut c_file = BufWriter::new(c_file);
-    let mut dat_file = BufWriter::new(dat_file);
-    circuit.produce_c(c_folder, c_run_name, &mut c_file, &mut dat_file)
+pub fn write_c(
+    circuit: &Circuit,
+    directory: &mut dyn OutputDirectory,
+    c_folder: &str,
+    c_run_name: &str,
+    c_file: &str,
+    dat_file: &str,
+) -> OutputResult<()> {
+    directory.create_folder(c_folder).map_err(|err| OutputError::new(c_folder, err))?;
+    circuit.produce_c(directory, c_folder, c_run_name, c_file, dat_file)
 }
 
-fn produce_debug_output(circuit: &Circuit) -> Result<(), ()> {
+// Writes the name and the size of every input signal of the main component
+// in log_input_signals.txt
+fn write_main_inputs_log(vcp: &VCP, directory: &mut dyn OutputDirectory) -> OutputResult<()> {
+    use program_structure::ast::SignalType::Input;
     use std::io::Write;
-    use std::path::Path;
-    let path = format!("ir_log");
-    if Path::new(&path).is_dir() {
-        std::fs::remove_dir_all(&path).map_err(|_err| {})?;
-    }
-    std::fs::create_dir(&path).map_err(|_err| {})?;
-    for id in 0..circuit.templates.len() {
-        let file = format!("ir_log/template_{}.txt", id);
-        let file_signals = File::create(file).map_err(|_err| {})?;
-        let mut writer = BufWriter::new(file_signals);
-        let body = circuit.produce_ir_string_for_template(id);
-        writer.write_all(body.as_bytes()).map_err(|_err| {})?;
-        writer.flush().map_err(|_err| {})?;
+    const INPUT_LOG: &str = "log_input_signals.txt";
+    let main = vcp.get_main_instance().unwrap();
+    let mut writer = directory.create_file(INPUT_LOG).map_err(|err| OutputError::new(INPUT_LOG, err))?;
+    for signal in main.signals.iter().filter(|signal| signal.xtype == Input) {
+        writeln!(writer, "main.{} {}", signal.name, signal.size()).map_err(|err| OutputError::new(INPUT_LOG, err))?;
     }
-    for id in 0..circuit.functions.len() {
-        let file = format!("ir_log/function_{}.txt", id);
-        let file_signals = File::create(file).map_err(|_err| {})?;
-        let mut writer = BufWriter::new(file_signals);
-        let body = circuit.produce_ir_string_for_function(id);
-        writer.write_all(body.as_bytes()).map_err(|_err| {})?;
-        writer.flush().map_err(|_err| {})?;
-    }
-    Result::Ok(())
+    writer.flush().map_err(|err| OutputError::new(INPUT_LOG, err))
+}
+
+// Writes the textual IR of the circuit in ir_log/circuit.ir. The file can
+// be read back with Circuit::read_ir_string.
+fn produce_debug_output(circuit: &Circuit, directory: &mut dyn OutputDirectory) -> OutputResult<()> {
+    use std::io::Write;
+    const IR_FOLDER: &str = "ir_log";
+    const IR_FILE: &str = "ir_log/circuit.ir";
+    directory.create_folder(IR_FOLDER).map_err(|err| OutputError::new(IR_FOLDER, err))?;
+    let mut writer = directory.create_file(IR_FILE).map_err(|err| OutputError::new(IR_FILE, err))?;
+    writer.write_all(circuit.produce_ir_string().as_bytes()).map_err(|err| OutputError::new(IR_FILE, err))?;
+    writer.flush().map_err(|err| OutputError::new(IR_FILE, err))
 }
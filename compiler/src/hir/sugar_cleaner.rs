@@ -560,11 +560,24 @@ fn into_single_substitution(stmt: Statement, stmts: &mut Vec<Statement>) {
     use Statement::Substitution;
     match &stmt {
         Substitution { rhe, .. } if rhe.is_switch() => rhe_switch_case(stmt, stmts),
+        Substitution { op: AssignOp::AssignVar, rhe, .. } if is_constant_array(rhe) => {
+            stmts.push(stmt)
+        }
         Substitution { rhe, .. } if rhe.is_array() => rhe_array_case(stmt, stmts),
         _ => stmts.push(stmt),
     }
 }
 
+// Inline arrays of numbers assigned to a variable are kept whole, so that
+// the values are copied at once from a block of constants
+fn is_constant_array(expr: &Expression) -> bool {
+    use Expression::{ArrayInLine, Number};
+    match expr {
+        ArrayInLine { values, .. } => values.iter().all(|value| matches!(value, Number(..))),
+        _ => false,
+    }
+}
+
 fn rhe_switch_case(stmt: Statement, stmts: &mut Vec<Statement>) {
     use Expression::InlineSwitchOp;
     use Statement::{Block, IfThenElse, Substitution};
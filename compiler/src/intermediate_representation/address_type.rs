@@ -1,19 +1,19 @@
 use super::ir_interface::*;
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 pub enum StatusInput {
     Last,
     NoLast,
     Unknown,
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 pub enum InputInformation {
     NoInput,
     Input {status: StatusInput},
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 pub enum AddressType {
     Variable,
     Signal,
@@ -3,7 +3,7 @@ use crate::translating_traits::*;
 use code_producers::c_elements::*;
 use code_producers::wasm_elements::*;
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 pub struct FinalData {
     // greater than one only with signals.
     pub context: InstrContext,
@@ -12,13 +12,13 @@ pub struct FinalData {
     pub dest: LocationRule,
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 pub enum ReturnType {
     Intermediate { op_aux_no: usize },
     Final(FinalData),
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 pub struct CallBucket {
     pub line: usize,
     pub message_id: usize,
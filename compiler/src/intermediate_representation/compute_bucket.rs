@@ -87,7 +87,7 @@ impl ToString for OperatorType {
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 pub struct ComputeBucket {
     pub line: usize,
     pub message_id: usize,
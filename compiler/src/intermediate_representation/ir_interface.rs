@@ -37,7 +37,7 @@ pub trait CheckCompute {
 pub type InstructionList = Vec<InstructionPointer>;
 pub type InstructionPointer = Box<Instruction>;
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 pub enum Instruction {
     Value(ValueBucket),
     Load(LoadBucket),
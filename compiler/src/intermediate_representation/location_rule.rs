@@ -1,6 +1,6 @@
 use super::ir_interface::*;
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 pub enum LocationRule {
     Indexed { location: InstructionPointer, template_header: Option<String> },
     Mapped { signal_code: usize, indexes: Vec<InstructionPointer> },
@@ -4,7 +4,7 @@ use code_producers::c_elements::*;
 use code_producers::wasm_elements::*;
 
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 pub enum LogBucketArg {
     LogExp(InstructionPointer),
     LogStr(usize)
@@ -19,7 +19,7 @@ impl LogBucketArg{
 }
 
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 pub struct LogBucket {
     pub line: usize,
     pub message_id: usize,
@@ -1,6 +1,7 @@
 use super::ir_interface::*;
 use crate::hir::very_concrete_program::*;
 use crate::intermediate_representation::log_bucket::LogBucketArg;
+use crate::ir_processing::{IllFormedIr, OptimizationLevel};
 use constant_tracking::ConstantTracker;
 use num_bigint_dig::BigInt;
 use program_structure::ast::*;
@@ -97,6 +98,8 @@ struct State {
     signal_stack: usize,
     variable_stack: usize,
     max_stack_depth: usize,
+    // first slot and size of every variable
+    variables: Vec<(usize, usize)>,
     fresh_cmp_id: usize,
     component_address_stack: usize,
     code: InstructionList,
@@ -125,6 +128,7 @@ impl State {
             component_address_stack: 0,
             fresh_cmp_id: cmp_id_offset,
             max_stack_depth: 0,
+            variables: vec![],
             code: vec![],
             string_table : HashMap::new(),
         }
@@ -139,6 +143,7 @@ impl State {
     }
     fn reserve_variable(&mut self, size: usize) -> usize {
         let ret = State::reserve(&mut self.variable_stack, size);
+        self.variables.push((ret, size));
         self.max_stack_depth = std::cmp::max(self.max_stack_depth, self.variable_stack);
         ret
     }
@@ -501,6 +506,8 @@ fn translate_substitution(stmt: Statement, state: &mut State, context: &Context)
             StoreInfo { prc_symbol: ProcessedSymbol::new(def, state, context), src: rhe };
         let store_instruction = if str_info.src.is_call() {
             translate_call_case(str_info, state, context)
+        } else if str_info.src.is_array() {
+            translate_constant_array_case(str_info, state, context)
         } else {
             translate_standard_case(str_info, state, context)
         };
@@ -529,6 +536,37 @@ fn translate_call_case(
     }
 }
 
+// The inline arrays left are arrays of numbers, see sugar_cleaner. They are
+// copied at once from a block of consecutive field constants.
+fn translate_constant_array_case(
+    info: StoreInfo,
+    state: &mut State,
+    context: &Context,
+) -> InstructionPointer {
+    use Expression::{ArrayInLine, Number};
+    if let ArrayInLine { meta, values } = info.src {
+        debug_assert_eq!(values.len(), info.prc_symbol.length);
+        let block = values
+            .into_iter()
+            .map(|value| match value {
+                Number(_, value) => value.to_str_radix(10),
+                _ => unreachable!(),
+            })
+            .collect();
+        let src = ValueBucket {
+            line: context.files.get_line(meta.start, meta.get_file_id()).unwrap(),
+            message_id: state.message_id,
+            op_aux_no: 0,
+            parse_as: ValueType::BigInt,
+            value: state.field_tracker.insert_block(block),
+        }
+        .allocate();
+        info.prc_symbol.into_store(src, state)
+    } else {
+        unreachable!()
+    }
+}
+
 fn translate_standard_case(
     info: StoreInfo,
     state: &mut State,
@@ -1370,20 +1408,28 @@ pub struct CodeInfo<'a> {
     pub component_to_parallel: HashMap<String, ParallelClusters>,
     pub string_table: HashMap<String, usize>,
     pub signals_to_tags: BTreeMap<String, TagInfo>,
+    pub prime: &'a BigInt,
+    pub optimization: OptimizationLevel,
+    // Checks the code after every transformation
+    pub verify_ir: bool,
 }
 
 pub struct CodeOutput {
     pub stack_depth: usize,
+    pub declared_stack_depth: usize,
     pub signal_depth: usize,
     pub expression_depth: usize,
     pub next_cmp_id: usize,
     pub code: InstructionList,
     pub constant_tracker: FieldTracker,
     pub string_table: HashMap<String, usize>,
+    pub ill_formed: Option<IllFormedIr>,
 }
 
 pub fn translate_code(body: Statement, code_info: CodeInfo) -> CodeOutput {
     use crate::ir_processing;
+    use crate::ir_processing::OptimizationLevel::*;
+    use crate::ir_processing::Verifier;
     let mut state = State::new(
         code_info.message_id,
         code_info.fresh_cmp_id,
@@ -1395,7 +1441,9 @@ pub fn translate_code(body: Statement, code_info: CodeInfo) -> CodeOutput {
     initialize_components(&mut state, code_info.components);
     initialize_signals(&mut state, code_info.signals);
     initialize_constants(&mut state, code_info.constants);
+    let first_parameter = state.variable_stack;
     initialize_parameters(&mut state, code_info.params);
+    let parameters = first_parameter..state.variable_stack;
 
     let context = Context {
         files: code_info.files,
@@ -1407,20 +1455,76 @@ pub fn translate_code(body: Statement, code_info: CodeInfo) -> CodeOutput {
 
     create_components(&mut state, &code_info.triggers, code_info.clusters);
     translate_statement(body, &mut state, &context);
+    let verify_ir = code_info.verify_ir;
+    let mut ill_formed = None;
+    let mut check = |pass: &str, code: &InstructionList, verifier: Verifier| {
+        if verify_ir {
+            ir_processing::check_well_formed(pass, code, verifier, &mut ill_formed);
+        }
+    };
+    check("translate", &state.code, Verifier::new(&context.translating));
+
+    if code_info.optimization >= O1 {
+        ir_processing::fold_constants(&mut state.code, &mut state.field_tracker, code_info.prime);
+        check("fold_constants", &state.code, Verifier::new(&context.translating));
+        ir_processing::remove_dead_stores(&mut state.code);
+        check("remove_dead_stores", &state.code, Verifier::new(&context.translating));
+    }
+    if code_info.optimization >= O2 {
+        state.max_stack_depth =
+            ir_processing::hoist_invariant_loads(&mut state.code, state.max_stack_depth);
+        check(
+            "hoist_invariant_loads",
+            &state.code,
+            Verifier::new(&context.translating).var_stack_depth(state.max_stack_depth),
+        );
+        state.max_stack_depth =
+            ir_processing::share_address_computations(&mut state.code, state.max_stack_depth);
+        check(
+            "share_address_computations",
+            &state.code,
+            Verifier::new(&context.translating).var_stack_depth(state.max_stack_depth),
+        );
+    }
+
+    let declared_stack_depth = state.max_stack_depth;
+    if code_info.optimization >= O1 {
+        state.max_stack_depth = ir_processing::allocate_var_slots(
+            &mut state.code,
+            &state.variables,
+            parameters,
+            state.max_stack_depth,
+        );
+        check(
+            "allocate_var_slots",
+            &state.code,
+            Verifier::new(&context.translating).var_stack_depth(state.max_stack_depth),
+        );
+    }
 
     ir_processing::build_inputs_info(&mut state.code);
+    check("build_inputs_info", &state.code, Verifier::new(&context.translating));
 
     let mut code = ir_processing::reduce_intermediate_operations(state.code);
+    check("reduce_intermediate_operations", &code, Verifier::new(&context.translating));
     let expression_depth = ir_processing::build_auxiliary_stack(&mut code);
-    
+    check(
+        "build_auxiliary_stack",
+        &code,
+        Verifier::new(&context.translating)
+            .expression_stack_depth(expression_depth)
+            .var_stack_depth(state.max_stack_depth),
+    );
 
     CodeOutput {
         code,
         expression_depth,
         next_cmp_id: state.fresh_cmp_id,
         stack_depth: state.max_stack_depth,
+        declared_stack_depth,
         signal_depth: state.signal_stack,
         constant_tracker: state.field_tracker,
-        string_table : state.string_table
+        string_table : state.string_table,
+        ill_formed,
     }
 }
@@ -2,6 +2,8 @@
 pub enum ValueType {
     BigInt,
     U32,
+    // Position in the constants of the instance running a shared template body
+    InstanceConstant,
 }
 
 impl ToString for ValueType {
@@ -9,6 +11,7 @@ impl ToString for ValueType {
         match self {
             ValueType::U32 => "U32",
             ValueType::BigInt => "BigInt",
+            ValueType::InstanceConstant => "InstanceConstant",
         }
         .to_string()
     }
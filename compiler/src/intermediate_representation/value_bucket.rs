@@ -3,7 +3,7 @@ use crate::translating_traits::*;
 use code_producers::c_elements::*;
 use code_producers::wasm_elements::*;
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 pub struct ValueBucket {
     pub line: usize,
     pub message_id: usize,
@@ -64,6 +64,12 @@ impl WriteWasm for ValueBucket {
                 const_pos += producer.get_constant_numbers_start();
                 instructions.push(set_constant(&const_pos.to_string()));
             }
+            ValueType::InstanceConstant => {
+                let const_pos = self.value * (producer.get_size_32_bit() + 2) * 4;
+                instructions.push(get_local(producer.get_instance_constants_tag()));
+                instructions.push(set_constant(&const_pos.to_string()));
+                instructions.push(add32());
+            }
         }
         if producer.needs_comments() {
             instructions.push(";; end of value bucket".to_string());
@@ -82,6 +88,10 @@ impl WriteC for ValueBucket {
                 let access = format!("&{}", circuit_constants(index));
                 (vec![], access)
             }
+            ValueType::InstanceConstant => {
+                let access = format!("&{}", instance_constant(index));
+                (vec![], access)
+            }
         }
     }
 }
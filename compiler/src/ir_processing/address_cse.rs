@@ -0,0 +1,111 @@
+use super::walk;
+use crate::intermediate_representation::ir_interface::*;
+
+// The field expressions that are converted to an address more than once in
+// the same statement are computed once and kept in a variable. The
+// variables only live until the end of the statement, so every statement
+// uses the slots that start at first_slot.
+pub fn share_list(list: &mut InstructionList, first_slot: usize, var_stack_depth: &mut usize) {
+    for mut instruction in std::mem::take(list) {
+        match &mut *instruction {
+            Instruction::Branch(b) => {
+                share_list(&mut b.if_branch, first_slot, var_stack_depth);
+                share_list(&mut b.else_branch, first_slot, var_stack_depth);
+            }
+            Instruction::Loop(b) => share_list(&mut b.body, first_slot, var_stack_depth),
+            Instruction::CreateCmp(_) => {}
+            statement => {
+                let shared = share_in_statement(statement, first_slot);
+                *var_stack_depth = std::cmp::max(*var_stack_depth, first_slot + shared.len());
+                for (slot, expression) in shared.into_iter().enumerate() {
+                    list.push(walk::store_variable(first_slot + slot, expression.allocate()));
+                }
+            }
+        }
+        list.push(instruction);
+    }
+}
+
+fn share_in_statement(statement: &mut Instruction, first_slot: usize) -> Vec<Instruction> {
+    let mut addressed = Vec::new();
+    collect_addressed(statement, &mut addressed);
+    let mut shared: Vec<Instruction> = Vec::new();
+    for (i, expression) in addressed.iter().enumerate() {
+        let repeated = addressed[i + 1..].contains(expression);
+        if repeated && !shared.contains(expression) {
+            shared.push((*expression).clone());
+        }
+    }
+    if !shared.is_empty() {
+        replace_addressed(statement, &shared, first_slot);
+    }
+    shared
+}
+
+// Operands of the conversions to addresses that compute something and do
+// not call functions. Expressions inside them are not visited.
+fn collect_addressed<'a>(instruction: &'a Instruction, addressed: &mut Vec<&'a Instruction>) {
+    if let Instruction::Compute(b) = instruction {
+        if b.op == OperatorType::ToAddress && is_shareable(&b.stack[0]) {
+            addressed.push(&b.stack[0]);
+            return;
+        }
+    }
+    walk::children(instruction, &mut |child| collect_addressed(child, addressed));
+}
+
+fn replace_addressed(instruction: &mut Instruction, shared: &[Instruction], first_slot: usize) {
+    if let Instruction::Compute(b) = instruction {
+        if b.op == OperatorType::ToAddress {
+            if let Some(slot) = shared.iter().position(|expression| *expression == *b.stack[0]) {
+                b.stack[0] = walk::load_variable(b.line, b.message_id, first_slot + slot);
+                return;
+            }
+        }
+    }
+    walk::children_mut(instruction, &mut |child| replace_addressed(child, shared, first_slot));
+}
+
+fn is_shareable(expression: &Instruction) -> bool {
+    matches!(expression, Instruction::Compute(_))
+        && !walk::any(expression, &|instruction| matches!(instruction, Instruction::Call(_)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn value(value: usize) -> InstructionPointer {
+        ValueBucket { line: 0, message_id: 0, parse_as: ValueType::U32, op_aux_no: 0, value }
+            .allocate()
+    }
+
+    fn compute(op: OperatorType, stack: Vec<InstructionPointer>) -> InstructionPointer {
+        ComputeBucket { line: 0, message_id: 0, op, op_aux_no: 0, stack }.allocate()
+    }
+
+    fn address(operand: InstructionPointer) -> InstructionPointer {
+        compute(OperatorType::ToAddress, vec![operand])
+    }
+
+    // var 0 + 1
+    fn index() -> InstructionPointer {
+        compute(OperatorType::Add, vec![walk::load_variable(0, 0, 0), value(1)])
+    }
+
+    #[test]
+    fn repeated_addresses_are_computed_once_per_statement() {
+        let twice = compute(OperatorType::Mul, vec![address(index()), address(index())]);
+        let once = compute(OperatorType::Mul, vec![address(index()), value(2)]);
+        let mut code = vec![walk::store_variable(1, twice), walk::store_variable(2, once.clone())];
+        let mut depth = 4;
+        share_list(&mut code, 4, &mut depth);
+        assert_eq!(depth, 5);
+        assert_eq!(code.len(), 3);
+        assert!(*code[0] == *walk::store_variable(4, index()));
+        let shared = address(walk::load_variable(0, 0, 4));
+        let expected = compute(OperatorType::Mul, vec![shared.clone(), shared]);
+        assert!(*code[1] == *walk::store_variable(1, expected));
+        assert!(*code[2] == *walk::store_variable(2, once));
+    }
+}
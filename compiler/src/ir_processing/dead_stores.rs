@@ -0,0 +1,109 @@
+use super::walk;
+use crate::intermediate_representation::ir_interface::*;
+use std::collections::HashSet;
+
+// Slots of the variables that are loaded somewhere in the code. If the
+// location of some load is only known at run time any slot may be read.
+#[derive(Default)]
+struct LoadedSlots {
+    any: bool,
+    slots: HashSet<usize>,
+}
+
+// Removing a store may leave other stores without loads, so the code is
+// visited until nothing else can be removed
+pub fn remove_list(code: &mut InstructionList) {
+    loop {
+        let mut loaded = LoadedSlots::default();
+        for instruction in code.iter() {
+            collect_loads(instruction, &mut loaded);
+        }
+        if loaded.any || !remove_stores(code, &loaded) {
+            break;
+        }
+    }
+}
+
+fn collect_loads(instruction: &Instruction, loaded: &mut LoadedSlots) {
+    if let Instruction::Load(b) = instruction {
+        if let AddressType::Variable = b.address_type {
+            match walk::constant_location(&b.src) {
+                Some(first) => loaded.slots.extend(first..first + b.context.size),
+                None => loaded.any = true,
+            }
+        }
+    }
+    walk::children(instruction, &mut |child| collect_loads(child, loaded));
+}
+
+fn remove_stores(list: &mut InstructionList, loaded: &LoadedSlots) -> bool {
+    let previous_len = list.len();
+    list.retain(|instruction| !is_dead_store(instruction, loaded));
+    let mut removed = list.len() != previous_len;
+    for instruction in list.iter_mut() {
+        match &mut **instruction {
+            Instruction::Branch(b) => {
+                removed |= remove_stores(&mut b.if_branch, loaded);
+                removed |= remove_stores(&mut b.else_branch, loaded);
+            }
+            Instruction::Loop(b) => removed |= remove_stores(&mut b.body, loaded),
+            _ => {}
+        }
+    }
+    removed
+}
+
+fn is_dead_store(instruction: &Instruction, loaded: &LoadedSlots) -> bool {
+    match instruction {
+        Instruction::Store(b) if matches!(b.dest_address_type, AddressType::Variable) => {
+            match walk::constant_location(&b.dest) {
+                Some(first) => {
+                    let mut slots = first..first + b.context.size;
+                    slots.all(|slot| !loaded.slots.contains(&slot)) && !may_fail(&b.src)
+                }
+                None => false,
+            }
+        }
+        _ => false,
+    }
+}
+
+// Expressions whose evaluation may stop the witness generation must be kept
+fn may_fail(expression: &Instruction) -> bool {
+    use OperatorType::*;
+    walk::any(expression, &|instruction| match instruction {
+        Instruction::Call(_) => true,
+        Instruction::Compute(b) => matches!(b.op, Div | IntDiv | Mod | ToAddress),
+        _ => false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn value(value: usize) -> InstructionPointer {
+        ValueBucket { line: 0, message_id: 0, parse_as: ValueType::U32, op_aux_no: 0, value }
+            .allocate()
+    }
+
+    #[test]
+    fn removes_stores_that_are_never_loaded() {
+        // var 0 is only read by the store to var 1, which is never read
+        let mut code = vec![
+            walk::store_variable(0, value(3)),
+            walk::store_variable(1, walk::load_variable(0, 0, 0)),
+            walk::store_variable(2, value(4)),
+            ReturnBucket {
+                line: 0,
+                message_id: 0,
+                with_size: 1,
+                value: walk::load_variable(0, 0, 2),
+            }
+            .allocate(),
+        ];
+        remove_list(&mut code);
+        assert_eq!(code.len(), 2);
+        assert!(*code[0] == *walk::store_variable(2, value(4)));
+    }
+}
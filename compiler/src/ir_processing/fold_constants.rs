@@ -0,0 +1,217 @@
+use super::walk;
+use crate::intermediate_representation::ir_interface::*;
+use crate::intermediate_representation::translate::FieldTracker;
+use circom_algebra::modular_arithmetic;
+use num_bigint_dig::BigInt;
+use num_traits::{One, ToPrimitive, Zero};
+
+// Constants are only turned into addresses if Fr_toInt reads them the same way
+const MAX_ADDRESS: usize = i32::MAX as usize;
+
+pub struct Folder<'a> {
+    pub field_tracker: &'a mut FieldTracker,
+    pub prime: &'a BigInt,
+}
+
+impl Folder<'_> {
+    // Folds the instructions of the list and replaces the branches and
+    // loops whose condition is known by the code that actually runs
+    pub fn fold_list(&mut self, list: &mut InstructionList) {
+        for mut instruction in std::mem::take(list) {
+            self.fold_instruction(&mut instruction);
+            match &mut *instruction {
+                Instruction::Branch(b) => match self.field_value(&b.cond) {
+                    Some(cond) if cond.is_zero() => list.append(&mut b.else_branch),
+                    Some(_) => list.append(&mut b.if_branch),
+                    None => list.push(instruction),
+                },
+                Instruction::Loop(b) => match self.field_value(&b.continue_condition) {
+                    Some(cond) if cond.is_zero() => {}
+                    _ => list.push(instruction),
+                },
+                _ => list.push(instruction),
+            }
+        }
+    }
+
+    fn fold_instruction(&mut self, instruction: &mut Instruction) {
+        match instruction {
+            Instruction::Branch(b) => {
+                self.fold_instruction(&mut b.cond);
+                self.fold_list(&mut b.if_branch);
+                self.fold_list(&mut b.else_branch);
+            }
+            Instruction::Loop(b) => {
+                self.fold_instruction(&mut b.continue_condition);
+                self.fold_list(&mut b.body);
+            }
+            _ => walk::children_mut(instruction, &mut |child| self.fold_instruction(child)),
+        }
+        if let Instruction::Compute(b) = instruction {
+            if let Some(folded) = self.fold_compute(b) {
+                *instruction = folded;
+            }
+        }
+    }
+
+    fn fold_compute(&mut self, bucket: &ComputeBucket) -> Option<Instruction> {
+        use OperatorType::*;
+        match bucket.op {
+            ToAddress => {
+                let value = self.field_value(&bucket.stack[0])?.to_usize()?;
+                if value > MAX_ADDRESS {
+                    return None;
+                }
+                Some(value_instruction(bucket, ValueType::U32, value))
+            }
+            MulAddress | AddAddress => {
+                let neutral = if bucket.op == MulAddress { 1 } else { 0 };
+                match (address_value(&bucket.stack[0]), address_value(&bucket.stack[1])) {
+                    (Some(left), Some(right)) => {
+                        let value = if bucket.op == MulAddress {
+                            left.checked_mul(right)?
+                        } else {
+                            left.checked_add(right)?
+                        };
+                        Some(value_instruction(bucket, ValueType::U32, value))
+                    }
+                    (Some(left), None) if left == neutral => Some(*bucket.stack[1].clone()),
+                    (None, Some(right)) if right == neutral => Some(*bucket.stack[0].clone()),
+                    _ => None,
+                }
+            }
+            _ => {
+                let values: Option<Vec<BigInt>> =
+                    bucket.stack.iter().map(|operand| self.field_value(operand)).collect();
+                match values {
+                    Some(values) => {
+                        let result = self.evaluate(bucket.op, &values)?;
+                        let id = self.field_tracker.insert(result.to_str_radix(10));
+                        Some(value_instruction(bucket, ValueType::BigInt, id))
+                    }
+                    None => self.fold_neutral_operand(bucket),
+                }
+            }
+        }
+    }
+
+    // x + 0, 0 + x, x - 0, x * 1 and 1 * x are x
+    fn fold_neutral_operand(&self, bucket: &ComputeBucket) -> Option<Instruction> {
+        use OperatorType::*;
+        let (left, right) = match bucket.stack.as_slice() {
+            [left, right] => (self.field_value(left), self.field_value(right)),
+            _ => return None,
+        };
+        let is_neutral = |value: &Option<BigInt>| match (bucket.op, value) {
+            (Add, Some(value)) | (Sub, Some(value)) => value.is_zero(),
+            (Mul, Some(value)) => value.is_one(),
+            _ => false,
+        };
+        if is_neutral(&right) {
+            Some(*bucket.stack[0].clone())
+        } else if bucket.op != Sub && is_neutral(&left) {
+            Some(*bucket.stack[1].clone())
+        } else {
+            None
+        }
+    }
+
+    // Only the operations that can not fail at run time are evaluated, so
+    // the errors of the witness generation are kept
+    fn evaluate(&self, op: OperatorType, values: &[BigInt]) -> Option<BigInt> {
+        use modular_arithmetic::*;
+        use OperatorType::*;
+        let field = self.prime;
+        let result = match (op, values) {
+            (Add, [left, right]) => add(left, right, field),
+            (Sub, [left, right]) => sub(left, right, field),
+            (Mul, [left, right]) => mul(left, right, field),
+            (Div, [left, right]) if !right.is_zero() => div(left, right, field).ok()?,
+            (Pow, [left, right]) => pow(left, right, field),
+            (Eq(1), [left, right]) => eq(left, right, field),
+            (NotEq, [left, right]) => not_eq(left, right, field),
+            (Lesser, [left, right]) => lesser(left, right, field),
+            (LesserEq, [left, right]) => lesser_eq(left, right, field),
+            (Greater, [left, right]) => greater(left, right, field),
+            (GreaterEq, [left, right]) => greater_eq(left, right, field),
+            (BoolAnd, [left, right]) => bool_and(left, right, field),
+            (BoolOr, [left, right]) => bool_or(left, right, field),
+            (PrefixSub, [value]) => prefix_sub(value, field),
+            (BoolNot, [value]) => not(value, field),
+            _ => return None,
+        };
+        Some(result)
+    }
+
+    fn field_value(&self, instruction: &Instruction) -> Option<BigInt> {
+        match instruction {
+            Instruction::Value(v) if v.parse_as == ValueType::BigInt => {
+                let constant = self.field_tracker.get_constant(v.value)?;
+                BigInt::parse_bytes(constant.as_bytes(), 10)
+            }
+            _ => None,
+        }
+    }
+}
+
+fn address_value(instruction: &Instruction) -> Option<usize> {
+    match instruction {
+        Instruction::Value(v) if v.parse_as == ValueType::U32 => Some(v.value),
+        _ => None,
+    }
+}
+
+fn value_instruction(bucket: &ComputeBucket, parse_as: ValueType, value: usize) -> Instruction {
+    ValueBucket {
+        line: bucket.line,
+        message_id: bucket.message_id,
+        parse_as,
+        op_aux_no: bucket.op_aux_no,
+        value,
+    }
+    .into_instruction()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn value(parse_as: ValueType, value: usize) -> InstructionPointer {
+        ValueBucket { line: 0, message_id: 0, parse_as, op_aux_no: 0, value }.allocate()
+    }
+
+    fn compute(op: OperatorType, stack: Vec<InstructionPointer>) -> InstructionPointer {
+        ComputeBucket { line: 0, message_id: 0, op, op_aux_no: 0, stack }.allocate()
+    }
+
+    fn store(src: InstructionPointer) -> InstructionPointer {
+        crate::ir_processing::walk::store_variable(0, src)
+    }
+
+    #[test]
+    fn folds_constants_and_known_branches() {
+        let mut field_tracker = FieldTracker::new();
+        let two = field_tracker.insert("2".to_string());
+        let five = field_tracker.insert("5".to_string());
+        let prime = BigInt::from(7);
+        let sum = compute(
+            OperatorType::Add,
+            vec![value(ValueType::BigInt, two), value(ValueType::BigInt, five)],
+        );
+        let address = compute(
+            OperatorType::MulAddress,
+            vec![value(ValueType::U32, 1), compute(OperatorType::ToAddress, vec![sum.clone()])],
+        );
+        let branch = BranchBucket {
+            line: 0,
+            message_id: 0,
+            cond: sum,
+            if_branch: vec![store(value(ValueType::BigInt, two))],
+            else_branch: vec![store(address)],
+        };
+        let mut code = vec![branch.allocate()];
+        Folder { field_tracker: &mut field_tracker, prime: &prime }.fold_list(&mut code);
+        // 2 + 5 is 0 modulo 7, so only the else branch is left
+        assert!(code.len() == 1 && *code[0] == *store(value(ValueType::U32, 0)));
+    }
+}
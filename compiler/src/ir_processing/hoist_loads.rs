@@ -0,0 +1,295 @@
+use super::walk;
+use crate::intermediate_representation::ir_interface::*;
+
+// What the body of a loop may write. Own signals and subcomponents are only
+// tracked as a whole, variables by slot when the location is a constant.
+#[derive(Default)]
+struct LoopWrites {
+    any_variable: bool,
+    variables: Vec<(usize, usize)>,
+    signals: bool,
+    subcomponents: bool,
+}
+
+impl LoopWrites {
+    fn collect(instruction: &Instruction, writes: &mut LoopWrites) {
+        match instruction {
+            Instruction::Store(b) => writes.add(&b.dest_address_type, &b.dest, b.context.size),
+            Instruction::Call(b) => {
+                if let ReturnType::Final(data) = &b.return_info {
+                    writes.add(&data.dest_address_type, &data.dest, data.context.size);
+                }
+            }
+            Instruction::CreateCmp(_) => writes.subcomponents = true,
+            _ => {}
+        }
+        walk::children(instruction, &mut |child| LoopWrites::collect(child, writes));
+    }
+
+    fn add(&mut self, address: &AddressType, location: &LocationRule, size: usize) {
+        match (address, walk::constant_location(location)) {
+            (AddressType::Variable, Some(first)) => self.variables.push((first, first + size)),
+            (AddressType::Variable, None) => self.any_variable = true,
+            (AddressType::Signal, _) => self.signals = true,
+            (AddressType::SubcmpSignal { .. }, _) => self.subcomponents = true,
+        }
+    }
+
+    fn writes_variable(&self, first: usize, size: usize) -> bool {
+        self.any_variable
+            || self.variables.iter().any(|(start, end)| first < *end && *start < first + size)
+    }
+
+    // Expressions that give the same value in every iteration
+    fn is_invariant(&self, expression: &Instruction) -> bool {
+        match expression {
+            Instruction::Value(_) => true,
+            Instruction::Compute(b) => b.stack.iter().all(|operand| self.is_invariant(operand)),
+            Instruction::Load(b) => match (&b.address_type, walk::constant_location(&b.src)) {
+                (AddressType::Variable, Some(first)) => {
+                    !self.writes_variable(first, b.context.size)
+                }
+                (AddressType::Signal, Some(_)) => !self.signals,
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    // Loads worth being done once before the loop: the signals of a
+    // subcomponent and the signals whose address has to be computed
+    fn is_hoistable(&self, load: &LoadBucket) -> bool {
+        let invariant_location = match &load.src {
+            LocationRule::Indexed { location, .. } => self.is_invariant(location),
+            LocationRule::Mapped { indexes, .. } => {
+                indexes.iter().all(|index| self.is_invariant(index))
+            }
+        };
+        load.context.size == 1
+            && invariant_location
+            && match &load.address_type {
+                AddressType::Variable => false,
+                AddressType::Signal => {
+                    !self.signals && walk::constant_location(&load.src).is_none()
+                }
+                AddressType::SubcmpSignal { cmp_address, .. } => {
+                    !self.subcomponents && self.is_invariant(cmp_address)
+                }
+            }
+    }
+}
+
+pub fn hoist_list(list: &mut InstructionList, var_stack_depth: &mut usize) {
+    for instruction in list.iter_mut() {
+        match &mut **instruction {
+            Instruction::Branch(b) => {
+                hoist_list(&mut b.if_branch, var_stack_depth);
+                hoist_list(&mut b.else_branch, var_stack_depth);
+            }
+            Instruction::Loop(b) => {
+                hoist_list(&mut b.body, var_stack_depth);
+                if let Some(hoisted) = hoist_loop(b, var_stack_depth) {
+                    *instruction = hoisted;
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+// Moves the invariant loads of the loop to new variables that are set
+// before it. Only the condition and the statements that run in every
+// iteration (the ones before any branch, loop or return) are visited, so
+// the loads are done anyway. As the loop may run no times, the variables
+// are set inside a branch that checks the condition first.
+fn hoist_loop(bucket: &mut LoopBucket, var_stack_depth: &mut usize) -> Option<InstructionPointer> {
+    if walk::any(&bucket.continue_condition, &|i| matches!(i, Instruction::Call(_))) {
+        return None;
+    }
+    let mut writes = LoopWrites::default();
+    for instruction in &bucket.body {
+        LoopWrites::collect(instruction, &mut writes);
+    }
+    let condition = bucket.continue_condition.clone();
+    let mut hoisted = Vec::new();
+    replace_loads(&mut bucket.continue_condition, &writes, &mut hoisted, var_stack_depth);
+    for instruction in &mut bucket.body {
+        if matches!(
+            **instruction,
+            Instruction::Branch(_) | Instruction::Loop(_) | Instruction::Return(_)
+        ) {
+            break;
+        }
+        replace_loads(instruction, &writes, &mut hoisted, var_stack_depth);
+    }
+    if hoisted.is_empty() {
+        return None;
+    }
+    let mut if_branch: InstructionList = hoisted
+        .into_iter()
+        .map(|(load, slot)| walk::store_variable(slot, load.allocate()))
+        .collect();
+    if_branch.push(bucket.clone().allocate());
+    let guard = BranchBucket {
+        line: bucket.line,
+        message_id: bucket.message_id,
+        cond: condition,
+        if_branch,
+        else_branch: Vec::new(),
+    };
+    Some(guard.allocate())
+}
+
+fn replace_loads(
+    instruction: &mut Instruction,
+    writes: &LoopWrites,
+    hoisted: &mut Vec<(Instruction, usize)>,
+    var_stack_depth: &mut usize,
+) {
+    if let Instruction::Load(b) = instruction {
+        if writes.is_hoistable(b) {
+            let (line, message_id) = (b.line, b.message_id);
+            let slot = match hoisted.iter().find(|(load, _)| load == instruction) {
+                Some((_, slot)) => *slot,
+                None => {
+                    let slot = *var_stack_depth;
+                    *var_stack_depth += 1;
+                    hoisted.push((instruction.clone(), slot));
+                    slot
+                }
+            };
+            *instruction = *walk::load_variable(line, message_id, slot);
+            return;
+        }
+    }
+    walk::children_mut(instruction, &mut |child| {
+        replace_loads(child, writes, hoisted, var_stack_depth)
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn value(value: usize) -> InstructionPointer {
+        ValueBucket { line: 0, message_id: 0, parse_as: ValueType::U32, op_aux_no: 0, value }
+            .allocate()
+    }
+
+    fn compute(
+        op: OperatorType,
+        left: InstructionPointer,
+        right: InstructionPointer,
+    ) -> InstructionPointer {
+        ComputeBucket { line: 0, message_id: 0, op, op_aux_no: 0, stack: vec![left, right] }
+            .allocate()
+    }
+
+    fn indexed(location: InstructionPointer) -> LocationRule {
+        LocationRule::Indexed { location, template_header: None }
+    }
+
+    fn subcomponent(cmp: usize) -> AddressType {
+        AddressType::SubcmpSignal {
+            cmp_address: value(cmp),
+            uniform_parallel_value: None,
+            is_output: true,
+            input_information: InputInformation::NoInput,
+        }
+    }
+
+    fn load(address_type: AddressType, location: InstructionPointer) -> InstructionPointer {
+        LoadBucket {
+            line: 0,
+            message_id: 0,
+            address_type,
+            src: indexed(location),
+            context: InstrContext { size: 1 },
+        }
+        .allocate()
+    }
+
+    fn store(
+        address_type: AddressType,
+        location: InstructionPointer,
+        src: InstructionPointer,
+    ) -> InstructionPointer {
+        StoreBucket {
+            line: 0,
+            message_id: 0,
+            context: InstrContext { size: 1 },
+            dest_is_output: false,
+            dest_address_type: address_type,
+            dest: indexed(location),
+            src,
+        }
+        .allocate()
+    }
+
+    // while (var 0 < 3) { body; var 0 = var 0 + 1 }
+    fn counted_loop(mut body: InstructionList) -> InstructionPointer {
+        let next = compute(OperatorType::Add, walk::load_variable(0, 0, 0), value(1));
+        body.push(walk::store_variable(0, next));
+        LoopBucket { line: 0, message_id: 0, continue_condition: condition(), body }.allocate()
+    }
+
+    fn condition() -> InstructionPointer {
+        compute(OperatorType::Lesser, walk::load_variable(0, 0, 0), value(3))
+    }
+
+    // Runs the pass and tells if the loop was left as it was
+    fn unchanged(body: InstructionList) -> bool {
+        let original = counted_loop(body);
+        let mut code = vec![original.clone()];
+        let mut depth = 2;
+        hoist_list(&mut code, &mut depth);
+        depth == 2 && *code[0] == *original
+    }
+
+    #[test]
+    fn hoisted_loads_only_run_when_the_loop_does() {
+        // var 1 = cmp[0].signal[4] is loaded once, inside a branch with the
+        // condition of the loop, so a loop that runs no times loads nothing
+        let subcomponent_load = load(subcomponent(0), value(4));
+        let mut code = vec![counted_loop(vec![walk::store_variable(1, subcomponent_load.clone())])];
+        let mut depth = 2;
+        hoist_list(&mut code, &mut depth);
+        assert_eq!(depth, 3);
+        let expected_loop =
+            counted_loop(vec![walk::store_variable(1, walk::load_variable(0, 0, 2))]);
+        let expected = BranchBucket {
+            line: 0,
+            message_id: 0,
+            cond: condition(),
+            if_branch: vec![walk::store_variable(2, subcomponent_load), expected_loop],
+            else_branch: Vec::new(),
+        }
+        .allocate();
+        assert!(*code[0] == *expected);
+    }
+
+    #[test]
+    fn loads_are_not_hoisted_past_stores_to_the_same_place() {
+        // A subcomponent whose inputs are set in the loop
+        let subcomponent_load = load(subcomponent(0), value(4));
+        assert!(unchanged(vec![
+            store(subcomponent(0), value(1), walk::load_variable(0, 0, 0)),
+            walk::store_variable(1, subcomponent_load.clone()),
+        ]));
+        // A signal with a computed address that the loop writes
+        let signal_load = load(AddressType::Signal, compute(OperatorType::Add, value(2), value(1)));
+        assert!(unchanged(vec![
+            store(AddressType::Signal, value(0), walk::load_variable(0, 0, 0)),
+            walk::store_variable(1, signal_load.clone()),
+        ]));
+        // The same signal indexed by the counter, a var slot the loop writes
+        let indexed_load = load(
+            AddressType::Signal,
+            compute(OperatorType::Add, value(2), walk::load_variable(0, 0, 0)),
+        );
+        assert!(unchanged(vec![walk::store_variable(1, indexed_load)]));
+        // Without the stores both loads are hoisted
+        assert!(!unchanged(vec![walk::store_variable(1, subcomponent_load)]));
+        assert!(!unchanged(vec![walk::store_variable(1, signal_load)]));
+    }
+}
@@ -0,0 +1,75 @@
+use super::walk;
+use crate::intermediate_representation::ir_interface::*;
+
+// Field constants of the code, in the order in which they are visited, with
+// the number of consecutive constants read from each one: a store of several
+// values copies them from a block of constants
+pub fn collect_list(code: &InstructionList) -> Vec<(usize, usize)> {
+    let mut constants = Vec::new();
+    for instruction in code {
+        collect(instruction, 1, &mut constants);
+    }
+    constants
+}
+
+fn collect(instruction: &Instruction, size: usize, constants: &mut Vec<(usize, usize)>) {
+    match instruction {
+        Instruction::Value(v) if v.parse_as == ValueType::BigInt => constants.push((v.value, size)),
+        Instruction::Store(b) => {
+            let src: &Instruction = &b.src;
+            walk::children(instruction, &mut |child| {
+                let size = if std::ptr::eq(child, src) { b.context.size } else { 1 };
+                collect(child, size, constants)
+            });
+        }
+        _ => walk::children(instruction, &mut |child| collect(child, 1, constants)),
+    }
+}
+
+// Every instruction gets the given message, and the field constant visited
+// in the i-th place becomes the instance constant parameters[i] if it has one
+pub fn parametrize_list(code: &mut InstructionList, parameters: &[Option<usize>], message_id: usize) {
+    let mut visited = 0;
+    for instruction in code {
+        parametrize(instruction, parameters, message_id, &mut visited);
+    }
+}
+
+fn parametrize(
+    instruction: &mut Instruction,
+    parameters: &[Option<usize>],
+    message_id: usize,
+    visited: &mut usize,
+) {
+    set_message_id(instruction, message_id);
+    if let Instruction::Value(v) = instruction {
+        if v.parse_as == ValueType::BigInt {
+            if let Some(parameter) = parameters[*visited] {
+                v.parse_as = ValueType::InstanceConstant;
+                v.value = parameter;
+            }
+            *visited += 1;
+        }
+    }
+    walk::children_mut(instruction, &mut |child| {
+        parametrize(child, parameters, message_id, visited)
+    });
+}
+
+fn set_message_id(instruction: &mut Instruction, message_id: usize) {
+    use Instruction::*;
+    let current = match instruction {
+        Value(b) => &mut b.message_id,
+        Load(b) => &mut b.message_id,
+        Store(b) => &mut b.message_id,
+        Compute(b) => &mut b.message_id,
+        Call(b) => &mut b.message_id,
+        Branch(b) => &mut b.message_id,
+        Return(b) => &mut b.message_id,
+        Assert(b) => &mut b.message_id,
+        Log(b) => &mut b.message_id,
+        Loop(b) => &mut b.message_id,
+        CreateCmp(b) => &mut b.message_id,
+    };
+    *current = message_id;
+}
@@ -2,8 +2,23 @@ mod build_stack;
 mod reduce_stack;
 mod set_arena_size;
 mod build_inputs_info;
+mod verify;
+mod walk;
+mod fold_constants;
+mod dead_stores;
+mod hoist_loads;
+mod address_cse;
+mod var_slots;
+mod instance_constants;
 use crate::intermediate_representation::ir_interface::InstructionList;
+use crate::intermediate_representation::translate::FieldTracker;
+use num_bigint_dig::BigInt;
+use program_structure::error_code::ReportCode;
+use program_structure::error_definition::Report;
 use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+
+pub use verify::{CircuitSummary, CodeKind, Verifier, VerifyError};
 
 pub fn reduce_intermediate_operations(code: InstructionList) -> InstructionList {
     reduce_stack::reduce_list(code)
@@ -24,3 +39,107 @@ pub fn build_inputs_info(code: &mut InstructionList){
     build_inputs_info::visit_list(code, &mut HashSet::new(), &mut HashSet::new(),false, false);
 }
 
+// Optimisations applied to the code of every template and function. Each
+// level includes the previous one: O1 folds the operations over constants,
+// removes the stores to variables that are never loaded and lets the
+// variables that are not alive at the same time share slots, and O2 also
+// hoists loop-invariant loads of signals and computes the repeated
+// expressions of an address once.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Default)]
+pub enum OptimizationLevel {
+    O0,
+    #[default]
+    O1,
+    O2,
+}
+
+pub fn fold_constants(code: &mut InstructionList, field_tracker: &mut FieldTracker, prime: &BigInt) {
+    fold_constants::Folder { field_tracker, prime }.fold_list(code);
+}
+
+pub fn remove_dead_stores(code: &mut InstructionList) {
+    dead_stores::remove_list(code);
+}
+
+// New variables are added after the last one; returns the new stack depth
+pub fn hoist_invariant_loads(code: &mut InstructionList, var_stack_depth: usize) -> usize {
+    let mut depth = var_stack_depth;
+    hoist_loads::hoist_list(code, &mut depth);
+    depth
+}
+
+pub fn share_address_computations(code: &mut InstructionList, var_stack_depth: usize) -> usize {
+    let mut depth = var_stack_depth;
+    address_cse::share_list(code, var_stack_depth, &mut depth);
+    depth
+}
+
+// Variables are given by their first slot and size. The fixed slots (the
+// parameters of a function, which are set by the caller) are not moved.
+// Returns the new stack depth, which is never greater than the given one.
+pub fn allocate_var_slots(
+    code: &mut InstructionList,
+    variables: &[(usize, usize)],
+    fixed: Range<usize>,
+    var_stack_depth: usize,
+) -> usize {
+    var_slots::allocate(code, variables, fixed, var_stack_depth).unwrap_or(var_stack_depth)
+}
+
+// Field constants (ids in the field tracker) of the code, in a fixed order,
+// with the number of consecutive constants read from each one
+pub fn field_constants(code: &InstructionList) -> Vec<(usize, usize)> {
+    instance_constants::collect_list(code)
+}
+
+// Turns the code into a body shared by several instances. The field
+// constant in the i-th place of field_constants becomes the instance
+// constant parameters[i] when it has one (the first of a block when several
+// are read), and every instruction reports its errors with the given message.
+pub fn parametrize_constants(code: &mut InstructionList, parameters: &[Option<usize>], message_id: usize) {
+    instance_constants::parametrize_list(code, parameters, message_id);
+}
+
+// Size of the var stack of a template or function before and after the
+// slots of the variables are shared
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct StackAllocation {
+    pub header: String,
+    pub declared: usize,
+    pub allocated: usize,
+}
+
+impl StackAllocation {
+    pub fn saved(&self) -> usize {
+        self.declared - self.allocated
+    }
+}
+
+// Errors of the verifier and the transformation that left the code with them
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct IllFormedIr {
+    pub pass: String,
+    pub errors: Vec<VerifyError>,
+}
+
+impl IllFormedIr {
+    pub fn into_report(self) -> Report {
+        let mut report = Report::error(format!("Ill-formed IR after {}", self.pass), ReportCode::IllFormedIr);
+        for error in self.errors {
+            report.add_note(error.to_string());
+        }
+        report
+    }
+}
+
+// Checks the code left by a transformation, so that an error is found here
+// instead of in the generated C++ or WASM. Only the first transformation that
+// breaks the code is kept, the errors of the next ones are a consequence.
+pub fn check_well_formed(pass: &str, code: &InstructionList, verifier: Verifier, found: &mut Option<IllFormedIr>) {
+    if found.is_some() {
+        return;
+    }
+    if let Err(errors) = verifier.verify(code) {
+        *found = Some(IllFormedIr { pass: pass.to_string(), errors });
+    }
+}
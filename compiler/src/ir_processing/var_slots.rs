@@ -0,0 +1,388 @@
+use super::walk;
+use crate::intermediate_representation::ir_interface::*;
+use std::collections::BTreeSet;
+use std::ops::Range;
+
+// Slots of the var stack, one bit per slot
+#[derive(Clone, PartialEq, Eq)]
+struct SlotSet(Vec<u64>);
+
+impl SlotSet {
+    fn new(slots: usize) -> SlotSet {
+        SlotSet(vec![0; slots.div_ceil(64)])
+    }
+
+    fn insert(&mut self, slots: Range<usize>) {
+        for slot in slots {
+            self.0[slot / 64] |= 1 << (slot % 64);
+        }
+    }
+
+    fn remove(&mut self, slots: Range<usize>) {
+        for slot in slots {
+            self.0[slot / 64] &= !(1 << (slot % 64));
+        }
+    }
+
+    fn union(&mut self, other: &SlotSet) {
+        for (word, other) in self.0.iter_mut().zip(&other.0) {
+            *word |= *other;
+        }
+    }
+
+    fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.0.iter().enumerate().filter(|(_, bits)| **bits != 0).flat_map(|(word, bits)| {
+            (0..64).filter(move |bit| (bits >> bit) & 1 == 1).map(move |bit| word * 64 + bit)
+        })
+    }
+}
+
+// Slots that are always moved together: the ones of a variable, or of the
+// variables of different blocks that were given overlapping slots, and
+// each of the slots added by the optimizations
+struct Units {
+    ranges: Vec<Range<usize>>,
+    of_slot: Vec<usize>,
+}
+
+impl Units {
+    fn new(variables: &[(usize, usize)], var_stack_depth: usize) -> Units {
+        let mut declared: Vec<Range<usize>> = variables
+            .iter()
+            .filter(|(_, size)| *size > 0)
+            .map(|(start, size)| *start..start + size)
+            .collect();
+        declared.sort_by_key(|range| range.start);
+        let mut ranges: Vec<Range<usize>> = Vec::new();
+        let mut next = 0;
+        for range in declared {
+            while next < range.start {
+                ranges.push(next..next + 1);
+                next += 1;
+            }
+            match ranges.last_mut() {
+                Some(last) if range.start < last.end => last.end = std::cmp::max(last.end, range.end),
+                _ => ranges.push(range.clone()),
+            }
+            next = std::cmp::max(next, range.end);
+        }
+        while next < var_stack_depth {
+            ranges.push(next..next + 1);
+            next += 1;
+        }
+        let mut of_slot = vec![0; next];
+        for (unit, range) in ranges.iter().enumerate() {
+            of_slot[range.clone()].iter_mut().for_each(|slot| *slot = unit);
+        }
+        Units { ranges, of_slot }
+    }
+
+    fn slots(&self) -> usize {
+        self.of_slot.len()
+    }
+}
+
+// Variable accessed by a load or a store, the number of slots accessed and
+// the slots themselves if they are known at compile time
+struct Access {
+    unit: usize,
+    size: usize,
+    slots: Option<Range<usize>>,
+}
+
+// The address of a variable is the sum of the constants of the top-level
+// additions and of some offsets. The constants give the first slot that
+// may be accessed, so they tell the variable even if the offsets are only
+// known at run time.
+fn access(units: &Units, location: &LocationRule, size: usize) -> Option<Access> {
+    let location = match location {
+        LocationRule::Indexed { location, .. } => location,
+        LocationRule::Mapped { .. } => return None,
+    };
+    let first = constant_part(location)?;
+    let unit = *units.of_slot.get(first)?;
+    let range = &units.ranges[unit];
+    let slots = match &**location {
+        Instruction::Value(_) => Some(first..first.checked_add(size)?),
+        _ => None,
+    };
+    let end = slots.as_ref().map_or(range.start + size, |slots| slots.end);
+    if end > range.end {
+        return None;
+    }
+    Some(Access { unit, size, slots })
+}
+
+fn constant_part(location: &Instruction) -> Option<usize> {
+    match location {
+        Instruction::Value(v) if v.parse_as == ValueType::U32 => Some(v.value),
+        Instruction::Compute(b) if b.op == OperatorType::AddAddress => {
+            constant_part(&b.stack[0])?.checked_add(constant_part(&b.stack[1])?)
+        }
+        _ => Some(0),
+    }
+}
+
+fn variable_access(units: &Units, instruction: &Instruction) -> Option<Option<Access>> {
+    match instruction {
+        Instruction::Load(b) if matches!(b.address_type, AddressType::Variable) => {
+            access(units, &b.src, b.context.size).map(Some)
+        }
+        Instruction::Store(b) if matches!(b.dest_address_type, AddressType::Variable) => {
+            access(units, &b.dest, b.context.size).map(Some)
+        }
+        Instruction::Call(b) => match &b.return_info {
+            ReturnType::Final(data) if matches!(data.dest_address_type, AddressType::Variable) => {
+                access(units, &data.dest, data.context.size).map(Some)
+            }
+            _ => Some(None),
+        },
+        _ => Some(None),
+    }
+}
+
+// Adds the slots that are read by the instruction
+fn add_uses(units: &Units, instruction: &Instruction, live: &mut SlotSet) -> Option<()> {
+    if let Instruction::Load(_) = instruction {
+        if let Some(access) = variable_access(units, instruction)? {
+            let unit = access.unit;
+            live.insert(access.slots.unwrap_or_else(|| units.ranges[unit].clone()));
+        }
+    }
+    let mut known = true;
+    walk::children(instruction, &mut |child| {
+        known = known && add_uses(units, child, live).is_some();
+    });
+    known.then_some(())
+}
+
+// Computes the slots that are live before each statement, going backwards
+// from the end of the code, and records the variables that are written
+// while others are live. Those are the ones that can not share slots.
+struct Liveness<'a> {
+    units: &'a Units,
+    interference: Vec<BTreeSet<usize>>,
+}
+
+impl Liveness<'_> {
+    fn live_list(&mut self, list: &InstructionList, mut live: SlotSet) -> Option<SlotSet> {
+        for instruction in list.iter().rev() {
+            live = self.live_instruction(instruction, live)?;
+        }
+        Some(live)
+    }
+
+    fn live_instruction(&mut self, instruction: &Instruction, after: SlotSet) -> Option<SlotSet> {
+        match instruction {
+            Instruction::Branch(b) => {
+                let mut live = self.live_list(&b.if_branch, after.clone())?;
+                live.union(&self.live_list(&b.else_branch, after)?);
+                add_uses(self.units, &b.cond, &mut live)?;
+                Some(live)
+            }
+            Instruction::Loop(b) => {
+                // The slots read by an iteration are live in the previous one
+                let mut head = after.clone();
+                add_uses(self.units, &b.continue_condition, &mut head)?;
+                loop {
+                    let mut next = self.live_list(&b.body, head.clone())?;
+                    next.union(&after);
+                    add_uses(self.units, &b.continue_condition, &mut next)?;
+                    if next == head {
+                        return Some(head);
+                    }
+                    head = next;
+                }
+            }
+            Instruction::Return(b) => {
+                let mut live = SlotSet::new(self.units.slots());
+                add_uses(self.units, &b.value, &mut live)?;
+                Some(live)
+            }
+            statement => {
+                let mut uses = SlotSet::new(self.units.slots());
+                add_uses(self.units, statement, &mut uses)?;
+                let mut live = after;
+                if let Some(access) = variable_access(self.units, statement)? {
+                    self.interfere(access.unit, &live);
+                    // Values of several slots may be copied one by one, so
+                    // the source and the destination must not overlap
+                    if access.size > 1 {
+                        self.interfere(access.unit, &uses);
+                    }
+                    if let Some(slots) = access.slots {
+                        live.remove(slots);
+                    }
+                }
+                live.union(&uses);
+                Some(live)
+            }
+        }
+    }
+
+    fn interfere(&mut self, unit: usize, live: &SlotSet) {
+        for slot in live.iter() {
+            let other = self.units.of_slot[slot];
+            if other != unit {
+                self.interference[unit].insert(other);
+                self.interference[other].insert(unit);
+            }
+        }
+    }
+}
+
+// Gives each unit the lowest slot where it does not overlap the units it
+// interferes with. The fixed units keep their slots.
+fn assign_slots(units: &Units, interference: &[BTreeSet<usize>], fixed: &Range<usize>) -> Vec<usize> {
+    let is_fixed = |unit: usize| {
+        let range = &units.ranges[unit];
+        range.start < fixed.end && fixed.start < range.end
+    };
+    let mut starts: Vec<Option<usize>> = vec![None; units.ranges.len()];
+    let (pinned, free): (Vec<usize>, Vec<usize>) = (0..units.ranges.len()).partition(|u| is_fixed(*u));
+    for unit in pinned {
+        starts[unit] = Some(units.ranges[unit].start);
+    }
+    for unit in free {
+        let size = units.ranges[unit].len();
+        let mut start = 0;
+        while let Some(end) = interference[unit].iter().find_map(|other| {
+            let other_start = starts[*other]?;
+            let other_end = other_start + units.ranges[*other].len();
+            (start < other_end && other_start < start + size).then_some(other_end)
+        }) {
+            start = end;
+        }
+        starts[unit] = Some(start);
+    }
+    starts.into_iter().map(|start| start.unwrap()).collect()
+}
+
+struct Relocation<'a> {
+    units: &'a Units,
+    starts: Vec<usize>,
+}
+
+impl Relocation<'_> {
+    fn relocate(&self, instruction: &mut Instruction) {
+        walk::children_mut(instruction, &mut |child| self.relocate(child));
+        let access = variable_access(self.units, instruction).flatten();
+        let location = match instruction {
+            Instruction::Load(b) => &mut b.src,
+            Instruction::Store(b) => &mut b.dest,
+            Instruction::Call(b) => match &mut b.return_info {
+                ReturnType::Final(data) => &mut data.dest,
+                ReturnType::Intermediate { .. } => return,
+            },
+            _ => return,
+        };
+        if let (Some(access), LocationRule::Indexed { location, .. }) = (access, location) {
+            let from = self.units.ranges[access.unit].start;
+            let to = self.starts[access.unit];
+            if from != to {
+                *location = relocated(location, constant_part(location).unwrap() - from + to);
+            }
+        }
+    }
+}
+
+// Same address with the given constant instead of the current one
+fn relocated(location: &Instruction, constant: usize) -> InstructionPointer {
+    fn offsets(location: &Instruction, found: &mut Vec<InstructionPointer>) {
+        match location {
+            Instruction::Value(v) if v.parse_as == ValueType::U32 => {}
+            Instruction::Compute(b) if b.op == OperatorType::AddAddress => {
+                offsets(&b.stack[0], found);
+                offsets(&b.stack[1], found);
+            }
+            offset => found.push(offset.clone().allocate()),
+        }
+    }
+    let (line, message_id) = (location.get_line(), location.get_message_id());
+    let mut terms = Vec::new();
+    offsets(location, &mut terms);
+    if constant != 0 || terms.is_empty() {
+        let value = ValueBucket { line, message_id, parse_as: ValueType::U32, op_aux_no: 0, value: constant };
+        terms.push(value.allocate());
+    }
+    let mut terms = terms.into_iter();
+    let first = terms.next().unwrap();
+    terms.fold(first, |left, right| {
+        ComputeBucket { line, message_id, op_aux_no: 0, op: OperatorType::AddAddress, stack: vec![left, right] }
+            .allocate()
+    })
+}
+
+// Returns the new stack depth, or None if some access to a variable can
+// not be assigned to one of them and the code is left as it is
+pub fn allocate(
+    code: &mut InstructionList,
+    variables: &[(usize, usize)],
+    fixed: Range<usize>,
+    var_stack_depth: usize,
+) -> Option<usize> {
+    let units = Units::new(variables, var_stack_depth);
+    let mut liveness = Liveness { units: &units, interference: vec![BTreeSet::new(); units.ranges.len()] };
+    liveness.live_list(code, SlotSet::new(units.slots()))?;
+    let starts = assign_slots(&units, &liveness.interference, &fixed);
+    let depth = (0..units.ranges.len()).map(|u| starts[u] + units.ranges[u].len()).max().unwrap_or(0);
+    if depth >= var_stack_depth {
+        return None;
+    }
+    let relocation = Relocation { units: &units, starts };
+    for instruction in code.iter_mut() {
+        relocation.relocate(instruction);
+    }
+    Some(depth)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn value(value: usize) -> InstructionPointer {
+        ValueBucket { line: 0, message_id: 0, parse_as: ValueType::U32, op_aux_no: 0, value }
+            .allocate()
+    }
+
+    fn ret(slot: usize) -> InstructionPointer {
+        ReturnBucket { line: 0, message_id: 0, with_size: 1, value: walk::load_variable(0, 0, slot) }
+            .allocate()
+    }
+
+    #[test]
+    fn variables_with_disjoint_live_ranges_share_a_slot() {
+        // each variable is only read by the store to the next one
+        let mut code = vec![
+            walk::store_variable(0, value(3)),
+            walk::store_variable(1, walk::load_variable(0, 0, 0)),
+            walk::store_variable(2, walk::load_variable(0, 0, 1)),
+            ret(2),
+        ];
+        let depth = allocate(&mut code, &[(0, 1), (1, 1), (2, 1)], 0..0, 3);
+        assert_eq!(depth, Some(1));
+        let expected = vec![
+            walk::store_variable(0, value(3)),
+            walk::store_variable(0, walk::load_variable(0, 0, 0)),
+            walk::store_variable(0, walk::load_variable(0, 0, 0)),
+            ret(0),
+        ];
+        assert!(code == expected);
+    }
+
+    #[test]
+    fn variables_read_in_later_iterations_keep_their_slots() {
+        // var 0 is last read at the start of the body, but it is read again
+        // in the next iteration while var 1 is alive
+        let assert = |slot| {
+            AssertBucket { line: 0, message_id: 0, evaluate: walk::load_variable(0, 0, slot) }
+                .allocate()
+        };
+        let body = vec![assert(0), walk::store_variable(1, value(5)), assert(1)];
+        let mut code = vec![
+            walk::store_variable(0, value(1)),
+            LoopBucket { line: 0, message_id: 0, continue_condition: value(1), body }.allocate(),
+        ];
+        assert_eq!(allocate(&mut code, &[(0, 1), (1, 1)], 0..0, 2), None);
+    }
+}
@@ -0,0 +1,435 @@
+use crate::intermediate_representation::ir_interface::*;
+use std::collections::{HashMap, HashSet};
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum CodeKind {
+    Template,
+    Function,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct VerifyError {
+    // header of the template or function where the error was found
+    pub code: String,
+    // line of the circom source the wrong instruction comes from
+    pub line: usize,
+    pub message: String,
+}
+
+impl std::fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} (line {}): {}", self.code, self.line, self.message)
+    }
+}
+
+// Information about the whole circuit needed to check calls and
+// component creations
+pub struct CircuitSummary {
+    // header of every template in the order of their ids
+    pub templates: Vec<String>,
+    // header of each function with its number of parameters and arena size
+    pub functions: HashMap<String, (usize, usize)>,
+}
+
+// Checks that a list of instructions is well formed. The checks that
+// depend on information not available yet (the kind of code, the stack
+// sizes or the rest of the circuit) are only done once it is given.
+pub struct Verifier<'a> {
+    code: &'a str,
+    kind: Option<CodeKind>,
+    expression_stack_depth: Option<usize>,
+    var_stack_depth: Option<usize>,
+    circuit: Option<&'a CircuitSummary>,
+    headers: HashSet<&'a str>,
+    errors: Vec<VerifyError>,
+}
+
+// What an instruction leaves once executed
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum Produces {
+    Field,
+    Address,
+    Nothing,
+}
+
+fn produces(instruction: &Instruction) -> Produces {
+    use Instruction::*;
+    match instruction {
+        Value(b) if b.parse_as == ValueType::U32 => Produces::Address,
+        Compute(b) if b.op.is_address_op() => Produces::Address,
+        Value(_) | Compute(_) | Load(_) => Produces::Field,
+        Call(b) => match b.return_info {
+            ReturnType::Intermediate { .. } => Produces::Field,
+            ReturnType::Final(_) => Produces::Nothing,
+        },
+        Store(_) | Branch(_) | Return(_) | Assert(_) | Log(_) | Loop(_) | CreateCmp(_) => {
+            Produces::Nothing
+        }
+    }
+}
+
+fn operands(op: OperatorType) -> usize {
+    use OperatorType::*;
+    match op {
+        PrefixSub | BoolNot | Complement | ToAddress => 1,
+        _ => 2,
+    }
+}
+
+impl<'a> Verifier<'a> {
+    pub fn new(code: &'a str) -> Verifier<'a> {
+        Verifier {
+            code,
+            kind: None,
+            expression_stack_depth: None,
+            var_stack_depth: None,
+            circuit: None,
+            headers: HashSet::new(),
+            errors: Vec::new(),
+        }
+    }
+    pub fn kind(mut self, kind: CodeKind) -> Verifier<'a> {
+        self.kind = Some(kind);
+        self
+    }
+    pub fn expression_stack_depth(mut self, depth: usize) -> Verifier<'a> {
+        self.expression_stack_depth = Some(depth);
+        self
+    }
+    pub fn var_stack_depth(mut self, depth: usize) -> Verifier<'a> {
+        self.var_stack_depth = Some(depth);
+        self
+    }
+    pub fn circuit(mut self, circuit: &'a CircuitSummary) -> Verifier<'a> {
+        self.headers = circuit.templates.iter().map(|header| header.as_str()).collect();
+        self.circuit = Some(circuit);
+        self
+    }
+
+    pub fn verify(mut self, code: &InstructionList) -> Result<(), Vec<VerifyError>> {
+        self.verify_statements(code);
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(self.errors)
+        }
+    }
+
+    fn error(&mut self, line: usize, message: String) {
+        self.errors.push(VerifyError { code: self.code.to_string(), line, message });
+    }
+
+    fn verify_statements(&mut self, code: &InstructionList) {
+        for instruction in code {
+            if produces(instruction) != Produces::Nothing {
+                self.error(instruction.get_line(), "expression used as a statement".to_string());
+            }
+            self.verify_instruction(instruction);
+        }
+    }
+
+    fn verify_expected(&mut self, instruction: &Instruction, expected: Produces, what: &str) {
+        let found = produces(instruction);
+        if found != expected {
+            let describe = |produces| match produces {
+                Produces::Field => "a field element",
+                Produces::Address => "an address",
+                Produces::Nothing => "nothing",
+            };
+            let message = format!(
+                "{} must produce {} but produces {}",
+                what,
+                describe(expected),
+                describe(found)
+            );
+            self.error(instruction.get_line(), message);
+        }
+        self.verify_instruction(instruction);
+    }
+
+    fn verify_op_aux_no(&mut self, line: usize, op_aux_no: usize) {
+        if let Some(depth) = self.expression_stack_depth {
+            if op_aux_no >= depth {
+                let message = format!(
+                    "auxiliary position {} is out of the expression stack of size {}",
+                    op_aux_no, depth
+                );
+                self.error(line, message);
+            }
+        }
+    }
+
+    fn verify_size(&mut self, line: usize, size: usize, what: &str) {
+        if size == 0 {
+            self.error(line, format!("{} has size 0", what));
+        }
+    }
+
+    fn verify_instruction(&mut self, instruction: &Instruction) {
+        use Instruction::*;
+        match instruction {
+            Value(b) => self.verify_op_aux_no(b.line, b.op_aux_no),
+            Load(b) => {
+                self.verify_size(b.line, b.context.size, "load");
+                self.verify_access(b.line, &b.address_type, &b.src, b.context.size);
+            }
+            Store(b) => {
+                self.verify_size(b.line, b.context.size, "store");
+                self.verify_access(b.line, &b.dest_address_type, &b.dest, b.context.size);
+                self.verify_expected(&b.src, Produces::Field, "stored value");
+            }
+            Compute(b) => self.verify_compute(b),
+            Call(b) => self.verify_call(b),
+            Branch(b) => {
+                self.verify_expected(&b.cond, Produces::Field, "branch condition");
+                self.verify_statements(&b.if_branch);
+                self.verify_statements(&b.else_branch);
+            }
+            Return(b) => {
+                if self.kind == Some(CodeKind::Template) {
+                    self.error(b.line, "return inside a template".to_string());
+                }
+                self.verify_size(b.line, b.with_size, "return");
+                self.verify_expected(&b.value, Produces::Field, "returned value");
+            }
+            Assert(b) => self.verify_expected(&b.evaluate, Produces::Field, "asserted value"),
+            Log(b) => {
+                for arg in &b.argsprint {
+                    if let LogBucketArg::LogExp(exp) = arg {
+                        self.verify_expected(exp, Produces::Field, "logged value");
+                    }
+                }
+            }
+            Loop(b) => {
+                self.verify_expected(&b.continue_condition, Produces::Field, "loop condition");
+                self.verify_statements(&b.body);
+            }
+            CreateCmp(b) => self.verify_create_cmp(b),
+        }
+    }
+
+    fn verify_compute(&mut self, bucket: &ComputeBucket) {
+        let expected = operands(bucket.op);
+        if bucket.stack.len() != expected {
+            let message = format!(
+                "{} expects {} operands, found {}",
+                bucket.op.to_string(),
+                expected,
+                bucket.stack.len()
+            );
+            self.error(bucket.line, message);
+        }
+        let operand = match bucket.op {
+            OperatorType::AddAddress | OperatorType::MulAddress => Produces::Address,
+            _ => Produces::Field,
+        };
+        if !bucket.op.is_address_op() {
+            self.verify_op_aux_no(bucket.line, bucket.op_aux_no);
+        }
+        for instruction in &bucket.stack {
+            self.verify_expected(instruction, operand, "operand");
+        }
+    }
+
+    fn verify_call(&mut self, bucket: &CallBucket) {
+        if bucket.argument_types.len() != bucket.arguments.len() {
+            let message = format!(
+                "call to {} has {} arguments but {} argument sizes",
+                bucket.symbol,
+                bucket.arguments.len(),
+                bucket.argument_types.len()
+            );
+            self.error(bucket.line, message);
+        }
+        for context in &bucket.argument_types {
+            self.verify_size(bucket.line, context.size, "argument");
+        }
+        for argument in &bucket.arguments {
+            self.verify_expected(argument, Produces::Field, "argument");
+        }
+        match &bucket.return_info {
+            ReturnType::Intermediate { op_aux_no } => {
+                self.verify_op_aux_no(bucket.line, *op_aux_no)
+            }
+            ReturnType::Final(data) => {
+                self.verify_size(bucket.line, data.context.size, "call result");
+                self.verify_access(
+                    bucket.line,
+                    &data.dest_address_type,
+                    &data.dest,
+                    data.context.size,
+                );
+            }
+        }
+        if let Some(circuit) = self.circuit {
+            match circuit.functions.get(&bucket.symbol) {
+                None => self
+                    .error(bucket.line, format!("call to the unknown function {}", bucket.symbol)),
+                Some((params, arena_size)) => {
+                    if *params != bucket.arguments.len() {
+                        let message = format!(
+                            "{} expects {} arguments, found {}",
+                            bucket.symbol,
+                            params,
+                            bucket.arguments.len()
+                        );
+                        self.error(bucket.line, message);
+                    }
+                    if *arena_size != bucket.arena_size {
+                        let message = format!(
+                            "call to {} has arena size {} instead of {}",
+                            bucket.symbol, bucket.arena_size, arena_size
+                        );
+                        self.error(bucket.line, message);
+                    }
+                }
+            }
+        }
+    }
+
+    fn verify_create_cmp(&mut self, bucket: &CreateCmpBucket) {
+        if self.kind == Some(CodeKind::Function) {
+            self.error(bucket.line, "component created inside a function".to_string());
+        }
+        self.verify_size(bucket.line, bucket.number_of_cmp, "component creation");
+        self.verify_expected(&bucket.sub_cmp_id, Produces::Address, "component id");
+        if let Some(circuit) = self.circuit {
+            if circuit.templates.get(bucket.template_id) != Some(&bucket.symbol) {
+                let message = format!("template {} is not {}", bucket.template_id, bucket.symbol);
+                self.error(bucket.line, message);
+            }
+        }
+    }
+
+    fn verify_access(
+        &mut self,
+        line: usize,
+        address: &AddressType,
+        location: &LocationRule,
+        size: usize,
+    ) {
+        match address {
+            AddressType::Variable => {}
+            _ if self.kind == Some(CodeKind::Function) => {
+                self.error(line, "signal accessed inside a function".to_string());
+            }
+            AddressType::Signal => {}
+            AddressType::SubcmpSignal { cmp_address, .. } => {
+                self.verify_expected(cmp_address, Produces::Address, "component address");
+            }
+        }
+        let is_subcmp = matches!(address, AddressType::SubcmpSignal { .. });
+        match location {
+            LocationRule::Indexed { location, template_header } => {
+                if let Some(header) = template_header {
+                    if !is_subcmp {
+                        self.error(
+                            line,
+                            "template header given to an access that is not to a subcomponent"
+                                .to_string(),
+                        );
+                    } else if self.circuit.is_some() && !self.headers.contains(header.as_str()) {
+                        self.error(line, format!("unknown template {}", header));
+                    }
+                }
+                self.verify_expected(location, Produces::Address, "location");
+                if let (AddressType::Variable, Some(depth), Instruction::Value(value)) =
+                    (address, self.var_stack_depth, location.as_ref())
+                {
+                    if value.value + size > depth {
+                        let message = format!(
+                            "variable position {} is out of the {} variables",
+                            value.value + size - 1,
+                            depth
+                        );
+                        self.error(line, message);
+                    }
+                }
+            }
+            LocationRule::Mapped { indexes, .. } => {
+                if !is_subcmp {
+                    self.error(
+                        line,
+                        "mapped location used in an access that is not to a subcomponent"
+                            .to_string(),
+                    );
+                }
+                for index in indexes {
+                    self.verify_expected(index, Produces::Address, "index");
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn value(parse_as: ValueType, value: usize) -> InstructionPointer {
+        ValueBucket { line: 1, message_id: 0, parse_as, op_aux_no: 0, value }.allocate()
+    }
+
+    fn store(address: AddressType, location: InstructionPointer) -> InstructionPointer {
+        StoreBucket {
+            line: 2,
+            message_id: 0,
+            context: InstrContext { size: 1 },
+            dest_is_output: false,
+            dest_address_type: address,
+            dest: LocationRule::Indexed { location, template_header: None },
+            src: value(ValueType::BigInt, 0),
+        }
+        .allocate()
+    }
+
+    #[test]
+    fn verifier_accepts_well_formed_code() {
+        let code = vec![store(AddressType::Variable, value(ValueType::U32, 1))];
+        let verifier = Verifier::new("f_0")
+            .kind(CodeKind::Function)
+            .expression_stack_depth(1)
+            .var_stack_depth(2);
+        assert!(verifier.verify(&code).is_ok());
+    }
+
+    #[test]
+    fn verifier_rejects_ill_formed_code() {
+        let code = vec![
+            store(AddressType::Signal, value(ValueType::U32, 0)),
+            store(AddressType::Variable, value(ValueType::BigInt, 0)),
+            store(AddressType::Variable, value(ValueType::U32, 2)),
+        ];
+        let verifier = Verifier::new("f_0")
+            .kind(CodeKind::Function)
+            .expression_stack_depth(1)
+            .var_stack_depth(2);
+        let errors = verifier.verify(&code).err().unwrap();
+        let messages: Vec<&str> = errors.iter().map(|error| error.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "signal accessed inside a function",
+                "location must produce an address but produces a field element",
+                "variable position 2 is out of the 2 variables",
+            ]
+        );
+    }
+
+    #[test]
+    fn first_pass_that_breaks_the_code_is_reported() {
+        use crate::ir_processing::check_well_formed;
+        let verifier = || Verifier::new("f_0").kind(CodeKind::Function).var_stack_depth(2);
+        let mut found = None;
+        check_well_formed("translate", &vec![store(AddressType::Variable, value(ValueType::U32, 1))], verifier(), &mut found);
+        assert!(found.is_none());
+        check_well_formed("fold_constants", &vec![store(AddressType::Variable, value(ValueType::U32, 2))], verifier(), &mut found);
+        check_well_formed("remove_dead_stores", &vec![store(AddressType::Signal, value(ValueType::U32, 0))], verifier(), &mut found);
+        let ill_formed = found.unwrap();
+        assert_eq!(ill_formed.pass, "fold_constants");
+        let report = ill_formed.into_report();
+        assert_eq!(report.get_message(), "Ill-formed IR after fold_constants");
+        assert_eq!(report.get_code().to_string(), "IR01");
+        assert_eq!(report.get_notes(), &vec!["f_0 (line 2): variable position 2 is out of the 2 variables".to_string()]);
+    }
+}
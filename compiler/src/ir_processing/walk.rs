@@ -0,0 +1,169 @@
+use crate::intermediate_representation::ir_interface::*;
+
+// Calls f on every instruction directly nested in the given one: operands,
+// locations, addresses of subcomponents and the bodies of branches and loops
+pub fn children<'a>(instruction: &'a Instruction, f: &mut dyn FnMut(&'a Instruction)) {
+    use Instruction::*;
+    match instruction {
+        Value(_) => {}
+        Load(b) => {
+            address(&b.address_type, f);
+            location(&b.src, f);
+        }
+        Store(b) => {
+            address(&b.dest_address_type, f);
+            location(&b.dest, f);
+            f(&b.src);
+        }
+        Compute(b) => b.stack.iter().for_each(|operand| f(operand)),
+        Call(b) => {
+            b.arguments.iter().for_each(|argument| f(argument));
+            if let ReturnType::Final(data) = &b.return_info {
+                address(&data.dest_address_type, f);
+                location(&data.dest, f);
+            }
+        }
+        Branch(b) => {
+            f(&b.cond);
+            b.if_branch.iter().for_each(|instruction| f(instruction));
+            b.else_branch.iter().for_each(|instruction| f(instruction));
+        }
+        Return(b) => f(&b.value),
+        Assert(b) => f(&b.evaluate),
+        Log(b) => {
+            for arg in &b.argsprint {
+                if let LogBucketArg::LogExp(exp) = arg {
+                    f(exp);
+                }
+            }
+        }
+        Loop(b) => {
+            f(&b.continue_condition);
+            b.body.iter().for_each(|instruction| f(instruction));
+        }
+        CreateCmp(b) => f(&b.sub_cmp_id),
+    }
+}
+
+pub fn children_mut(instruction: &mut Instruction, f: &mut dyn FnMut(&mut Instruction)) {
+    use Instruction::*;
+    match instruction {
+        Value(_) => {}
+        Load(b) => {
+            address_mut(&mut b.address_type, f);
+            location_mut(&mut b.src, f);
+        }
+        Store(b) => {
+            address_mut(&mut b.dest_address_type, f);
+            location_mut(&mut b.dest, f);
+            f(&mut b.src);
+        }
+        Compute(b) => b.stack.iter_mut().for_each(|operand| f(operand)),
+        Call(b) => {
+            b.arguments.iter_mut().for_each(|argument| f(argument));
+            if let ReturnType::Final(data) = &mut b.return_info {
+                address_mut(&mut data.dest_address_type, f);
+                location_mut(&mut data.dest, f);
+            }
+        }
+        Branch(b) => {
+            f(&mut b.cond);
+            b.if_branch.iter_mut().for_each(|instruction| f(instruction));
+            b.else_branch.iter_mut().for_each(|instruction| f(instruction));
+        }
+        Return(b) => f(&mut b.value),
+        Assert(b) => f(&mut b.evaluate),
+        Log(b) => {
+            for arg in &mut b.argsprint {
+                if let LogBucketArg::LogExp(exp) = arg {
+                    f(exp);
+                }
+            }
+        }
+        Loop(b) => {
+            f(&mut b.continue_condition);
+            b.body.iter_mut().for_each(|instruction| f(instruction));
+        }
+        CreateCmp(b) => f(&mut b.sub_cmp_id),
+    }
+}
+
+// True if some instruction of the tree (the given one included) satisfies
+// the predicate
+pub fn any(instruction: &Instruction, predicate: &dyn Fn(&Instruction) -> bool) -> bool {
+    let mut found = predicate(instruction);
+    if !found {
+        children(instruction, &mut |child| found = found || any(child, predicate));
+    }
+    found
+}
+
+// First address of a location that is a constant
+pub fn constant_location(location: &LocationRule) -> Option<usize> {
+    match location {
+        LocationRule::Indexed { location, .. } => match &**location {
+            Instruction::Value(v) if v.parse_as == ValueType::U32 => Some(v.value),
+            _ => None,
+        },
+        LocationRule::Mapped { .. } => None,
+    }
+}
+
+// Instructions that access the variable in the given slot
+pub fn variable_location(line: usize, message_id: usize, slot: usize) -> LocationRule {
+    let location =
+        ValueBucket { line, message_id, parse_as: ValueType::U32, op_aux_no: 0, value: slot }
+            .allocate();
+    LocationRule::Indexed { location, template_header: None }
+}
+
+pub fn load_variable(line: usize, message_id: usize, slot: usize) -> InstructionPointer {
+    LoadBucket {
+        line,
+        message_id,
+        address_type: AddressType::Variable,
+        src: variable_location(line, message_id, slot),
+        context: InstrContext { size: 1 },
+    }
+    .allocate()
+}
+
+pub fn store_variable(slot: usize, src: InstructionPointer) -> InstructionPointer {
+    let (line, message_id) = (src.get_line(), src.get_message_id());
+    StoreBucket {
+        line,
+        message_id,
+        context: InstrContext { size: 1 },
+        dest_is_output: false,
+        dest_address_type: AddressType::Variable,
+        dest: variable_location(line, message_id, slot),
+        src,
+    }
+    .allocate()
+}
+
+fn address<'a>(address: &'a AddressType, f: &mut dyn FnMut(&'a Instruction)) {
+    if let AddressType::SubcmpSignal { cmp_address, .. } = address {
+        f(cmp_address);
+    }
+}
+
+fn location<'a>(location: &'a LocationRule, f: &mut dyn FnMut(&'a Instruction)) {
+    match location {
+        LocationRule::Indexed { location, .. } => f(location),
+        LocationRule::Mapped { indexes, .. } => indexes.iter().for_each(|index| f(index)),
+    }
+}
+
+fn address_mut(address: &mut AddressType, f: &mut dyn FnMut(&mut Instruction)) {
+    if let AddressType::SubcmpSignal { cmp_address, .. } = address {
+        f(cmp_address);
+    }
+}
+
+fn location_mut(location: &mut LocationRule, f: &mut dyn FnMut(&mut Instruction)) {
+    match location {
+        LocationRule::Indexed { location, .. } => f(location),
+        LocationRule::Mapped { indexes, .. } => indexes.iter_mut().for_each(|index| f(index)),
+    }
+}
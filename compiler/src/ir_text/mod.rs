@@ -0,0 +1,263 @@
+// Textual form of the intermediate representation. Every template and
+// function is printed as an s-expression holding all the fields of its
+// buckets, so reading the text back gives exactly the same code.
+// The format is described in mkdocs/docs/circom-language/circom-insight/ir-format.md
+mod printer;
+mod reader;
+mod sexp;
+
+pub use sexp::{ReadError, ReadResult};
+
+use crate::circuit_design::function::FunctionCodeInfo;
+use crate::circuit_design::template::TemplateCodeInfo;
+use sexp::SExp;
+
+pub fn template_to_text(template: &TemplateCodeInfo) -> String {
+    printer::template_to_sexp(template).pretty()
+}
+
+pub fn function_to_text(function: &FunctionCodeInfo) -> String {
+    printer::function_to_sexp(function).pretty()
+}
+
+// The code of a circuit read from its text
+#[derive(Default)]
+pub struct CircuitCode {
+    pub templates: Vec<TemplateCodeInfo>,
+    pub shared_templates: Vec<TemplateCodeInfo>,
+    pub functions: Vec<FunctionCodeInfo>,
+}
+
+// (circuit (template ...)* (shared (template ...)*) (function ...)*), where
+// shared holds the bodies run by several instances
+pub fn circuit_to_text(
+    templates: &[&TemplateCodeInfo],
+    shared_templates: &[&TemplateCodeInfo],
+    functions: &[&FunctionCodeInfo],
+) -> String {
+    let mut items = Vec::new();
+    items.extend(templates.iter().map(|template| printer::template_to_sexp(template)));
+    let shared = shared_templates.iter().map(|template| printer::template_to_sexp(template));
+    items.push(SExp::field("shared", shared.collect()));
+    items.extend(functions.iter().map(|function| printer::function_to_sexp(function)));
+    SExp::field("circuit", items).pretty()
+}
+
+pub fn text_to_circuit(text: &str) -> ReadResult<CircuitCode> {
+    let sexps = sexp::parse(text)?;
+    let circuit = match sexps.as_slice() {
+        [circuit @ SExp::List(..)] => circuit,
+        _ => {
+            return Err(ReadError::new(1, "expected a single (circuit ...) expression".to_string()))
+        }
+    };
+    let (line, items) = match circuit {
+        SExp::List(line, items) if items.first() == Some(&SExp::atom("circuit")) => {
+            (*line, &items[1..])
+        }
+        _ => {
+            return Err(ReadError::new(1, "expected a single (circuit ...) expression".to_string()))
+        }
+    };
+    let is_list = |item: &SExp, tag: &str| {
+        matches!(item, SExp::List(_, fields) if fields.first() == Some(&SExp::atom(tag)))
+    };
+    let mut code = CircuitCode::default();
+    let mut items = items.iter().peekable();
+    while let Some(item) = items.next_if(|item| is_list(item, "template")) {
+        code.templates.push(reader::read_template(item)?);
+    }
+    match items.next() {
+        Some(SExp::List(_, fields)) if fields.first() == Some(&SExp::atom("shared")) => {
+            for template in &fields[1..] {
+                code.shared_templates.push(reader::read_template(template)?);
+            }
+        }
+        other => {
+            let line = match other {
+                Some(SExp::List(line, _)) => *line,
+                _ => line,
+            };
+            return Err(ReadError::new(
+                line,
+                "expected the (shared ...) templates after the templates".to_string(),
+            ));
+        }
+    }
+    for item in items {
+        if is_list(item, "template") || is_list(item, "shared") {
+            return Err(ReadError::new(
+                line,
+                "templates must go before the functions".to_string(),
+            ));
+        }
+        code.functions.push(reader::read_function(item)?);
+    }
+    Ok(code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hir::very_concrete_program::Param;
+    use crate::intermediate_representation::ir_interface::*;
+
+    fn value(parse_as: ValueType, value: usize) -> InstructionPointer {
+        ValueBucket { line: 1, message_id: 0, parse_as, op_aux_no: 0, value }.allocate()
+    }
+
+    fn sample_template() -> TemplateCodeInfo {
+        let load = LoadBucket {
+            line: 2,
+            message_id: 0,
+            address_type: AddressType::SubcmpSignal {
+                cmp_address: value(ValueType::U32, 0),
+                uniform_parallel_value: Some(false),
+                is_output: true,
+                input_information: InputInformation::NoInput,
+            },
+            src: LocationRule::Mapped { signal_code: 1, indexes: vec![value(ValueType::U32, 2)] },
+            context: InstrContext { size: 1 },
+        }
+        .allocate();
+        let store = StoreBucket {
+            line: 3,
+            message_id: 0,
+            context: InstrContext { size: 1 },
+            dest_is_output: false,
+            dest_address_type: AddressType::Signal,
+            dest: LocationRule::Indexed {
+                location: value(ValueType::U32, 1),
+                template_header: Some("A \"x\"".to_string()),
+            },
+            src: ComputeBucket {
+                line: 3,
+                message_id: 0,
+                op: OperatorType::Eq(2),
+                op_aux_no: 1,
+                stack: vec![load, value(ValueType::BigInt, 0)],
+            }
+            .allocate(),
+        }
+        .allocate();
+        let log = LogBucket {
+            line: 4,
+            message_id: 0,
+            argsprint: vec![
+                LogBucketArg::LogStr(3),
+                LogBucketArg::LogExp(value(ValueType::BigInt, 1)),
+            ],
+        }
+        .allocate();
+        let create = CreateCmpBucket {
+            line: 5,
+            message_id: 0,
+            template_id: 1,
+            cmp_unique_id: 0,
+            symbol: "B_1".to_string(),
+            sub_cmp_id: value(ValueType::U32, 0),
+            name_subcomponent: "b".to_string(),
+            defined_positions: vec![(0, false), (1, true)],
+            is_part_mixed_array_not_uniform_parallel: false,
+            uniform_parallel: None,
+            dimensions: vec![2],
+            signal_offset: 3,
+            signal_offset_jump: 2,
+            component_offset: 1,
+            component_offset_jump: 1,
+            number_of_cmp: 2,
+            has_inputs: true,
+        }
+        .allocate();
+        let body = vec![
+            create,
+            LoopBucket {
+                line: 6,
+                message_id: 0,
+                continue_condition: value(ValueType::BigInt, 1),
+                body: vec![store, log],
+            }
+            .allocate(),
+        ];
+        TemplateCodeInfo {
+            id: 0,
+            header: "A_0".to_string(),
+            name: "A".to_string(),
+            body,
+            number_of_components: 2,
+            var_stack_depth: 1,
+            expression_stack_depth: 3,
+            ..TemplateCodeInfo::default()
+        }
+    }
+
+    fn sample_function() -> FunctionCodeInfo {
+        let call = CallBucket {
+            line: 8,
+            message_id: 0,
+            symbol: "f_0".to_string(),
+            argument_types: vec![InstrContext { size: 2 }],
+            arguments: vec![LoadBucket {
+                line: 8,
+                message_id: 0,
+                address_type: AddressType::Variable,
+                src: LocationRule::Indexed {
+                    location: value(ValueType::U32, 0),
+                    template_header: None,
+                },
+                context: InstrContext { size: 2 },
+            }
+            .allocate()],
+            arena_size: 4,
+            return_info: ReturnType::Intermediate { op_aux_no: 0 },
+        }
+        .allocate();
+        let body = vec![BranchBucket {
+            line: 7,
+            message_id: 0,
+            cond: value(ValueType::BigInt, 0),
+            if_branch: vec![
+                ReturnBucket { line: 8, message_id: 0, with_size: 1, value: call }.allocate()
+            ],
+            else_branch: vec![AssertBucket {
+                line: 9,
+                message_id: 0,
+                evaluate: value(ValueType::BigInt, 1),
+            }
+            .allocate()],
+        }
+        .allocate()];
+        FunctionCodeInfo {
+            header: "f_0".to_string(),
+            name: "f".to_string(),
+            params: vec![Param { name: "a".to_string(), length: vec![2] }],
+            returns: vec![],
+            body,
+            max_number_of_vars: 4,
+            max_number_of_ops_in_expression: 2,
+        }
+    }
+
+    #[test]
+    fn circuit_text_round_trip() {
+        let template = sample_template();
+        let function = sample_function();
+        let text = circuit_to_text(&[&template], &[], &[&function]);
+        let code = text_to_circuit(&text).unwrap();
+        assert!(code.templates.len() == 1 && code.templates[0] == template);
+        assert!(code.shared_templates.is_empty());
+        assert!(code.functions.len() == 1 && code.functions[0] == function);
+        assert_eq!(circuit_to_text(&[&code.templates[0]], &[], &[&code.functions[0]]), text);
+    }
+
+    #[test]
+    fn circuit_text_errors_point_at_the_line() {
+        let text = "(circuit\n  (shared)\n  (function (header \"f_0\")\n    (name 3)))";
+        let error = text_to_circuit(text).err().unwrap();
+        assert_eq!(error.line, 4);
+        let error = text_to_circuit("(circuit\n  (function (header \"f_0\")))").err().unwrap();
+        assert_eq!(error.line, 2);
+        let error = text_to_circuit("(circuit (template (id 0)").err().unwrap();
+        assert_eq!(error.line, 1);
+    }
+}
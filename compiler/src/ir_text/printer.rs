@@ -0,0 +1,320 @@
+use super::sexp::SExp;
+use crate::circuit_design::function::FunctionCodeInfo;
+use crate::circuit_design::template::{SharedRun, TemplateCodeInfo};
+use crate::intermediate_representation::ir_interface::*;
+
+pub fn template_to_sexp(template: &TemplateCodeInfo) -> SExp {
+    SExp::field(
+        "template",
+        vec![
+            number("id", template.id),
+            string("header", &template.header),
+            string("name", &template.name),
+            boolean("is_parallel", template.is_parallel),
+            boolean("is_parallel_component", template.is_parallel_component),
+            boolean("is_not_parallel_component", template.is_not_parallel_component),
+            boolean("has_parallel_sub_cmp", template.has_parallel_sub_cmp),
+            number("number_of_inputs", template.number_of_inputs),
+            number("number_of_outputs", template.number_of_outputs),
+            number("number_of_intermediates", template.number_of_intermediates),
+            number("number_of_components", template.number_of_components),
+            number("var_stack_depth", template.var_stack_depth),
+            number("expression_stack_depth", template.expression_stack_depth),
+            number("signal_stack_depth", template.signal_stack_depth),
+            SExp::field("shared_run", shared_run_to_sexp(&template.shared_run)),
+            list("body", &template.body),
+        ],
+    )
+}
+
+// (shared_run none) or (shared_run "header" first_constant)
+fn shared_run_to_sexp(shared_run: &Option<SharedRun>) -> Vec<SExp> {
+    match shared_run {
+        Some(shared) => vec![SExp::string(&shared.header), SExp::atom(shared.first_constant)],
+        None => vec![SExp::atom("none")],
+    }
+}
+
+pub fn function_to_sexp(function: &FunctionCodeInfo) -> SExp {
+    let params = function
+        .params
+        .iter()
+        .map(|param| {
+            let mut items = vec![SExp::string(&param.name)];
+            items.extend(param.length.iter().map(SExp::atom));
+            SExp::field("param", items)
+        })
+        .collect();
+    SExp::field(
+        "function",
+        vec![
+            string("header", &function.header),
+            string("name", &function.name),
+            SExp::field("params", params),
+            numbers("returns", &function.returns),
+            number("max_number_of_vars", function.max_number_of_vars),
+            number("max_number_of_ops_in_expression", function.max_number_of_ops_in_expression),
+            list("body", &function.body),
+        ],
+    )
+}
+
+pub fn instruction_to_sexp(instruction: &Instruction) -> SExp {
+    use Instruction::*;
+    let (tag, mut fields) = match instruction {
+        Value(b) => ("value", value_fields(b)),
+        Load(b) => ("load", load_fields(b)),
+        Store(b) => ("store", store_fields(b)),
+        Compute(b) => ("compute", compute_fields(b)),
+        Call(b) => ("call", call_fields(b)),
+        Branch(b) => ("branch", branch_fields(b)),
+        Return(b) => ("return", return_fields(b)),
+        Assert(b) => ("assert", vec![single("evaluate", &b.evaluate)]),
+        Log(b) => ("log", log_fields(b)),
+        Loop(b) => ("loop", loop_fields(b)),
+        CreateCmp(b) => ("create_cmp", create_cmp_fields(b)),
+    };
+    let mut items = vec![
+        number("line", instruction.get_line()),
+        number("message", instruction.get_message_id()),
+    ];
+    items.append(&mut fields);
+    SExp::field(tag, items)
+}
+
+fn value_fields(bucket: &ValueBucket) -> Vec<SExp> {
+    let parse_as = match bucket.parse_as {
+        ValueType::U32 => "u32",
+        ValueType::BigInt => "bigint",
+        ValueType::InstanceConstant => "instance",
+    };
+    vec![
+        SExp::field("parse_as", vec![SExp::atom(parse_as)]),
+        number("op_aux_no", bucket.op_aux_no),
+        number("value", bucket.value),
+    ]
+}
+
+fn load_fields(bucket: &LoadBucket) -> Vec<SExp> {
+    vec![
+        number("size", bucket.context.size),
+        SExp::field("address", vec![address_to_sexp(&bucket.address_type)]),
+        SExp::field("src", vec![location_to_sexp(&bucket.src)]),
+    ]
+}
+
+fn store_fields(bucket: &StoreBucket) -> Vec<SExp> {
+    vec![
+        number("size", bucket.context.size),
+        boolean("dest_is_output", bucket.dest_is_output),
+        SExp::field("dest_address", vec![address_to_sexp(&bucket.dest_address_type)]),
+        SExp::field("dest", vec![location_to_sexp(&bucket.dest)]),
+        single("src", &bucket.src),
+    ]
+}
+
+fn compute_fields(bucket: &ComputeBucket) -> Vec<SExp> {
+    vec![
+        SExp::field("op", operator_to_sexp(bucket.op)),
+        number("op_aux_no", bucket.op_aux_no),
+        list("stack", &bucket.stack),
+    ]
+}
+
+fn call_fields(bucket: &CallBucket) -> Vec<SExp> {
+    let sizes: Vec<usize> = bucket.argument_types.iter().map(|context| context.size).collect();
+    let return_info = match &bucket.return_info {
+        ReturnType::Intermediate { op_aux_no } => {
+            SExp::field("intermediate", vec![number("op_aux_no", *op_aux_no)])
+        }
+        ReturnType::Final(data) => SExp::field(
+            "final",
+            vec![
+                number("size", data.context.size),
+                boolean("dest_is_output", data.dest_is_output),
+                SExp::field("dest_address", vec![address_to_sexp(&data.dest_address_type)]),
+                SExp::field("dest", vec![location_to_sexp(&data.dest)]),
+            ],
+        ),
+    };
+    vec![
+        string("symbol", &bucket.symbol),
+        numbers("argument_sizes", &sizes),
+        list("arguments", &bucket.arguments),
+        number("arena_size", bucket.arena_size),
+        SExp::field("return", vec![return_info]),
+    ]
+}
+
+fn branch_fields(bucket: &BranchBucket) -> Vec<SExp> {
+    vec![
+        single("cond", &bucket.cond),
+        list("if", &bucket.if_branch),
+        list("else", &bucket.else_branch),
+    ]
+}
+
+fn return_fields(bucket: &ReturnBucket) -> Vec<SExp> {
+    vec![number("with_size", bucket.with_size), single("value", &bucket.value)]
+}
+
+fn log_fields(bucket: &LogBucket) -> Vec<SExp> {
+    let args = bucket
+        .argsprint
+        .iter()
+        .map(|arg| match arg {
+            LogBucketArg::LogExp(exp) => single("exp", exp),
+            LogBucketArg::LogStr(id) => number("str", *id),
+        })
+        .collect();
+    vec![SExp::field("args", args)]
+}
+
+fn loop_fields(bucket: &LoopBucket) -> Vec<SExp> {
+    vec![single("condition", &bucket.continue_condition), list("body", &bucket.body)]
+}
+
+fn create_cmp_fields(bucket: &CreateCmpBucket) -> Vec<SExp> {
+    let positions = bucket
+        .defined_positions
+        .iter()
+        .map(|(position, parallel)| {
+            SExp::field("position", vec![SExp::atom(position), SExp::atom(parallel)])
+        })
+        .collect();
+    vec![
+        number("template_id", bucket.template_id),
+        number("cmp_unique_id", bucket.cmp_unique_id),
+        string("symbol", &bucket.symbol),
+        single("sub_cmp_id", &bucket.sub_cmp_id),
+        string("name_subcomponent", &bucket.name_subcomponent),
+        SExp::field("defined_positions", positions),
+        boolean(
+            "is_part_mixed_array_not_uniform_parallel",
+            bucket.is_part_mixed_array_not_uniform_parallel,
+        ),
+        SExp::field("uniform_parallel", vec![optional_bool(bucket.uniform_parallel)]),
+        numbers("dimensions", &bucket.dimensions),
+        number("signal_offset", bucket.signal_offset),
+        number("signal_offset_jump", bucket.signal_offset_jump),
+        number("component_offset", bucket.component_offset),
+        number("component_offset_jump", bucket.component_offset_jump),
+        number("number_of_cmp", bucket.number_of_cmp),
+        boolean("has_inputs", bucket.has_inputs),
+    ]
+}
+
+fn address_to_sexp(address: &AddressType) -> SExp {
+    match address {
+        AddressType::Variable => SExp::atom("variable"),
+        AddressType::Signal => SExp::atom("signal"),
+        AddressType::SubcmpSignal {
+            cmp_address,
+            uniform_parallel_value,
+            is_output,
+            input_information,
+        } => {
+            let input_information = match input_information {
+                InputInformation::NoInput => vec![SExp::atom("no_input")],
+                InputInformation::Input { status } => {
+                    let status = match status {
+                        StatusInput::Last => "last",
+                        StatusInput::NoLast => "no_last",
+                        StatusInput::Unknown => "unknown",
+                    };
+                    vec![SExp::atom("input"), SExp::atom(status)]
+                }
+            };
+            SExp::field(
+                "subcmp_signal",
+                vec![
+                    single("cmp_address", cmp_address),
+                    SExp::field(
+                        "uniform_parallel_value",
+                        vec![optional_bool(*uniform_parallel_value)],
+                    ),
+                    boolean("is_output", *is_output),
+                    SExp::field("input_information", input_information),
+                ],
+            )
+        }
+    }
+}
+
+fn location_to_sexp(location: &LocationRule) -> SExp {
+    match location {
+        LocationRule::Indexed { location, template_header } => {
+            let header =
+                template_header.as_ref().map_or(SExp::atom("none"), |header| SExp::string(header));
+            SExp::field(
+                "indexed",
+                vec![single("location", location), SExp::field("template_header", vec![header])],
+            )
+        }
+        LocationRule::Mapped { signal_code, indexes } => SExp::field(
+            "mapped",
+            vec![number("signal_code", *signal_code), list("indexes", indexes)],
+        ),
+    }
+}
+
+fn operator_to_sexp(op: OperatorType) -> Vec<SExp> {
+    use OperatorType::*;
+    let name = match op {
+        Eq(n) => return vec![SExp::atom("eq"), SExp::atom(n)],
+        Mul => "mul",
+        Div => "div",
+        Add => "add",
+        Sub => "sub",
+        Pow => "pow",
+        IntDiv => "int_div",
+        Mod => "mod",
+        ShiftL => "shift_l",
+        ShiftR => "shift_r",
+        LesserEq => "lesser_eq",
+        GreaterEq => "greater_eq",
+        Lesser => "lesser",
+        Greater => "greater",
+        NotEq => "not_eq",
+        BoolOr => "bool_or",
+        BoolAnd => "bool_and",
+        BitOr => "bit_or",
+        BitAnd => "bit_and",
+        BitXor => "bit_xor",
+        PrefixSub => "prefix_sub",
+        BoolNot => "bool_not",
+        Complement => "complement",
+        ToAddress => "to_address",
+        MulAddress => "mul_address",
+        AddAddress => "add_address",
+    };
+    vec![SExp::atom(name)]
+}
+
+fn optional_bool(value: Option<bool>) -> SExp {
+    value.map_or(SExp::atom("none"), SExp::atom)
+}
+
+fn number(key: &str, value: usize) -> SExp {
+    SExp::field(key, vec![SExp::atom(value)])
+}
+
+fn numbers(key: &str, values: &[usize]) -> SExp {
+    SExp::field(key, values.iter().map(SExp::atom).collect())
+}
+
+fn boolean(key: &str, value: bool) -> SExp {
+    SExp::field(key, vec![SExp::atom(value)])
+}
+
+fn string(key: &str, value: &str) -> SExp {
+    SExp::field(key, vec![SExp::string(value)])
+}
+
+fn single(key: &str, instruction: &Instruction) -> SExp {
+    SExp::field(key, vec![instruction_to_sexp(instruction)])
+}
+
+fn list(key: &str, instructions: &[InstructionPointer]) -> SExp {
+    SExp::field(key, instructions.iter().map(|i| instruction_to_sexp(i)).collect())
+}
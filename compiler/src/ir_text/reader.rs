@@ -0,0 +1,534 @@
+use super::sexp::{ReadError, ReadResult, SExp};
+use crate::circuit_design::function::FunctionCodeInfo;
+use crate::circuit_design::template::{SharedRun, TemplateCodeInfo};
+use crate::hir::very_concrete_program::Param;
+use crate::intermediate_representation::ir_interface::*;
+use std::slice::Iter;
+
+// Fields of a list of the form (tag (key values...)*). The keys must
+// appear exactly in the order the printer writes them.
+struct Fields<'a> {
+    tag: &'a str,
+    line: usize,
+    items: Iter<'a, SExp>,
+}
+
+impl<'a> Fields<'a> {
+    fn open(sexp: &'a SExp) -> ReadResult<Fields<'a>> {
+        match sexp {
+            SExp::List(line, items) => match items.first() {
+                Some(SExp::Atom(tag)) => Ok(Fields { tag, line: *line, items: items[1..].iter() }),
+                _ => Err(ReadError::new(*line, "expected a list starting with a name".to_string())),
+            },
+            _ => Err(ReadError::new(0, format!("expected a list, found {}", describe(sexp)))),
+        }
+    }
+
+    fn expect(sexp: &'a SExp, tag: &str) -> ReadResult<Fields<'a>> {
+        let fields = Fields::open(sexp)?;
+        if fields.tag != tag {
+            return Err(fields.error(format!("expected ({} ...), found ({} ...)", tag, fields.tag)));
+        }
+        Ok(fields)
+    }
+
+    fn error(&self, message: String) -> ReadError {
+        ReadError::new(self.line, message)
+    }
+
+    fn field(&mut self, key: &str) -> ReadResult<(usize, &'a [SExp])> {
+        let next = self.items.next();
+        let (line, items) = match next {
+            Some(SExp::List(line, items)) => (*line, items),
+            _ => return Err(self.error(format!("({} ...) is missing the field {}", self.tag, key))),
+        };
+        match items.first() {
+            Some(SExp::Atom(name)) if name == key => Ok((line, &items[1..])),
+            _ => Err(ReadError::new(
+                line,
+                format!("expected the field {} of ({} ...)", key, self.tag),
+            )),
+        }
+    }
+
+    fn single(&mut self, key: &str) -> ReadResult<(usize, &'a SExp)> {
+        let (line, values) = self.field(key)?;
+        if values.len() != 1 {
+            return Err(ReadError::new(line, format!("the field {} expects a single value", key)));
+        }
+        Ok((line, &values[0]))
+    }
+
+    fn number(&mut self, key: &str) -> ReadResult<usize> {
+        let (line, value) = self.single(key)?;
+        read_number(line, value)
+    }
+
+    fn numbers(&mut self, key: &str) -> ReadResult<Vec<usize>> {
+        let (line, values) = self.field(key)?;
+        values.iter().map(|value| read_number(line, value)).collect()
+    }
+
+    fn boolean(&mut self, key: &str) -> ReadResult<bool> {
+        let (line, value) = self.single(key)?;
+        read_bool(line, value)
+    }
+
+    fn optional_bool(&mut self, key: &str) -> ReadResult<Option<bool>> {
+        let (line, value) = self.single(key)?;
+        match value {
+            SExp::Atom(atom) if atom == "none" => Ok(None),
+            _ => read_bool(line, value).map(Some),
+        }
+    }
+
+    fn string(&mut self, key: &str) -> ReadResult<String> {
+        let (line, value) = self.single(key)?;
+        read_string(line, value)
+    }
+
+    fn instruction(&mut self, key: &str) -> ReadResult<InstructionPointer> {
+        let (_, value) = self.single(key)?;
+        read_instruction(value)
+    }
+
+    fn instructions(&mut self, key: &str) -> ReadResult<InstructionList> {
+        let (_, values) = self.field(key)?;
+        values.iter().map(read_instruction).collect()
+    }
+
+    fn finish(mut self) -> ReadResult<()> {
+        match self.items.next() {
+            None => Ok(()),
+            Some(extra) => {
+                Err(self.error(format!("unexpected {} in ({} ...)", describe(extra), self.tag)))
+            }
+        }
+    }
+}
+
+fn describe(sexp: &SExp) -> String {
+    match sexp {
+        SExp::Atom(atom) => format!("'{}'", atom),
+        SExp::Str(string) => format!("the string \"{}\"", string),
+        SExp::List(..) => "a list".to_string(),
+    }
+}
+
+fn read_number(line: usize, value: &SExp) -> ReadResult<usize> {
+    match value {
+        SExp::Atom(atom) => atom
+            .parse()
+            .map_err(|_err| ReadError::new(line, format!("expected a number, found '{}'", atom))),
+        _ => Err(ReadError::new(line, format!("expected a number, found {}", describe(value)))),
+    }
+}
+
+fn read_bool(line: usize, value: &SExp) -> ReadResult<bool> {
+    match value {
+        SExp::Atom(atom) if atom == "true" => Ok(true),
+        SExp::Atom(atom) if atom == "false" => Ok(false),
+        _ => {
+            Err(ReadError::new(line, format!("expected true or false, found {}", describe(value))))
+        }
+    }
+}
+
+fn read_string(line: usize, value: &SExp) -> ReadResult<String> {
+    match value {
+        SExp::Str(string) => Ok(string.clone()),
+        _ => Err(ReadError::new(line, format!("expected a string, found {}", describe(value)))),
+    }
+}
+
+fn read_atom(line: usize, value: &SExp) -> ReadResult<&str> {
+    match value {
+        SExp::Atom(atom) => Ok(atom),
+        _ => Err(ReadError::new(line, format!("expected a name, found {}", describe(value)))),
+    }
+}
+
+pub fn read_template(sexp: &SExp) -> ReadResult<TemplateCodeInfo> {
+    let mut fields = Fields::expect(sexp, "template")?;
+    let template = TemplateCodeInfo {
+        id: fields.number("id")?,
+        header: fields.string("header")?,
+        name: fields.string("name")?,
+        is_parallel: fields.boolean("is_parallel")?,
+        is_parallel_component: fields.boolean("is_parallel_component")?,
+        is_not_parallel_component: fields.boolean("is_not_parallel_component")?,
+        has_parallel_sub_cmp: fields.boolean("has_parallel_sub_cmp")?,
+        number_of_inputs: fields.number("number_of_inputs")?,
+        number_of_outputs: fields.number("number_of_outputs")?,
+        number_of_intermediates: fields.number("number_of_intermediates")?,
+        number_of_components: fields.number("number_of_components")?,
+        var_stack_depth: fields.number("var_stack_depth")?,
+        expression_stack_depth: fields.number("expression_stack_depth")?,
+        signal_stack_depth: fields.number("signal_stack_depth")?,
+        shared_run: read_shared_run(&mut fields)?,
+        body: fields.instructions("body")?,
+    };
+    fields.finish()?;
+    Ok(template)
+}
+
+fn read_shared_run(fields: &mut Fields) -> ReadResult<Option<SharedRun>> {
+    let (line, values) = fields.field("shared_run")?;
+    match values {
+        [SExp::Atom(atom)] if atom == "none" => Ok(None),
+        [header, first_constant] => Ok(Some(SharedRun {
+            header: read_string(line, header)?,
+            first_constant: read_number(line, first_constant)?,
+        })),
+        _ => Err(ReadError::new(
+            line,
+            "the field shared_run expects none or a header and a constant".to_string(),
+        )),
+    }
+}
+
+pub fn read_function(sexp: &SExp) -> ReadResult<FunctionCodeInfo> {
+    let mut fields = Fields::expect(sexp, "function")?;
+    let header = fields.string("header")?;
+    let name = fields.string("name")?;
+    let (_, param_list) = fields.field("params")?;
+    let mut params = Vec::new();
+    for param in param_list {
+        let mut param_fields = Fields::expect(param, "param")?;
+        let line = param_fields.line;
+        let values: Vec<&SExp> = param_fields.items.by_ref().collect();
+        let name = match values.first() {
+            Some(value) => read_string(line, value)?,
+            None => {
+                return Err(ReadError::new(line, "(param ...) is missing its name".to_string()))
+            }
+        };
+        let length =
+            values[1..].iter().map(|value| read_number(line, value)).collect::<ReadResult<_>>()?;
+        params.push(Param { name, length });
+    }
+    let function = FunctionCodeInfo {
+        header,
+        name,
+        params,
+        returns: fields.numbers("returns")?,
+        max_number_of_vars: fields.number("max_number_of_vars")?,
+        max_number_of_ops_in_expression: fields.number("max_number_of_ops_in_expression")?,
+        body: fields.instructions("body")?,
+    };
+    fields.finish()?;
+    Ok(function)
+}
+
+const INSTRUCTIONS: [&str; 11] = [
+    "value",
+    "load",
+    "store",
+    "compute",
+    "call",
+    "branch",
+    "return",
+    "assert",
+    "log",
+    "loop",
+    "create_cmp",
+];
+
+pub fn read_instruction(sexp: &SExp) -> ReadResult<InstructionPointer> {
+    let mut fields = Fields::open(sexp)?;
+    if !INSTRUCTIONS.contains(&fields.tag) {
+        return Err(fields.error(format!("unknown instruction ({} ...)", fields.tag)));
+    }
+    let line = fields.number("line")?;
+    let message_id = fields.number("message")?;
+    let instruction = match fields.tag {
+        "value" => {
+            let (parse_line, parse_as) = fields.single("parse_as")?;
+            let parse_as = match read_atom(parse_line, parse_as)? {
+                "u32" => ValueType::U32,
+                "bigint" => ValueType::BigInt,
+                "instance" => ValueType::InstanceConstant,
+                other => {
+                    return Err(ReadError::new(
+                        parse_line,
+                        format!("unknown value type '{}'", other),
+                    ))
+                }
+            };
+            ValueBucket {
+                line,
+                message_id,
+                parse_as,
+                op_aux_no: fields.number("op_aux_no")?,
+                value: fields.number("value")?,
+            }
+            .into_instruction()
+        }
+        "load" => LoadBucket {
+            line,
+            message_id,
+            context: InstrContext { size: fields.number("size")? },
+            address_type: read_address(&mut fields, "address")?,
+            src: read_location(&mut fields, "src")?,
+        }
+        .into_instruction(),
+        "store" => StoreBucket {
+            line,
+            message_id,
+            context: InstrContext { size: fields.number("size")? },
+            dest_is_output: fields.boolean("dest_is_output")?,
+            dest_address_type: read_address(&mut fields, "dest_address")?,
+            dest: read_location(&mut fields, "dest")?,
+            src: fields.instruction("src")?,
+        }
+        .into_instruction(),
+        "compute" => {
+            let (op_line, op) = fields.field("op")?;
+            ComputeBucket {
+                line,
+                message_id,
+                op: read_operator(op_line, op)?,
+                op_aux_no: fields.number("op_aux_no")?,
+                stack: fields.instructions("stack")?,
+            }
+            .into_instruction()
+        }
+        "call" => CallBucket {
+            line,
+            message_id,
+            symbol: fields.string("symbol")?,
+            argument_types: fields
+                .numbers("argument_sizes")?
+                .into_iter()
+                .map(|size| InstrContext { size })
+                .collect(),
+            arguments: fields.instructions("arguments")?,
+            arena_size: fields.number("arena_size")?,
+            return_info: read_return_type(&mut fields)?,
+        }
+        .into_instruction(),
+        "branch" => BranchBucket {
+            line,
+            message_id,
+            cond: fields.instruction("cond")?,
+            if_branch: fields.instructions("if")?,
+            else_branch: fields.instructions("else")?,
+        }
+        .into_instruction(),
+        "return" => ReturnBucket {
+            line,
+            message_id,
+            with_size: fields.number("with_size")?,
+            value: fields.instruction("value")?,
+        }
+        .into_instruction(),
+        "assert" => AssertBucket { line, message_id, evaluate: fields.instruction("evaluate")? }
+            .into_instruction(),
+        "log" => {
+            let (_, args) = fields.field("args")?;
+            let mut argsprint = Vec::new();
+            for arg in args {
+                let mut arg_fields = Fields::open(arg)?;
+                let arg = match arg_fields.tag {
+                    "exp" => {
+                        let items: Vec<&SExp> = arg_fields.items.by_ref().collect();
+                        if items.len() != 1 {
+                            return Err(arg_fields
+                                .error("(exp ...) expects a single instruction".to_string()));
+                        }
+                        LogBucketArg::LogExp(read_instruction(items[0])?)
+                    }
+                    "str" => {
+                        let items: Vec<&SExp> = arg_fields.items.by_ref().collect();
+                        if items.len() != 1 {
+                            return Err(
+                                arg_fields.error("(str ...) expects a single number".to_string())
+                            );
+                        }
+                        LogBucketArg::LogStr(read_number(arg_fields.line, items[0])?)
+                    }
+                    other => {
+                        return Err(
+                            arg_fields.error(format!("unknown log argument ({} ...)", other))
+                        )
+                    }
+                };
+                arg_fields.finish()?;
+                argsprint.push(arg);
+            }
+            LogBucket { line, message_id, argsprint }.into_instruction()
+        }
+        "loop" => LoopBucket {
+            line,
+            message_id,
+            continue_condition: fields.instruction("condition")?,
+            body: fields.instructions("body")?,
+        }
+        .into_instruction(),
+        "create_cmp" => {
+            let template_id = fields.number("template_id")?;
+            let cmp_unique_id = fields.number("cmp_unique_id")?;
+            let symbol = fields.string("symbol")?;
+            let sub_cmp_id = fields.instruction("sub_cmp_id")?;
+            let name_subcomponent = fields.string("name_subcomponent")?;
+            let (_, positions) = fields.field("defined_positions")?;
+            let mut defined_positions = Vec::new();
+            for position in positions {
+                let mut position_fields = Fields::expect(position, "position")?;
+                let line = position_fields.line;
+                let values: Vec<&SExp> = position_fields.items.by_ref().collect();
+                if values.len() != 2 {
+                    return Err(ReadError::new(
+                        line,
+                        "(position ...) expects a number and a boolean".to_string(),
+                    ));
+                }
+                defined_positions
+                    .push((read_number(line, values[0])?, read_bool(line, values[1])?));
+            }
+            CreateCmpBucket {
+                line,
+                message_id,
+                template_id,
+                cmp_unique_id,
+                symbol,
+                sub_cmp_id,
+                name_subcomponent,
+                defined_positions,
+                is_part_mixed_array_not_uniform_parallel: fields
+                    .boolean("is_part_mixed_array_not_uniform_parallel")?,
+                uniform_parallel: fields.optional_bool("uniform_parallel")?,
+                dimensions: fields.numbers("dimensions")?,
+                signal_offset: fields.number("signal_offset")?,
+                signal_offset_jump: fields.number("signal_offset_jump")?,
+                component_offset: fields.number("component_offset")?,
+                component_offset_jump: fields.number("component_offset_jump")?,
+                number_of_cmp: fields.number("number_of_cmp")?,
+                has_inputs: fields.boolean("has_inputs")?,
+            }
+            .into_instruction()
+        }
+        _ => unreachable!(),
+    };
+    fields.finish()?;
+    Ok(instruction.allocate())
+}
+
+fn read_return_type(fields: &mut Fields) -> ReadResult<ReturnType> {
+    let (_, value) = fields.single("return")?;
+    let mut return_fields = Fields::open(value)?;
+    let return_type = match return_fields.tag {
+        "intermediate" => {
+            ReturnType::Intermediate { op_aux_no: return_fields.number("op_aux_no")? }
+        }
+        "final" => ReturnType::Final(FinalData {
+            context: InstrContext { size: return_fields.number("size")? },
+            dest_is_output: return_fields.boolean("dest_is_output")?,
+            dest_address_type: read_address(&mut return_fields, "dest_address")?,
+            dest: read_location(&mut return_fields, "dest")?,
+        }),
+        other => return Err(return_fields.error(format!("unknown return type ({} ...)", other))),
+    };
+    return_fields.finish()?;
+    Ok(return_type)
+}
+
+fn read_address(fields: &mut Fields, key: &str) -> ReadResult<AddressType> {
+    let (line, value) = fields.single(key)?;
+    if let SExp::Atom(atom) = value {
+        return match atom.as_str() {
+            "variable" => Ok(AddressType::Variable),
+            "signal" => Ok(AddressType::Signal),
+            _ => Err(ReadError::new(line, format!("unknown address type '{}'", atom))),
+        };
+    }
+    let mut address_fields = Fields::expect(value, "subcmp_signal")?;
+    let cmp_address = address_fields.instruction("cmp_address")?;
+    let uniform_parallel_value = address_fields.optional_bool("uniform_parallel_value")?;
+    let is_output = address_fields.boolean("is_output")?;
+    let (info_line, info) = address_fields.field("input_information")?;
+    let names =
+        info.iter().map(|value| read_atom(info_line, value)).collect::<ReadResult<Vec<&str>>>()?;
+    let input_information = match names.as_slice() {
+        ["no_input"] => InputInformation::NoInput,
+        ["input", "last"] => InputInformation::Input { status: StatusInput::Last },
+        ["input", "no_last"] => InputInformation::Input { status: StatusInput::NoLast },
+        ["input", "unknown"] => InputInformation::Input { status: StatusInput::Unknown },
+        _ => return Err(ReadError::new(info_line, "invalid input information".to_string())),
+    };
+    address_fields.finish()?;
+    Ok(AddressType::SubcmpSignal {
+        cmp_address,
+        uniform_parallel_value,
+        is_output,
+        input_information,
+    })
+}
+
+fn read_location(fields: &mut Fields, key: &str) -> ReadResult<LocationRule> {
+    let (_, value) = fields.single(key)?;
+    let mut location_fields = Fields::open(value)?;
+    let location = match location_fields.tag {
+        "indexed" => {
+            let location = location_fields.instruction("location")?;
+            let (line, header) = location_fields.single("template_header")?;
+            let template_header = match header {
+                SExp::Atom(atom) if atom == "none" => None,
+                _ => Some(read_string(line, header)?),
+            };
+            LocationRule::Indexed { location, template_header }
+        }
+        "mapped" => LocationRule::Mapped {
+            signal_code: location_fields.number("signal_code")?,
+            indexes: location_fields.instructions("indexes")?,
+        },
+        other => {
+            return Err(location_fields.error(format!("unknown location rule ({} ...)", other)))
+        }
+    };
+    location_fields.finish()?;
+    Ok(location)
+}
+
+fn read_operator(line: usize, values: &[SExp]) -> ReadResult<OperatorType> {
+    use OperatorType::*;
+    let name = match values.first() {
+        Some(value) => read_atom(line, value)?,
+        None => return Err(ReadError::new(line, "missing operator".to_string())),
+    };
+    if name == "eq" && values.len() == 2 {
+        return Ok(Eq(read_number(line, &values[1])?));
+    }
+    if values.len() != 1 {
+        return Err(ReadError::new(line, format!("unexpected arguments of the operator {}", name)));
+    }
+    let op = match name {
+        "mul" => Mul,
+        "div" => Div,
+        "add" => Add,
+        "sub" => Sub,
+        "pow" => Pow,
+        "int_div" => IntDiv,
+        "mod" => Mod,
+        "shift_l" => ShiftL,
+        "shift_r" => ShiftR,
+        "lesser_eq" => LesserEq,
+        "greater_eq" => GreaterEq,
+        "lesser" => Lesser,
+        "greater" => Greater,
+        "not_eq" => NotEq,
+        "bool_or" => BoolOr,
+        "bool_and" => BoolAnd,
+        "bit_or" => BitOr,
+        "bit_and" => BitAnd,
+        "bit_xor" => BitXor,
+        "prefix_sub" => PrefixSub,
+        "bool_not" => BoolNot,
+        "complement" => Complement,
+        "to_address" => ToAddress,
+        "mul_address" => MulAddress,
+        "add_address" => AddAddress,
+        _ => return Err(ReadError::new(line, format!("unknown operator '{}'", name))),
+    };
+    Ok(op)
+}
@@ -0,0 +1,177 @@
+use std::fmt::Write;
+
+// Lines wider than this are split, one element per line
+const MAX_WIDTH: usize = 100;
+
+// Generic s-expression the textual IR is made of. Lists remember the line
+// of the text where they were opened so the reader can point at errors.
+#[derive(Clone, PartialEq, Debug)]
+pub enum SExp {
+    Atom(String),
+    Str(String),
+    List(usize, Vec<SExp>),
+}
+
+impl SExp {
+    pub fn atom<T: ToString>(value: T) -> SExp {
+        SExp::Atom(value.to_string())
+    }
+    pub fn string(value: &str) -> SExp {
+        SExp::Str(value.to_string())
+    }
+    pub fn list(items: Vec<SExp>) -> SExp {
+        SExp::List(0, items)
+    }
+    // (key items...)
+    pub fn field(key: &str, mut items: Vec<SExp>) -> SExp {
+        items.insert(0, SExp::atom(key));
+        SExp::list(items)
+    }
+
+    pub fn pretty(&self) -> String {
+        let mut out = String::new();
+        self.write_pretty(0, &mut out);
+        out.push('\n');
+        out
+    }
+
+    fn flat(&self) -> String {
+        match self {
+            SExp::Atom(atom) => atom.clone(),
+            SExp::Str(string) => quote(string),
+            SExp::List(_, items) => {
+                let items: Vec<String> = items.iter().map(|item| item.flat()).collect();
+                format!("({})", items.join(" "))
+            }
+        }
+    }
+
+    fn write_pretty(&self, indent: usize, out: &mut String) {
+        let flat = self.flat();
+        let items = match self {
+            SExp::List(_, items) if indent + flat.len() > MAX_WIDTH && items.len() > 1 => items,
+            _ => {
+                out.push_str(&flat);
+                return;
+            }
+        };
+        out.push('(');
+        out.push_str(&items[0].flat());
+        for item in &items[1..] {
+            write!(out, "\n{:width$}", "", width = indent + 2).unwrap();
+            item.write_pretty(indent + 2, out);
+        }
+        out.push(')');
+    }
+}
+
+fn quote(string: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in string.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            _ => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ReadError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl ReadError {
+    pub fn new(line: usize, message: String) -> ReadError {
+        ReadError { line, message }
+    }
+}
+
+impl std::fmt::Display for ReadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+pub type ReadResult<T> = Result<T, ReadError>;
+
+// Reads every s-expression of the text. Comments start with ';' and run
+// until the end of the line.
+pub fn parse(text: &str) -> ReadResult<Vec<SExp>> {
+    let mut chars = text.chars().peekable();
+    let mut line = 1;
+    // lists being built together with the line they were opened in
+    let mut open: Vec<(usize, Vec<SExp>)> = vec![(0, Vec::new())];
+    while let Some(c) = chars.next() {
+        match c {
+            '\n' => line += 1,
+            ';' => {
+                while chars.peek().is_some_and(|c| *c != '\n') {
+                    chars.next();
+                }
+            }
+            '(' => open.push((line, Vec::new())),
+            ')' => {
+                if open.len() == 1 {
+                    return Err(ReadError::new(line, "unexpected ')'".to_string()));
+                }
+                let (start, items) = open.pop().unwrap();
+                open.last_mut().unwrap().1.push(SExp::List(start, items));
+            }
+            '"' => {
+                let mut string = String::new();
+                loop {
+                    match chars.next() {
+                        None => {
+                            return Err(ReadError::new(line, "unterminated string".to_string()))
+                        }
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some('n') => string.push('\n'),
+                            Some(escaped @ '"') | Some(escaped @ '\\') => string.push(escaped),
+                            _ => {
+                                return Err(ReadError::new(
+                                    line,
+                                    "invalid escape in string".to_string(),
+                                ))
+                            }
+                        },
+                        Some(c) => {
+                            if c == '\n' {
+                                line += 1;
+                            }
+                            string.push(c)
+                        }
+                    }
+                }
+                open.last_mut().unwrap().1.push(SExp::Str(string));
+            }
+            c if c.is_whitespace() => {}
+            c => {
+                let mut atom = c.to_string();
+                while let Some(next) = chars.peek() {
+                    if next.is_whitespace()
+                        || *next == '('
+                        || *next == ')'
+                        || *next == '"'
+                        || *next == ';'
+                    {
+                        break;
+                    }
+                    atom.push(*next);
+                    chars.next();
+                }
+                open.last_mut().unwrap().1.push(SExp::Atom(atom));
+            }
+        }
+    }
+    if open.len() > 1 {
+        let (start, _) = open.pop().unwrap();
+        return Err(ReadError::new(start, "unclosed '('".to_string()));
+    }
+    Ok(open.pop().unwrap().1)
+}
@@ -2,6 +2,7 @@
 mod circuit_design;
 mod intermediate_representation;
 mod ir_processing;
+mod ir_text;
 pub extern crate num_bigint_dig as num_bigint;
 pub extern crate num_traits;
 
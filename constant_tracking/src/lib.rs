@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::convert::TryInto;
 use std::hash::Hash;
 
 pub type CID = usize;
@@ -8,6 +9,28 @@ where
 {
     lookup: HashMap<C, CID>,
     constants: Vec<C>,
+    // Id of the first constant of every block
+    blocks: HashMap<Vec<C>, CID>,
+}
+
+// Constants that can be kept in the binary form of a tracker
+pub trait BinaryConstant: Sized {
+    fn write_bytes(&self, out: &mut Vec<u8>);
+    // The constant together with the number of bytes it takes
+    fn read_bytes(bytes: &[u8]) -> Option<(Self, usize)>;
+}
+
+impl BinaryConstant for String {
+    fn write_bytes(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&(self.len() as u32).to_le_bytes());
+        out.extend_from_slice(self.as_bytes());
+    }
+
+    fn read_bytes(bytes: &[u8]) -> Option<(Self, usize)> {
+        let length = u32::from_le_bytes(bytes.get(..4)?.try_into().ok()?) as usize;
+        let value = String::from_utf8(bytes.get(4..4 + length)?.to_vec()).ok()?;
+        Some((value, 4 + length))
+    }
 }
 
 impl<C> ConstantTracker<C>
@@ -15,7 +38,7 @@ where
     C: Eq + Hash + Clone,
 {
     pub fn new() -> ConstantTracker<C> {
-        ConstantTracker { lookup: HashMap::new(), constants: Vec::new() }
+        ConstantTracker { lookup: HashMap::new(), constants: Vec::new(), blocks: HashMap::new() }
     }
 
     pub fn get_id(&self, constant: &C) -> Option<CID> {
@@ -33,6 +56,23 @@ where
         }
     }
 
+    // Adds the constants one after the other, so that they can be read from
+    // the id of the first one, which is returned. An equal block added before
+    // is reused. Merging or compacting the tracker only keeps single constants.
+    pub fn insert_block(&mut self, block: Vec<C>) -> CID {
+        if let Some(id) = self.blocks.get(&block) {
+            return *id;
+        }
+        let first = self.constants.len();
+        for constant in &block {
+            let id = self.constants.len();
+            self.lookup.entry(constant.clone()).or_insert(id);
+            self.constants.push(constant.clone());
+        }
+        self.blocks.insert(block, first);
+        first
+    }
+
     pub fn get_constant(&self, id: CID) -> Option<&C> {
         if id < self.constants.len() {
             Some(&self.constants[id])
@@ -44,4 +84,109 @@ where
     pub fn next_id(&self) -> CID {
         self.constants.len()
     }
+
+    // Adds the constants of other, in the order of their ids. The result
+    // maps every id of other to the id of the same constant in self.
+    pub fn merge(&mut self, other: ConstantTracker<C>) -> Vec<CID> {
+        other.constants.into_iter().map(|constant| self.insert(constant)).collect()
+    }
+
+    // Removes the constants whose id is not kept. The remaining ones keep
+    // their order and the result maps every old id to its new one.
+    pub fn compact<F>(&mut self, mut keep: F) -> Vec<Option<CID>>
+    where
+        F: FnMut(CID) -> bool,
+    {
+        let old = std::mem::take(&mut self.constants);
+        self.lookup.clear();
+        self.blocks.clear();
+        let mut remap = Vec::with_capacity(old.len());
+        for (id, constant) in old.into_iter().enumerate() {
+            if keep(id) {
+                remap.push(Some(self.insert(constant)));
+            } else {
+                remap.push(None);
+            }
+        }
+        remap
+    }
+}
+
+// The binary form is the number of constants followed by the constants in the
+// order of their ids, so a tracker is read back with the same ids
+impl<C> ConstantTracker<C>
+where
+    C: Eq + Hash + Clone + BinaryConstant,
+{
+    pub fn write_bytes(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&(self.constants.len() as u64).to_le_bytes());
+        for constant in &self.constants {
+            constant.write_bytes(out);
+        }
+    }
+
+    // The tracker together with the number of bytes it takes
+    pub fn read_bytes(bytes: &[u8]) -> Option<(ConstantTracker<C>, usize)> {
+        let number = u64::from_le_bytes(bytes.get(..8)?.try_into().ok()?);
+        let mut tracker = ConstantTracker::new();
+        let mut position = 8;
+        for _ in 0..number {
+            let (constant, used) = C::read_bytes(bytes.get(position..)?)?;
+            position += used;
+            if tracker.insert(constant) + 1 != tracker.next_id() {
+                return None;
+            }
+        }
+        Some((tracker, position))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tracker(constants: &[&str]) -> ConstantTracker<String> {
+        let mut tracker = ConstantTracker::new();
+        for constant in constants {
+            tracker.insert(constant.to_string());
+        }
+        tracker
+    }
+
+    #[test]
+    fn merge_compact_and_binary_form_keep_the_ids_consistent() {
+        let mut left = tracker(&["1", "2", "3"]);
+        let right = tracker(&["3", "4", "1"]);
+        assert_eq!(left.merge(right), vec![2, 3, 0]);
+        assert_eq!(left.get_constant(3).unwrap(), "4");
+
+        let remap = left.compact(|id| id != 1);
+        assert_eq!(remap, vec![Some(0), None, Some(1), Some(2)]);
+        assert_eq!(left.get_id(&"4".to_string()), Some(2));
+        assert_eq!(left.get_id(&"2".to_string()), None);
+
+        let mut bytes = vec![];
+        left.write_bytes(&mut bytes);
+        let (read, used) = ConstantTracker::<String>::read_bytes(&bytes).unwrap();
+        assert_eq!(used, bytes.len());
+        assert_eq!(read.constants, left.constants);
+        assert_eq!(read.get_id(&"3".to_string()), Some(1));
+        assert!(ConstantTracker::<String>::read_bytes(&bytes[..bytes.len() - 1]).is_none());
+    }
+
+    #[test]
+    fn blocks_keep_their_constants_together() {
+        let mut tracker = tracker(&["1", "2"]);
+        let block = |constants: &[&str]| constants.iter().map(|c| c.to_string()).collect();
+        assert_eq!(tracker.insert_block(block(&["2", "3", "2"])), 2);
+        assert_eq!(tracker.insert_block(block(&["2", "3", "2"])), 2);
+        assert_eq!(tracker.insert_block(block(&["3", "2"])), 5);
+        let constants: Vec<&str> = (0..tracker.next_id())
+            .map(|id| tracker.get_constant(id).unwrap().as_str())
+            .collect();
+        assert_eq!(constants, ["1", "2", "2", "3", "2", "3", "2"]);
+        // The single constants keep their first id
+        assert_eq!(tracker.insert("2".to_string()), 1);
+        assert_eq!(tracker.insert("3".to_string()), 3);
+    }
 }
@@ -19,6 +19,7 @@ use super::execution_data::{ExecutedProgram, ExecutedTemplate, PreExecutedTempla
 use super::{
     ast::*, ArithmeticError, FileID, ProgramArchive, Report, ReportCode, ReportCollection
 };
+use circom_algebra::field::Field;
 use circom_algebra::num_bigint::BigInt;
 use std::collections::{HashMap, BTreeMap};
 use std::mem;
@@ -36,7 +37,7 @@ struct RuntimeInformation {
     pub block_type: BlockType,
     pub analysis: Analysis,
     pub public_inputs: Vec<String>,
-    pub constants: UsefulConstants,
+    pub field: Field,
     pub call_trace: Vec<String>,
     pub current_file: FileID,
     pub runtime_errors: ReportCollection,
@@ -46,12 +47,13 @@ struct RuntimeInformation {
 }
 impl RuntimeInformation {
     pub fn new(current_file: FileID, id_max: usize, prime: &String) -> RuntimeInformation {
+        let field = Field::new(UsefulConstants::new(prime).get_p());
         RuntimeInformation {
             current_file,
             block_type: BlockType::Known,
             analysis: Analysis::new(id_max),
             public_inputs: vec![],
-            constants: UsefulConstants::new(prime),
+            field,
             call_trace: Vec::new(),
             runtime_errors: ReportCollection::new(),
             environment: ExecutionEnvironment::new(),
@@ -300,11 +302,10 @@ fn execute_statement(
                                     &runtime.call_trace,
                                 )?;
                             } else {
-                                let p = runtime.constants.get_p().clone();
                                 let symbol = AExpr::Signal { symbol: full_symbol };
-                                let expr = AExpr::sub(&symbol, &value_right, &p);
-                                let ctr = AExpr::transform_expression_to_constraint_form(expr, &p).unwrap();
-                                node.add_constraint(ctr);
+                                let expr = AExpr::sub(&symbol, &value_right, &runtime.field);
+                                let ctr = AExpr::transform_expression_to_constraint_form(expr, &runtime.field).unwrap();
+                                node.add_constraint(ctr, meta);
                             }
                         } else if let AssignOp::AssignSignal = op {// needs fix, check case arrays
                             //debug_assert!(possible_constraint.is_some());
@@ -373,7 +374,7 @@ fn execute_statement(
                     AExpr::sub(
                         &value_left, 
                         &value_right, 
-                        &runtime.constants.get_p()
+                        &runtime.field
                     );
                 if possible_non_quadratic.is_nonquadratic() {
                     treat_result_with_execution_error(
@@ -386,11 +387,11 @@ fn execute_statement(
                 let quadratic_expression = possible_non_quadratic;
                 let constraint_expression = AExpr::transform_expression_to_constraint_form(
                     quadratic_expression,
-                    runtime.constants.get_p(),
+                    &runtime.field,
                 )
                 .unwrap();
                 if let Option::Some(node) = actual_node {
-                    node.add_constraint(constraint_expression);
+                    node.add_constraint(constraint_expression, meta);
                 }    
             }
             Option::None
@@ -492,7 +493,7 @@ fn execute_statement(
         Assert { arg, meta, .. } => {
             let f_result = execute_expression(arg, program_archive, runtime, flags)?;
             let arith = safe_unwrap_to_single_arithmetic_expression(f_result, line!());
-            let possible_bool = AExpr::get_boolean_equivalence(&arith, runtime.constants.get_p());
+            let possible_bool = AExpr::get_boolean_equivalence(&arith, &runtime.field);
             let result = match possible_bool {
                 Some(b) if !b => Err(ExecutionError::FalseAssert),
                 Some(b) if b => Ok(None),
@@ -521,7 +522,7 @@ fn execute_statement(
                     )?;
                     let constraint_expression = AExpr::transform_expression_to_constraint_form(
                         value_cell,
-                        runtime.constants.get_p(),
+                        &runtime.field,
                     ).unwrap();
                     if let Option::Some(node) = actual_node {
                         for signal in constraint_expression.take_signals(){
@@ -650,7 +651,7 @@ fn execute_expression(
             let f_cond = execute_expression(cond, program_archive, runtime, flags)?;
             let ae_cond = safe_unwrap_to_single_arithmetic_expression(f_cond, line!());
             let possible_bool_cond =
-                AExpr::get_boolean_equivalence(&ae_cond, runtime.constants.get_p());
+                AExpr::get_boolean_equivalence(&ae_cond, &runtime.field);
             if let Option::Some(bool_cond) = possible_bool_cond {
                 if bool_cond {
                     execute_expression(if_true, program_archive, runtime, flags)?
@@ -681,6 +682,10 @@ fn execute_expression(
         if slice.is_single() && can_be_simplified{
             let value = AExpressionSlice::unwrap_to_single(slice);
             Analysis::computed(&mut runtime.analysis, expr_id, value);
+        } else if !slice.is_single() && expr.is_call() {
+            let (dimensions, values) = slice.destruct();
+            let values = if can_be_simplified { Some(values) } else { None };
+            Analysis::computed_array(&mut runtime.analysis, expr_id, dimensions, values);
         }
     }
     Result::Ok(res)
@@ -1368,7 +1373,7 @@ fn execute_conditional_statement(
     let f_cond = execute_expression(condition, program_archive, runtime, flags)?;
     let ae_cond = safe_unwrap_to_single_arithmetic_expression(f_cond, line!());
     let possible_cond_bool_value =
-        AExpr::get_boolean_equivalence(&ae_cond, runtime.constants.get_p());
+        AExpr::get_boolean_equivalence(&ae_cond, &runtime.field);
     if let Some(cond_bool_value) = possible_cond_bool_value {
         let (ret_value, can_simplify) = match false_case {
             Some(else_stmt) if !cond_bool_value => {
@@ -1924,7 +1929,7 @@ fn execute_infix_op(
     runtime: &mut RuntimeInformation,
 ) -> Result<AExpr, ()> {
     use ExpressionInfixOpcode::*;
-    let field = runtime.constants.get_p();
+    let field = &runtime.field;
     let possible_result = match infix {
         Mul => Result::Ok(AExpr::mul(l_value, r_value, field)),
         Div => AExpr::div(l_value, r_value, field),
@@ -1961,7 +1966,7 @@ fn execute_prefix_op(
     runtime: &mut RuntimeInformation,
 ) -> Result<AExpr, ()> {
     use ExpressionPrefixOpcode::*;
-    let field = runtime.constants.get_p();
+    let field = &runtime.field;
     let result = match prefix_op {
         BoolNot => AExpr::not(value, field),
         Sub => AExpr::prefix_sub(value, field),
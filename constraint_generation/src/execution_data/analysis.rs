@@ -1,15 +1,23 @@
 use circom_algebra::algebra;
 use num_bigint::BigInt;
+use std::collections::HashMap;
 
 type AExpr = algebra::ArithmeticExpression<String>;
+// Dimensions and values, in row-major order, of an array
+type ArrayValue = (Vec<usize>, Vec<BigInt>);
 
 pub struct Analysis {
     reached: Vec<bool>,
-    computed_values: Vec<ValuePOS>,
+    computed_values: Vec<ValuePOS<BigInt>>,
+    computed_arrays: HashMap<usize, ValuePOS<ArrayValue>>,
 }
 impl Analysis {
     pub fn new(id_max: usize) -> Analysis {
-        Analysis { reached: vec![false; id_max], computed_values: vec![ValuePOS::Bottom; id_max] }
+        Analysis {
+            reached: vec![false; id_max],
+            computed_values: vec![ValuePOS::Bottom; id_max],
+            computed_arrays: HashMap::new(),
+        }
     }
 
     pub fn reached(analysis: &mut Analysis, id: usize) {
@@ -35,17 +43,41 @@ impl Analysis {
             _ => None,
         }
     }
+
+    // Arrays returned by a call. Values is None if the call can not be
+    // replaced by its result, for instance because it logs something.
+    pub fn computed_array(analysis: &mut Analysis, id: usize, dimensions: Vec<usize>, values: Option<Vec<AExpr>>) {
+        let numbers: Option<Vec<BigInt>> = values.and_then(|values| {
+            values
+                .into_iter()
+                .map(|value| if let AExpr::Number { value } = value { Some(value) } else { None })
+                .collect()
+        });
+        let new = match numbers {
+            Some(numbers) => ValuePOS::Val((dimensions, numbers)),
+            None => ValuePOS::Top,
+        };
+        let old = analysis.computed_arrays.remove(&id).unwrap_or(ValuePOS::Bottom);
+        analysis.computed_arrays.insert(id, ValuePOS::least_upper_bound(&old, &new));
+    }
+
+    pub fn read_computed_array(analysis: &Analysis, id: usize) -> Option<&ArrayValue> {
+        match analysis.computed_arrays.get(&id) {
+            Some(ValuePOS::Val(v)) => Some(v),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Clone)]
-enum ValuePOS {
+enum ValuePOS<V> {
     Bottom,
-    Val(BigInt),
+    Val(V),
     Top,
 }
 
-impl ValuePOS {
-    pub fn least_upper_bound(l: &ValuePOS, r: &ValuePOS) -> ValuePOS {
+impl<V: Clone + PartialEq> ValuePOS<V> {
+    pub fn least_upper_bound(l: &ValuePOS<V>, r: &ValuePOS<V>) -> ValuePOS<V> {
         use ValuePOS::*;
         match (l, r) {
             (v, Bottom) | (Bottom, v) => v.clone(),
@@ -5,6 +5,7 @@ use super::type_definitions::*;
 use compiler::hir::very_concrete_program::{Stats, VCPConfig, VCP};
 use dag::DAG;
 use program_structure::program_archive::ProgramArchive;
+use program_structure::output_error::OutputResult;
 use program_structure::program_library::error_definition::ReportCollection;
 use std::collections::HashMap;
 
@@ -101,6 +102,14 @@ impl ExecutedProgram {
         node_index
     }
 
+    pub fn generate_hierarchy_dot(&self, output_file: &str) -> OutputResult<()> {
+        super::hierarchy_porting::port_hierarchy_dot(self, output_file)
+    }
+
+    pub fn generate_hierarchy_json(&self, output_file: &str) -> OutputResult<()> {
+        super::hierarchy_porting::port_hierarchy_json(self, output_file)
+    }
+
     pub fn export(mut self, mut program: ProgramArchive, flags: FlagsExecution) -> ExportResult {
         use super::executed_template::templates_in_mixed_arrays;
         fn merge_mixed(org: Vec<bool>, new: Vec<bool>) -> Vec<bool> {
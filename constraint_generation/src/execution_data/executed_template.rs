@@ -1,9 +1,9 @@
 use super::type_definitions::*;
 use circom_algebra::algebra::ArithmeticExpression;
 use compiler::hir::very_concrete_program::*;
-use dag::DAG;
+use dag::{ConstraintLocation, DAG};
 use num_bigint::BigInt;
-use program_structure::ast::{SignalType, Statement};
+use program_structure::ast::{Meta, SignalType, Statement};
 use std::collections::{HashMap, HashSet};
 use crate::execution_data::AExpressionSlice;
 use crate::execution_data::TagInfo;
@@ -69,6 +69,7 @@ pub struct ExecutedTemplate {
     pub intermediates: SignalCollector,
     pub ordered_signals: Vec<String>,
     pub constraints: Vec<Constraint>,
+    pub constraint_locations: Vec<ConstraintLocation>,
     pub components: ComponentCollector,
     pub number_of_components: usize,
     pub public_inputs: HashSet<String>,
@@ -110,6 +111,7 @@ impl ExecutedTemplate {
             intermediates: SignalCollector::new(),
             ordered_signals: Vec::new(),
             constraints: Vec::new(),
+            constraint_locations: Vec::new(),
             components: ComponentCollector::new(),
             number_of_components: 0,
             connexions: Vec::new(),
@@ -178,8 +180,9 @@ impl ExecutedTemplate {
         self.number_of_components += dimensions.iter().fold(1, |p, c| p * (*c));
     }
 
-    pub fn add_constraint(&mut self, constraint: Constraint) {
+    pub fn add_constraint(&mut self, constraint: Constraint, meta: &Meta) {
         self.constraints.push(constraint);
+        self.constraint_locations.push((meta.file_id, meta.location.clone()));
     }
 
     pub fn add_underscored_signal(&mut self, signal: &str) {
@@ -210,6 +213,14 @@ impl ExecutedTemplate {
         &self.intermediates
     }
 
+    pub fn constraints(&self) -> &[Constraint] {
+        &self.constraints
+    }
+
+    pub fn subcomponents(&self) -> Vec<(&str, &SubComponentData)> {
+        self.connexions.iter().map(|cnn| (cnn.full_name.as_str(), &cnn.inspect)).collect()
+    }
+
     pub fn insert_in_dag(&mut self, dag: &mut DAG) {
         let parameters = {
             let mut parameters = vec![];
@@ -286,10 +297,10 @@ impl ExecutedTemplate {
     }
     fn build_constraints(&self, dag: &mut DAG) {
         
-        for c in &self.constraints {
+        for (c, location) in self.constraints.iter().zip(&self.constraint_locations) {
             let correspondence = dag.get_main().unwrap().correspondence();
             let cc = Constraint::apply_correspondence(c, correspondence);
-            dag.add_constraint(cc);
+            dag.add_constraint(cc, location.clone());
         }
         for s in &self.underscored_signals{
             let correspondence = dag.get_main().unwrap().correspondence();
@@ -343,7 +354,8 @@ impl ExecutedTemplate {
             arguments
         }
 
-        let header = format!("{}_{}", self.template_name, instances.len());
+        // names of included namespaces contain a '.' (alias.name)
+        let header = format!("{}_{}", self.template_name.replace('.', "_"), instances.len());
         let clusters = build_clusters(&self, instances);
         let triggers = build_triggers(instances, self.connexions);
         let components = build_components(self.components);
@@ -1,4 +1,5 @@
 use super::analysis::Analysis;
+use num_bigint::BigInt;
 use program_structure::ast::*;
 
 pub fn apply_unused(stmt: &mut Statement, analysis: &Analysis, prime: &String) {
@@ -77,6 +78,13 @@ pub fn apply_computed(stmt: &mut Statement, analysis: &Analysis) {
         Declaration { dimensions, .. } => {
             apply_computed_expr_vec(dimensions, analysis);
         }
+        Substitution { meta, var, access, op: AssignOp::AssignVar, rhe, .. }
+            if known_call_result(analysis, rhe).is_some() =>
+        {
+            apply_computed_to_access(access, analysis);
+            let (_, values) = known_call_result(analysis, rhe).unwrap();
+            *stmt = assign_values(meta, var, access, rhe.get_meta(), values);
+        }
         Substitution { access, rhe, .. } => {
             *rhe = computed_or_original(analysis, rhe);
             apply_computed_expr(rhe, analysis);
@@ -107,6 +115,37 @@ pub fn apply_computed(stmt: &mut Statement, analysis: &Analysis) {
     }
 }
 
+// Arrays returned by calls that give the same values in every execution
+// of the template, so the witness calculator does not compute them again
+fn known_call_result<'a>(analysis: &'a Analysis, expr: &Expression) -> Option<&'a (Vec<usize>, Vec<BigInt>)> {
+    if expr.is_call() {
+        Analysis::read_computed_array(analysis, expr.get_meta().elem_id)
+    } else {
+        None
+    }
+}
+
+// Assigns all the values at once as an inline array of numbers in row-major
+// order, which the compiler copies from a block of constants
+fn assign_values(
+    meta: &Meta,
+    var: &str,
+    access: &[Access],
+    call_meta: &Meta,
+    values: &[BigInt],
+) -> Statement {
+    let number = |value: &BigInt| Expression::Number(call_meta.clone(), value.clone());
+    let values = values.iter().map(number).collect();
+    Statement::Substitution {
+        meta: meta.clone(),
+        var: var.to_string(),
+        access: access.to_vec(),
+        op: AssignOp::AssignVar,
+        rhe: Expression::ArrayInLine { meta: call_meta.clone(), values },
+        is_artificial: false,
+    }
+}
+
 fn apply_computed_stmt_vec(stmts: &mut Vec<Statement>, analysis: &Analysis) {
     for stmt in stmts {
         apply_computed(stmt, analysis);
@@ -192,3 +231,107 @@ fn apply_computed_expr(expr: &mut Expression, analysis: &Analysis) {
         _ => {unreachable!("Anonymous calls should not be reachable at this point."); }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::execute::constraint_execution;
+    use crate::FlagsExecution;
+    use parser::test_project::Project;
+
+    const BN128: &str = "bn128";
+
+    // Code of the only instance of the main template of the circuit
+    fn instantiate(name: &str, src: &str) -> Statement {
+        let project = Project::new(&format!("filters_{}", name), &[("main.circom", src)]);
+        let main = project.path("main.circom").to_str().unwrap().to_string();
+        let parsed = parser::run_parser(main, "2.1.6", vec![], None);
+        let (mut program, _) = parsed.ok().expect("the circuit should parse");
+        assert!(type_analysis::check_types::check_types(&mut program).is_ok());
+        let flags = FlagsExecution { verbose: false, inspect: false };
+        let (executed, _) = constraint_execution(&program, flags, &BN128.to_string()).ok().unwrap();
+        executed.model[0].code.clone()
+    }
+
+    // Right hand sides of the substitutions to the variable
+    fn assigned<'a>(stmt: &'a Statement, name: &str, found: &mut Vec<&'a Expression>) {
+        use Statement::*;
+        match stmt {
+            Substitution { var, rhe, .. } if var == name => found.push(rhe),
+            IfThenElse { if_case, else_case, .. } => {
+                assigned(if_case, name, found);
+                if let Some(else_case) = else_case {
+                    assigned(else_case, name, found);
+                }
+            }
+            While { stmt, .. } => assigned(stmt, name, found),
+            InitializationBlock { initializations: stmts, .. } | Block { stmts, .. } => {
+                for stmt in stmts {
+                    assigned(stmt, name, found);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn assigned_to<'a>(code: &'a Statement, name: &str) -> Vec<&'a Expression> {
+        let mut found = Vec::new();
+        assigned(code, name, &mut found);
+        found
+    }
+
+    const CIRCUIT: &str = r#"
+        pragma circom 2.1.6;
+
+        function table(n) {
+            var t[2][2];
+            for (var i = 0; i < 4; i++) {
+                t[i \ 2][i % 2] = n * i;
+            }
+            return t;
+        }
+
+        function logged(n) {
+            var t[2];
+            log("logged", n);
+            t[0] = n;
+            t[1] = n + 1;
+            return t;
+        }
+
+        template T(n) {
+            signal input a;
+            signal output b;
+            var r[2][2] = table(n);
+            var s[2] = logged(n);
+            b <== a * (r[1][1] + s[1]);
+        }
+
+        component main = T(5);
+    "#;
+
+    #[test]
+    fn known_arrays_returned_by_calls_are_replaced_by_their_values() {
+        let code = instantiate("known", CIRCUIT);
+        let assigned = assigned_to(&code, "r");
+        assert_eq!(assigned.len(), 1);
+        // A single assignment of all the values in row-major order
+        let values: Vec<_> = match assigned[0] {
+            Expression::ArrayInLine { values, .. } => values
+                .iter()
+                .filter_map(|v| if let Expression::Number(_, v) = v { Some(v.clone()) } else { None })
+                .collect(),
+            _ => panic!("expected the values of the call"),
+        };
+        let expected: Vec<BigInt> = [0, 5, 10, 15].iter().map(|v| BigInt::from(*v)).collect();
+        assert_eq!(values, expected);
+    }
+
+    #[test]
+    fn calls_that_log_are_kept() {
+        let code = instantiate("logged", CIRCUIT);
+        let assigned = assigned_to(&code, "s");
+        assert_eq!(assigned.len(), 1);
+        assert!(assigned[0].is_call());
+    }
+}
@@ -0,0 +1,337 @@
+use super::executed_program::ExecutedProgram;
+use super::executed_template::ExecutedTemplate;
+use super::type_definitions::*;
+use circom_algebra::algebra::ArithmeticExpression;
+use json::JsonValue;
+use program_structure::dot::escape_dot;
+use program_structure::output_directory::{FileSystem, OutputDirectory};
+use program_structure::output_error::OutputResult;
+use std::collections::BTreeMap;
+
+struct NodeStatistics {
+    number_of_inputs: usize,
+    number_of_outputs: usize,
+    number_of_intermediates: usize,
+    number_of_constraints: usize,
+    subtree_signals: usize,
+    subtree_constraints: usize,
+    // number of connexions pointing to this node
+    references: usize,
+    // number of times this node appears in the unfolded component tree
+    instances: usize,
+}
+
+impl NodeStatistics {
+    fn number_of_signals(&self) -> usize {
+        self.number_of_inputs + self.number_of_outputs + self.number_of_intermediates
+    }
+
+    fn is_shared(&self) -> bool {
+        self.references > 1
+    }
+}
+
+fn count_signals(signals: &SignalCollector) -> usize {
+    signals.iter().map(|(_, dims)| dims.iter().fold(1, |p, c| p * (*c))).sum()
+}
+
+// Nodes are inserted in the model after all their subcomponents, so the
+// main component is the last one and every edge goes to a lower index.
+fn compute_statistics(program: &ExecutedProgram) -> Vec<NodeStatistics> {
+    let mut statistics = Vec::with_capacity(program.model.len());
+    for node in &program.model {
+        let number_of_signals = count_signals(node.inputs())
+            + count_signals(node.outputs())
+            + count_signals(node.intermediates());
+        let mut subtree_signals = number_of_signals;
+        let mut subtree_constraints = node.constraints().len();
+        for (_, data) in node.subcomponents() {
+            let child: &NodeStatistics = &statistics[data.goes_to];
+            subtree_signals += child.subtree_signals;
+            subtree_constraints += child.subtree_constraints;
+        }
+        statistics.push(NodeStatistics {
+            number_of_inputs: count_signals(node.inputs()),
+            number_of_outputs: count_signals(node.outputs()),
+            number_of_intermediates: count_signals(node.intermediates()),
+            number_of_constraints: node.constraints().len(),
+            subtree_signals,
+            subtree_constraints,
+            references: 0,
+            instances: 0,
+        });
+    }
+    if let Some(main) = statistics.last_mut() {
+        main.instances = 1;
+    }
+    for index in (0..program.model.len()).rev() {
+        let instances = statistics[index].instances;
+        for (_, data) in program.model[index].subcomponents() {
+            statistics[data.goes_to].references += 1;
+            statistics[data.goes_to].instances += instances;
+        }
+    }
+    statistics
+}
+
+fn parameters_as_strings(node: &ExecutedTemplate) -> Vec<(String, String)> {
+    let mut parameters = vec![];
+    for (name, slice) in node.parameter_instances() {
+        let (dims, values) = slice.clone().destruct();
+        let values: Vec<String> = values.iter().map(ArithmeticExpression::to_string).collect();
+        let value = if dims.is_empty() {
+            values.join(", ")
+        } else {
+            format!("[{}]", values.join(", "))
+        };
+        parameters.push((name.clone(), value));
+    }
+    parameters
+}
+
+fn tags_as_strings(node: &ExecutedTemplate) -> Vec<(String, String)> {
+    let mut tags = vec![];
+    for (signal, info) in node.tag_instances() {
+        for (tag, value) in info {
+            let value = match value {
+                Some(value) => value.to_str_radix(10),
+                None => "?".to_string(),
+            };
+            tags.push((format!("{}.{}", signal, tag), value));
+        }
+    }
+    tags
+}
+
+// Groups the connexions of a node by (component name, target node) so that
+// arrays of components are drawn as a single edge.
+fn grouped_edges(node: &ExecutedTemplate) -> BTreeMap<(String, NodePointer), (usize, bool)> {
+    let mut edges = BTreeMap::new();
+    for (_, data) in node.subcomponents() {
+        let entry = edges.entry((data.name.clone(), data.goes_to)).or_insert((0, false));
+        entry.0 += 1;
+        entry.1 |= data.is_parallel;
+    }
+    edges
+}
+
+fn produce_dot(program: &ExecutedProgram, statistics: &[NodeStatistics]) -> String {
+    let mut dot = String::new();
+    dot.push_str("digraph hierarchy {\n");
+    dot.push_str("    node [shape=box, fontname=\"monospace\"];\n");
+    for (id, node) in program.model.iter().enumerate() {
+        let stats = &statistics[id];
+        let mut lines = vec![node.template_name().clone()];
+        for (name, value) in parameters_as_strings(node) {
+            lines.push(format!("{} = {}", name, value));
+        }
+        for (name, value) in tags_as_strings(node) {
+            lines.push(format!("tag {} = {}", name, value));
+        }
+        lines.push(format!(
+            "signals: {} (in {}, out {}, inter {})",
+            stats.number_of_signals(),
+            stats.number_of_inputs,
+            stats.number_of_outputs,
+            stats.number_of_intermediates
+        ));
+        lines.push(format!("constraints: {}", stats.number_of_constraints));
+        lines.push(format!(
+            "subtree: {} signals, {} constraints",
+            stats.subtree_signals, stats.subtree_constraints
+        ));
+        lines.push(format!("instances: {}", stats.instances));
+        let label: Vec<String> = lines.iter().map(|l| escape_dot(l)).collect();
+        let mut style = vec![];
+        if stats.is_shared() {
+            style.push("filled");
+        }
+        if node.is_parallel {
+            style.push("bold");
+        }
+        if node.is_custom_gate {
+            style.push("rounded");
+        }
+        dot.push_str(&format!("    n{} [label=\"{}\\l\"", id, label.join("\\l")));
+        if !style.is_empty() {
+            dot.push_str(&format!(", style=\"{}\"", style.join(",")));
+        }
+        if stats.is_shared() {
+            dot.push_str(", fillcolor=\"lightyellow\"");
+        }
+        dot.push_str("];\n");
+    }
+    for (id, node) in program.model.iter().enumerate() {
+        for ((name, goes_to), (times, is_parallel)) in grouped_edges(node) {
+            let label = if times > 1 { format!("{} (x{})", name, times) } else { name };
+            dot.push_str(&format!("    n{} -> n{} [label=\"{}\"", id, goes_to, escape_dot(&label)));
+            if is_parallel {
+                dot.push_str(", style=\"dashed\"");
+            }
+            dot.push_str("];\n");
+        }
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+fn produce_json(program: &ExecutedProgram, statistics: &[NodeStatistics]) -> JsonValue {
+    let mut nodes = JsonValue::new_array();
+    for (id, node) in program.model.iter().enumerate() {
+        let stats = &statistics[id];
+        let mut parameters = JsonValue::new_object();
+        for (name, value) in parameters_as_strings(node) {
+            parameters[name] = value.into();
+        }
+        let mut tags = JsonValue::new_object();
+        for (signal, info) in node.tag_instances() {
+            if info.is_empty() {
+                continue;
+            }
+            let mut signal_tags = JsonValue::new_object();
+            for (tag, value) in info {
+                signal_tags[tag.as_str()] = match value {
+                    Some(value) => value.to_str_radix(10).into(),
+                    None => JsonValue::Null,
+                };
+            }
+            tags[signal.as_str()] = signal_tags;
+        }
+        let mut subcomponents = JsonValue::new_array();
+        for (full_name, data) in node.subcomponents() {
+            let sub = json::object! {
+                "name" => full_name,
+                "node" => data.goes_to,
+                "is_parallel" => data.is_parallel,
+            };
+            subcomponents.push(sub).unwrap();
+        }
+        let json_node = json::object! {
+            "id" => id,
+            "template" => node.template_name().as_str(),
+            "parameters" => parameters,
+            "tags" => tags,
+            "is_parallel" => node.is_parallel,
+            "is_custom_gate" => node.is_custom_gate,
+            "signals" => json::object! {
+                "inputs" => stats.number_of_inputs,
+                "outputs" => stats.number_of_outputs,
+                "intermediates" => stats.number_of_intermediates,
+                "total" => stats.number_of_signals(),
+            },
+            "constraints" => stats.number_of_constraints,
+            "subtree_signals" => stats.subtree_signals,
+            "subtree_constraints" => stats.subtree_constraints,
+            "references" => stats.references,
+            "instances" => stats.instances,
+            "shared" => stats.is_shared(),
+            "subcomponents" => subcomponents,
+        };
+        nodes.push(json_node).unwrap();
+    }
+    let main: JsonValue = match program.model.len() {
+        0 => JsonValue::Null,
+        len => (len - 1).into(),
+    };
+    json::object! {
+        "main" => main,
+        "nodes" => nodes,
+    }
+}
+
+pub fn port_hierarchy_dot(program: &ExecutedProgram, file: &str) -> OutputResult<()> {
+    let statistics = compute_statistics(program);
+    FileSystem.write_file(file, produce_dot(program, &statistics).as_bytes())
+}
+
+pub fn port_hierarchy_json(program: &ExecutedProgram, file: &str) -> OutputResult<()> {
+    let statistics = compute_statistics(program);
+    FileSystem.write_file(file, produce_json(program, &statistics).pretty(4).as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment_utils::slice_types::AExpressionSlice;
+    use num_bigint::BigInt;
+    use program_structure::ast::Meta;
+    use program_structure::statement_builders::build_block;
+
+    fn template(name: &str, parameter: Option<u64>, inputs: usize) -> ExecutedTemplate {
+        let mut instance = ParameterContext::new();
+        if let Some(value) = parameter {
+            let value = ArithmeticExpression::Number { value: BigInt::from(value) };
+            instance.insert("n".to_string(), AExpressionSlice::new(&value));
+        }
+        let code = build_block(Meta::new(0, 0), vec![]);
+        let mut node =
+            ExecutedTemplate::new(vec![], name.to_string(), name.to_string(), instance, TagContext::new(), code, false, false);
+        node.add_input("in", &[inputs]);
+        node.add_output("out", &[]);
+        node
+    }
+
+    fn connect(node: &mut ExecutedTemplate, full_name: &str, name: &str, goes_to: NodePointer) {
+        let data = SubComponentData { name: name.to_string(), is_parallel: false, indexed_with: vec![], goes_to };
+        node.add_arrow(full_name.to_string(), data);
+    }
+
+    // Main has an array of two Pair and a Leaf, and every Pair has two Leaf,
+    // all the Leaf are the same instance.
+    fn shared_program() -> ExecutedProgram {
+        let mut program = ExecutedProgram::new(&"bn128".to_string());
+        let leaf = template("Leaf", Some(3), 3);
+        let mut pair = template("Pair", None, 2);
+        connect(&mut pair, "a", "a", 0);
+        connect(&mut pair, "b", "b", 0);
+        let mut main = template("Main", None, 1);
+        connect(&mut main, "c[0]", "c", 1);
+        connect(&mut main, "c[1]", "c", 1);
+        connect(&mut main, "d", "d", 0);
+        program.model = vec![leaf, pair, main];
+        program
+    }
+
+    #[test]
+    fn hierarchy_counts_shared_instances() {
+        let program = shared_program();
+        let statistics = compute_statistics(&program);
+        let counts: Vec<_> = statistics.iter().map(|s| (s.references, s.instances)).collect();
+        assert_eq!(counts, vec![(3, 5), (2, 2), (0, 1)]);
+        let subtree: Vec<_> = statistics.iter().map(|s| s.subtree_signals).collect();
+        assert_eq!(subtree, vec![4, 11, 28]);
+    }
+
+    #[test]
+    fn hierarchy_dot_groups_arrays_and_marks_shared_nodes() {
+        let program = shared_program();
+        let dot = produce_dot(&program, &compute_statistics(&program));
+        assert!(dot.starts_with("digraph hierarchy {\n"));
+        assert!(dot.contains("n0 [label=\"Leaf\\ln = 3\\lsignals: 4 (in 3, out 1, inter 0)\\l"));
+        assert!(dot.contains("instances: 5\\l\", style=\"filled\", fillcolor=\"lightyellow\"];"));
+        assert!(dot.contains("    n2 -> n1 [label=\"c (x2)\"];\n"));
+        assert!(dot.contains("    n2 -> n0 [label=\"d\"];\n"));
+        assert!(dot.contains("    n1 -> n0 [label=\"a\"];\n"));
+        assert!(dot.contains("instances: 1\\l\"];\n"));
+    }
+
+    #[test]
+    fn hierarchy_json_lists_nodes_and_subcomponents() {
+        let program = shared_program();
+        let json = produce_json(&program, &compute_statistics(&program));
+        assert_eq!(json["main"], 2);
+        let leaf = &json["nodes"][0];
+        assert_eq!(leaf["template"], "Leaf");
+        assert_eq!(leaf["parameters"]["n"], "3");
+        assert_eq!(leaf["signals"]["total"], 4);
+        assert_eq!(leaf["references"], 3);
+        assert_eq!(leaf["instances"], 5);
+        assert_eq!(leaf["shared"], true);
+        let main = &json["nodes"][2];
+        assert_eq!(main["shared"], false);
+        assert_eq!(main["subtree_signals"], 28);
+        let names: Vec<_> = main["subcomponents"].members().map(|sub| sub["name"].to_string()).collect();
+        assert_eq!(names, vec!["c[0]", "c[1]", "d"]);
+        assert_eq!(main["subcomponents"][2]["node"], 0);
+    }
+}
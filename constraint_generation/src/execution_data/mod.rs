@@ -8,4 +8,5 @@ pub mod analysis;
 pub mod executed_program;
 pub mod executed_template;
 mod filters;
+mod hierarchy_porting;
 pub mod type_definitions;
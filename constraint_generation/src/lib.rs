@@ -6,10 +6,9 @@ mod environment_utils;
 mod execute;
 mod execution_data;
 
-use ansi_term::Colour;
 use circom_algebra::algebra::{ArithmeticError, ArithmeticExpression};
 use compiler::hir::very_concrete_program::VCP;
-use constraint_list::ConstraintList;
+use constraint_list::{storage_error_report, ConstraintList};
 use constraint_writers::ConstraintExporter;
 use dag::DAG;
 use execution_data::executed_program::ExportResult;
@@ -23,7 +22,12 @@ use std::rc::Rc;
 
 pub struct BuildConfig {
     pub no_rounds: usize,
+    // In megabytes, the constraints are kept in a temporary file when given
+    pub memory_budget: Option<usize>,
     pub flag_json_sub: bool,
+    pub json_substitutions: String,
+    // Signals whose fate in the simplification is reported
+    pub explain: Vec<String>,
     pub flag_s: bool,
     pub flag_f: bool,
     pub flag_p: bool,
@@ -31,6 +35,9 @@ pub struct BuildConfig {
     pub flag_old_heuristics: bool,
     pub inspect_constraints: bool,
     pub prime: String,
+    pub flag_hierarchy: bool,
+    pub hierarchy_dot: String,
+    pub hierarchy_json: String,
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -40,44 +47,75 @@ pub struct FlagsExecution{
 }
 
 pub type ConstraintWriter = Box<dyn ConstraintExporter>;
-type BuildResponse = Result<(ConstraintWriter, VCP), ()>;
-pub fn build_circuit(program: ProgramArchive, config: BuildConfig) -> BuildResponse {
-    let files = program.file_library.clone();
+
+// Constraints produced by the constraint generation: the DAG of the circuit
+// when no simplification is applied (--O0) or the simplified constraint list
+pub enum ConstraintSystem {
+    Unsimplified(DAG),
+    Simplified(ConstraintList),
+}
+
+impl ConstraintSystem {
+    pub fn exporter(&self) -> &dyn ConstraintExporter {
+        match self {
+            ConstraintSystem::Unsimplified(dag) => dag,
+            ConstraintSystem::Simplified(list) => list,
+        }
+    }
+    pub fn into_exporter(self) -> ConstraintWriter {
+        match self {
+            ConstraintSystem::Unsimplified(dag) => Box::new(dag),
+            ConstraintSystem::Simplified(list) => Box::new(list),
+        }
+    }
+}
+
+pub struct BuildOutput {
+    pub constraints: ConstraintSystem,
+    pub vcp: VCP,
+    pub template_instances: usize,
+    pub warnings: ReportCollection,
+}
+
+// Nothing is printed, the reports are returned so that the caller decides how
+// to show them
+pub fn build_circuit_with_reports(program: ProgramArchive, config: &BuildConfig) -> Result<BuildOutput, ReportCollection> {
     let flags = FlagsExecution{
         verbose: config.flag_verbose,
         inspect: config.inspect_constraints,
     };
-    let (exe, warnings) = instantiation(&program, flags, &config.prime).map_err(|r| {
-        Report::print_reports(&r, &files);
-    })?;
-    Report::print_reports(&warnings, &files);
-    let (mut dag, mut vcp, warnings) = export(exe, program, flags).map_err(|r| {
-        Report::print_reports(&r, &files);
-    })?;
+    let (exe, mut warnings) = instantiation(&program, flags, &config.prime)?;
+    let template_instances = exe.number_of_nodes();
+    if config.flag_hierarchy {
+        generate_output_hierarchy(&exe, config)?;
+    }
+    let (mut dag, mut vcp, mut inspect_warnings) = export(exe, program, flags)?;
     if config.inspect_constraints {
-        Report::print_reports(&warnings, &files);
+        warnings.append(&mut inspect_warnings);
     }
-    if config.flag_f {
+    let constraints = if config.flag_f {
         sync_dag_and_vcp(&mut vcp, &mut dag);
-        Result::Ok((Box::new(dag), vcp))
+        ConstraintSystem::Unsimplified(dag)
     } else {
-        let list = simplification_process(&mut vcp, dag, &config);
-        Result::Ok((Box::new(list), vcp))
-    }
+        ConstraintSystem::Simplified(simplification_process(&mut vcp, dag, config)?)
+    };
+    Result::Ok(BuildOutput { constraints, vcp, template_instances, warnings })
 }
 
 type InstantiationResponse = Result<(ExecutedProgram, ReportCollection), ReportCollection>;
 fn instantiation(program: &ProgramArchive, flags: FlagsExecution, prime: &String) -> InstantiationResponse {
-    let execution_result = execute::constraint_execution(&program, flags, prime);
-    match execution_result {
-        Ok((program_exe, warnings)) => {
-            let no_nodes = program_exe.number_of_nodes();
-            let success = Colour::Green.paint("template instances");
-            let nodes_created = format!("{}: {}", success, no_nodes);
-            println!("{}", &nodes_created);
-            InstantiationResponse::Ok((program_exe,warnings))
-        }
-        Err(reports) => InstantiationResponse::Err(reports),
+    execute::constraint_execution(&program, flags, prime)
+}
+
+fn generate_output_hierarchy(exe: &ExecutedProgram, config: &BuildConfig) -> Result<(), ReportCollection> {
+    let dot = exe.generate_hierarchy_dot(&config.hierarchy_dot);
+    let json = exe.generate_hierarchy_json(&config.hierarchy_json);
+    let reports: ReportCollection =
+        vec![dot, json].into_iter().filter_map(Result::err).map(|err| err.into_report()).collect();
+    if reports.is_empty() {
+        Result::Ok(())
+    } else {
+        Result::Err(reports)
     }
 }
 
@@ -91,17 +129,19 @@ fn sync_dag_and_vcp(vcp: &mut VCP, dag: &mut DAG) {
     VCP::add_witness_list(vcp, Rc::clone(&witness));
 }
 
-fn simplification_process(vcp: &mut VCP, dag: DAG, config: &BuildConfig) -> ConstraintList {
+fn simplification_process(vcp: &mut VCP, dag: DAG, config: &BuildConfig) -> Result<ConstraintList, ReportCollection> {
     use dag::SimplificationFlags;
     let flags = SimplificationFlags {
         flag_s: config.flag_s,
         parallel_flag: config.flag_p,
-        port_substitution: config.flag_json_sub,
+        port_substitution: if config.flag_json_sub { Some(config.json_substitutions.clone()) } else { None },
+        explain: !config.explain.is_empty(),
         no_rounds: config.no_rounds,
+        memory_budget: config.memory_budget,
         flag_old_heuristics: config.flag_old_heuristics,
         prime : config.prime.clone(),
     };
-    let list = DAG::map_to_list(dag, flags);
+    let list = DAG::map_to_list(dag, flags).map_err(|err| vec![storage_error_report(err)])?;
     VCP::add_witness_list(vcp, Rc::new(list.get_witness_as_vec()));
-    list
+    Ok(list)
 }
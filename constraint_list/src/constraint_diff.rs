@@ -0,0 +1,401 @@
+use super::{sym_porting, ConstraintList, C};
+use circom_algebra::algebra::Constraint;
+use circom_algebra::field::{Field, FieldElement};
+use circom_algebra::linear_combination::LinearCombination;
+use circom_algebra::num_bigint::BigInt;
+use constraint_writers::r1cs_reader::R1CSFile;
+use constraint_writers::sym_writer::SymElem;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::io;
+
+// A linear expression over the names of the signals, the constant term has
+// the empty name. The coefficients are taken in (-p/2, p/2].
+pub type NamedExpression = Vec<(String, BigInt)>;
+
+// A normalized constraint A*B - C = 0, A and B are empty if it is linear
+#[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+pub struct CanonicalConstraint {
+    pub a: NamedExpression,
+    pub b: NamedExpression,
+    pub c: NamedExpression,
+}
+
+// A constraint system that does not depend on the numbering of the wires:
+// every wire takes the shortest of its names in the sym file and every
+// constraint is normalized. The constraints 0 = 0 are dropped.
+pub struct CanonicalSystem {
+    pub field: BigInt,
+    pub public_outputs: usize,
+    pub public_inputs: usize,
+    pub private_inputs: usize,
+    pub constraints: Vec<CanonicalConstraint>,
+}
+
+impl CanonicalSystem {
+    // The wires without a symbol are named #wire
+    pub fn from_r1cs(r1cs: &R1CSFile, symbols: &[SymElem]) -> CanonicalSystem {
+        let header = &r1cs.header;
+        let names = wire_names(header.total_wires, symbols);
+        let constraints = r1cs.constraints.iter().map(|[a, b, c]| C::new(a.clone(), b.clone(), c.clone()));
+        CanonicalSystem {
+            field: r1cs.field.prime().clone(),
+            public_outputs: header.public_outputs,
+            public_inputs: header.public_inputs,
+            private_inputs: header.private_inputs,
+            constraints: canonicalize(constraints, &names, &r1cs.field),
+        }
+    }
+
+    // Fails when the constraints of the list are in a temporary file that can
+    // not be read
+    pub fn from_list(list: &ConstraintList) -> io::Result<CanonicalSystem> {
+        let field = Field::new(&list.field);
+        let names = wire_names(list.no_wires(), &sym_porting::collect_symbols(list));
+        let mut constraints = Vec::new();
+        for id in list.constraints.get_ids() {
+            let constraint = list.constraints.read_constraint(id)?.unwrap();
+            constraints.push(C::apply_correspondence(&constraint, &list.signal_map));
+        }
+        Ok(CanonicalSystem {
+            field: list.field.clone(),
+            public_outputs: list.no_public_outputs,
+            public_inputs: list.no_public_inputs,
+            private_inputs: list.no_private_inputs,
+            constraints: canonicalize(constraints.into_iter(), &names, &field),
+        })
+    }
+}
+
+fn wire_names(no_wires: usize, symbols: &[SymElem]) -> Vec<String> {
+    let mut names: Vec<Option<&str>> = vec![None; no_wires];
+    for elem in symbols {
+        if elem.witness < 0 || elem.witness as usize >= no_wires {
+            continue;
+        }
+        let name = &mut names[elem.witness as usize];
+        let shorter = |current: &str| (elem.symbol.len(), elem.symbol.as_str()) < (current.len(), current);
+        if name.is_none_or(shorter) {
+            *name = Some(&elem.symbol);
+        }
+    }
+    names
+        .into_iter()
+        .enumerate()
+        .map(|(wire, name)| name.map_or_else(|| format!("#{}", wire), str::to_string))
+        .collect()
+}
+
+fn canonicalize(
+    constraints: impl Iterator<Item = C>,
+    names: &[String],
+    field: &Field,
+) -> Vec<CanonicalConstraint> {
+    let rename = |wire: &usize| names[*wire].clone();
+    let mut canonical = Vec::new();
+    for constraint in constraints {
+        let mut named = Constraint::new(
+            constraint.a().map_symbols(rename),
+            constraint.b().map_symbols(rename),
+            constraint.c().map_symbols(rename),
+        );
+        Constraint::normalize(&mut named, field);
+        if named.is_empty() {
+            continue;
+        }
+        canonical.push(CanonicalConstraint {
+            a: named_expression(named.a(), field),
+            b: named_expression(named.b(), field),
+            c: named_expression(named.c(), field),
+        });
+    }
+    canonical
+}
+
+pub(crate) fn named_expression(expr: &LinearCombination<String>, field: &Field) -> NamedExpression {
+    let half = field.prime() / 2;
+    let signed = |value: &FieldElement| {
+        let value = field.to_bigint(value);
+        if value > half { value - field.prime() } else { value }
+    };
+    expr.iter().map(|(name, value)| (name.clone(), signed(value))).collect()
+}
+
+// The innermost component that contains all the signals of the constraint,
+// the component of a signal is its name up to the last dot
+fn component(constraint: &CanonicalConstraint) -> String {
+    let names = constraint.a.iter().chain(&constraint.b).chain(&constraint.c).map(|(name, _)| name);
+    let mut common: Option<Vec<&str>> = None;
+    for name in names {
+        let path: Vec<&str> = match name.rfind('.') {
+            Some(dot) => name[..dot].split('.').collect(),
+            None => continue,
+        };
+        common = Some(match common {
+            None => path,
+            Some(common) => common.into_iter().zip(path).take_while(|(l, r)| l == r).map(|(l, _)| l).collect(),
+        });
+    }
+    match common {
+        Some(path) if !path.is_empty() => path.join("."),
+        _ => "main".to_string(),
+    }
+}
+
+pub struct CountDelta {
+    pub name: String,
+    pub old: usize,
+    pub new: usize,
+}
+
+// The differences between two constraint systems over the same prime. The
+// constraints are compared as multisets, the components only appear if
+// their number of constraints changed.
+pub struct SystemDiff {
+    pub inputs: Vec<CountDelta>,
+    pub removed: Vec<CanonicalConstraint>,
+    pub added: Vec<CanonicalConstraint>,
+    pub components: Vec<CountDelta>,
+}
+
+impl SystemDiff {
+    pub fn compare(old: &CanonicalSystem, new: &CanonicalSystem) -> SystemDiff {
+        let inputs = vec![
+            ("public outputs", old.public_outputs, new.public_outputs),
+            ("public inputs", old.public_inputs, new.public_inputs),
+            ("private inputs", old.private_inputs, new.private_inputs),
+        ];
+        let inputs = inputs
+            .into_iter()
+            .filter(|(_, old, new)| old != new)
+            .map(|(name, old, new)| CountDelta { name: name.to_string(), old, new })
+            .collect();
+
+        let mut balance: HashMap<&CanonicalConstraint, isize> = HashMap::new();
+        for constraint in &old.constraints {
+            *balance.entry(constraint).or_insert(0) -= 1;
+        }
+        for constraint in &new.constraints {
+            *balance.entry(constraint).or_insert(0) += 1;
+        }
+        let mut removed = Vec::new();
+        let mut added = Vec::new();
+        for (constraint, count) in balance {
+            let side = if count < 0 { &mut removed } else { &mut added };
+            for _ in 0..count.abs() {
+                side.push(constraint.clone());
+            }
+        }
+        removed.sort();
+        added.sort();
+
+        let mut counts: BTreeMap<String, (usize, usize)> = BTreeMap::new();
+        for constraint in &old.constraints {
+            counts.entry(component(constraint)).or_insert((0, 0)).0 += 1;
+        }
+        for constraint in &new.constraints {
+            counts.entry(component(constraint)).or_insert((0, 0)).1 += 1;
+        }
+        let components = counts
+            .into_iter()
+            .filter(|(_, (old, new))| old != new)
+            .map(|(name, (old, new))| CountDelta { name, old, new })
+            .collect();
+
+        SystemDiff { inputs, removed, added, components }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inputs.is_empty() && self.removed.is_empty() && self.added.is_empty()
+    }
+}
+
+impl Display for CountDelta {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        let delta = self.new as i64 - self.old as i64;
+        write!(f, "{}: {} -> {} ({:+})", self.name, self.old, self.new, delta)
+    }
+}
+
+pub(crate) fn write_expression(f: &mut Formatter, expr: &NamedExpression) -> FmtResult {
+    if expr.is_empty() {
+        return write!(f, "0");
+    }
+    for (i, (name, value)) in expr.iter().enumerate() {
+        let sign = if value < &BigInt::from(0) { "-" } else { "+" };
+        if i == 0 && sign == "-" {
+            write!(f, "-")?;
+        } else if i > 0 {
+            write!(f, " {} ", sign)?;
+        }
+        let value = if sign == "-" { -value } else { value.clone() };
+        if name.is_empty() {
+            write!(f, "{}", value)?;
+        } else if value == BigInt::from(1) {
+            write!(f, "{}", name)?;
+        } else {
+            write!(f, "{}*{}", value, name)?;
+        }
+    }
+    Ok(())
+}
+
+impl Display for CanonicalConstraint {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        if !self.a.is_empty() {
+            write!(f, "(")?;
+            write_expression(f, &self.a)?;
+            write!(f, ") * (")?;
+            write_expression(f, &self.b)?;
+            write!(f, ") = ")?;
+            write_expression(f, &self.c)
+        } else {
+            write_expression(f, &self.c)?;
+            write!(f, " = 0")
+        }
+    }
+}
+
+impl Display for SystemDiff {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        for delta in &self.inputs {
+            writeln!(f, "Number of {}", delta)?;
+        }
+        if !self.removed.is_empty() {
+            writeln!(f, "Removed constraints:")?;
+            for constraint in &self.removed {
+                writeln!(f, "  - {}", constraint)?;
+            }
+        }
+        if !self.added.is_empty() {
+            writeln!(f, "Added constraints:")?;
+            for constraint in &self.added {
+                writeln!(f, "  + {}", constraint)?;
+            }
+        }
+        if !self.components.is_empty() {
+            writeln!(f, "Constraints per component:")?;
+            for delta in &self.components {
+                writeln!(f, "  {}", delta)?;
+            }
+        }
+        write!(f, "{} constraints removed, {} added", self.removed.len(), self.added.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_circuit;
+    use constraint_writers::r1cs_reader::{field_size, read_r1cs_for_prime};
+    use constraint_writers::r1cs_writer::HeaderData;
+    use constraint_writers::sym_reader::read_sym;
+    use constraint_writers::ConstraintExporter;
+    use program_structure::file_definition::FileLibrary;
+    use program_structure::output_directory::MemoryDirectory;
+
+    fn lc(field: &Field, terms: &[(usize, i64)]) -> LinearCombination<usize> {
+        let value = |v: i64| field.from_bigint(&BigInt::from(v));
+        terms.iter().map(|(s, v)| (*s, value(*v))).collect()
+    }
+
+    fn system(field: &Field, constraints: Vec<[LinearCombination<usize>; 3]>, wires: &[&str]) -> CanonicalSystem {
+        let header = HeaderData {
+            field: field.prime().clone(),
+            total_wires: wires.len(),
+            public_outputs: 1,
+            public_inputs: 0,
+            private_inputs: 2,
+            number_of_labels: wires.len(),
+            number_of_constraints: constraints.len(),
+        };
+        let r1cs = R1CSFile {
+            field: field.clone(),
+            field_size: field_size(field.prime()),
+            header,
+            constraints,
+            wire_to_label: (0..wires.len()).collect(),
+            custom_gates_used: None,
+            custom_gates_applied: None,
+        };
+        let symbols: Vec<SymElem> = wires
+            .iter()
+            .enumerate()
+            .skip(1)
+            .map(|(wire, name)| SymElem { original: wire as i64, witness: wire as i64, node_id: 0, symbol: name.to_string() })
+            .collect();
+        CanonicalSystem::from_r1cs(&r1cs, &symbols)
+    }
+
+    #[test]
+    fn diff_of_renumbered_and_scaled_systems() {
+        let field = Field::new(&BigInt::from(257));
+        let f = |terms: &[(usize, i64)]| lc(&field, terms);
+        // out = x * y, x = 2 * c.in and y = c.in * c.in
+        let old = system(
+            &field,
+            vec![
+                [f(&[(2, 1)]), f(&[(3, 1)]), f(&[(1, 1)])],
+                [f(&[]), f(&[]), f(&[(2, 1), (4, -2)])],
+                [f(&[(4, 1)]), f(&[(4, 1)]), f(&[(3, 1)])],
+            ],
+            &["one", "main.out", "main.x", "main.y", "main.c.in"],
+        );
+        // The same system with other wires, scaled constraints and 0 = 0
+        let renumbered = system(
+            &field,
+            vec![
+                [f(&[(2, 3)]), f(&[(1, -2)]), f(&[(3, -6)])],
+                [f(&[(0, 5)]), f(&[(2, 1), (4, -2)]), f(&[])],
+                [f(&[(0, 0)]), f(&[(1, 1)]), f(&[])],
+                [f(&[(4, -1)]), f(&[(4, 1)]), f(&[(1, -1)])],
+            ],
+            &["one", "main.y", "main.x", "main.out", "main.c.in"],
+        );
+        assert!(SystemDiff::compare(&old, &renumbered).is_empty());
+
+        // y = c.in * c.in is computed through c.aux, a signal of main.c
+        let changed = system(
+            &field,
+            vec![
+                [f(&[(2, 1)]), f(&[(3, 1)]), f(&[(1, 1)])],
+                [f(&[]), f(&[]), f(&[(2, 1), (4, -2)])],
+                [f(&[(4, 1)]), f(&[(4, 1)]), f(&[(5, 1)])],
+                [f(&[(5, 1)]), f(&[(4, 1)]), f(&[(3, 1)])],
+            ],
+            &["one", "main.out", "main.x", "main.y", "main.c.in", "main.c.aux"],
+        );
+        let diff = SystemDiff::compare(&old, &changed);
+        assert!(!diff.is_empty());
+        let printed: Vec<String> = diff.removed.iter().chain(&diff.added).map(|c| c.to_string()).collect();
+        assert_eq!(
+            printed,
+            vec!["(main.c.in) * (main.c.in) = main.y", "(main.c.aux) * (main.c.in) = main.y", "(main.c.in) * (main.c.in) = main.c.aux"]
+        );
+        assert_eq!(diff.components.len(), 1);
+        assert_eq!(diff.components[0].to_string(), "main.c: 0 -> 1 (+1)");
+        assert!(diff.inputs.is_empty());
+    }
+
+    #[test]
+    fn constraint_list_and_its_r1cs_are_equivalent() {
+        let list = test_circuit::simplifier(&mut FileLibrary::new()).simplify_constraints().unwrap();
+        let mut directory = MemoryDirectory::new();
+        list.r1cs(&mut directory, "circuit.r1cs", false).unwrap();
+        list.sym(&mut directory, "circuit.sym").unwrap();
+        let r1cs = read_r1cs_for_prime(directory.get_file("circuit.r1cs").unwrap(), "circuit.r1cs", &list.field).unwrap();
+        let symbols = read_sym(directory.get_file("circuit.sym").unwrap(), "circuit.sym").unwrap();
+
+        let from_list = CanonicalSystem::from_list(&list).unwrap();
+        let from_files = CanonicalSystem::from_r1cs(&r1cs, &symbols);
+        let printed: Vec<String> = from_list.constraints.iter().map(|c| c.to_string()).collect();
+        assert_eq!(printed, vec!["(main.a + 2*main.b) * (main.b) = main.c"]);
+        assert!(SystemDiff::compare(&from_list, &from_files).is_empty());
+
+        // Without the symbols every wire has another name
+        let unnamed = CanonicalSystem::from_r1cs(&r1cs, &[]);
+        let diff = SystemDiff::compare(&from_files, &unnamed);
+        assert_eq!(diff.removed.len(), from_files.constraints.len());
+        assert_eq!(diff.added.len(), from_files.constraints.len());
+    }
+}
@@ -1,21 +1,37 @@
 // Uncomment lines 163, 165, 336 and 338 to print cluster information
-use super::{ConstraintStorage, EncodingIterator, SEncoded, Simplifier, A, C, S};
+use super::{
+    ConstraintStorage, EncodingIterator, RemovedConstraints, SEncoded, SimplificationStage, Simplifier,
+    SubstitutionTrace, A, C, S,
+};
 use crate::SignalMap;
-use circom_algebra::num_bigint::BigInt;
+use circom_algebra::field::Field;
 use constraint_writers::json_writer::SubstitutionJSON;
+use program_structure::output_directory::{FileSystem, OutputDirectory};
 use std::collections::{HashMap, HashSet, LinkedList, BTreeSet};
+use std::io;
 use std::sync::Arc;
 
-const SUB_LOG: &str = "./log_substitution.json";
+// Where the substitutions are reported: the json log and the trace used to
+// explain them, each one only when requested
+struct SubstitutionLog<'a> {
+    json: Option<SubstitutionJSON<'a>>,
+    trace: Option<SubstitutionTrace>,
+    stage: SimplificationStage,
+}
 
-fn log_substitutions(substitutions: &LinkedList<S>, writer: &mut Option<SubstitutionJSON>) {
+fn log_substitutions(substitutions: &LinkedList<S>, log: &mut SubstitutionLog, field: &Field) {
     use super::json_porting::port_substitution;
-    if let Some(w) = writer {
+    if let Some(w) = &mut log.json {
         for s in substitutions {
-            let (from, to) = port_substitution(s);
+            let (from, to) = port_substitution(s, field);
             w.write_substitution(&from, &to).unwrap();
         }
     }
+    if let Some(trace) = &mut log.trace {
+        for s in substitutions {
+            trace.substitutions.push((s.clone(), log.stage));
+        }
+    }
 }
 
 #[derive(Default, Clone)]
@@ -127,7 +143,7 @@ fn rebuild_witness(
 fn eq_cluster_simplification(
     mut cluster: Cluster,
     forbidden: &HashSet<usize>,
-    field: &BigInt,
+    field: &Field,
 ) -> (LinkedList<S>, LinkedList<C>) {
     if Cluster::size(&cluster) == 1 {
         let mut substitutions = LinkedList::new();
@@ -141,16 +157,16 @@ fn eq_cluster_simplification(
         } else if HashSet::contains(forbidden, &s_0) {
             LinkedList::push_back(
                 &mut substitutions,
-                S::new(s_1, A::Signal { symbol: s_0 }).unwrap(),
+                S::new(s_1, A::Signal { symbol: s_0 }, field).unwrap(),
             );
         } else if HashSet::contains(forbidden, &s_1) {
             LinkedList::push_back(
                 &mut substitutions,
-                S::new(s_0, A::Signal { symbol: s_1 }).unwrap(),
+                S::new(s_0, A::Signal { symbol: s_1 }, field).unwrap(),
             );
         } else {
             let (l, r) = if s_0 > s_1 { (s_0, s_1) } else { (s_1, s_0) };
-            LinkedList::push_back(&mut substitutions, S::new(l, A::Signal { symbol: r }).unwrap());
+            LinkedList::push_back(&mut substitutions, S::new(l, A::Signal { symbol: r }, field).unwrap());
         }
         (substitutions, constraints)
     } else {
@@ -188,7 +204,7 @@ fn eq_cluster_simplification(
         }
 
         for signal in remove {
-            let sub = S::new(signal, A::Signal { symbol: rh_signal }).unwrap();
+            let sub = S::new(signal, A::Signal { symbol: rh_signal }, field).unwrap();
             LinkedList::push_back(&mut subs, sub);
         }
 
@@ -200,8 +216,8 @@ fn eq_simplification(
     equalities: LinkedList<C>,
     forbidden: Arc<HashSet<usize>>,
     no_vars: usize,
-    field: &BigInt,
-    substitution_log: &mut Option<SubstitutionJSON>,
+    field: &Field,
+    substitution_log: &mut SubstitutionLog,
 ) -> (LinkedList<S>, LinkedList<C>) {
     use std::sync::mpsc;
     use threadpool::ThreadPool;
@@ -247,15 +263,15 @@ fn eq_simplification(
     for id in 0..no_clusters {
         LinkedList::append(&mut constraints, &mut aux_constraints[id]);
     }
-    log_substitutions(&substitutions, substitution_log);
+    log_substitutions(&substitutions, substitution_log, &field);
     (substitutions, constraints)
 }
 
 fn constant_eq_simplification(
     c_eq: LinkedList<C>,
     forbidden: &HashSet<usize>,
-    field: &BigInt,
-    substitution_log: &mut Option<SubstitutionJSON>,
+    field: &Field,
+    substitution_log: &mut SubstitutionLog,
 ) -> (LinkedList<S>, LinkedList<C>) {
     let mut cons = LinkedList::new();
     let mut subs = LinkedList::new();
@@ -269,16 +285,16 @@ fn constant_eq_simplification(
             LinkedList::push_back(&mut subs, sub);
         }
     }
-    log_substitutions(&subs, substitution_log);
+    log_substitutions(&subs, substitution_log, field);
     (subs, cons)
 }
 
 fn linear_simplification(
-    log: &mut Option<SubstitutionJSON>,
+    log: &mut SubstitutionLog,
     linear: LinkedList<C>,
     forbidden: Arc<HashSet<usize>>,
     no_labels: usize,
-    field: &BigInt,
+    field: &Field,
     use_old_heuristics: bool,
 ) -> (LinkedList<S>, LinkedList<C>) {
     use circom_algebra::simplification_utils::full_simplification;
@@ -318,7 +334,7 @@ fn linear_simplification(
 
     for _ in 0..no_clusters {
         let mut result = simplified_rx.recv().unwrap();
-        log_substitutions(&result.substitutions, log);
+        log_substitutions(&result.substitutions, log, field);
         LinkedList::append(&mut cons, &mut result.constraints);
         LinkedList::append(&mut substitutions, &mut result.substitutions);
     }
@@ -326,10 +342,10 @@ fn linear_simplification(
 }
 
 type SignalToConstraints = HashMap<usize, LinkedList<usize>>;
-fn build_non_linear_signal_map(non_linear: &ConstraintStorage) -> SignalToConstraints {
+fn build_non_linear_signal_map(non_linear: &ConstraintStorage) -> io::Result<SignalToConstraints> {
     let mut map = SignalToConstraints::new();
     for c_id in non_linear.get_ids() {
-        let constraint = non_linear.read_constraint(c_id).unwrap();
+        let constraint = non_linear.read_constraint(c_id)?.unwrap();
         for signal in C::take_cloned_signals(&constraint) {
             if let Some(list) = map.get_mut(&signal) {
                 list.push_back(c_id);
@@ -340,33 +356,33 @@ fn build_non_linear_signal_map(non_linear: &ConstraintStorage) -> SignalToConstr
             }
         }
     }
-    map
+    Ok(map)
 }
 
 fn apply_substitution_to_map(
     storage: &mut ConstraintStorage,
     map: &mut SignalToConstraints,
     substitutions: &LinkedList<S>,
-    field: &BigInt,
-) -> LinkedList<C> {
+    field: &Field,
+) -> io::Result<LinkedList<C>> {
     fn constraint_processing(
         storage: &mut ConstraintStorage,
         map: &mut SignalToConstraints,
         c_ids: &LinkedList<usize>,
         substitution: &S,
-        field: &BigInt,
-    ) -> LinkedList<usize> {
+        field: &Field,
+    ) -> io::Result<LinkedList<usize>> {
         let mut linear = LinkedList::new();
         let signals: LinkedList<_> = substitution.to().keys().cloned().collect();
         for c_id in c_ids {
             let c_id = *c_id;
-            let mut constraint = storage.read_constraint(c_id).unwrap();
+            let mut constraint = storage.read_constraint(c_id)?.unwrap();
             C::apply_substitution(&mut constraint, substitution, field);
             C::fix_constraint(&mut constraint, field);
             if C::is_linear(&constraint) {
                 linear.push_back(c_id);
             }
-            storage.replace(c_id, constraint);
+            storage.replace(c_id, constraint)?;
             for signal in &signals {
                 if let Some(list) = map.get_mut(&signal) {
                     list.push_back(c_id);
@@ -377,23 +393,110 @@ fn apply_substitution_to_map(
                 }
             }
         }
-        linear
+        Ok(linear)
     }
 
     let mut linear_id = LinkedList::new();
     for substitution in substitutions {
         if let Some(c_ids) = map.get(substitution.from()).cloned() {
-            let mut new_linear = constraint_processing(storage, map, &c_ids, substitution, field);
+            let mut new_linear = constraint_processing(storage, map, &c_ids, substitution, field)?;
             linear_id.append(&mut new_linear);
         }
     }
     let mut linear = LinkedList::new();
     for c_id in linear_id {
-        let constraint = storage.read_constraint(c_id).unwrap();
+        let constraint = storage.read_constraint(c_id)?.unwrap();
         linear.push_back(constraint);
-        storage.replace(c_id, C::empty());
+        storage.replace(c_id, C::empty())?;
+    }
+    Ok(linear)
+}
+
+// Drops the constraints that became 0 = 0
+fn remove_trivial(constraints: &mut LinkedList<C>, removed: &mut RemovedConstraints) {
+    let (trivial, rest): (LinkedList<C>, LinkedList<C>) =
+        std::mem::take(constraints).into_iter().partition(C::is_empty);
+    removed.trivial += trivial.len();
+    *constraints = rest;
+}
+
+// Empties the constraints that are a multiple of a previous one. The normal
+// forms are computed by blocks, with a single inversion for each block, and
+// only their hashes are kept. When a hash repeats the constraints are
+// compared, and the normal forms of the ones involved are kept from then on.
+// The constraints whose normal form is 0 = 0 are emptied as trivial.
+fn remove_duplicates(
+    storage: &mut ConstraintStorage,
+    removed: &mut RemovedConstraints,
+    field: &Field,
+) -> io::Result<()> {
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+    // Small enough to respect the memory budget
+    const BLOCK: usize = 4096;
+    fn normal_forms(
+        storage: &ConstraintStorage,
+        ids: &[usize],
+        field: &Field,
+    ) -> io::Result<Vec<C>> {
+        let mut constraints = Vec::with_capacity(ids.len());
+        let mut leading = Vec::new();
+        let mut ends = Vec::with_capacity(ids.len());
+        for c_id in ids {
+            let mut constraint = storage.read_constraint(*c_id)?.unwrap();
+            C::fix_constraint(&mut constraint, field);
+            leading.extend(constraint.leading_coefficients());
+            ends.push(leading.len());
+            constraints.push(constraint);
+        }
+        let inverses = field.batch_inverse(&leading).expect("zero coefficient in a constraint");
+        let mut start = 0;
+        for (constraint, end) in constraints.iter_mut().zip(ends) {
+            C::normalize_fixed(constraint, &inverses[start..end], field);
+            start = end;
+        }
+        Ok(constraints)
+    }
+
+    let mut seen: HashMap<u64, Vec<usize>> = HashMap::new();
+    let mut compared: HashMap<usize, C> = HashMap::new();
+    for block in storage.get_ids().chunks(BLOCK) {
+        let constraints = normal_forms(storage, block, field)?;
+        for (c_id, constraint) in block.iter().cloned().zip(constraints) {
+            if constraint.is_empty() {
+                // The ones already empty were moved to the linear constraints
+                if !storage.read_constraint(c_id)?.unwrap().is_empty() {
+                    storage.replace(c_id, C::empty())?;
+                    removed.trivial += 1;
+                }
+                continue;
+            }
+            let mut hasher = DefaultHasher::new();
+            constraint.hash(&mut hasher);
+            let candidates = seen.entry(hasher.finish()).or_default();
+            let mut repeated = false;
+            for other in candidates.iter() {
+                if !compared.contains_key(other) {
+                    let normal = normal_forms(storage, &[*other], field)?.pop().unwrap();
+                    compared.insert(*other, normal);
+                }
+                if compared[other] == constraint {
+                    repeated = true;
+                    break;
+                }
+            }
+            if repeated {
+                storage.replace(c_id, C::empty())?;
+                removed.duplicated += 1;
+            } else {
+                if !candidates.is_empty() {
+                    compared.insert(c_id, constraint);
+                }
+                candidates.push(c_id);
+            }
+        }
     }
-    linear
+    Ok(())
 }
 
 fn build_relevant_set(
@@ -438,17 +541,31 @@ fn remove_not_relevant(substitutions: &mut SEncoded, relevant: &HashSet<usize>)
     }
 }
 
-pub fn simplification(smp: &mut Simplifier) -> (ConstraintStorage, SignalMap) {
+// Fails when the constraints are kept in a temporary file and it can not be
+// read or written, or when the json log of the substitutions can not be created
+pub fn simplification(smp: &mut Simplifier) -> io::Result<(ConstraintStorage, SignalMap, RemovedConstraints)> {
     use super::non_linear_utils::obtain_and_simplify_non_linear;
     use circom_algebra::simplification_utils::build_encoded_fast_substitutions;
     use circom_algebra::simplification_utils::fast_encoded_constraint_substitution;
     use std::time::SystemTime;
 
-    let mut substitution_log =
-        if smp.port_substitution { Some(SubstitutionJSON::new(SUB_LOG).unwrap()) } else { None };
+    let mut file_system = FileSystem;
+    let json_log = if let Some(file) = &smp.port_substitution {
+        let writer = file_system.create_file(file)?;
+        let json = SubstitutionJSON::new(writer, file)
+            .map_err(|err| io::Error::new(err.cause.kind(), err.to_string()))?;
+        Some(json)
+    } else {
+        None
+    };
+    let mut substitution_log = SubstitutionLog {
+        json: json_log,
+        trace: smp.trace.take(),
+        stage: SimplificationStage::Equalities,
+    };
     let apply_linear = !smp.flag_s;
     let use_old_heuristics = smp.flag_old_heuristics;
-    let field = smp.field.clone();
+    let field = Field::new(&smp.field);
     let forbidden = Arc::new(std::mem::replace(&mut smp.forbidden, HashSet::with_capacity(0)));
     let no_labels = Simplifier::no_labels(smp);
     let equalities = std::mem::replace(&mut smp.equalities, LinkedList::new());
@@ -458,6 +575,7 @@ pub fn simplification(smp: &mut Simplifier) -> (ConstraintStorage, SignalMap) {
     let mut deleted = HashSet::new();
     let mut lconst = LinkedList::new();
     let mut no_rounds = smp.no_rounds;
+    let mut removed = RemovedConstraints::default();
     let remove_unused = true;
 
     let relevant_signals = {
@@ -485,7 +603,7 @@ pub fn simplification(smp: &mut Simplifier) -> (ConstraintStorage, SignalMap) {
         );
 
         LinkedList::append(&mut lconst, &mut cons);
-        let mut substitutions = build_encoded_fast_substitutions(subs);
+        let mut substitutions = build_encoded_fast_substitutions(subs, &field);
         for constraint in &mut linear {
             if fast_encoded_constraint_substitution(constraint, &substitutions, &field){
                 C::fix_constraint(constraint, &field);
@@ -508,10 +626,11 @@ pub fn simplification(smp: &mut Simplifier) -> (ConstraintStorage, SignalMap) {
     let cons_substitutions = {
         // println!("Start of constant assignment simplification");
         let now = SystemTime::now();
+        substitution_log.stage = SimplificationStage::ConstantEqualities;
         let (subs, mut cons) =
             constant_eq_simplification(cons_equalities, &forbidden, &field, &mut substitution_log);
         LinkedList::append(&mut lconst, &mut cons);
-        let substitutions = build_encoded_fast_substitutions(subs);
+        let substitutions = build_encoded_fast_substitutions(subs, &field);
         for constraint in &mut linear {
             if fast_encoded_constraint_substitution(constraint, &substitutions, &field){
                 C::fix_constraint(constraint, &field);
@@ -536,8 +655,10 @@ pub fn simplification(smp: &mut Simplifier) -> (ConstraintStorage, SignalMap) {
         relevant
     };
 
+    remove_trivial(&mut linear, &mut removed);
     let linear_substitutions = if apply_linear {
         let now = SystemTime::now();
+        substitution_log.stage = SimplificationStage::Linear(1);
         let (subs, mut cons) = linear_simplification(
             &mut substitution_log,
             linear,
@@ -555,7 +676,7 @@ pub fn simplification(smp: &mut Simplifier) -> (ConstraintStorage, SignalMap) {
                 only_relevant.push_back(substitution);
             }
         }
-        let substitutions = build_encoded_fast_substitutions(only_relevant);
+        let substitutions = build_encoded_fast_substitutions(only_relevant, &field);
         let _dur0 = now0.elapsed().unwrap().as_millis();
         // println!("End of substitution map: {} ms", dur0);
         let _dur = now.elapsed().unwrap().as_millis();
@@ -580,12 +701,15 @@ pub fn simplification(smp: &mut Simplifier) -> (ConstraintStorage, SignalMap) {
         LinkedList::push_back(&mut frames, cons_substitutions);
         LinkedList::push_back(&mut frames, linear_substitutions);
         let iter = EncodingIterator::new(&smp.dag_encoding);
-        let mut storage = ConstraintStorage::new();
-        let with_linear = obtain_and_simplify_non_linear(iter, &mut storage, &frames, &field);
+        let mut storage = match smp.memory_budget {
+            Some(budget) => ConstraintStorage::on_disk(budget << 20)?,
+            None => ConstraintStorage::new(),
+        };
+        let with_linear = obtain_and_simplify_non_linear(iter, &mut storage, &frames, &field)?;
         crate::state_utils::empty_encoding_constraints(&mut smp.dag_encoding);
         let _dur = now.elapsed().unwrap().as_millis();
         // println!("Storages built in {} ms", dur);
-        no_rounds -= 1;
+        no_rounds = no_rounds.saturating_sub(1);
         (with_linear, storage)
     };
 
@@ -596,7 +720,7 @@ pub fn simplification(smp: &mut Simplifier) -> (ConstraintStorage, SignalMap) {
     let mut non_linear_map = if apply_round || remove_unused {
         // println!("Building non-linear map");
         let now = SystemTime::now();
-        let non_linear_map = build_non_linear_signal_map(&constraint_storage);
+        let non_linear_map = build_non_linear_signal_map(&constraint_storage)?;
         let _dur = now.elapsed().unwrap().as_millis();
         // println!("Non-linear was built in {} ms", dur);
         non_linear_map
@@ -606,6 +730,8 @@ pub fn simplification(smp: &mut Simplifier) -> (ConstraintStorage, SignalMap) {
     while apply_round {
         let now = SystemTime::now();
         // println!("Number of linear constraints: {}", linear.len());
+        remove_trivial(&mut linear, &mut removed);
+        substitution_log.stage = SimplificationStage::Linear(round_id + 2);
         let (substitutions, mut constants) = linear_simplification(
             &mut substitution_log,
             linear,
@@ -630,7 +756,7 @@ pub fn simplification(smp: &mut Simplifier) -> (ConstraintStorage, SignalMap) {
             &mut non_linear_map,
             &substitutions,
             &field,
-        );
+        )?;
         round_id += 1;
         no_rounds -= 1;
         apply_round = !linear.is_empty() && no_rounds > 0;
@@ -638,10 +764,11 @@ pub fn simplification(smp: &mut Simplifier) -> (ConstraintStorage, SignalMap) {
         // println!("Iteration no {} took {} ms", round_id, dur);
     }
 
+    remove_trivial(&mut linear, &mut removed);
     for constraint in linear {
         if remove_unused {
             let signals =  C::take_cloned_signals(&constraint);
-            let c_id = constraint_storage.add_constraint(constraint);
+            let c_id = constraint_storage.add_constraint(constraint)?;
             for signal in signals {
                 if let Some(list) = non_linear_map.get_mut(&signal) {
                     list.push_back(c_id);
@@ -653,14 +780,17 @@ pub fn simplification(smp: &mut Simplifier) -> (ConstraintStorage, SignalMap) {
             }
         }
         else{
-            constraint_storage.add_constraint(constraint);
+            constraint_storage.add_constraint(constraint)?;
         }
     }
-    for mut constraint in lconst {
+    for constraint in &mut lconst {
+        C::fix_constraint(constraint, &field);
+    }
+    remove_trivial(&mut lconst, &mut removed);
+    for constraint in lconst {
         if remove_unused{
-            C::fix_constraint(&mut constraint, &field);
             let signals =  C::take_cloned_signals(&constraint);
-            let c_id = constraint_storage.add_constraint(constraint);
+            let c_id = constraint_storage.add_constraint(constraint)?;
             for signal in signals {
                 if let Some(list) = non_linear_map.get_mut(&signal) {
                     list.push_back(c_id);
@@ -672,8 +802,7 @@ pub fn simplification(smp: &mut Simplifier) -> (ConstraintStorage, SignalMap) {
             }
         }
         else{
-            C::fix_constraint(&mut constraint, &field);
-            constraint_storage.add_constraint(constraint);
+            constraint_storage.add_constraint(constraint)?;
         }
     }
 
@@ -687,7 +816,11 @@ pub fn simplification(smp: &mut Simplifier) -> (ConstraintStorage, SignalMap) {
         deleted.insert(signal);
     }
 
-    let _trash = constraint_storage.extract_with(&|c| C::is_empty(c));
+    // The empty constraints left are the duplicates, the trivial ones and the
+    // ones moved to the linear constraints during the rounds
+    remove_duplicates(&mut constraint_storage, &mut removed, &field)?;
+    let _trash = constraint_storage.extract_with(&|c| C::is_empty(c))?;
+    constraint_storage.compact_constants();
 
     let signal_map = {
         // println!("Rebuild witness");
@@ -698,12 +831,44 @@ pub fn simplification(smp: &mut Simplifier) -> (ConstraintStorage, SignalMap) {
         signal_map
     };
 
-    if let Some(w) = substitution_log {
+    if let Some(w) = substitution_log.json {
         w.end().unwrap();
     }
+    smp.trace = substitution_log.trace;
     // println!("NO CONSTANTS: {}", constraint_storage.no_constants());
-    (constraint_storage, signal_map)
+    Ok((constraint_storage, signal_map, removed))
 }
 
-
-
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use circom_algebra::linear_combination::LinearCombination;
+    use circom_algebra::num_bigint::BigInt;
+
+    #[test]
+    fn trivial_non_linear_constraints_are_emptied_with_the_duplicates() {
+        let field = Field::new(&BigInt::from(257));
+        let lc = |terms: &[(usize, i64)]| -> LinearCombination<usize> {
+            terms.iter().map(|(s, v)| (*s, field.from_bigint(&BigInt::from(*v)))).collect()
+        };
+        let (one, out, a, b) = (0, 1, 2, 3);
+        let mut storage = ConstraintStorage::new();
+        let constraints = vec![
+            C::new(lc(&[(a, 1)]), lc(&[(b, 1)]), lc(&[(out, 1)])),
+            C::new(lc(&[(a, 2)]), lc(&[(b, 3)]), lc(&[(out, 6)])),
+            // 2 * 3 = 6 is only 0 = 0 once it is fixed
+            C::new(lc(&[(one, 2)]), lc(&[(one, 3)]), lc(&[(one, 6)])),
+            // Moved to the linear constraints, it is not counted again
+            C::empty(),
+        ];
+        for constraint in constraints {
+            storage.add_constraint(constraint).unwrap();
+        }
+        let mut removed = RemovedConstraints::default();
+        remove_duplicates(&mut storage, &mut removed, &field).unwrap();
+        assert_eq!(removed.duplicated, 1);
+        assert_eq!(removed.trivial, 1);
+        assert_eq!(storage.extract_with(&|c| C::is_empty(c)).unwrap().len(), 3);
+        assert_eq!(storage.get_ids().len(), 1);
+    }
+}
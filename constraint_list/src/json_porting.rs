@@ -1,33 +1,32 @@
 use super::{ConstraintStorage, C, S};
 use crate::SignalMap;
-use circom_algebra::num_bigint::BigInt;
+use circom_algebra::field::Field;
+use circom_algebra::linear_combination::LinearCombination;
 use constraint_writers::debug_writer::DebugWriter;
 use json::JsonValue;
-use std::collections::HashMap;
+use program_structure::output_directory::OutputDirectory;
+use program_structure::output_error::{OutputError, OutputResult};
 
-pub fn transform_constraint_to_json(constraint: &C) -> JsonValue {
+pub fn transform_constraint_to_json(constraint: &C, field: &Field) -> JsonValue {
     JsonValue::Array(vec![
-        hashmap_as_json(constraint.a()),
-        hashmap_as_json(constraint.b()),
-        hashmap_as_json(constraint.c()),
+        hashmap_as_json(constraint.a(), field),
+        hashmap_as_json(constraint.b(), field),
+        hashmap_as_json(constraint.c(), field),
     ])
 }
 
-fn hashmap_as_json(values: &HashMap<usize, BigInt>) -> JsonValue {
-    let mut order: Vec<&usize> = values.keys().collect();
-    order.sort();
+fn hashmap_as_json(values: &LinearCombination<usize>, field: &Field) -> JsonValue {
     let mut correspondence = json::object! {};
-    for i in order {
-        let (key, value) = values.get_key_value(i).unwrap();
-        let value = value.to_str_radix(10);
+    for (key, value) in values {
+        let value = field.to_bigint(value).to_str_radix(10);
         correspondence[format!("{}", key)] = value.as_str().into();
     }
     correspondence
 }
 
 #[allow(unused)]
-pub fn port_substitution(sub: &S) -> (String, String) {
-    let to = hashmap_as_json(sub.to()).to_string();
+pub fn port_substitution(sub: &S, field: &Field) -> (String, String) {
+    let to = hashmap_as_json(sub.to(), field).to_string();
     let from = sub.from().to_string();
     (from, to)
 }
@@ -35,13 +34,15 @@ pub fn port_substitution(sub: &S) -> (String, String) {
 pub fn port_constraints(
     storage: &ConstraintStorage,
     map: &SignalMap,
+    field: &Field,
+    directory: &mut dyn OutputDirectory,
     debug: &DebugWriter,
-) -> Result<(), ()> {
-    let mut writer = debug.build_constraints_file()?;
+) -> OutputResult<()> {
+    let mut writer = debug.build_constraints_file(directory)?;
     for c_id in storage.get_ids() {
-        let constraint = storage.read_constraint(c_id).unwrap();
+        let constraint = storage.read_constraint(c_id).map_err(|err| OutputError::new(&debug.json_constraints, err))?.unwrap();
         let constraint = C::apply_correspondence(&constraint, map);
-        let json_value = transform_constraint_to_json(&constraint);
+        let json_value = transform_constraint_to_json(&constraint, field);
         writer.write_constraint(&json_value.to_string())?;
     }
     writer.end()
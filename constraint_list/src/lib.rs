@@ -1,17 +1,34 @@
 use std::collections::{HashMap, HashSet, LinkedList};
 
 use circom_algebra::constraint_storage::ConstraintStorage;
+use circom_algebra::field::Field;
 use circom_algebra::num_bigint::BigInt;
 use constraint_writers::debug_writer::DebugWriter;
+use constraint_writers::log_writer::Log;
 use constraint_writers::ConstraintExporter;
+use program_structure::error_code::ReportCode;
+use program_structure::error_definition::Report;
+use program_structure::output_directory::OutputDirectory;
+use program_structure::output_error::OutputResult;
+use std::io;
 
+mod constraint_diff;
 mod constraint_simplification;
 mod json_porting;
 mod non_linear_utils;
+mod r1cs_import;
 mod r1cs_porting;
 mod state_utils;
 mod sym_porting;
 mod non_linear_simplification;
+mod signal_explanation;
+#[cfg(test)]
+mod test_circuit;
+
+pub use constraint_diff::{CanonicalConstraint, CanonicalSystem, CountDelta, NamedExpression, SystemDiff};
+pub use signal_explanation::{
+    Cause, ConstraintOrigin, SignalExplanation, SignalFate, SimplificationStage, SubstitutionTrace,
+};
 
 type C = circom_algebra::algebra::Constraint<usize>;
 type S = circom_algebra::algebra::Substitution<usize>;
@@ -121,15 +138,22 @@ pub struct Simplifier {
     pub max_signal: usize,
     // Flags
     pub no_rounds: usize,
+    // In megabytes, the constraints left after the linear simplification are
+    // kept in a temporary file when given. The encoding and the lists above
+    // stay in memory.
+    pub memory_budget: Option<usize>,
     pub parallel_flag: bool,
     pub flag_s: bool,
     pub flag_old_heuristics: bool,
-    pub port_substitution: bool,
+    // When given, the substitutions applied are written in this json file
+    pub port_substitution: Option<String>,
+    // When given, the substitutions are recorded to explain them afterwards
+    pub trace: Option<SubstitutionTrace>,
 }
 impl Simplifier {
-    pub fn simplify_constraints(mut self) -> ConstraintList {
-        let (portable, map) = constraint_simplification::simplification(&mut self);
-        ConstraintList {
+    pub fn simplify_constraints(mut self) -> io::Result<ConstraintList> {
+        let (portable, map, removed) = constraint_simplification::simplification(&mut self)?;
+        Ok(ConstraintList {
             field: self.field,
             dag_encoding: self.dag_encoding,
             no_public_outputs: self.no_public_outputs,
@@ -138,7 +162,9 @@ impl Simplifier {
             no_labels: self.max_signal,
             constraints: portable,
             signal_map: map,
-        }
+            removed,
+            trace: self.trace,
+        })
     }
 
     pub fn no_labels(&self) -> usize {
@@ -150,6 +176,23 @@ impl Simplifier {
     }
 }
 
+// Error of the temporary file that keeps the constraints when the
+// simplification has a memory budget
+pub fn storage_error_report(error: io::Error) -> Report {
+    let mut report =
+        Report::error("Could not use the temporary file of the constraints".to_string(), ReportCode::FileOs);
+    report.add_note(format!("Cause: {}", error));
+    report
+}
+
+// Constraints dropped at the end of the simplification: the ones that are
+// a multiple of another one and the ones that became 0 = 0
+#[derive(Clone, Copy, Default, Debug)]
+pub struct RemovedConstraints {
+    pub duplicated: usize,
+    pub trivial: usize,
+}
+
 pub struct ConstraintList {
     pub field: BigInt,
     pub dag_encoding: DAGEncoding,
@@ -160,19 +203,22 @@ pub struct ConstraintList {
     pub no_labels: usize,
     //  Signals in [witness_len, Vec::len(&signal_map)) are the ones deleted
     pub signal_map: SignalMap,
+    pub removed: RemovedConstraints,
+    pub trace: Option<SubstitutionTrace>,
 }
 
 impl ConstraintExporter for ConstraintList {
-    fn r1cs(&self, out: &str, custom_gates: bool) -> Result<(), ()> {
-        r1cs_porting::port_r1cs(self, out, custom_gates)
+    fn r1cs(&self, directory: &mut dyn OutputDirectory, out: &str, custom_gates: bool) -> OutputResult<Log> {
+        r1cs_porting::port_r1cs(self, directory, out, custom_gates)
     }
 
-    fn json_constraints(&self, writer: &DebugWriter) -> Result<(), ()> {
-        json_porting::port_constraints(&self.constraints, &self.signal_map, writer)
+    fn json_constraints(&self, directory: &mut dyn OutputDirectory, writer: &DebugWriter) -> OutputResult<()> {
+        let field = Field::new(&self.field);
+        json_porting::port_constraints(&self.constraints, &self.signal_map, &field, directory, writer)
     }
 
-    fn sym(&self, out: &str) -> Result<(), ()> {
-        sym_porting::port_sym(self, out)
+    fn sym(&self, directory: &mut dyn OutputDirectory, out: &str) -> OutputResult<()> {
+        sym_porting::port_sym(self, directory, out)
     }
 }
 
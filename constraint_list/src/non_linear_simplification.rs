@@ -1,4 +1,4 @@
-use circom_algebra::num_bigint::BigInt;
+use circom_algebra::field::Field;
 use circom_algebra::constraint_storage::ConstraintStorage;
 use std::collections::{HashSet, LinkedList};
 
@@ -6,7 +6,7 @@ use std::collections::{HashSet, LinkedList};
 pub fn simplify(
     _storage: &mut ConstraintStorage,
     _forbidden: &HashSet<usize>,
-    _field: &BigInt
+    _field: &Field
 ) -> LinkedList<usize> {
     LinkedList::new()
 }
@@ -0,0 +1,228 @@
+use super::{ConstraintList, DAGEncoding, EncodingEdge, EncodingNode, Simplifier, C};
+use constraint_writers::r1cs_reader::R1CSFile;
+use constraint_writers::sym_writer::SymElem;
+use std::collections::{HashMap, HashSet, LinkedList};
+
+impl Simplifier {
+    // Simplifier of a constraint system read from an r1cs file. The signals
+    // are the labels of the wires, as in a compilation, so the result maps
+    // labels to the new wires. Every custom gate is a node of the encoding
+    // and every application an edge to it, which keeps them in the output.
+    // The flags are the ones of --O2 and can be changed before simplifying.
+    pub fn from_r1cs(r1cs: &R1CSFile) -> Simplifier {
+        let field = &r1cs.field;
+        let header = &r1cs.header;
+        let labels = &r1cs.wire_to_label;
+        let mut forbidden: HashSet<usize> =
+            labels.iter().take(1 + header.public_outputs + header.public_inputs).cloned().collect();
+        let mut linear = LinkedList::new();
+        let mut equalities = LinkedList::new();
+        let mut cons_equalities = LinkedList::new();
+        let mut non_linear = LinkedList::new();
+        for [a, b, c] in &r1cs.constraints {
+            let mut constraint = C::new(a.clone(), b.clone(), c.clone()).apply_witness(labels);
+            C::fix_constraint(&mut constraint, field);
+            if constraint.is_empty() {
+                continue;
+            } else if constraint.is_constant_equality() {
+                cons_equalities.push_back(constraint);
+            } else if constraint.is_equality(field) {
+                equalities.push_back(constraint);
+            } else if C::is_linear(&constraint) {
+                linear.push_back(constraint);
+            } else {
+                non_linear.push_back(constraint);
+            }
+        }
+
+        let no_constraints = non_linear.len();
+        let main = EncodingNode {
+            id: 0,
+            name: "main".to_string(),
+            parameters: Vec::new(),
+            signals: Vec::new(),
+            ordered_signals: Vec::new(),
+            non_linear,
+            is_custom_gate: false,
+        };
+        let mut nodes = vec![main];
+        let mut adjacency = vec![Vec::new()];
+        let usages = r1cs.custom_gates_used.iter().flatten().enumerate();
+        for (index, (name, parameters)) in usages {
+            // The writer takes the name of the template up to the parenthesis
+            nodes.push(EncodingNode {
+                id: index + 1,
+                name: format!("{}({})", name, index),
+                parameters: parameters.clone(),
+                signals: Vec::new(),
+                ordered_signals: Vec::new(),
+                non_linear: LinkedList::new(),
+                is_custom_gate: true,
+            });
+            adjacency.push(Vec::new());
+        }
+        for (gate, wires) in r1cs.custom_gates_applied.iter().flatten() {
+            let signals: Vec<usize> = wires.iter().map(|wire| labels[*wire]).collect();
+            forbidden.extend(signals.iter().cloned());
+            nodes[gate + 1].ordered_signals = signals;
+            let path = format!("custom_gate_{}", adjacency[0].len());
+            adjacency[0].push(EncodingEdge { goes_to: gate + 1, path, offset: 0 });
+        }
+
+        Simplifier {
+            field: field.prime().clone(),
+            dag_encoding: DAGEncoding { init: 0, no_constraints, nodes, adjacency },
+            no_public_inputs: header.public_inputs,
+            no_public_outputs: header.public_outputs,
+            no_private_inputs: header.private_inputs,
+            forbidden,
+            cons_equalities,
+            equalities,
+            linear,
+            max_signal: header.number_of_labels,
+            no_rounds: usize::MAX,
+            memory_budget: None,
+            parallel_flag: false,
+            flag_s: false,
+            flag_old_heuristics: false,
+            port_substitution: None,
+            trace: None,
+        }
+    }
+}
+
+impl ConstraintList {
+    // The symbols of the original file with the wires of this list. The
+    // ones whose label is not a wire anymore take the witness -1.
+    pub fn port_symbols(&self, symbols: &[SymElem]) -> Vec<SymElem> {
+        let map: &HashMap<usize, usize> = &self.signal_map;
+        let wire = |label: i64| {
+            if label < 0 {
+                return -1;
+            }
+            map.get(&(label as usize)).map_or(-1, |wire| *wire as i64)
+        };
+        symbols
+            .iter()
+            .map(|elem| SymElem {
+                original: elem.original,
+                witness: if elem.witness < 0 { -1 } else { wire(elem.original) },
+                node_id: elem.node_id,
+                symbol: elem.symbol.clone(),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use circom_algebra::field::{Field, FieldElement};
+    use circom_algebra::linear_combination::LinearCombination;
+    use circom_algebra::num_bigint::BigInt;
+    use constraint_writers::r1cs_reader::{field_size, read_r1cs};
+    use constraint_writers::r1cs_writer::HeaderData;
+    use constraint_writers::ConstraintExporter;
+    use program_structure::output_directory::MemoryDirectory;
+
+    // out = x * y with the linear chain x = a + 1, y = 2 * x. The wires are
+    // one, out, a and the intermediate x and y, whose labels skip the 3.
+    fn r1cs(field: &Field) -> R1CSFile {
+        let value = |v: u64| field.from_bigint(&BigInt::from(v));
+        let lc = |terms: &[(usize, u64)]| -> LinearCombination<usize> {
+            terms.iter().map(|(s, v)| (*s, value(*v))).collect()
+        };
+        let minus_one = field.neg(&value(1));
+        let minus_two = field.neg(&value(2));
+        let linear = |terms: Vec<(usize, FieldElement)>| -> [LinearCombination<usize>; 3] {
+            [LinearCombination::new(), LinearCombination::new(), terms.into_iter().collect()]
+        };
+        R1CSFile {
+            field: field.clone(),
+            field_size: field_size(field.prime()),
+            header: HeaderData {
+                field: field.prime().clone(),
+                total_wires: 5,
+                public_outputs: 1,
+                public_inputs: 1,
+                private_inputs: 0,
+                number_of_labels: 6,
+                number_of_constraints: 3,
+            },
+            constraints: vec![
+                [lc(&[(3, 1)]), lc(&[(4, 1)]), lc(&[(1, 1)])],
+                linear(vec![(3, value(1)), (2, minus_one.clone()), (0, minus_one)]),
+                linear(vec![(4, value(1)), (3, minus_two)]),
+            ],
+            wire_to_label: vec![0, 1, 2, 4, 5],
+            custom_gates_used: None,
+            custom_gates_applied: None,
+        }
+    }
+
+    #[test]
+    fn resimplification_of_an_r1cs() {
+        let field = Field::new(&BigInt::from(257));
+        let input = r1cs(&field);
+        let list = Simplifier::from_r1cs(&input).simplify_constraints().unwrap();
+        let mut directory = MemoryDirectory::new();
+        list.r1cs(&mut directory, "out.r1cs", false).unwrap();
+        let output = read_r1cs(directory.get_file("out.r1cs").unwrap(), "out.r1cs").unwrap();
+
+        // x and y are replaced, so out = (a + 1) * (2a + 2) remains
+        assert_eq!(output.constraints.len(), 1);
+        assert_eq!(output.header.total_wires, 3);
+        assert_eq!(output.header.number_of_labels, 6);
+        assert_eq!(output.wire_to_label, vec![0, 1, 2]);
+        let [a, b, c] = &output.constraints[0];
+        assert_eq!(c, &vec![(1, field.from_bigint(&BigInt::from(1)))].into_iter().collect());
+        assert_eq!(a.len() + b.len(), 4);
+
+        let symbols = vec![
+            SymElem { original: 1, witness: 1, node_id: 0, symbol: "main.out".to_string() },
+            SymElem { original: 3, witness: -1, node_id: 0, symbol: "main.gone".to_string() },
+            SymElem { original: 4, witness: 3, node_id: 0, symbol: "main.x".to_string() },
+        ];
+        let ported = list.port_symbols(&symbols);
+        assert_eq!(ported.iter().map(|elem| elem.witness).collect::<Vec<_>>(), vec![1, -1, -1]);
+    }
+
+    #[test]
+    fn duplicated_and_trivial_constraints_are_removed() {
+        let field = Field::new(&BigInt::from(257));
+        let lc = |terms: &[(usize, i64)]| -> LinearCombination<usize> {
+            terms.iter().map(|(s, v)| (*s, field.from_bigint(&BigInt::from(*v)))).collect()
+        };
+        let (one, out, a, b, x, k) = (0, 1, 2, 3, 4, 5);
+        let input = R1CSFile {
+            field: field.clone(),
+            field_size: field_size(field.prime()),
+            header: HeaderData {
+                field: field.prime().clone(),
+                total_wires: 6,
+                public_outputs: 1,
+                public_inputs: 2,
+                private_inputs: 0,
+                number_of_labels: 6,
+                number_of_constraints: 6,
+            },
+            constraints: vec![
+                // out = a * b, twice more as multiples
+                [lc(&[(a, 1)]), lc(&[(b, 1)]), lc(&[(out, 1)])],
+                [lc(&[(a, 2)]), lc(&[(b, 3)]), lc(&[(out, 6)])],
+                [lc(&[(b, -1)]), lc(&[(a, 1)]), lc(&[(out, -1)])],
+                // x = a + 1 and k = x - a - 1, so k * b = 0 becomes 0 = 0
+                [lc(&[]), lc(&[]), lc(&[(x, 1), (a, -1), (one, -1)])],
+                [lc(&[]), lc(&[]), lc(&[(k, 1), (x, -1), (a, 1), (one, 1)])],
+                [lc(&[(k, 1)]), lc(&[(b, 1)]), lc(&[])],
+            ],
+            wire_to_label: (0..6).collect(),
+            custom_gates_used: None,
+            custom_gates_applied: None,
+        };
+        let list = Simplifier::from_r1cs(&input).simplify_constraints().unwrap();
+        assert_eq!(list.removed.duplicated, 2);
+        assert_eq!(list.removed.trivial, 1);
+        assert_eq!(list.constraints.get_ids().len(), 1);
+    }
+}
@@ -1,8 +1,16 @@
 use super::{ConstraintList, C, EncodingIterator, SignalMap};
-use constraint_writers::r1cs_writer::{ConstraintSection, CustomGatesAppliedData, HeaderData, R1CSWriter, SignalSection};
+use circom_algebra::field::Field;
+use constraint_writers::r1cs_writer::{constraint_size, ConstraintSection, CustomGatesAppliedData, HeaderData, R1CSWriter, SignalSection};
+use constraint_writers::log_writer::Log;
+use program_structure::output_directory::OutputDirectory;
+use program_structure::output_error::{OutputError, OutputResult};
 
-pub fn port_r1cs(list: &ConstraintList, output: &str, custom_gates: bool) -> Result<(), ()> {
-    use constraint_writers::log_writer::Log;
+pub fn port_r1cs(
+    list: &ConstraintList,
+    directory: &mut dyn OutputDirectory,
+    output: &str,
+    custom_gates: bool,
+) -> OutputResult<Log> {
     let field_size = if list.field.bits() % 64 == 0 {
         list.field.bits() / 8
     } else{
@@ -14,15 +22,34 @@ pub fn port_r1cs(list: &ConstraintList, output: &str, custom_gates: bool) -> Res
     log.no_private_inputs = list.no_private_inputs;
     log.no_public_inputs = list.no_public_inputs;
     log.no_public_outputs = list.no_public_outputs;
+    log.no_duplicated = list.removed.duplicated;
+    log.no_trivial = list.removed.trivial;
 
-    let r1cs = R1CSWriter::new(output.to_string(), field_size, custom_gates)?;
-    let mut constraint_section = R1CSWriter::start_constraints_section(r1cs)?;
+    // The size of the constraints section is computed before writing it, the
+    // constraints are read one at a time in both passes
+    let read_error = |err| OutputError::new(output, err).in_part("constraints section");
+    let mut section_size = 0;
+    for c_id in list.constraints.get_ids() {
+        let c = list.constraints.read_constraint(c_id).map_err(read_error)?.unwrap();
+        section_size += constraint_size(c.a().len() + c.b().len() + c.c().len(), field_size);
+    }
+
+    let writer = directory.create_file(output).map_err(|err| OutputError::new(output, err))?;
+    let r1cs = R1CSWriter::new(writer, output.to_string(), field_size, custom_gates)?;
+    let mut constraint_section = R1CSWriter::start_constraints_section(r1cs, section_size)?;
     let mut written = 0;
+    let field = Field::new(&list.field);
 
     for c_id in list.constraints.get_ids() {
-        let c = list.constraints.read_constraint(c_id).unwrap();
+        let c = list.constraints.read_constraint(c_id).map_err(read_error)?.unwrap();
         let c = C::apply_correspondence(&c, &list.signal_map);
-        ConstraintSection::write_constraint_usize(&mut constraint_section, c.a(), c.b(), c.c())?;
+        ConstraintSection::write_constraint_usize(
+            &mut constraint_section,
+            c.a(),
+            c.b(),
+            c.c(),
+            &field,
+        )?;
         if C::is_linear(&c) {
             log.no_linear += 1;
         } else {
@@ -44,9 +71,10 @@ pub fn port_r1cs(list: &ConstraintList, output: &str, custom_gates: bool) -> Res
     };
     header_section.write_section(header_data)?;
     let r1cs = header_section.end_section()?;
-    let mut signal_section = R1CSWriter::start_signal_section(r1cs)?;
+    let witness = list.get_witness_as_vec();
+    let mut signal_section = R1CSWriter::start_signal_section(r1cs, witness.len())?;
 
-    for id in list.get_witness_as_vec() {
+    for id in witness {
         SignalSection::write_signal_usize(&mut signal_section, id)?;
     }
     let r1cs = signal_section.end_section()?;
@@ -118,6 +146,41 @@ pub fn port_r1cs(list: &ConstraintList, output: &str, custom_gates: bool) -> Res
         let r1cs = custom_gates_applied_section.end_section()?;
 	R1CSWriter::finish_writing(r1cs)?;
     }
-    Log::print(&log);
-    Ok(())
+    Ok(log)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_circuit;
+    use constraint_writers::r1cs_reader::read_r1cs_for_prime;
+    use constraint_writers::sym_reader::read_sym;
+    use constraint_writers::ConstraintExporter;
+    use program_structure::file_definition::FileLibrary;
+    use program_structure::output_directory::MemoryDirectory;
+
+    #[test]
+    fn r1cs_and_sym_files_read_back() {
+        let list = test_circuit::simplifier(&mut FileLibrary::new()).simplify_constraints().unwrap();
+        let mut directory = MemoryDirectory::new();
+        let log = list.r1cs(&mut directory, "circuit.r1cs", false).unwrap();
+        list.sym(&mut directory, "circuit.sym").unwrap();
+
+        let bytes = directory.get_file("circuit.r1cs").unwrap();
+        let r1cs = read_r1cs_for_prime(bytes, "circuit.r1cs", &list.field).unwrap();
+        assert_eq!(r1cs.header.public_outputs, 1);
+        assert_eq!(r1cs.header.public_inputs, 1);
+        assert_eq!(r1cs.header.private_inputs, 1);
+        assert_eq!(r1cs.header.total_wires, 4);
+        assert_eq!(r1cs.constraints.len(), r1cs.header.number_of_constraints);
+        assert_eq!(log.no_public_inputs, r1cs.header.public_inputs);
+        assert_eq!(log.no_wires, r1cs.header.total_wires);
+        assert_eq!(log.no_linear + log.no_non_linear, r1cs.constraints.len());
+
+        let symbols = read_sym(directory.get_file("circuit.sym").unwrap(), "circuit.sym").unwrap();
+        assert_eq!(symbols.len(), test_circuit::SIGNALS.len());
+        assert!(symbols.iter().any(|elem| elem.symbol == "main.c" && elem.witness == 1));
+        for elem in symbols.iter().filter(|elem| elem.witness >= 0) {
+            assert_eq!(r1cs.wire_to_label[elem.witness as usize] as i64, elem.original);
+        }
+    }
 }
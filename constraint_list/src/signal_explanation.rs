@@ -0,0 +1,304 @@
+use super::constraint_diff::{named_expression, write_expression};
+use super::{sym_porting, CanonicalConstraint, ConstraintList, NamedExpression, C, S};
+use circom_algebra::field::Field;
+use circom_algebra::linear_combination::LinearCombination;
+use circom_algebra::num_traits::Zero;
+use program_structure::file_definition::{FileID, FileLibrary, FileLocation};
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+// The component and the statement that generated a constraint
+#[derive(Clone)]
+pub struct ConstraintOrigin {
+    pub component: String,
+    pub template: String,
+    pub file_id: Option<FileID>,
+    pub location: FileLocation,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum SimplificationStage {
+    Equalities,
+    ConstantEqualities,
+    // Rounds of the linear simplification, starting from 1
+    Linear(usize),
+}
+
+// The constraints given to the simplification, over the labels, and the
+// substitutions it applied in the order they were found
+#[derive(Default)]
+pub struct SubstitutionTrace {
+    pub originals: Vec<(C, ConstraintOrigin)>,
+    pub substitutions: Vec<(S, SimplificationStage)>,
+}
+
+pub struct Cause {
+    pub constraint: CanonicalConstraint,
+    pub origin: String,
+}
+
+pub enum SignalFate {
+    Unknown,
+    Kept(usize),
+    // Not substituted, but left out of every constraint
+    Unused,
+    // The causes are the original constraints that, after the substitutions
+    // of the previous stages, are a multiple of the substitution. When there
+    // is none, it combines several constraints and the causes are the linear
+    // ones that involve the signal.
+    Substituted {
+        stage: SimplificationStage,
+        replaced_by: NamedExpression,
+        resolved: NamedExpression,
+        causes: Vec<Cause>,
+        exact: bool,
+    },
+}
+
+pub struct SignalExplanation {
+    pub signal: String,
+    pub fate: SignalFate,
+}
+
+impl ConstraintList {
+    // What the simplification did to each of the signals, the list must have
+    // been simplified with a trace of the substitutions
+    pub fn explain(&self, signals: &[String], files: &FileLibrary) -> Vec<SignalExplanation> {
+        let trace = self.trace.as_ref().expect("the substitutions were not traced");
+        let field = Field::new(&self.field);
+        let mut names: Vec<String> = (0..self.no_labels).map(|label| format!("#{}", label)).collect();
+        let mut labels = HashMap::new();
+        for elem in sym_porting::collect_symbols(self) {
+            let label = elem.original as usize;
+            labels.insert(elem.symbol.clone(), label);
+            names[label] = elem.symbol;
+        }
+        let by_signal: HashMap<usize, &S> = trace.substitutions.iter().map(|(s, _)| (*s.from(), s)).collect();
+        signals
+            .iter()
+            .map(|signal| {
+                let fate = match labels.get(signal) {
+                    None => SignalFate::Unknown,
+                    Some(label) => match self.signal_map.get(label) {
+                        Some(wire) => SignalFate::Kept(*wire),
+                        None => explain_removed(*label, trace, &by_signal, &names, &field, files),
+                    },
+                };
+                SignalExplanation { signal: signal.clone(), fate }
+            })
+            .collect()
+    }
+}
+
+fn explain_removed(
+    label: usize,
+    trace: &SubstitutionTrace,
+    by_signal: &HashMap<usize, &S>,
+    names: &[String],
+    field: &Field,
+    files: &FileLibrary,
+) -> SignalFate {
+    let (substitution, stage) = match trace.substitutions.iter().find(|(s, _)| *s.from() == label) {
+        Some(found) => found,
+        None => return SignalFate::Unused,
+    };
+    let resolved = resolve(substitution.clone(), by_signal, label, field);
+    let previous: HashMap<usize, &S> =
+        trace.substitutions.iter().filter(|(_, s_stage)| s_stage < stage).map(|(s, _)| (*s.from(), s)).collect();
+    let as_constraint = |substitution: S| {
+        let mut constraint = S::substitution_into_constraint(substitution, field);
+        C::normalize(&mut constraint, field);
+        constraint
+    };
+    let expected = as_constraint(resolve(substitution.clone(), &previous, label, field));
+
+    // The signals whose substitution may bring this one into a constraint
+    let mut users: HashMap<usize, Vec<usize>> = HashMap::new();
+    for s in previous.values() {
+        for signal in s.take_signals() {
+            users.entry(*signal).or_default().push(*s.from());
+        }
+    }
+    let mut related = HashSet::new();
+    let mut pending = vec![label];
+    while let Some(signal) = pending.pop() {
+        if related.insert(signal) {
+            pending.extend(users.get(&signal).into_iter().flatten());
+        }
+    }
+
+    let mut exact = Vec::new();
+    let mut involved = Vec::new();
+    for (original, origin) in &trace.originals {
+        if !original.take_signals().iter().any(|signal| related.contains(signal)) {
+            continue;
+        }
+        let mut constraint = resolve(original.clone(), &previous, label, field);
+        C::fix_constraint(&mut constraint, field);
+        if !C::is_linear(&constraint) || !constraint.take_signals().contains(&label) {
+            continue;
+        }
+        C::normalize(&mut constraint, field);
+        let cause = Cause { constraint: named_constraint(original, names, field), origin: describe_origin(origin, files) };
+        if constraint == expected { exact.push(cause) } else { involved.push(cause) }
+    }
+    SignalFate::Substituted {
+        stage: *stage,
+        replaced_by: rename(substitution.to(), names, field),
+        resolved: rename(resolved.to(), names, field),
+        exact: !exact.is_empty(),
+        causes: if exact.is_empty() { involved } else { exact },
+    }
+}
+
+// The expression over the names of the signals, without the zero terms
+fn rename(expr: &LinearCombination<usize>, names: &[String], field: &Field) -> NamedExpression {
+    let mut named = named_expression(&expr.map_symbols(|label| names[*label].clone()), field);
+    named.retain(|(_, value)| !value.is_zero());
+    named
+}
+
+// The constraint as it was generated, over the names of the signals
+fn named_constraint(constraint: &C, names: &[String], field: &Field) -> CanonicalConstraint {
+    CanonicalConstraint {
+        a: rename(constraint.a(), names, field),
+        b: rename(constraint.b(), names, field),
+        c: rename(constraint.c(), names, field),
+    }
+}
+
+// Substitutions and constraints, both can be resolved with the substitutions
+trait Resolvable: Sized {
+    fn signals(&self) -> HashSet<usize>;
+    fn substitute(&mut self, substitution: &S, field: &Field);
+}
+
+impl Resolvable for S {
+    fn signals(&self) -> HashSet<usize> {
+        self.take_cloned_signals()
+    }
+    fn substitute(&mut self, substitution: &S, field: &Field) {
+        S::apply_substitution(self, substitution, field);
+    }
+}
+
+impl Resolvable for C {
+    fn signals(&self) -> HashSet<usize> {
+        self.take_cloned_signals()
+    }
+    fn substitute(&mut self, substitution: &S, field: &Field) {
+        C::apply_substitution(self, substitution, field);
+    }
+}
+
+// Applies the substitutions until none of their signals is left, except the
+// one given, which is the signal being explained
+fn resolve<R: Resolvable>(mut value: R, by_signal: &HashMap<usize, &S>, kept: usize, field: &Field) -> R {
+    // The substitutions are acyclic, the bound only guards against a wrong trace
+    for _ in 0..=by_signal.len() {
+        let pending: Vec<&S> = value
+            .signals()
+            .iter()
+            .filter(|signal| **signal != kept)
+            .filter_map(|signal| by_signal.get(signal).cloned())
+            .collect();
+        if pending.is_empty() {
+            break;
+        }
+        for substitution in pending {
+            value.substitute(substitution, field);
+        }
+    }
+    value
+}
+
+fn describe_origin(origin: &ConstraintOrigin, files: &FileLibrary) -> String {
+    let place = origin.file_id.and_then(|file_id| {
+        let file = files.to_storage().get(file_id)?;
+        let line = files.get_line(origin.location.start, file_id)?;
+        Some(format!("{}:{}", file.name(), line))
+    });
+    match place {
+        Some(place) => format!("{}, an instance of {}, at {}", origin.component, origin.template, place),
+        None => format!("{}, an instance of {}", origin.component, origin.template),
+    }
+}
+
+impl Display for SimplificationStage {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            SimplificationStage::Equalities => write!(f, "the simplification of the equalities between signals"),
+            SimplificationStage::ConstantEqualities => write!(f, "the simplification of the equalities to constants"),
+            SimplificationStage::Linear(round) => write!(f, "round {} of the linear simplification", round),
+        }
+    }
+}
+
+impl Display for SignalExplanation {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        let (stage, replaced_by, resolved, causes, exact) = match &self.fate {
+            SignalFate::Unknown => return write!(f, "{}: there is no signal with this name", self.signal),
+            SignalFate::Kept(wire) => return write!(f, "{}: kept as the wire {}", self.signal, wire),
+            SignalFate::Unused => {
+                return write!(f, "{}: removed, no constraint uses it after the simplification", self.signal)
+            }
+            SignalFate::Substituted { stage, replaced_by, resolved, causes, exact } => {
+                (stage, replaced_by, resolved, causes, exact)
+            }
+        };
+        write!(f, "{}: replaced by ", self.signal)?;
+        write_expression(f, replaced_by)?;
+        writeln!(f, " in {}", stage)?;
+        if resolved != replaced_by {
+            write!(f, "  in terms of the remaining signals: ")?;
+            write_expression(f, resolved)?;
+            writeln!(f)?;
+        }
+        if causes.is_empty() {
+            return write!(f, "  no original constraint involves the signal");
+        }
+        let heading = if *exact { "caused by" } else { "obtained combining these with other constraints of the same stage" };
+        write!(f, "  {}:", heading)?;
+        for cause in causes {
+            write!(f, "\n    {}\n      in {}", cause.constraint, cause.origin)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_circuit;
+
+    #[test]
+    fn simplification_explains_what_happened_to_a_signal() {
+        let mut files = FileLibrary::new();
+        let list = test_circuit::simplifier(&mut files).simplify_constraints().unwrap();
+
+        let signals: Vec<String> =
+            ["main.s", "main.t", "main.c", "main.d"].iter().map(|s| s.to_string()).collect();
+        let explanations = list.explain(&signals, &files);
+        match &explanations[0].fate {
+            SignalFate::Substituted { stage, causes, exact, .. } => {
+                assert_eq!(*stage, SimplificationStage::Equalities);
+                assert!(*exact);
+                assert_eq!(causes.len(), 1);
+                assert!(causes[0].origin.starts_with("main, an instance of Main(3), at"));
+                assert!(causes[0].origin.ends_with(":2"));
+            }
+            _ => panic!("main.s should be substituted"),
+        }
+        match &explanations[1].fate {
+            SignalFate::Substituted { stage, causes, exact, .. } => {
+                assert_eq!(*stage, SimplificationStage::Linear(1));
+                assert!(*exact);
+                assert!(causes[0].origin.ends_with(":3"));
+            }
+            _ => panic!("main.t should be substituted"),
+        }
+        assert!(matches!(explanations[2].fate, SignalFate::Kept(1)));
+        assert!(matches!(explanations[3].fate, SignalFate::Unknown));
+        assert!(explanations[1].to_string().starts_with("main.t: replaced by "));
+    }
+}
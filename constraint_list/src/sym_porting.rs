@@ -1,21 +1,37 @@
 use super::{ConstraintList, EncodingIterator, IteratorSignal, SignalMap};
 use circom_algebra::num_traits::AsPrimitive;
 use constraint_writers::sym_writer::*;
+use program_structure::output_directory::OutputDirectory;
+use program_structure::output_error::{OutputError, OutputResult};
+use std::convert::Infallible;
 
-pub fn port_sym(list: &ConstraintList, file_name: &str) -> Result<(), ()> {
+pub fn port_sym(list: &ConstraintList, directory: &mut dyn OutputDirectory, file_name: &str) -> OutputResult<()> {
     let iter = EncodingIterator::new(&list.dag_encoding);
-    let mut dot_sym = SymFile::new(file_name)?;
-    signal_iteration(iter, &list.signal_map, &mut dot_sym)?;
+    let writer = directory.create_file(file_name).map_err(|err| OutputError::new(file_name, err))?;
+    let mut dot_sym = SymFile::new(writer, file_name);
+    signal_iteration(iter, &list.signal_map, &mut |elem| SymFile::write_sym_elem(&mut dot_sym, elem))?;
     SymFile::finish_writing(dot_sym)?;
     //SymFile::close(dot_sym);
     Ok(())
 }
 
-pub fn signal_iteration(
+// The lines that port_sym writes, in the same order
+pub fn collect_symbols(list: &ConstraintList) -> Vec<SymElem> {
+    let iter = EncodingIterator::new(&list.dag_encoding);
+    let mut symbols = Vec::new();
+    let result: Result<(), Infallible> = signal_iteration(iter, &list.signal_map, &mut |elem| {
+        symbols.push(elem);
+        Ok(())
+    });
+    result.unwrap();
+    symbols
+}
+
+pub fn signal_iteration<E>(
     mut iter: EncodingIterator,
     map: &SignalMap,
-    dot_sym: &mut SymFile,
-) -> Result<(), ()> {
+    emit: &mut impl FnMut(SymElem) -> Result<(), E>,
+) -> Result<(), E> {
     let (signals, _) = EncodingIterator::take(&mut iter);
 
     for signal in signals {
@@ -26,12 +42,12 @@ pub fn signal_iteration(
             node_id: iter.node_id.as_(),
             symbol: signal.name.clone(),
         };
-        SymFile::write_sym_elem(dot_sym, sym_elem)?;
+        emit(sym_elem)?;
     }
 
     for edge in EncodingIterator::edges(&iter) {
         let next = EncodingIterator::next(&iter, edge);
-        signal_iteration(next, map, dot_sym)?;
+        signal_iteration(next, map, emit)?;
     }
     Ok(())
 }
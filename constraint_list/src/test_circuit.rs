@@ -0,0 +1,74 @@
+// A small circuit built by hand, as the compiler would give it to the
+// simplification, for the tests of the outputs of a constraint list
+use super::*;
+use crate::signal_explanation::ConstraintOrigin;
+use circom_algebra::linear_combination::LinearCombination;
+use program_structure::file_definition::FileLibrary;
+
+pub const SOURCE: &str = "template Main(n) {
+    s <== b;
+    t <== a + 2 * s;
+    c <== t * s;
+}
+";
+
+// The labels of the signals, 0 is the constant one
+pub const SIGNALS: [(&str, usize); 5] = [("c", 1), ("b", 2), ("a", 3), ("s", 4), ("t", 5)];
+
+// c is the output and b the public input. The simplification replaces s by
+// b with the equalities and t by a + 2 * b in the first linear round, which
+// leaves c = (a + 2 * b) * b.
+pub fn simplifier(files: &mut FileLibrary) -> Simplifier {
+    let field = Field::new(&BigInt::from(257));
+    let lc = |terms: &[(usize, i64)]| -> LinearCombination<usize> {
+        let value = |v: i64| field.from_bigint(&((BigInt::from(v) + 257) % 257));
+        terms.iter().map(|(s, v)| (*s, value(*v))).collect()
+    };
+    let (c, b, a, s, t) = (1, 2, 3, 4, 5);
+    let equality = C::new(lc(&[]), lc(&[]), lc(&[(s, 1), (b, -1)]));
+    let linear = C::new(lc(&[]), lc(&[]), lc(&[(t, 1), (a, -1), (s, -2)]));
+    let non_linear = C::new(lc(&[(t, 1)]), lc(&[(s, 1)]), lc(&[(c, 1)]));
+
+    let file_id = files.add_file("main.circom".to_string(), SOURCE.to_string());
+    let origin = |line: usize| {
+        let start: usize = SOURCE.lines().take(line - 1).map(|l| l.len() + 1).sum();
+        ConstraintOrigin {
+            component: "main".to_string(),
+            template: "Main(3)".to_string(),
+            file_id: Some(file_id),
+            location: start..start + 1,
+        }
+    };
+    let trace = SubstitutionTrace {
+        originals: vec![(equality.clone(), origin(2)), (linear.clone(), origin(3)), (non_linear.clone(), origin(4))],
+        substitutions: Vec::new(),
+    };
+    let main = EncodingNode {
+        id: 0,
+        name: "Main(3)".to_string(),
+        parameters: vec![BigInt::from(3)],
+        signals: SIGNALS.iter().map(|(name, id)| SignalInfo { name: name.to_string(), id: *id }).collect(),
+        ordered_signals: Vec::new(),
+        non_linear: vec![non_linear].into_iter().collect(),
+        is_custom_gate: false,
+    };
+    Simplifier {
+        field: field.prime().clone(),
+        dag_encoding: DAGEncoding { init: 0, no_constraints: 1, nodes: vec![main], adjacency: vec![Vec::new()] },
+        no_public_inputs: 1,
+        no_public_outputs: 1,
+        no_private_inputs: 1,
+        forbidden: vec![0, c, b].into_iter().collect(),
+        cons_equalities: LinkedList::new(),
+        equalities: vec![equality].into_iter().collect(),
+        linear: vec![linear].into_iter().collect(),
+        max_signal: 6,
+        no_rounds: usize::MAX,
+        memory_budget: None,
+        parallel_flag: false,
+        flag_s: false,
+        flag_old_heuristics: false,
+        port_substitution: None,
+        trace: Some(trace),
+    }
+}
@@ -1,4 +1,6 @@
 use super::json_writer::ConstraintJSON;
+use program_structure::output_directory::OutputDirectory;
+use program_structure::output_error::{OutputError, OutputResult};
 
 #[derive(Clone)]
 pub struct DebugWriter {
@@ -9,7 +11,12 @@ impl DebugWriter {
         Result::Ok(DebugWriter { json_constraints: c })
     }
 
-    pub fn build_constraints_file(&self) -> Result<ConstraintJSON, ()> {
-        ConstraintJSON::new(&self.json_constraints)
+    pub fn build_constraints_file<'a>(
+        &self,
+        directory: &'a mut dyn OutputDirectory,
+    ) -> OutputResult<ConstraintJSON<'a>> {
+        let file = &self.json_constraints;
+        let writer = directory.create_file(file).map_err(|err| OutputError::new(file, err))?;
+        ConstraintJSON::new(writer, file)
     }
 }
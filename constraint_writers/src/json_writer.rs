@@ -1,100 +1,98 @@
-use std::fs::File;
-use std::io::{BufWriter, Write};
+use std::io::Write;
+use program_structure::output_directory::OutputSink;
+use program_structure::output_error::{OutputError, OutputResult};
 
-pub struct ConstraintJSON {
-    writer_constraints: BufWriter<File>,
+pub struct ConstraintJSON<'a> {
+    file: String,
+    writer_constraints: Box<dyn OutputSink + 'a>,
     constraints_flag: bool,
 }
 
-impl ConstraintJSON {
-    pub fn new(file: &str) -> Result<ConstraintJSON, ()> {
-        let file_constraints = File::create(file).map_err(|_err| {})?;
-        let mut writer_constraints = BufWriter::new(file_constraints);
+impl<'a> ConstraintJSON<'a> {
+    pub fn new(mut writer_constraints: Box<dyn OutputSink + 'a>, file: &str) -> OutputResult<ConstraintJSON<'a>> {
 
-        writer_constraints.write_all(b"{").map_err(|_err| {})?;
-        writer_constraints.flush().map_err(|_err| {})?;
-        writer_constraints.write_all(b"\n\"constraints\": [").map_err(|_err| {})?;
-        writer_constraints.flush().map_err(|_err| {})?;
+        writer_constraints.write_all(b"{").map_err(|err| OutputError::new(file, err))?;
+        writer_constraints.flush().map_err(|err| OutputError::new(file, err))?;
+        writer_constraints.write_all(b"\n\"constraints\": [").map_err(|err| OutputError::new(file, err))?;
+        writer_constraints.flush().map_err(|err| OutputError::new(file, err))?;
 
-        Result::Ok(ConstraintJSON { writer_constraints, constraints_flag: false })
+        Result::Ok(ConstraintJSON { file: file.to_string(), writer_constraints, constraints_flag: false })
     }
-    pub fn write_constraint(&mut self, constraint: &str) -> Result<(), ()> {
+    pub fn write_constraint(&mut self, constraint: &str) -> OutputResult<()> {
         if !self.constraints_flag {
             self.constraints_flag = true;
-            self.writer_constraints.write_all(b"\n").map_err(|_err| {})?;
-            self.writer_constraints.flush().map_err(|_err| {})?;
+            self.writer_constraints.write_all(b"\n").map_err(|err| OutputError::new(&self.file, err))?;
+            self.writer_constraints.flush().map_err(|err| OutputError::new(&self.file, err))?;
         } else {
-            self.writer_constraints.write_all(b",\n").map_err(|_err| {})?;
-            self.writer_constraints.flush().map_err(|_err| {})?;
+            self.writer_constraints.write_all(b",\n").map_err(|err| OutputError::new(&self.file, err))?;
+            self.writer_constraints.flush().map_err(|err| OutputError::new(&self.file, err))?;
         }
-        self.writer_constraints.write_all(constraint.as_bytes()).map_err(|_err| {})?;
-        self.writer_constraints.flush().map_err(|_err| {})?;
+        self.writer_constraints.write_all(constraint.as_bytes()).map_err(|err| OutputError::new(&self.file, err))?;
+        self.writer_constraints.flush().map_err(|err| OutputError::new(&self.file, err))?;
         Result::Ok(())
     }
-    pub fn end(mut self) -> Result<(), ()> {
-        self.writer_constraints.write_all(b"\n]\n}").map_err(|_err| {})?;
-        self.writer_constraints.flush().map_err(|_err| {})?;
+    pub fn end(mut self) -> OutputResult<()> {
+        self.writer_constraints.write_all(b"\n]\n}").map_err(|err| OutputError::new(&self.file, err))?;
+        self.writer_constraints.flush().map_err(|err| OutputError::new(&self.file, err))?;
         Result::Ok(())
     }
 }
 
-pub struct SignalsJSON {
-    writer_signals: BufWriter<File>,
+pub struct SignalsJSON<'a> {
+    file: String,
+    writer_signals: Box<dyn OutputSink + 'a>,
 }
-impl SignalsJSON {
-    pub fn new(file: &str) -> Result<SignalsJSON, ()> {
-        let file_signals = File::create(file).map_err(|_err| {})?;
-        let mut writer_signals = BufWriter::new(file_signals);
-        writer_signals.write_all(b"{").map_err(|_err| {})?;
-        writer_signals.flush().map_err(|_err| {})?;
-        writer_signals.write_all(b"\n\"signalName2Idx\": {").map_err(|_err| {})?;
-        writer_signals.flush().map_err(|_err| {})?;
-        writer_signals.write_all(b"\n\"one\" : \"0\"").map_err(|_err| {})?;
-        writer_signals.flush().map_err(|_err| {})?;
-        Result::Ok(SignalsJSON { writer_signals })
+impl<'a> SignalsJSON<'a> {
+    pub fn new(mut writer_signals: Box<dyn OutputSink + 'a>, file: &str) -> OutputResult<SignalsJSON<'a>> {
+        writer_signals.write_all(b"{").map_err(|err| OutputError::new(file, err))?;
+        writer_signals.flush().map_err(|err| OutputError::new(file, err))?;
+        writer_signals.write_all(b"\n\"signalName2Idx\": {").map_err(|err| OutputError::new(file, err))?;
+        writer_signals.flush().map_err(|err| OutputError::new(file, err))?;
+        writer_signals.write_all(b"\n\"one\" : \"0\"").map_err(|err| OutputError::new(file, err))?;
+        writer_signals.flush().map_err(|err| OutputError::new(file, err))?;
+        Result::Ok(SignalsJSON { file: file.to_string(), writer_signals })
     }
-    pub fn write_correspondence(&mut self, signal: String, data: String) -> Result<(), ()> {
+    pub fn write_correspondence(&mut self, signal: String, data: String) -> OutputResult<()> {
         self.writer_signals
             .write_all(format!(",\n\"{}\" : {}", signal, data).as_bytes())
-            .map_err(|_err| {})?;
-        self.writer_signals.flush().map_err(|_err| {})
+            .map_err(|err| OutputError::new(&self.file, err))?;
+        self.writer_signals.flush().map_err(|err| OutputError::new(&self.file, err))
     }
-    pub fn end(mut self) -> Result<(), ()> {
-        self.writer_signals.write_all(b"\n}\n}").map_err(|_err| {})?;
-        self.writer_signals.flush().map_err(|_err| {})
+    pub fn end(mut self) -> OutputResult<()> {
+        self.writer_signals.write_all(b"\n}\n}").map_err(|err| OutputError::new(&self.file, err))?;
+        self.writer_signals.flush().map_err(|err| OutputError::new(&self.file, err))
     }
 }
 
-pub struct SubstitutionJSON {
-    writer_substitutions: BufWriter<File>,
+pub struct SubstitutionJSON<'a> {
+    file: String,
+    writer_substitutions: Box<dyn OutputSink + 'a>,
     first: bool,
 }
-impl SubstitutionJSON {
-    pub fn new(file: &str) -> Result<SubstitutionJSON, ()> {
+impl<'a> SubstitutionJSON<'a> {
+    pub fn new(mut writer_substitutions: Box<dyn OutputSink + 'a>, file: &str) -> OutputResult<SubstitutionJSON<'a>> {
         let first = true;
-        let file_substitutions = File::create(file).map_err(|_err| {})?;
-        let mut writer_substitutions = BufWriter::new(file_substitutions);
-        writer_substitutions.write_all(b"{").map_err(|_err| {})?;
-        writer_substitutions.flush().map_err(|_err| {})?;
-        writer_substitutions.write_all(b"\n\"substitution\": {").map_err(|_err| {})?;
-        writer_substitutions.flush().map_err(|_err| {})?;
-        Result::Ok(SubstitutionJSON { writer_substitutions, first })
+        writer_substitutions.write_all(b"{").map_err(|err| OutputError::new(file, err))?;
+        writer_substitutions.flush().map_err(|err| OutputError::new(file, err))?;
+        writer_substitutions.write_all(b"\n\"substitution\": {").map_err(|err| OutputError::new(file, err))?;
+        writer_substitutions.flush().map_err(|err| OutputError::new(file, err))?;
+        Result::Ok(SubstitutionJSON { file: file.to_string(), writer_substitutions, first })
     }
-    pub fn write_substitution(&mut self, signal: &str, substitution: &str) -> Result<(), ()> {
+    pub fn write_substitution(&mut self, signal: &str, substitution: &str) -> OutputResult<()> {
         if self.first {
             self.first = false;
-            self.writer_substitutions.write_all(b"\n").map_err(|_err| {})?;
+            self.writer_substitutions.write_all(b"\n").map_err(|err| OutputError::new(&self.file, err))?;
         } else {
-            self.writer_substitutions.write_all(b",\n").map_err(|_err| {})?;
+            self.writer_substitutions.write_all(b",\n").map_err(|err| OutputError::new(&self.file, err))?;
         }
         let substitution = format!("\"{}\" : {}", signal, substitution);
-        self.writer_substitutions.flush().map_err(|_err| {})?;
-        self.writer_substitutions.write_all(substitution.as_bytes()).map_err(|_err| {})?;
-        self.writer_substitutions.flush().map_err(|_err| {})?;
+        self.writer_substitutions.flush().map_err(|err| OutputError::new(&self.file, err))?;
+        self.writer_substitutions.write_all(substitution.as_bytes()).map_err(|err| OutputError::new(&self.file, err))?;
+        self.writer_substitutions.flush().map_err(|err| OutputError::new(&self.file, err))?;
         Result::Ok(())
     }
-    pub fn end(mut self) -> Result<(), ()> {
-        self.writer_substitutions.write_all(b"\n}\n}").map_err(|_err| {})?;
-        self.writer_substitutions.flush().map_err(|_err| {})
+    pub fn end(mut self) -> OutputResult<()> {
+        self.writer_substitutions.write_all(b"\n}\n}").map_err(|err| OutputError::new(&self.file, err))?;
+        self.writer_substitutions.flush().map_err(|err| OutputError::new(&self.file, err))
     }
 }
@@ -1,11 +1,20 @@
 pub mod debug_writer;
 pub mod json_writer;
 pub mod log_writer;
+pub mod r1cs_reader;
 pub mod r1cs_writer;
+pub mod sym_reader;
 pub mod sym_writer;
 
+use log_writer::Log;
+use program_structure::output_directory::OutputDirectory;
+use program_structure::output_error::OutputResult;
+
+// The files are created in the given directory, so the same exporter can
+// write to disk or keep the results in memory. Writing the r1cs file returns
+// the statistics of the constraints it contains.
 pub trait ConstraintExporter {
-    fn r1cs(&self, out: &str, custom_gates: bool) -> Result<(), ()>;
-    fn json_constraints(&self, writer: &debug_writer::DebugWriter) -> Result<(), ()>;
-    fn sym(&self, out: &str) -> Result<(), ()>;
+    fn r1cs(&self, directory: &mut dyn OutputDirectory, out: &str, custom_gates: bool) -> OutputResult<Log>;
+    fn json_constraints(&self, directory: &mut dyn OutputDirectory, writer: &debug_writer::DebugWriter) -> OutputResult<()>;
+    fn sym(&self, directory: &mut dyn OutputDirectory, out: &str) -> OutputResult<()>;
 }
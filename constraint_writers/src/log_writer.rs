@@ -1,3 +1,4 @@
+#[derive(Clone, Debug)]
 pub struct Log {
     pub no_linear: usize,
     pub no_non_linear: usize,
@@ -7,6 +8,8 @@ pub struct Log {
     pub no_private_inputs: usize,
     pub no_public_outputs: usize,
     pub no_private_outputs: usize,
+    pub no_duplicated: usize,
+    pub no_trivial: usize,
 }
 
 impl Log {
@@ -20,6 +23,8 @@ impl Log {
             no_private_outputs: 0,
             no_wires: 0,
             no_labels: 0,
+            no_duplicated: 0,
+            no_trivial: 0,
         }
     }
 
@@ -32,5 +37,7 @@ impl Log {
         println!("private outputs: {}", log.no_private_outputs);
         println!("wires: {}", log.no_wires);
         println!("labels: {}", log.no_labels);
+        println!("duplicated constraints removed: {}", log.no_duplicated);
+        println!("trivial constraints removed: {}", log.no_trivial);
     }
 }
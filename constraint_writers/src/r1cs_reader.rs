@@ -0,0 +1,436 @@
+use super::r1cs_writer::{constraint_size, CustomGatesAppliedData, CustomGatesUsedData, HeaderData, R1CSWriter};
+use circom_algebra::field::Field;
+use circom_algebra::linear_combination::LinearCombination;
+use circom_algebra::num_bigint::{BigInt, Sign};
+use program_structure::input_error::{InputError, InputResult};
+use program_structure::output_directory::OutputDirectory;
+use program_structure::output_error::{OutputError, OutputResult};
+use std::convert::TryInto;
+
+const MAGIC: &[u8] = b"r1cs";
+const VERSION: u32 = 1;
+const HEADER_TYPE: u32 = 1;
+const CONSTRAINT_TYPE: u32 = 2;
+const WIRE2LABEL_TYPE: u32 = 3;
+const CUSTOM_GATES_USED_TYPE: u32 = 4;
+const CUSTOM_GATES_APPLIED_TYPE: u32 = 5;
+
+// A, B and C of a constraint A*B - C = 0
+pub type R1CSConstraint = [LinearCombination<usize>; 3];
+
+// Contents of an r1cs file, in the structures the writer takes
+pub struct R1CSFile {
+    pub field: Field,
+    pub field_size: usize,
+    pub header: HeaderData,
+    pub constraints: Vec<R1CSConstraint>,
+    pub wire_to_label: Vec<usize>,
+    pub custom_gates_used: Option<CustomGatesUsedData>,
+    pub custom_gates_applied: Option<CustomGatesAppliedData>,
+}
+
+impl R1CSFile {
+    // Writes the sections in the order of the compiler, so a file produced
+    // by it is written back byte by byte
+    pub fn write(&self, directory: &mut dyn OutputDirectory, output: &str) -> OutputResult<()> {
+        let custom_gates = self.custom_gates_used.is_some();
+        let writer = directory.create_file(output).map_err(|err| OutputError::new(output, err))?;
+        let r1cs = R1CSWriter::new(writer, output.to_string(), self.field_size, custom_gates)?;
+        let size = self
+            .constraints
+            .iter()
+            .map(|[a, b, c]| constraint_size(a.len() + b.len() + c.len(), self.field_size))
+            .sum();
+        let mut constraint_section = R1CSWriter::start_constraints_section(r1cs, size)?;
+        for [a, b, c] in &self.constraints {
+            constraint_section.write_constraint_usize(a, b, c, &self.field)?;
+        }
+        let r1cs = constraint_section.end_section()?;
+        let mut header_section = R1CSWriter::start_header_section(r1cs)?;
+        header_section.write_section(self.header.clone())?;
+        let r1cs = header_section.end_section()?;
+        let mut signal_section = R1CSWriter::start_signal_section(r1cs, self.wire_to_label.len())?;
+        for label in &self.wire_to_label {
+            signal_section.write_signal_usize(*label)?;
+        }
+        let mut r1cs = signal_section.end_section()?;
+        if let (Some(usages), Some(applications)) = (&self.custom_gates_used, &self.custom_gates_applied) {
+            let mut used_section = R1CSWriter::start_custom_gates_used_section(r1cs)?;
+            used_section.write_custom_gates_usages(usages.clone())?;
+            let next = used_section.end_section()?;
+            let mut applied_section = R1CSWriter::start_custom_gates_applied_section(next)?;
+            applied_section.write_custom_gates_applications(applications.clone())?;
+            r1cs = applied_section.end_section()?;
+        }
+        R1CSWriter::finish_writing(r1cs)
+    }
+}
+
+// Number of bytes used for every element of the field, as the writer does
+pub fn field_size(prime: &BigInt) -> usize {
+    if prime.bits() % 64 == 0 {
+        prime.bits() / 8
+    } else {
+        (prime.bits() / 64 + 1) * 8
+    }
+}
+
+pub fn read_r1cs_file(path: &str) -> InputResult<R1CSFile> {
+    let bytes = std::fs::read(path).map_err(|err| InputError::new(path, err))?;
+    read_r1cs(&bytes, path)
+}
+
+// Fails as well if the file was produced for another prime
+pub fn read_r1cs_for_prime(bytes: &[u8], file: &str, prime: &BigInt) -> InputResult<R1CSFile> {
+    let r1cs = read_r1cs(bytes, file)?;
+    if r1cs.field.prime() != prime {
+        let reason = format!("the prime is {} instead of {}", r1cs.field.prime(), prime);
+        return Result::Err(InputError::invalid(file, reason).in_part(HEADER));
+    }
+    Result::Ok(r1cs)
+}
+
+const HEADER: &str = "header section";
+const CONSTRAINTS: &str = "constraints section";
+const WIRE2LABEL: &str = "wire to label section";
+const CUSTOM_GATES_USED: &str = "custom gates used section";
+const CUSTOM_GATES_APPLIED: &str = "custom gates applied section";
+
+// The sections can be in any order, so they are located first and read
+// once the header is known. Every section must be consumed exactly.
+pub fn read_r1cs(bytes: &[u8], file: &str) -> InputResult<R1CSFile> {
+    let invalid = |reason: String| InputError::invalid(file, reason);
+    let mut input = Bytes::new(bytes);
+    if input.take(4).map_err(invalid)? != MAGIC {
+        return Result::Err(invalid("it is not an r1cs file".to_string()));
+    }
+    let version = input.u32().map_err(invalid)?;
+    if version != VERSION {
+        return Result::Err(invalid(format!("the version {} is not supported", version)));
+    }
+    let number_of_sections = input.u32().map_err(invalid)?;
+    let mut sections: [Option<&[u8]>; 5] = [None; 5];
+    for _ in 0..number_of_sections {
+        let section_type = input.u32().map_err(invalid)?;
+        let size = input.u64().map_err(invalid)?;
+        let contents = input.take(size as usize).map_err(invalid)?;
+        if section_type < HEADER_TYPE || section_type > CUSTOM_GATES_APPLIED_TYPE {
+            continue;
+        }
+        let slot = &mut sections[section_type as usize - 1];
+        if slot.is_some() {
+            return Result::Err(invalid(format!("the section {} appears twice", section_type)));
+        }
+        *slot = Some(contents);
+    }
+    if !input.is_empty() {
+        return Result::Err(invalid("there are bytes after the last section".to_string()));
+    }
+    let section = |section_type: u32, part: &str| {
+        sections[section_type as usize - 1]
+            .ok_or_else(|| InputError::invalid(file, "the section is missing".to_string()).in_part(part))
+    };
+
+    let (field, field_size, header) =
+        read_header(section(HEADER_TYPE, HEADER)?).map_err(|reason| invalid(reason).in_part(HEADER))?;
+    let constraints = read_constraints(section(CONSTRAINT_TYPE, CONSTRAINTS)?, &field, field_size, &header)
+        .map_err(|reason| invalid(reason).in_part(CONSTRAINTS))?;
+    let wire_to_label = read_wire_to_label(section(WIRE2LABEL_TYPE, WIRE2LABEL)?, &header)
+        .map_err(|reason| invalid(reason).in_part(WIRE2LABEL))?;
+    let custom_gates_used = match sections[CUSTOM_GATES_USED_TYPE as usize - 1] {
+        Some(contents) => Some(
+            read_custom_gates_used(contents, &field, field_size)
+                .map_err(|reason| invalid(reason).in_part(CUSTOM_GATES_USED))?,
+        ),
+        None => None,
+    };
+    let custom_gates_applied = match sections[CUSTOM_GATES_APPLIED_TYPE as usize - 1] {
+        Some(contents) => Some(
+            read_custom_gates_applied(contents, &header, custom_gates_used.as_ref())
+                .map_err(|reason| invalid(reason).in_part(CUSTOM_GATES_APPLIED))?,
+        ),
+        None => None,
+    };
+    if custom_gates_used.is_some() != custom_gates_applied.is_some() {
+        return Result::Err(invalid("only one of the custom gates sections is present".to_string()));
+    }
+    Result::Ok(R1CSFile {
+        field,
+        field_size,
+        header,
+        constraints,
+        wire_to_label,
+        custom_gates_used,
+        custom_gates_applied,
+    })
+}
+
+fn read_header(contents: &[u8]) -> Result<(Field, usize, HeaderData), String> {
+    let mut input = Bytes::new(contents);
+    let size = input.u32()? as usize;
+    let prime = BigInt::from_bytes_le(Sign::Plus, input.take(size)?);
+    if prime <= BigInt::from(2) || &prime % BigInt::from(2) == BigInt::from(0) {
+        return Result::Err(format!("{} is not a valid prime", prime));
+    }
+    if size != field_size(&prime) {
+        return Result::Err(format!("the prime {} does not take {} bytes", prime, size));
+    }
+    let header = HeaderData {
+        field: prime.clone(),
+        total_wires: input.u32()? as usize,
+        public_outputs: input.u32()? as usize,
+        public_inputs: input.u32()? as usize,
+        private_inputs: input.u32()? as usize,
+        number_of_labels: input.u64()? as usize,
+        number_of_constraints: input.u32()? as usize,
+    };
+    input.finish()?;
+    if 1 + header.public_outputs + header.public_inputs + header.private_inputs > header.total_wires {
+        return Result::Err("there are more inputs and outputs than wires".to_string());
+    }
+    Result::Ok((Field::new(&prime), size, header))
+}
+
+fn read_constraints(
+    contents: &[u8],
+    field: &Field,
+    field_size: usize,
+    header: &HeaderData,
+) -> Result<Vec<R1CSConstraint>, String> {
+    let mut input = Bytes::new(contents);
+    // Every constraint takes at least 12 bytes, a wrong count does not allocate more
+    let mut constraints = Vec::with_capacity(header.number_of_constraints.min(contents.len() / 12));
+    for _ in 0..header.number_of_constraints {
+        let a = read_linear_combination(&mut input, field, field_size, header)?;
+        let b = read_linear_combination(&mut input, field, field_size, header)?;
+        let c = read_linear_combination(&mut input, field, field_size, header)?;
+        constraints.push([a, b, c]);
+    }
+    input.finish()?;
+    Result::Ok(constraints)
+}
+
+fn read_linear_combination(
+    input: &mut Bytes,
+    field: &Field,
+    field_size: usize,
+    header: &HeaderData,
+) -> Result<LinearCombination<usize>, String> {
+    let terms = input.u32()? as usize;
+    let mut linear_combination = Vec::new();
+    for _ in 0..terms {
+        let wire = input.u32()? as usize;
+        if wire >= header.total_wires {
+            return Result::Err(format!("the wire {} does not exist", wire));
+        }
+        let value = read_field_element(input, field, field_size)?;
+        linear_combination.push((wire, field.from_bigint(&value)));
+    }
+    let linear_combination: LinearCombination<usize> = linear_combination.into_iter().collect();
+    if linear_combination.len() != terms {
+        return Result::Err("a linear combination uses the same wire twice".to_string());
+    }
+    Result::Ok(linear_combination)
+}
+
+fn read_field_element(input: &mut Bytes, field: &Field, field_size: usize) -> Result<BigInt, String> {
+    let value = BigInt::from_bytes_le(Sign::Plus, input.take(field_size)?);
+    if &value >= field.prime() {
+        return Result::Err(format!("the value {} is not an element of the field", value));
+    }
+    Result::Ok(value)
+}
+
+fn read_wire_to_label(contents: &[u8], header: &HeaderData) -> Result<Vec<usize>, String> {
+    let mut input = Bytes::new(contents);
+    let mut wire_to_label = Vec::with_capacity(header.total_wires.min(contents.len() / 8));
+    for _ in 0..header.total_wires {
+        let label = input.u64()? as usize;
+        if label >= header.number_of_labels {
+            return Result::Err(format!("the label {} does not exist", label));
+        }
+        wire_to_label.push(label);
+    }
+    input.finish()?;
+    Result::Ok(wire_to_label)
+}
+
+fn read_custom_gates_used(contents: &[u8], field: &Field, field_size: usize) -> Result<CustomGatesUsedData, String> {
+    let mut input = Bytes::new(contents);
+    let number_of_gates = input.u32()?;
+    let mut usages = Vec::new();
+    for _ in 0..number_of_gates {
+        let name = input.null_terminated()?;
+        let number_of_parameters = input.u32()?;
+        let mut parameters = Vec::new();
+        for _ in 0..number_of_parameters {
+            parameters.push(read_field_element(&mut input, field, field_size)?);
+        }
+        usages.push((name, parameters));
+    }
+    input.finish()?;
+    Result::Ok(usages)
+}
+
+fn read_custom_gates_applied(
+    contents: &[u8],
+    header: &HeaderData,
+    usages: Option<&CustomGatesUsedData>,
+) -> Result<CustomGatesAppliedData, String> {
+    let number_of_gates = usages.map_or(0, |usages| usages.len());
+    let mut input = Bytes::new(contents);
+    let number_of_applications = input.u32()?;
+    let mut applications = Vec::new();
+    for _ in 0..number_of_applications {
+        let gate = input.u32()? as usize;
+        if gate >= number_of_gates {
+            return Result::Err(format!("the custom gate {} does not exist", gate));
+        }
+        let number_of_signals = input.u32()?;
+        let mut signals = Vec::new();
+        for _ in 0..number_of_signals {
+            let signal = input.u64()? as usize;
+            if signal >= header.total_wires {
+                return Result::Err(format!("the wire {} does not exist", signal));
+            }
+            signals.push(signal);
+        }
+        applications.push((gate, signals));
+    }
+    input.finish()?;
+    Result::Ok(applications)
+}
+
+// Little endian reading of a slice, failing instead of going past its end
+struct Bytes<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Bytes<'a> {
+    fn new(bytes: &'a [u8]) -> Bytes<'a> {
+        Bytes { bytes }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    fn take(&mut self, size: usize) -> Result<&'a [u8], String> {
+        if size > self.bytes.len() {
+            return Result::Err(format!("{} bytes are missing", size - self.bytes.len()));
+        }
+        let (taken, rest) = self.bytes.split_at(size);
+        self.bytes = rest;
+        Result::Ok(taken)
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        Result::Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, String> {
+        Result::Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn null_terminated(&mut self) -> Result<String, String> {
+        let end = self.bytes.iter().position(|byte| *byte == 0).ok_or("a name is not terminated")?;
+        let name = String::from_utf8(self.take(end)?.to_vec()).map_err(|_| "a name is not valid utf-8")?;
+        self.take(1)?;
+        Result::Ok(name)
+    }
+
+    // The contents of a section must take exactly its size
+    fn finish(&self) -> Result<(), String> {
+        if self.bytes.is_empty() {
+            Result::Ok(())
+        } else {
+            Result::Err(format!("{} bytes are left unread", self.bytes.len()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use program_structure::output_directory::MemoryDirectory;
+
+    fn header(field: &BigInt, number_of_constraints: usize) -> HeaderData {
+        HeaderData {
+            field: field.clone(),
+            total_wires: 4,
+            public_outputs: 1,
+            public_inputs: 1,
+            private_inputs: 1,
+            number_of_labels: 6,
+            number_of_constraints,
+        }
+    }
+
+    fn write(field: &Field, constraints: &[R1CSConstraint], custom_gates: bool) -> Vec<u8> {
+        let r1cs = R1CSFile {
+            field: field.clone(),
+            field_size: field_size(field.prime()),
+            header: header(field.prime(), constraints.len()),
+            constraints: constraints.to_vec(),
+            wire_to_label: vec![0, 1, 2, 5],
+            custom_gates_used: Some(vec![("Mul".to_string(), vec![BigInt::from(7)])]).filter(|_| custom_gates),
+            custom_gates_applied: Some(vec![(0, vec![1, 3])]).filter(|_| custom_gates),
+        };
+        let mut directory = MemoryDirectory::new();
+        r1cs.write(&mut directory, "test.r1cs").unwrap();
+        directory.remove_file("test.r1cs").unwrap()
+    }
+
+    fn constraints(field: &Field) -> Vec<R1CSConstraint> {
+        let value = |v: i64| field.from_bigint(&(BigInt::from(v) % field.prime() + field.prime()));
+        let lc = |terms: &[(usize, i64)]| terms.iter().map(|(s, v)| (*s, value(*v))).collect();
+        vec![
+            [lc(&[(2, 1)]), lc(&[(3, 1)]), lc(&[(1, 1)])],
+            [lc(&[(0, 5), (2, -1)]), lc(&[]), lc(&[(3, 2), (1, -3)])],
+        ]
+    }
+
+    #[test]
+    fn r1cs_round_trip() {
+        let field = Field::new(&BigInt::parse_bytes(b"21888242871839275222246405745257275088548364400416034343698204186575808495617", 10).unwrap());
+        for custom_gates in [false, true] {
+            let written = constraints(&field);
+            let bytes = write(&field, &written, custom_gates);
+            let read = read_r1cs_for_prime(&bytes, "test.r1cs", field.prime()).unwrap();
+            assert_eq!(read.field_size, 32);
+            assert_eq!(read.header.number_of_constraints, 2);
+            assert_eq!(read.header.number_of_labels, 6);
+            assert_eq!(read.constraints, written);
+            assert_eq!(read.wire_to_label, vec![0, 1, 2, 5]);
+            assert_eq!(read.custom_gates_used.is_some(), custom_gates);
+            // Writing what was read gives the same file
+            let mut directory = MemoryDirectory::new();
+            read.write(&mut directory, "test.r1cs").unwrap();
+            assert!(directory.get_file("test.r1cs").unwrap() == &bytes[..]);
+            if custom_gates {
+                assert_eq!(read.custom_gates_used.unwrap(), vec![("Mul".to_string(), vec![BigInt::from(7)])]);
+                assert_eq!(read.custom_gates_applied.unwrap(), vec![(0, vec![1, 3])]);
+            }
+        }
+    }
+
+    #[test]
+    fn r1cs_reader_rejects_malformed_files() {
+        let field = Field::new(&BigInt::from(257));
+        let bytes = write(&field, &constraints(&field), false);
+        assert!(read_r1cs(&bytes, "test.r1cs").is_ok());
+        assert!(read_r1cs_for_prime(&bytes, "test.r1cs", &BigInt::from(263)).is_err());
+        assert!(read_r1cs(&bytes[..bytes.len() - 1], "test.r1cs").is_err());
+
+        // Size of the constraints section, which is the first one
+        let mut wrong_size = bytes.clone();
+        wrong_size[16] -= 1;
+        assert!(read_r1cs(&wrong_size, "test.r1cs").is_err());
+
+        // The coefficient of the first term of A becomes the prime
+        let mut out_of_field = bytes.clone();
+        out_of_field[24 + 8..24 + 10].copy_from_slice(&[1, 1]);
+        let error = read_r1cs(&out_of_field, "test.r1cs").err().unwrap();
+        assert_eq!(error.part.as_deref(), Some(CONSTRAINTS));
+    }
+}
@@ -1,7 +1,9 @@
+use circom_algebra::field::Field;
+use circom_algebra::linear_combination::LinearCombination;
 use circom_algebra::num_bigint::BigInt;
-use std::collections::HashMap;
-use std::fs::File;
-use std::io::{BufWriter, Seek, SeekFrom, Write};
+use std::io::{self, Write};
+use program_structure::output_directory::OutputSink;
+use program_structure::output_error::{OutputError, OutputResult};
 
 const SECTIONS: u8 = 5;
 const MAGIC: &[u8] = b"r1cs";
@@ -11,7 +13,6 @@ const CONSTRAINT_TYPE: &[u8] = &[2, 0, 0, 0];
 const WIRE2LABEL_TYPE: &[u8] = &[3, 0, 0, 0];
 const CUSTOM_GATES_USED_TYPE: &[u8] = &[4, 0, 0, 0];
 const CUSTOM_GATES_APPLIED_TYPE: &[u8] = &[5, 0, 0, 0];
-const PLACE_HOLDER: &[u8] = &[3, 3, 3, 3, 3, 3, 3, 3];
 
 fn into_format(number: &[u8], with_bytes: usize) -> (Vec<u8>, usize) {
     let mut value = number.to_vec();
@@ -27,211 +28,220 @@ fn bigint_as_bytes(number: &BigInt, with_bytes: usize) -> (Vec<u8>, usize) {
     into_format(&value, with_bytes)
 }
 
-fn initialize_section(writer: &mut BufWriter<File>, header: &[u8]) -> Result<u64, ()> {
-    writer.write_all(header).map_err(|_err| {})?;
-    //writer.flush().map_err(|_err| {})?;
-    let go_back = writer.seek(SeekFrom::Current(0)).map_err(|_err| {})?;
-    writer.write_all(PLACE_HOLDER).map_err(|_| {})?;
-    //writer.flush().map_err(|_err| {})?;
-    Result::Ok(go_back)
+// Every section starts with its type and its size, so the size of a section
+// must be known before its contents are written
+fn initialize_section<W: Write>(writer: &mut W, header: &[u8], size: usize) -> io::Result<()> {
+    writer.write_all(header)?;
+    let (stream, _) = bigint_as_bytes(&BigInt::from(size), 8);
+    writer.write_all(&stream)
 }
 
-fn end_section(writer: &mut BufWriter<File>, go_back: u64, size: usize) -> Result<(), ()> {
-    let go_back_1 = writer.seek(SeekFrom::Current(0)).map_err(|_err| {})?;
-    writer.seek(SeekFrom::Start(go_back)).map_err(|_err| {})?;
-    let (stream, _) = bigint_as_bytes(&BigInt::from(size), 8);
-    writer.write_all(&stream).map_err(|_err| {})?;
-    writer.seek(SeekFrom::Start(go_back_1)).map_err(|_err| {})?;
-    //writer.flush().map_err(|_| {})
-    Result::Ok(())
+fn size_mismatch(expected: usize, written: usize) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("the section takes {} bytes but {} were announced", written, expected),
+    )
 }
 
-fn obtain_linear_combination_block<T>(
-    linear_combination: &HashMap<T, BigInt>,
+// Size in bytes of a constraint with that number of terms in A, B and C
+pub fn constraint_size(terms: usize, field_size: usize) -> usize {
+    3 * 4 + terms * (4 + field_size)
+}
+
+// The terms are already sorted by signal, so they are written in that order
+fn obtain_linear_combination_block(
+    linear_combination: &LinearCombination<usize>,
+    field: &Field,
     field_size: usize,
-) -> (Vec<u8>, usize) where T: AsRef<[u8]> + std::cmp::Ord + std::hash::Hash {
+) -> (Vec<u8>, usize) {
     let mut block = Vec::new();
     let non_zero_factors = BigInt::from(linear_combination.len());
     let mut size = 0;
     let (stream, bytes) = bigint_as_bytes(&non_zero_factors, 4);
     size += bytes;
     block.extend_from_slice(&stream);
-    let mut order: Vec<&T> = linear_combination.keys().collect();
-    order.sort();
-    for i in order {
-        let (id, factor) = linear_combination.get_key_value(i).unwrap();
-        let (stream, bytes) = into_format(id.as_ref(), 4);
+    for (id, factor) in linear_combination {
+        let (stream, bytes) = bigint_as_bytes(&BigInt::from(*id), 4);
         size += bytes;
         block.extend_from_slice(&stream);
 
-        let (stream, bytes) = bigint_as_bytes(factor, field_size);
+        let (stream, bytes) = bigint_as_bytes(&field.to_bigint(factor), field_size);
         size += bytes;
         block.extend_from_slice(&stream);
     }
     (block, size)
 }
 
-fn write_constraint<T>(
-    file: &mut BufWriter<File>,
-    a: &HashMap<T, BigInt>,
-    b: &HashMap<T, BigInt>,
-    c: &HashMap<T, BigInt>,
+fn write_constraint<W: Write>(
+    file: &mut W,
+    constraint: [&LinearCombination<usize>; 3],
+    field: &Field,
     field_size: usize,
-) -> Result<usize, ()> where T: AsRef<[u8]> + std::cmp::Ord + std::hash::Hash {
-    let (block_a, size_a) = obtain_linear_combination_block(a, field_size);
-    let (block_b, size_b) = obtain_linear_combination_block(b, field_size);
-    let (block_c, size_c) = obtain_linear_combination_block(c, field_size);
-    file.write_all(&block_a).map_err(|_err| {})?;
-    //file.flush().map_err(|_err| {})?;
-    file.write_all(&block_b).map_err(|_err| {})?;
-    //file.flush().map_err(|_err| {})?;
-    file.write_all(&block_c).map_err(|_err| {})?;
-    //file.flush().map_err(|_err| {})?;
-    Result::Ok(size_a + size_b + size_c)
+) -> io::Result<usize> {
+    let mut size = 0;
+    for linear_combination in constraint {
+        let (block, block_size) = obtain_linear_combination_block(linear_combination, field, field_size);
+        file.write_all(&block)?;
+        size += block_size;
+    }
+    Result::Ok(size)
 }
 
-fn initialize_file(writer: &mut BufWriter<File>, num_sections: u8) -> Result<(), ()> {
-    writer.write_all(MAGIC).map_err(|_err| {})?;
-    //writer.flush().map_err(|_err| {})?;
-    writer.write_all(VERSION).map_err(|_err| {})?;
-    //writer.flush().map_err(|_err| {})?;
-    writer.write_all(&[num_sections, 0, 0, 0]).map_err(|_err| {})?;
-    //writer.flush().map_err(|_err| {})?;
+fn initialize_file<W: Write>(writer: &mut W, num_sections: u8) -> io::Result<()> {
+    writer.write_all(MAGIC)?;
+    //writer.flush()?;
+    writer.write_all(VERSION)?;
+    //writer.flush()?;
+    writer.write_all(&[num_sections, 0, 0, 0])?;
+    //writer.flush()?;
     Result::Ok(())
 }
 
-pub struct R1CSWriter {
+pub struct R1CSWriter<'a> {
+    file: String,
     field_size: usize,
-    writer: BufWriter<File>,
+    writer: Box<dyn OutputSink + 'a>,
     sections: [bool; SECTIONS as usize]
 }
 
-pub struct HeaderSection {
-    writer: BufWriter<File>,
-    go_back: u64,
-    size: usize,
+// The header and the custom gates sections are small, their contents are
+// kept until the end of the section to write them after their size
+pub struct HeaderSection<'a> {
+    file: String,
+    writer: Box<dyn OutputSink + 'a>,
+    contents: Vec<u8>,
     index: usize,
     field_size: usize,
     sections: [bool; SECTIONS as usize]
 }
 
-pub struct ConstraintSection {
-    writer: BufWriter<File>,
+pub struct ConstraintSection<'a> {
+    file: String,
+    writer: Box<dyn OutputSink + 'a>,
     number_of_constraints: usize,
-    go_back: u64,
     size: usize,
+    expected_size: usize,
     index: usize,
     field_size: usize,
     sections: [bool; SECTIONS as usize]
 }
 
-pub struct SignalSection {
-    writer: BufWriter<File>,
-    go_back: u64,
+pub struct SignalSection<'a> {
+    file: String,
+    writer: Box<dyn OutputSink + 'a>,
     size: usize,
+    expected_size: usize,
     index: usize,
     field_size: usize,
     sections: [bool; SECTIONS as usize]
 }
 
-pub struct CustomGatesUsedSection {
-    writer: BufWriter<File>,
-    go_back: u64,
-    size: usize,
+pub struct CustomGatesUsedSection<'a> {
+    file: String,
+    writer: Box<dyn OutputSink + 'a>,
+    contents: Vec<u8>,
     index: usize,
     field_size: usize,
     sections: [bool; SECTIONS as usize]
 }
 
-pub struct CustomGatesAppliedSection {
-    writer: BufWriter<File>,
-    go_back: u64,
-    size: usize,
+pub struct CustomGatesAppliedSection<'a> {
+    file: String,
+    writer: Box<dyn OutputSink + 'a>,
+    contents: Vec<u8>,
     index: usize,
     field_size: usize,
     sections: [bool; SECTIONS as usize]
 }
 
-impl R1CSWriter {
+impl<'a> R1CSWriter<'a> {
     pub fn new(
+        mut writer: Box<dyn OutputSink + 'a>,
         output_file: String,
         field_size: usize,
         custom_gates: bool
-    ) -> Result<R1CSWriter, ()> {
+    ) -> OutputResult<R1CSWriter<'a>> {
         let sections = [false; SECTIONS as usize];
         let num_sections: u8 = if custom_gates { 5 } else { 3 };
-        let mut writer =
-            File::create(&output_file).map_err(|_err| {}).map(|f| BufWriter::new(f))?;
-        initialize_file(&mut writer, num_sections)?;
-        Result::Ok(R1CSWriter { writer, sections, field_size })
+        initialize_file(&mut writer, num_sections)
+            .map_err(|err| OutputError::new(&output_file, err).in_part("file header"))?;
+        Result::Ok(R1CSWriter { file: output_file, writer, sections, field_size })
     }
 
-    pub fn start_header_section(mut r1cs: R1CSWriter) -> Result<HeaderSection, ()> {
-        let start = initialize_section(&mut r1cs.writer, HEADER_TYPE)?;
+    fn error(&self, cause: io::Error, part: &str) -> OutputError {
+        OutputError::new(&self.file, cause).in_part(part)
+    }
+
+    pub fn start_header_section(r1cs: R1CSWriter<'a>) -> OutputResult<HeaderSection<'a>> {
         Result::Ok(HeaderSection {
+            file: r1cs.file,
             writer: r1cs.writer,
-            go_back: start,
-            size: 0,
+            contents: Vec::new(),
             index: 0,
             field_size: r1cs.field_size,
             sections: r1cs.sections,
         })
     }
 
-    pub fn start_constraints_section(mut r1cs: R1CSWriter) -> Result<ConstraintSection, ()> {
-        let start = initialize_section(&mut r1cs.writer, CONSTRAINT_TYPE)?;
+    // size is the sum of the constraint_size of the constraints to write
+    pub fn start_constraints_section(mut r1cs: R1CSWriter<'a>, size: usize) -> OutputResult<ConstraintSection<'a>> {
+        initialize_section(&mut r1cs.writer, CONSTRAINT_TYPE, size)
+            .map_err(|err| r1cs.error(err, ConstraintSection::PART))?;
         Result::Ok(ConstraintSection {
+            file: r1cs.file,
             number_of_constraints: 0,
             writer: r1cs.writer,
-            go_back: start,
             size: 0,
+            expected_size: size,
             index: 1,
             field_size: r1cs.field_size,
             sections: r1cs.sections,
         })
     }
 
-    pub fn start_signal_section(mut r1cs: R1CSWriter) -> Result<SignalSection, ()> {
-        let start = initialize_section(&mut r1cs.writer, WIRE2LABEL_TYPE)?;
+    pub fn start_signal_section(mut r1cs: R1CSWriter<'a>, number_of_signals: usize) -> OutputResult<SignalSection<'a>> {
+        let size = 8 * number_of_signals;
+        initialize_section(&mut r1cs.writer, WIRE2LABEL_TYPE, size)
+            .map_err(|err| r1cs.error(err, SignalSection::PART))?;
         Result::Ok(SignalSection {
+            file: r1cs.file,
             writer: r1cs.writer,
-            go_back: start,
             size: 0,
+            expected_size: size,
             index: 2,
             field_size: r1cs.field_size,
             sections: r1cs.sections,
         })
     }
 
-    pub fn start_custom_gates_used_section(mut r1cs: R1CSWriter) -> Result<CustomGatesUsedSection, ()> {
-        let start = initialize_section(&mut r1cs.writer, CUSTOM_GATES_USED_TYPE)?;
+    pub fn start_custom_gates_used_section(r1cs: R1CSWriter<'a>) -> OutputResult<CustomGatesUsedSection<'a>> {
         Result::Ok(CustomGatesUsedSection {
+            file: r1cs.file,
             writer: r1cs.writer,
-            go_back: start,
-            size: 0,
+            contents: Vec::new(),
             index: 3,
             field_size: r1cs.field_size,
             sections: r1cs.sections
         })
     }
 
-    pub fn start_custom_gates_applied_section(mut r1cs: R1CSWriter) -> Result<CustomGatesAppliedSection, ()> {
-        let start = initialize_section(&mut r1cs.writer, CUSTOM_GATES_APPLIED_TYPE)?;
+    pub fn start_custom_gates_applied_section(r1cs: R1CSWriter<'a>) -> OutputResult<CustomGatesAppliedSection<'a>> {
         Result::Ok(CustomGatesAppliedSection {
+            file: r1cs.file,
             writer: r1cs.writer,
-            go_back: start,
-            size: 0,
+            contents: Vec::new(),
             index: 4,
             field_size: r1cs.field_size,
             sections: r1cs.sections
         })
     }
 
-    pub fn finish_writing(mut r1cs: R1CSWriter) -> Result<(), ()> {
-	r1cs.writer.flush().map_err(|_err| {})
+    pub fn finish_writing(mut r1cs: R1CSWriter<'a>) -> OutputResult<()> {
+        r1cs.writer.flush().map_err(|err| OutputError::new(&r1cs.file, err))
     }
 }
 
+#[derive(Clone, Debug)]
 pub struct HeaderData {
     pub field: BigInt,
     pub total_wires: usize,
@@ -242,14 +252,18 @@ pub struct HeaderData {
     pub number_of_constraints: usize,
 }
 
-impl HeaderSection {
-    pub fn write_section(&mut self, data: HeaderData) -> Result<(), ()> {
-        let (field_stream, bytes_field) = bigint_as_bytes(&data.field, self.field_size);
-        let (length_stream, bytes_size) = bigint_as_bytes(&BigInt::from(self.field_size), 4);
-        self.writer.write_all(&length_stream).map_err(|_err| {})?;
-        self.writer.write_all(&field_stream).map_err(|_err| {})?;
-        //self.writer.flush().map_err(|_err| {})?;
-        self.size += bytes_field + bytes_size;
+impl<'a> HeaderSection<'a> {
+    const PART: &'static str = "header section";
+
+    fn error(&self, cause: io::Error) -> OutputError {
+        OutputError::new(&self.file, cause).in_part(Self::PART)
+    }
+
+    pub fn write_section(&mut self, data: HeaderData) -> OutputResult<()> {
+        let (field_stream, _) = bigint_as_bytes(&data.field, self.field_size);
+        let (length_stream, _) = bigint_as_bytes(&BigInt::from(self.field_size), 4);
+        self.contents.extend_from_slice(&length_stream);
+        self.contents.extend_from_slice(&field_stream);
 
         let data_stream = [
             [data.total_wires, 4],
@@ -260,59 +274,56 @@ impl HeaderSection {
             [data.number_of_constraints, 4],
         ];
         for data in &data_stream {
-            let (stream, size) = bigint_as_bytes(&BigInt::from(data[0]), data[1]);
-            self.size += size;
-            self.writer.write_all(&stream).map_err(|_err| {})?;
-            //self.writer.flush().map_err(|_err| {})?;
+            let (stream, _) = bigint_as_bytes(&BigInt::from(data[0]), data[1]);
+            self.contents.extend_from_slice(&stream);
         }
         Result::Ok(())
     }
 
-    pub fn end_section(mut self) -> Result<R1CSWriter, ()> {
-        end_section(&mut self.writer, self.go_back, self.size)?;
+    pub fn end_section(mut self) -> OutputResult<R1CSWriter<'a>> {
+        initialize_section(&mut self.writer, HEADER_TYPE, self.contents.len())
+            .and_then(|_| self.writer.write_all(&self.contents))
+            .map_err(|err| self.error(err))?;
         let mut sections = self.sections;
         let index = self.index;
         sections[index] = true;
-        Result::Ok(R1CSWriter { writer: self.writer, field_size: self.field_size, sections })
+        Result::Ok(R1CSWriter {
+            file: self.file,
+            writer: self.writer, field_size: self.field_size, sections })
     }
 }
 
-type Constraint = HashMap<usize, BigInt>;
-impl ConstraintSection {
+impl<'a> ConstraintSection<'a> {
+    const PART: &'static str = "constraints section";
+
+    fn error(&self, cause: io::Error) -> OutputError {
+        OutputError::new(&self.file, cause).in_part(Self::PART)
+    }
+
     pub fn write_constraint_usize(
         &mut self,
-        a: &Constraint,
-        b: &Constraint,
-        c: &Constraint,
-    ) -> Result<(), ()> {
+        a: &LinearCombination<usize>,
+        b: &LinearCombination<usize>,
+        c: &LinearCombination<usize>,
+        field: &Field,
+    ) -> OutputResult<()> {
         let field_size = self.field_size;
-        let mut r1cs_a = HashMap::new();
-        for (k, v) in a {
-            let (_, bytes) = BigInt::from(*k).to_bytes_le();
-            r1cs_a.insert(bytes, v.clone());
-        }
-        let mut r1cs_b = HashMap::new();
-        for (k, v) in b {
-            let (_, bytes) = BigInt::from(*k).to_bytes_le();
-            r1cs_b.insert(bytes, v.clone());
-        }
-        let mut r1cs_c = HashMap::new();
-        for (k, v) in c {
-            let (_, bytes) = BigInt::from(*k).to_bytes_le();
-            r1cs_c.insert(bytes, v.clone());
-        }
-        let size = write_constraint(&mut self.writer, &r1cs_a, &r1cs_b, &r1cs_c, field_size)?;
+        let size = write_constraint(&mut self.writer, [a, b, c], field, field_size)
+            .map_err(|err| self.error(err))?;
         self.size += size;
         self.number_of_constraints += 1;
         Result::Ok(())
     }
 
-    pub fn end_section(mut self) -> Result<R1CSWriter, ()> {
-        end_section(&mut self.writer, self.go_back, self.size)?;
+    pub fn end_section(self) -> OutputResult<R1CSWriter<'a>> {
+        if self.size != self.expected_size {
+            return Result::Err(self.error(size_mismatch(self.expected_size, self.size)));
+        }
         let mut sections = self.sections;
         let index = self.index;
         sections[index] = true;
         Result::Ok(R1CSWriter {
+            file: self.file,
             writer: self.writer,
             field_size: self.field_size,
             sections
@@ -324,28 +335,36 @@ impl ConstraintSection {
     }
 }
 
-impl SignalSection {
+impl<'a> SignalSection<'a> {
+    const PART: &'static str = "wire to label section";
+
+    fn error(&self, cause: io::Error) -> OutputError {
+        OutputError::new(&self.file, cause).in_part(Self::PART)
+    }
+
     pub fn write_signal<T>(
         &mut self,
         bytes: &T
-    ) -> Result<(), ()> where T: AsRef<[u8]> {
+    ) -> OutputResult<()> where T: AsRef<[u8]> {
         let (bytes, size) = into_format(bytes.as_ref(), 8);
         self.size += size;
-        self.writer.write_all(&bytes).map_err(|_err| {})//?;
-        //self.writer.flush().map_err(|_err| {})
+        self.writer.write_all(&bytes).map_err(|err| self.error(err))
     }
 
-    pub fn write_signal_usize(&mut self, signal: usize) -> Result<(), ()> {
+    pub fn write_signal_usize(&mut self, signal: usize) -> OutputResult<()> {
         let (_, as_bytes) = BigInt::from(signal).to_bytes_le();
         SignalSection::write_signal(self, &as_bytes)
     }
 
-    pub fn end_section(mut self) -> Result<R1CSWriter, ()> {
-        end_section(&mut self.writer, self.go_back, self.size)?;
+    pub fn end_section(self) -> OutputResult<R1CSWriter<'a>> {
+        if self.size != self.expected_size {
+            return Result::Err(self.error(size_mismatch(self.expected_size, self.size)));
+        }
         let mut sections = self.sections;
         let index = self.index;
         sections[index] = true;
         Result::Ok(R1CSWriter {
+            file: self.file,
             writer: self.writer,
             field_size: self.field_size,
             sections
@@ -354,48 +373,42 @@ impl SignalSection {
 }
 
 pub type CustomGatesUsedData = Vec<(String, Vec<BigInt>)>;
-impl CustomGatesUsedSection {
-    pub fn write_custom_gates_usages(&mut self, data: CustomGatesUsedData) -> Result<(), ()> {
-        let no_custom_gates = data.len();
-        let (no_custom_gates_stream, no_custom_gates_size) =
-            bigint_as_bytes(&BigInt::from(no_custom_gates), 4);
-        self.size += no_custom_gates_size;
-        self.writer.write_all(&no_custom_gates_stream).map_err(|_err| {})?;
-        //self.writer.flush().map_err(|_err| {})?;
-
-        for custom_gate in data {
-            let custom_gate_name = custom_gate.0;
-            let custom_gate_name_stream = custom_gate_name.as_bytes();
-            self.size += custom_gate_name_stream.len() + 1;
-            self.writer.write_all(custom_gate_name_stream).map_err(|_err| {})?;
-            self.writer.write_all(&[0]).map_err(|_err| {})?;
-            //self.writer.flush().map_err(|_err| {})?;
-
-            let custom_gate_parameters = custom_gate.1;
-            let no_custom_gate_parameters = custom_gate_parameters.len();
-            let (no_custom_gate_parameters_stream, no_custom_gate_parameters_size) =
-                bigint_as_bytes(&BigInt::from(no_custom_gate_parameters), 4);
-            self.size += no_custom_gate_parameters_size;
-            self.writer.write_all(&no_custom_gate_parameters_stream).map_err(|_err| {})?;
-            //self.writer.flush().map_err(|_err| {})?;
+impl<'a> CustomGatesUsedSection<'a> {
+    const PART: &'static str = "custom gates used section";
+
+    fn error(&self, cause: io::Error) -> OutputError {
+        OutputError::new(&self.file, cause).in_part(Self::PART)
+    }
+
+    pub fn write_custom_gates_usages(&mut self, data: CustomGatesUsedData) -> OutputResult<()> {
+        let (no_custom_gates_stream, _) = bigint_as_bytes(&BigInt::from(data.len()), 4);
+        self.contents.extend_from_slice(&no_custom_gates_stream);
+
+        for (custom_gate_name, custom_gate_parameters) in data {
+            self.contents.extend_from_slice(custom_gate_name.as_bytes());
+            self.contents.push(0);
+
+            let (no_custom_gate_parameters_stream, _) =
+                bigint_as_bytes(&BigInt::from(custom_gate_parameters.len()), 4);
+            self.contents.extend_from_slice(&no_custom_gate_parameters_stream);
 
             for parameter in custom_gate_parameters {
-                let (parameter_stream, parameter_size) = bigint_as_bytes(&parameter, self.field_size);
-                self.size += parameter_size;
-                self.writer.write(&parameter_stream).map_err(|_err| {})?;
-                //self.writer.flush().map_err(|_err| {})?;
+                let (parameter_stream, _) = bigint_as_bytes(&parameter, self.field_size);
+                self.contents.extend_from_slice(&parameter_stream);
             }
         }
-
         Result::Ok(())
     }
 
-    pub fn end_section(mut self) -> Result<R1CSWriter, ()> {
-        end_section(&mut self.writer, self.go_back, self.size)?;
+    pub fn end_section(mut self) -> OutputResult<R1CSWriter<'a>> {
+        initialize_section(&mut self.writer, CUSTOM_GATES_USED_TYPE, self.contents.len())
+            .and_then(|_| self.writer.write_all(&self.contents))
+            .map_err(|err| self.error(err))?;
         let mut sections = self.sections;
         let index = self.index;
         sections[index] = true;
         Result::Ok(R1CSWriter {
+            file: self.file,
             writer: self.writer,
             field_size: self.field_size,
             sections
@@ -404,51 +417,109 @@ impl CustomGatesUsedSection {
 }
 
 pub type CustomGatesAppliedData = Vec<(usize, Vec<usize>)>;
-impl CustomGatesAppliedSection {
-    pub fn write_custom_gates_applications(&mut self, data: CustomGatesAppliedData) -> Result<(), ()> {
-        let no_custom_gate_applications = data.len();
-        let (no_custom_gate_applications_stream, no_custom_gate_applications_size) =
-            bigint_as_bytes(&BigInt::from(no_custom_gate_applications), 4);
-        self.size += no_custom_gate_applications_size;
-        self.writer.write_all(&no_custom_gate_applications_stream).map_err(|_err| {})?;
-        //self.writer.flush().map_err(|_err| {})?;
-
-        for custom_gate_application in data {
-            let custom_gate_index = custom_gate_application.0;
-            let (custom_gate_index_stream, custom_gate_index_size) =
-                bigint_as_bytes(&BigInt::from(custom_gate_index), 4);
-            self.size += custom_gate_index_size;
-            self.writer.write_all(&custom_gate_index_stream).map_err(|_err| {})?;
-            //self.writer.flush().map_err(|_err| {})?;
-
-            let custom_gate_signals = custom_gate_application.1;
-            let no_custom_gate_signals = custom_gate_signals.len();
-            let (no_custom_gate_signals_stream, no_custom_gate_signals_size) =
-                bigint_as_bytes(&BigInt::from(no_custom_gate_signals), 4);
-            self.size += no_custom_gate_signals_size;
-            self.writer.write_all(&no_custom_gate_signals_stream).map_err(|_err| {})?;
-            //self.writer.flush().map_err(|_err| {})?;
+impl<'a> CustomGatesAppliedSection<'a> {
+    const PART: &'static str = "custom gates applied section";
+
+    fn error(&self, cause: io::Error) -> OutputError {
+        OutputError::new(&self.file, cause).in_part(Self::PART)
+    }
+
+    pub fn write_custom_gates_applications(&mut self, data: CustomGatesAppliedData) -> OutputResult<()> {
+        let (no_custom_gate_applications_stream, _) = bigint_as_bytes(&BigInt::from(data.len()), 4);
+        self.contents.extend_from_slice(&no_custom_gate_applications_stream);
+
+        for (custom_gate_index, custom_gate_signals) in data {
+            let (custom_gate_index_stream, _) = bigint_as_bytes(&BigInt::from(custom_gate_index), 4);
+            self.contents.extend_from_slice(&custom_gate_index_stream);
+
+            let (no_custom_gate_signals_stream, _) =
+                bigint_as_bytes(&BigInt::from(custom_gate_signals.len()), 4);
+            self.contents.extend_from_slice(&no_custom_gate_signals_stream);
 
             for signal in custom_gate_signals {
-                let (signal_stream, signal_size) = bigint_as_bytes(&BigInt::from(signal), 8);
-                self.size += signal_size;
-                self.writer.write(&signal_stream).map_err(|_err| {})?;
-                //self.writer.flush().map_err(|_err| {})?;
+                let (signal_stream, _) = bigint_as_bytes(&BigInt::from(signal), 8);
+                self.contents.extend_from_slice(&signal_stream);
             }
         }
-	//self.writer.flush().map_err(|_err| {})?;
         Result::Ok(())
     }
 
-    pub fn end_section(mut self) -> Result<R1CSWriter, ()> {
-        end_section(&mut self.writer, self.go_back, self.size)?;
+    pub fn end_section(mut self) -> OutputResult<R1CSWriter<'a>> {
+        initialize_section(&mut self.writer, CUSTOM_GATES_APPLIED_TYPE, self.contents.len())
+            .and_then(|_| self.writer.write_all(&self.contents))
+            .map_err(|err| self.error(err))?;
         let mut sections = self.sections;
         let index = self.index;
         sections[index] = true;
         Result::Ok(R1CSWriter {
+            file: self.file,
             writer: self.writer,
             field_size: self.field_size,
             sections
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::TryInto;
+
+    fn write_one_constraint<'a, W: OutputSink + 'a>(out: W, announced: usize) -> OutputResult<()> {
+        let field = Field::new(&BigInt::from(257));
+        let a: LinearCombination<usize> =
+            vec![(0, field.from_bigint(&BigInt::from(3))), (2, field.from_bigint(&BigInt::from(1)))].into_iter().collect();
+        let b = LinearCombination::new();
+        let r1cs = R1CSWriter::new(Box::new(out), "test.r1cs".to_string(), 8, false)?;
+        let mut section = R1CSWriter::start_constraints_section(r1cs, announced)?;
+        section.write_constraint_usize(&a, &b, &a, &field)?;
+        let r1cs = section.end_section()?;
+        R1CSWriter::finish_writing(r1cs)
+    }
+
+    #[test]
+    fn constraints_section_is_written_with_the_announced_size() {
+        let size = constraint_size(4, 8);
+        let mut bytes = Vec::new();
+        assert!(write_one_constraint(&mut bytes, size).is_ok());
+        assert_eq!(&bytes[12..16], CONSTRAINT_TYPE);
+        assert_eq!(u64::from_le_bytes(bytes[16..24].try_into().unwrap()) as usize, size);
+        assert_eq!(bytes.len(), 24 + size);
+
+        assert!(write_one_constraint(Vec::new(), size + 1).is_err());
+    }
+
+    // Accepts the given number of bytes and fails afterwards, as a full disk
+    struct FullDisk {
+        space: usize,
+    }
+
+    impl Write for FullDisk {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if self.space == 0 {
+                return Err(io::Error::other("no space left on device"));
+            }
+            let written = buf.len().min(self.space);
+            self.space -= written;
+            Ok(written)
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn write_errors_tell_the_file_and_the_section() {
+        let size = constraint_size(4, 8);
+        let error = write_one_constraint(FullDisk { space: 4 }, size).unwrap_err();
+        assert_eq!(error.file, "test.r1cs");
+        assert_eq!(error.part.as_deref(), Some("file header"));
+
+        let error = write_one_constraint(FullDisk { space: 16 }, size).unwrap_err();
+        assert_eq!(error.part.as_deref(), Some("constraints section"));
+        assert_eq!(error.to_string(), "could not write the constraints section of test.r1cs: no space left on device");
+        let report = error.into_report();
+        assert_eq!(report.get_message(), "Could not write test.r1cs");
+        assert!(report.get_notes().iter().any(|note| note == "The error happened while writing the constraints section"));
+    }
+}
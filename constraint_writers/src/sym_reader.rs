@@ -0,0 +1,54 @@
+use super::sym_writer::SymElem;
+use program_structure::input_error::{InputError, InputResult};
+
+pub fn read_sym_file(path: &str) -> InputResult<Vec<SymElem>> {
+    let contents = std::fs::read(path).map_err(|err| InputError::new(path, err))?;
+    read_sym(&contents, path)
+}
+
+// Every line is original,witness,node_id,symbol. The symbol is the last
+// field, so it is taken as it is even if it contains commas.
+pub fn read_sym(contents: &[u8], file: &str) -> InputResult<Vec<SymElem>> {
+    let contents = std::str::from_utf8(contents)
+        .map_err(|_| InputError::invalid(file, "the file is not valid utf-8".to_string()))?;
+    let mut elems = Vec::new();
+    for (number, line) in contents.lines().enumerate() {
+        let elem = read_sym_elem(line)
+            .ok_or_else(|| InputError::invalid(file, format!("the line {} is not a symbol: {}", number + 1, line)))?;
+        elems.push(elem);
+    }
+    Result::Ok(elems)
+}
+
+fn read_sym_elem(line: &str) -> Option<SymElem> {
+    let mut fields = line.splitn(4, ',');
+    let original = fields.next()?.parse().ok()?;
+    let witness = fields.next()?.parse().ok()?;
+    let node_id = fields.next()?.parse().ok()?;
+    let symbol = fields.next()?.to_string();
+    Some(SymElem { original, witness, node_id, symbol })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sym_writer::SymFile;
+    use program_structure::output_directory::{MemoryDirectory, OutputDirectory};
+
+    #[test]
+    fn sym_round_trip() {
+        let written = vec![
+            SymElem { original: 1, witness: 1, node_id: 0, symbol: "main.out".to_string() },
+            SymElem { original: 2, witness: -1, node_id: 3, symbol: "main.c[1].in".to_string() },
+        ];
+        let mut directory = MemoryDirectory::new();
+        let mut sym = SymFile::new(directory.create_file("test.sym").unwrap(), "test.sym");
+        for elem in &written {
+            SymFile::write_sym_elem(&mut sym, elem.clone()).unwrap();
+        }
+        SymFile::finish_writing(sym).unwrap();
+        let read = read_sym(directory.get_file("test.sym").unwrap(), "test.sym").unwrap();
+        assert_eq!(read, written);
+        assert!(read_sym(b"1,1,main.out\n", "test.sym").is_err());
+    }
+}
@@ -1,6 +1,8 @@
-use std::fs::File;
-use std::io::{BufWriter, Write};
+use std::io::Write;
+use program_structure::output_directory::OutputSink;
+use program_structure::output_error::{OutputError, OutputResult};
 
+#[derive(Clone, PartialEq, Eq, Debug)]
 pub struct SymElem {
     pub original: i64,
     pub witness: i64,
@@ -13,25 +15,23 @@ impl ToString for SymElem {
     }
 }
 
-pub struct SymFile {
-    writer: BufWriter<File>,
+pub struct SymFile<'a> {
+    file: String,
+    writer: Box<dyn OutputSink + 'a>,
 }
 
-impl SymFile {
-    pub fn new(file: &str) -> Result<SymFile, ()> {
-        let file = File::create(file).map_err(|_err| {})?;
-        let writer = BufWriter::new(file);
-        Result::Ok(SymFile { writer })
+impl<'a> SymFile<'a> {
+    pub fn new(writer: Box<dyn OutputSink + 'a>, file: &str) -> SymFile<'a> {
+        SymFile { file: file.to_string(), writer }
     }
 
-    pub fn write_sym_elem(sym: &mut SymFile, elem: SymElem) -> Result<(), ()> {
-        sym.writer.write_all(elem.to_string().as_bytes()).map_err(|_err| {})?;
-        sym.writer.write_all(b"\n").map_err(|_err| {}) //?;
-        //sym.writer.flush().map_err(|_err| {})
+    pub fn write_sym_elem(sym: &mut SymFile<'a>, elem: SymElem) -> OutputResult<()> {
+        writeln!(sym.writer, "{},{},{},{}", elem.original, elem.witness, elem.node_id, elem.symbol)
+            .map_err(|err| OutputError::new(&sym.file, err))
     }
     
-    pub fn finish_writing(mut sym: SymFile) -> Result<(), ()> {
-	sym.writer.flush().map_err(|_err| {})
+    pub fn finish_writing(mut sym: SymFile<'a>) -> OutputResult<()> {
+	sym.writer.flush().map_err(|err| OutputError::new(&sym.file, err))
     }
 
     // pub fn close(_sym: SymFile) {}
@@ -147,10 +147,12 @@ pub fn clean_constraints(nodes: &mut [Node]){
     for node in nodes{
         let length_bound = Vec::len(&node.constraints);
         let work = std::mem::replace(&mut node.constraints, Vec::with_capacity(length_bound));
-        for mut constraint in work {
+        let locations = std::mem::replace(&mut node.constraint_locations, Vec::with_capacity(length_bound));
+        for (mut constraint, location) in work.into_iter().zip(locations) {
             C::remove_zero_value_coefficients(&mut constraint);
             if !C::is_empty(&constraint) {
                 Vec::push(&mut node.constraints, constraint);
+                Vec::push(&mut node.constraint_locations, location);
             }
         }
     }
@@ -1,35 +1,34 @@
 use super::{Tree, DAG};
 use circom_algebra::algebra::Constraint;
-use circom_algebra::num_bigint::BigInt;
+use circom_algebra::field::Field;
+use circom_algebra::linear_combination::LinearCombination;
 use constraint_writers::debug_writer::DebugWriter;
 use constraint_writers::json_writer::ConstraintJSON;
 use json::JsonValue;
-use std::collections::HashMap;
+use program_structure::output_directory::OutputDirectory;
+use program_structure::output_error::OutputResult;
 
 type C = Constraint<usize>;
 
-fn transform_constraint_to_json(constraint: &C) -> JsonValue {
+fn transform_constraint_to_json(constraint: &C, field: &Field) -> JsonValue {
     JsonValue::Array(vec![
-        hashmap_as_json(constraint.a()),
-        hashmap_as_json(constraint.b()),
-        hashmap_as_json(constraint.c()),
+        hashmap_as_json(constraint.a(), field),
+        hashmap_as_json(constraint.b(), field),
+        hashmap_as_json(constraint.c(), field),
     ])
 }
-fn hashmap_as_json(values: &HashMap<usize, BigInt>) -> JsonValue {
-    let mut order: Vec<&usize> = values.keys().collect();
-    order.sort();
+fn hashmap_as_json(values: &LinearCombination<usize>, field: &Field) -> JsonValue {
     let mut correspondence = json::object! {};
-    for i in order {
-        let (key, value) = values.get_key_value(i).unwrap();
-        let value = value.to_str_radix(10);
+    for (key, value) in values {
+        let value = field.to_bigint(value).to_str_radix(10);
         correspondence[format!("{}", key)] = value.as_str().into();
     }
     correspondence
 }
 
-fn visit_tree(tree: &Tree, writer: &mut ConstraintJSON) -> Result<(), ()> {
+fn visit_tree(tree: &Tree, writer: &mut ConstraintJSON) -> OutputResult<()> {
     for constraint in &tree.constraints {
-        let json_value = transform_constraint_to_json(&constraint);
+        let json_value = transform_constraint_to_json(&constraint, &tree.field);
         writer.write_constraint(&json_value.to_string())?;
     }
     for edge in Tree::get_edges(tree) {
@@ -39,8 +38,8 @@ fn visit_tree(tree: &Tree, writer: &mut ConstraintJSON) -> Result<(), ()> {
     Result::Ok(())
 }
 
-pub fn port_constraints(dag: &DAG, debug: &DebugWriter) -> Result<(), ()> {
-    let mut writer = debug.build_constraints_file()?;
+pub fn port_constraints(dag: &DAG, directory: &mut dyn OutputDirectory, debug: &DebugWriter) -> OutputResult<()> {
+    let mut writer = debug.build_constraints_file(directory)?;
     visit_tree(&Tree::new(dag), &mut writer)?;
     writer.end()
 }
@@ -4,23 +4,31 @@ mod map_to_constraint_list;
 mod r1cs_porting;
 mod sym_porting;
 mod witness_producer;
+use circom_algebra::field::Field;
 use circom_algebra::num_bigint::BigInt;
 use constraint_list::ConstraintList;
 use constraint_writers::debug_writer::DebugWriter;
+use constraint_writers::log_writer::Log;
 use constraint_writers::ConstraintExporter;
 use program_structure::constants::UsefulConstants;
 use program_structure::error_definition::ReportCollection;
+use program_structure::file_definition::{FileID, FileLocation};
 use std::collections::{HashMap, HashSet};
+use std::io;
+use program_structure::output_directory::OutputDirectory;
+use program_structure::output_error::OutputResult;
 type Signal = usize;
 type Constraint = circom_algebra::algebra::Constraint<usize>;
 type Substitution = circom_algebra::algebra::Substitution<usize>;
 type Range = std::ops::Range<usize>;
+// The file and the span of the statement that produced a constraint
+pub type ConstraintLocation = (Option<FileID>, FileLocation);
 
 pub type FastSubAccess = HashMap<usize, Substitution>;
 
 pub struct Tree<'a> {
     dag: &'a DAG,
-    pub field: BigInt,
+    pub field: Field,
     pub path: String,
     pub offset: usize,
     pub node_id: usize,
@@ -33,7 +41,7 @@ pub struct Tree<'a> {
 impl<'a> Tree<'a> {
     pub fn new(dag: &DAG) -> Tree {
         let constants = UsefulConstants::new(&dag.prime);
-        let field = constants.get_p().clone();
+        let field = Field::new(constants.get_p());
         let root = dag.get_main().unwrap();
         let node_id = dag.main_id();
         let offset = dag.get_entry().unwrap().in_number;
@@ -149,6 +157,7 @@ pub struct Node {
     forbidden_if_main: HashSet<usize>,
     io_signals: Vec<usize>,
     constraints: Vec<Constraint>,
+    constraint_locations: Vec<ConstraintLocation>,
     underscored_signals: Vec<usize>,
     is_parallel: bool,
     has_parallel_sub_cmp: bool,
@@ -215,8 +224,9 @@ impl Node {
         self.intermediates_length += 1;
     }
 
-    fn add_constraint(&mut self, constraint: Constraint) {
-        self.constraints.push(constraint)
+    fn add_constraint(&mut self, constraint: Constraint, location: ConstraintLocation) {
+        self.constraints.push(constraint);
+        self.constraint_locations.push(location);
     }
 
     fn add_underscored_signal(&mut self, signal: usize) {
@@ -300,16 +310,16 @@ pub struct DAG {
 }
 
 impl ConstraintExporter for DAG {
-    fn r1cs(&self, out: &str, custom_gates: bool) -> Result<(), ()> {
-        DAG::generate_r1cs_output(self, out, custom_gates)
+    fn r1cs(&self, directory: &mut dyn OutputDirectory, out: &str, custom_gates: bool) -> OutputResult<Log> {
+        DAG::generate_r1cs_output(self, directory, out, custom_gates)
     }
 
-    fn json_constraints(&self, writer: &DebugWriter) -> Result<(), ()> {
-        DAG::generate_json_constraints(self, writer)
+    fn json_constraints(&self, directory: &mut dyn OutputDirectory, writer: &DebugWriter) -> OutputResult<()> {
+        DAG::generate_json_constraints(self, directory, writer)
     }
 
-    fn sym(&self, out: &str) -> Result<(), ()> {
-        DAG::generate_sym_output(self, out)
+    fn sym(&self, directory: &mut dyn OutputDirectory, out: &str) -> OutputResult<()> {
+        DAG::generate_sym_output(self, directory, out)
     }
 }
 
@@ -402,9 +412,9 @@ impl DAG {
         }
     }
 
-    pub fn add_constraint(&mut self, constraint: Constraint) {
+    pub fn add_constraint(&mut self, constraint: Constraint, location: ConstraintLocation) {
         if let Option::Some(node) = self.get_mut_main() {
-            node.add_constraint(constraint);
+            node.add_constraint(constraint, location);
         }
     }
 
@@ -471,16 +481,21 @@ impl DAG {
         constraint_correctness_analysis::clean_constraints(&mut self.nodes);
     }
 
-    pub fn generate_r1cs_output(&self, output_file: &str, custom_gates: bool) -> Result<(), ()> {
-        r1cs_porting::write(self, output_file, custom_gates)
+    pub fn generate_r1cs_output(
+        &self,
+        directory: &mut dyn OutputDirectory,
+        output_file: &str,
+        custom_gates: bool,
+    ) -> OutputResult<Log> {
+        r1cs_porting::write(self, directory, output_file, custom_gates)
     }
 
-    pub fn generate_sym_output(&self, output_file: &str) -> Result<(), ()> {
-        sym_porting::write(self, output_file)
+    pub fn generate_sym_output(&self, directory: &mut dyn OutputDirectory, output_file: &str) -> OutputResult<()> {
+        sym_porting::write(self, directory, output_file)
     }
 
-    pub fn generate_json_constraints(&self, debug: &DebugWriter) -> Result<(), ()> {
-        json_porting::port_constraints(self, debug)
+    pub fn generate_json_constraints(&self, directory: &mut dyn OutputDirectory, debug: &DebugWriter) -> OutputResult<()> {
+        json_porting::port_constraints(self, directory, debug)
     }
 
     pub fn produce_witness(&self) -> Vec<usize> {
@@ -531,16 +546,22 @@ impl DAG {
         }
     }
 
-    pub fn map_to_list(self, flags: SimplificationFlags) -> ConstraintList {
+    // Fails only when the memory budget keeps the constraints in a temporary
+    // file and it can not be used
+    pub fn map_to_list(self, flags: SimplificationFlags) -> io::Result<ConstraintList> {
         map_to_constraint_list::map(self, flags)
     }
 }
 
 pub struct SimplificationFlags {
     pub no_rounds: usize,
+    pub memory_budget: Option<usize>,
     pub flag_s: bool,
     pub parallel_flag: bool,
-    pub port_substitution: bool,
+    // File where the substitutions are written, if any
+    pub port_substitution: Option<String>,
+    // Keeps the origin of the constraints and the substitutions to explain them
+    pub explain: bool,
     pub flag_old_heuristics: bool,
     pub prime : String,
 }
@@ -1,12 +1,17 @@
 use super::{Constraint, Edge, Node, SimplificationFlags, Tree, DAG};
-use constraint_list::{ConstraintList, DAGEncoding, EncodingEdge, EncodingNode, SignalInfo, Simplifier};
+use constraint_list::{
+    ConstraintList, ConstraintOrigin, DAGEncoding, EncodingEdge, EncodingNode, SignalInfo, Simplifier,
+    SubstitutionTrace,
+};
 use program_structure::utils::constants::UsefulConstants;
 use std::collections::{HashSet, LinkedList};
+use std::io;
 #[derive(Default)]
 struct CHolder {
     linear: LinkedList<Constraint>,
     equalities: LinkedList<Constraint>,
     constant_equalities: LinkedList<Constraint>,
+    trace: Option<SubstitutionTrace>,
 }
 
 fn map_tree(
@@ -36,6 +41,22 @@ fn map_tree(
         }
     }
 
+    if let Some(trace) = &mut c_holder.trace {
+        let node = &tree.dag.nodes[tree.node_id];
+        for (constraint, (file_id, location)) in node.constraints.iter().zip(&node.constraint_locations) {
+            if constraint.is_empty() {
+                continue;
+            }
+            let origin = ConstraintOrigin {
+                component: tree.path.clone(),
+                template: node.template_name.clone(),
+                file_id: *file_id,
+                location: location.clone(),
+            };
+            trace.originals.push((Constraint::apply_offset(constraint, tree.offset), origin));
+        }
+    }
+
     for edge in Tree::get_edges(tree) {
         let subtree = Tree::go_to_subtree(tree, edge);
         no_constraints += map_tree(&subtree, witness, c_holder, forbidden);
@@ -107,7 +128,7 @@ fn map_edge_to_encoding(edge: Edge) -> EncodingEdge {
     EncodingEdge { goes_to: edge.goes_to, path: edge.label, offset: edge.in_number }
 }
 
-pub fn map(dag: DAG, flags: SimplificationFlags) -> ConstraintList {
+pub fn map(dag: DAG, flags: SimplificationFlags) -> io::Result<ConstraintList> {
     use std::time::SystemTime;
     // println!("Start of dag to list mapping");
     let now = SystemTime::now();
@@ -119,6 +140,9 @@ pub fn map(dag: DAG, flags: SimplificationFlags) -> ConstraintList {
     let no_private_inputs = dag.private_inputs();
     let mut forbidden = dag.get_main().unwrap().forbidden_if_main.clone();
     let mut c_holder = CHolder::default();
+    if flags.explain {
+        c_holder.trace = Some(SubstitutionTrace::default());
+    }
     let mut signal_map = vec![0];
     let no_constraints = map_tree(&Tree::new(&dag), &mut signal_map, &mut c_holder, &mut forbidden);
     let max_signal = Vec::len(&signal_map);
@@ -137,10 +161,12 @@ pub fn map(dag: DAG, flags: SimplificationFlags) -> ConstraintList {
         equalities: c_holder.equalities,
         cons_equalities: c_holder.constant_equalities,
         no_rounds: flags.no_rounds,
+        memory_budget: flags.memory_budget,
         flag_s: flags.flag_s,
         parallel_flag: flags.parallel_flag,
         flag_old_heuristics: flags.flag_old_heuristics,
         port_substitution: flags.port_substitution,
+        trace: c_holder.trace,
     }
     .simplify_constraints()
 }
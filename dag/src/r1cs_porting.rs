@@ -1,25 +1,30 @@
 use super::{Constraint, Tree, DAG};
 use constraint_writers::log_writer::Log;
-use constraint_writers::r1cs_writer::{ConstraintSection, CustomGatesAppliedData, HeaderData, R1CSWriter};
+use constraint_writers::r1cs_writer::{constraint_size, ConstraintSection, CustomGatesAppliedData, HeaderData, R1CSWriter};
+use program_structure::output_directory::OutputDirectory;
+use program_structure::output_error::{OutputError, OutputResult};
 
-pub fn write(dag: &DAG, output: &str, custom_gates: bool) -> Result<(), ()> {
+pub fn write(dag: &DAG, directory: &mut dyn OutputDirectory, output: &str, custom_gates: bool) -> OutputResult<Log> {
     let tree = Tree::new(dag);
-    let field_size = if tree.field.bits() % 64 == 0 {
-        tree.field.bits() / 8
+    let field_size = if tree.field.prime().bits() % 64 == 0 {
+        tree.field.prime().bits() / 8
     } else{
-        (tree.field.bits() / 64 + 1) * 8
+        (tree.field.prime().bits() / 64 + 1) * 8
     };
     let mut log = Log::new();
-    let r1cs = R1CSWriter::new(output.to_string(), field_size, custom_gates)?;
+    let writer = directory.create_file(output).map_err(|err| OutputError::new(output, err))?;
+    let r1cs = R1CSWriter::new(writer, output.to_string(), field_size, custom_gates)?;
 
-    let mut constraint_section = R1CSWriter::start_constraints_section(r1cs)?;
-    let wires = write_constraint_section(&mut constraint_section, &mut log, &tree)? + 1; // adding 1 to include the signal used to represent value 1 in the field (signal one)
+    let (section_size, no_signals) = measure_constraint_section(&tree, field_size);
+    let mut constraint_section = R1CSWriter::start_constraints_section(r1cs, section_size)?;
+    write_constraint_section(&mut constraint_section, &mut log, &tree)?;
+    let wires = no_signals + 1; // adding 1 to include the signal used to represent value 1 in the field (signal one)
     let labels = wires;
     let constraint_counter = constraint_section.constraints_written();
     let r1cs = constraint_section.end_section()?;
 
     let header_data = HeaderData {
-        field: tree.field.clone(),
+        field: tree.field.prime().clone(),
         total_wires: wires,
         number_of_labels: labels,
         public_outputs: dag.public_outputs(),
@@ -38,7 +43,7 @@ pub fn write(dag: &DAG, output: &str, custom_gates: bool) -> Result<(), ()> {
     header_section.write_section(header_data)?;
     let r1cs = header_section.end_section()?;
 
-    let mut signal_section = R1CSWriter::start_signal_section(r1cs)?;
+    let mut signal_section = R1CSWriter::start_signal_section(r1cs, labels)?;
     for signal in 0..labels {
         signal_section.write_signal_usize(signal)?;
     }
@@ -107,28 +112,48 @@ pub fn write(dag: &DAG, output: &str, custom_gates: bool) -> Result<(), ()> {
         R1CSWriter::finish_writing(r1cs)?;
     }
 
-    Log::print(&log);
-    Result::Ok(())
+    Result::Ok(log)
+}
+
+// Size of the constraints section and number of signals of the tree, computed
+// before writing so the constraints are streamed straight from the DAG
+fn measure_constraint_section(tree: &Tree, field_size: usize) -> (usize, usize) {
+    let mut size = 0;
+    let mut no_signals = tree.signals.len();
+    for c in &tree.constraints {
+        size += constraint_size(c.a().len() + c.b().len() + c.c().len(), field_size);
+    }
+    for edge in Tree::get_edges(tree) {
+        let subtree = Tree::go_to_subtree(tree, edge);
+        let (subtree_size, subtree_signals) = measure_constraint_section(&subtree, field_size);
+        size += subtree_size;
+        no_signals += subtree_signals;
+    }
+    (size, no_signals)
 }
 
 fn write_constraint_section(
     constraint_section: &mut ConstraintSection,
     log: &mut Log,
     tree: &Tree,
-) -> Result<usize, ()> {
-    let mut no_signals = tree.signals.len();
+) -> OutputResult<()> {
     for c in &tree.constraints {
         if Constraint::is_linear(c) {
             log.no_linear += 1;
         } else {
             log.no_non_linear += 1;
         }
-        ConstraintSection::write_constraint_usize(constraint_section, c.a(), c.b(), c.c())?;
+        ConstraintSection::write_constraint_usize(
+            constraint_section,
+            c.a(),
+            c.b(),
+            c.c(),
+            &tree.field,
+        )?;
     }
     for edge in Tree::get_edges(tree) {
         let subtree = Tree::go_to_subtree(tree, edge);
-        let subtree_signals = write_constraint_section(constraint_section, log, &subtree)?;
-        no_signals += subtree_signals;
+        write_constraint_section(constraint_section, log, &subtree)?;
     }
-    Result::Ok(no_signals)
+    Result::Ok(())
 }
@@ -2,17 +2,20 @@ use super::{Tree, DAG};
 use circom_algebra::num_traits::AsPrimitive;
 use constraint_writers::sym_writer::*;
 use std::collections::HashMap;
+use program_structure::output_directory::OutputDirectory;
+use program_structure::output_error::{OutputError, OutputResult};
 
-pub fn write(dag: &DAG, file_name: &str) -> Result<(), ()> {
+pub fn write(dag: &DAG, directory: &mut dyn OutputDirectory, file_name: &str) -> OutputResult<()> {
     let tree = Tree::new(dag);
-    let mut dot_sym = SymFile::new(file_name)?;
+    let writer = directory.create_file(file_name).map_err(|err| OutputError::new(file_name, err))?;
+    let mut dot_sym = SymFile::new(writer, file_name);
     visit_tree(&tree, &mut dot_sym)?;
     SymFile::finish_writing(dot_sym)?;
     //SymFile::close(dot_sym);
     Ok(())
 }
 
-fn visit_tree(tree: &Tree, dot_sym: &mut SymFile) -> Result<(), ()> {
+fn visit_tree(tree: &Tree, dot_sym: &mut SymFile) -> OutputResult<()> {
     for signal in &tree.signals {
         let name = HashMap::get(&tree.id_to_name, signal).unwrap();
         let symbol = format!("{}.{}", tree.path, name);
@@ -1,6 +1,8 @@
-use program_structure::ast::produce_report_with_message;
+use program_structure::ast::{produce_report_with_message, Access, Definition, Expression, Include, LogArgument, Statement, AST};
 use program_structure::error_code::ReportCode;
-use program_structure::error_definition::Report;
+use program_structure::error_definition::{Report, ReportCollection};
+use program_structure::file_definition::{FileID, FileLocation};
+use program_structure::program_merger::Namespace;
 use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
 
@@ -21,28 +23,39 @@ impl FileStack {
         f_stack: &mut FileStack,
         name: String,
         libraries: &Vec<PathBuf>,
-    ) -> Result<String, Report> {
+    ) -> Result<IncludeResolution, Report> {
         let mut libraries2 = Vec::new();
-        libraries2.push(f_stack.current_location.clone());
-        libraries2.append(&mut libraries.clone());
+        libraries2.push(None);
+        libraries2.extend(libraries.iter().cloned().map(Some));
+        let mut resolution: Option<IncludeResolution> = None;
         for lib in libraries2 {
             let mut path = PathBuf::new();
-            path.push(lib);
+            path.push(lib.clone().unwrap_or_else(|| f_stack.current_location.clone()));
             path.push(name.clone());
             let path = std::fs::canonicalize(path);
             match path {
                 Err(_) => {}
                 Ok(path) => {
-                    if path.is_file() {
-                        if !f_stack.black_paths.contains(&path) {
-                            f_stack.stack.push(path.clone());
+                    if !path.is_file() {
+                        continue;
+                    }
+                    match &mut resolution {
+                        Some(found) => {
+                            if found.path != path && !found.shadowed.contains(&path) {
+                                found.shadowed.push(path);
+                            }
+                        }
+                        None => {
+                            if !f_stack.black_paths.contains(&path) {
+                                f_stack.stack.push(path.clone());
+                            }
+                            resolution = Some(IncludeResolution { path, library: lib, shadowed: vec![] });
                         }
-                        return Result::Ok(path.to_str().unwrap().to_string());
                     }
                 }
             }
         }
-        Result::Err( produce_report_with_message(ReportCode::IncludeNotFound, name))
+        resolution.ok_or_else(|| produce_report_with_message(ReportCode::IncludeNotFound, name))
     }
 
     pub fn take_next(f_stack: &mut FileStack) -> Option<PathBuf> {
@@ -63,9 +76,31 @@ impl FileStack {
     }
 }
 
+pub struct IncludeResolution {
+    pub path: PathBuf,
+    // None when the file was found relative to the including file
+    pub library: Option<PathBuf>,
+    // Files with the same name found in libraries with less priority
+    pub shadowed: Vec<PathBuf>,
+}
+
 pub struct IncludesNode {
     pub path: PathBuf,
+    pub canonical_path: PathBuf,
+    pub file_id: FileID,
     pub custom_gates_pragma: bool,
+    pub custom_gates_usage: bool,
+    pub templates: Vec<String>,
+    pub functions: Vec<String>,
+    pub calls: HashSet<String>,
+}
+
+pub struct IncludesEdge {
+    pub from: usize,
+    pub include: String,
+    pub alias: Option<String>,
+    pub location: FileLocation,
+    pub resolution: IncludeResolution,
 }
 
 #[derive(Default)]
@@ -73,6 +108,7 @@ pub struct IncludesGraph {
     nodes: Vec<IncludesNode>,
     adjacency: HashMap<PathBuf, Vec<usize>>,
     custom_gates_nodes: Vec<usize>,
+    edges: Vec<IncludesEdge>,
 }
 
 impl IncludesGraph {
@@ -80,21 +116,193 @@ impl IncludesGraph {
         IncludesGraph::default()
     }
 
-    pub fn add_node(&mut self, path: PathBuf, custom_gates_pragma: bool, custom_gates_usage: bool) {
-        self.nodes.push(IncludesNode { path, custom_gates_pragma });
-        if custom_gates_usage {
+    pub fn add_node(&mut self, path: PathBuf, file_id: FileID, program: &AST) {
+        let mut templates = vec![];
+        let mut functions = vec![];
+        let mut calls = HashSet::new();
+        for definition in &program.definitions {
+            match definition {
+                Definition::Template { name, body, .. } => {
+                    templates.push(name.clone());
+                    collect_calls_statement(body, &mut calls);
+                }
+                Definition::Function { name, body, .. } => {
+                    functions.push(name.clone());
+                    collect_calls_statement(body, &mut calls);
+                }
+            }
+        }
+        if let Some((_, main_call)) = &program.main_component {
+            collect_calls_expression(main_call, &mut calls);
+        }
+        let canonical_path = std::fs::canonicalize(&path).unwrap_or_else(|_| path.clone());
+        self.nodes.push(IncludesNode {
+            path,
+            canonical_path,
+            file_id,
+            custom_gates_pragma: program.custom_gates,
+            custom_gates_usage: program.custom_gates_declared,
+            templates,
+            functions,
+            calls,
+        });
+        if program.custom_gates_declared {
             self.custom_gates_nodes.push(self.nodes.len() - 1);
         }
     }
 
-    pub fn add_edge(&mut self, old_path: String) -> Result<(), Report> {
-        let mut crr = PathBuf::new();
-        crr.push(old_path.clone());
-        let path = std::fs::canonicalize(crr)
-            .map_err(|_e| produce_report_with_message(ReportCode::FileOs,old_path))?;
-        let edges = self.adjacency.entry(path).or_insert(vec![]);
-        edges.push(self.nodes.len() - 1);
-        Ok(())
+    pub fn add_edge(&mut self, include: &Include, resolution: IncludeResolution) {
+        let from = self.nodes.len() - 1;
+        let edges = self.adjacency.entry(resolution.path.clone()).or_insert(vec![]);
+        edges.push(from);
+        self.edges.push(IncludesEdge {
+            from,
+            include: include.path.clone(),
+            alias: include.alias.clone(),
+            location: include.meta.file_location(),
+            resolution,
+        });
+    }
+
+    // The main file and the files reachable from it through plain includes
+    // form the root namespace. Every include with an alias starts a new
+    // namespace formed by the included file and its own plain includes.
+    pub fn get_namespaces(&self) -> Result<Vec<Namespace>, ReportCollection> {
+        let mut reports = vec![];
+        let mut entries: Vec<(Option<String>, usize)> = vec![];
+        let mut alias_to_edge: HashMap<&str, &IncludesEdge> = HashMap::new();
+        if !self.nodes.is_empty() {
+            entries.push((None, 0));
+        }
+        for edge in &self.edges {
+            let alias = match &edge.alias {
+                Some(alias) => alias,
+                None => continue,
+            };
+            match alias_to_edge.get(alias.as_str()) {
+                Some(previous) if previous.resolution.path != edge.resolution.path => {
+                    let mut report = Report::error(
+                        format!("The alias {} is used for two different files", alias),
+                        ReportCode::IncludeAliasConflict,
+                    );
+                    report.add_primary(
+                        edge.location.clone(),
+                        self.nodes[edge.from].file_id,
+                        format!("{} refers to {}", alias, edge.resolution.path.display()),
+                    );
+                    report.add_secondary(
+                        previous.location.clone(),
+                        self.nodes[previous.from].file_id,
+                        Some(format!("{} refers to {}", alias, previous.resolution.path.display())),
+                    );
+                    reports.push(report);
+                }
+                Some(_) => {}
+                None => {
+                    alias_to_edge.insert(alias, edge);
+                    if let Some(entry) = self.get_node_by_path(&edge.resolution.path) {
+                        entries.push((Some(alias.clone()), entry));
+                    }
+                }
+            }
+        }
+        if !reports.is_empty() {
+            return Err(reports);
+        }
+        let mut namespaces = vec![];
+        for (alias, entry) in entries {
+            let mut members = vec![];
+            let mut visible_aliases = HashSet::new();
+            let mut pending = vec![entry];
+            while let Some(index) = pending.pop() {
+                if members.contains(&index) {
+                    continue;
+                }
+                members.push(index);
+                for edge in self.edges.iter().filter(|e| e.from == index) {
+                    match &edge.alias {
+                        Some(alias) => {
+                            visible_aliases.insert(alias.clone());
+                        }
+                        None => {
+                            if let Some(next) = self.get_node_by_path(&edge.resolution.path) {
+                                pending.push(next);
+                            }
+                        }
+                    }
+                }
+            }
+            members.sort();
+            let files = members.iter().map(|index| self.nodes[*index].file_id).collect();
+            namespaces.push(Namespace { alias, files, visible_aliases });
+        }
+        Ok(namespaces)
+    }
+
+    pub fn get_nodes(&self) -> &Vec<IncludesNode> {
+        &self.nodes
+    }
+
+    pub fn get_edges(&self) -> &Vec<IncludesEdge> {
+        &self.edges
+    }
+
+    pub fn get_node_by_path(&self, path: &PathBuf) -> Option<usize> {
+        self.nodes.iter().position(|node| node.canonical_path == *path)
+    }
+
+    // For every callable, the files where it is defined in parsing order.
+    // The first one is the definition taken by the compiler.
+    pub fn get_definitions_origin(&self) -> Vec<(String, bool, Vec<usize>)> {
+        let mut order = vec![];
+        let mut origin: HashMap<String, (bool, Vec<usize>)> = HashMap::new();
+        for (index, node) in self.nodes.iter().enumerate() {
+            let templates = node.templates.iter().map(|name| (name, true));
+            let functions = node.functions.iter().map(|name| (name, false));
+            for (name, is_template) in templates.chain(functions) {
+                let entry = origin.entry(name.clone()).or_insert_with(|| {
+                    order.push(name.clone());
+                    (is_template, vec![])
+                });
+                entry.1.push(index);
+            }
+        }
+        order
+            .into_iter()
+            .map(|name| {
+                let (is_template, files) = origin.remove(&name).unwrap();
+                (name, is_template, files)
+            })
+            .collect()
+    }
+
+    // An include is used if some callable invoked in the including file is
+    // defined in the included file or in any file reachable from it. Callables
+    // of an include with an alias are invoked as `alias.name`.
+    pub fn is_edge_used(&self, edge: &IncludesEdge) -> bool {
+        let calls = &self.nodes[edge.from].calls;
+        let qualify = |name: &String| match &edge.alias {
+            Some(alias) => format!("{}.{}", alias, name),
+            None => name.clone(),
+        };
+        let mut visited = HashSet::new();
+        let mut pending = vec![edge.resolution.path.clone()];
+        while let Some(path) = pending.pop() {
+            if !visited.insert(path.clone()) {
+                continue;
+            }
+            if let Some(index) = self.get_node_by_path(&path) {
+                let node = &self.nodes[index];
+                let mut names = node.templates.iter().chain(node.functions.iter());
+                if names.any(|name| calls.contains(&qualify(name))) {
+                    return true;
+                }
+                for next in self.edges.iter().filter(|e| e.from == index) {
+                    pending.push(next.resolution.path.clone());
+                }
+            }
+        }
+        false
     }
 
     pub fn get_problematic_paths(&self) -> Vec<Vec<PathBuf>> {
@@ -160,3 +368,100 @@ impl IncludesGraph {
         path_covered
     }
 }
+
+pub(crate) fn collect_calls_statement(stmt: &Statement, calls: &mut HashSet<String>) {
+    use Statement::*;
+    match stmt {
+        IfThenElse { cond, if_case, else_case, .. } => {
+            collect_calls_expression(cond, calls);
+            collect_calls_statement(if_case, calls);
+            if let Some(else_case) = else_case {
+                collect_calls_statement(else_case, calls);
+            }
+        }
+        While { cond, stmt, .. } => {
+            collect_calls_expression(cond, calls);
+            collect_calls_statement(stmt, calls);
+        }
+        Return { value, .. } => collect_calls_expression(value, calls),
+        InitializationBlock { initializations, .. } => {
+            for init in initializations {
+                collect_calls_statement(init, calls);
+            }
+        }
+        Declaration { dimensions, .. } => {
+            for dim in dimensions {
+                collect_calls_expression(dim, calls);
+            }
+        }
+        Substitution { access, rhe, .. } => {
+            collect_calls_access(access, calls);
+            collect_calls_expression(rhe, calls);
+        }
+        MultSubstitution { lhe, rhe, .. } | ConstraintEquality { lhe, rhe, .. } => {
+            collect_calls_expression(lhe, calls);
+            collect_calls_expression(rhe, calls);
+        }
+        UnderscoreSubstitution { rhe, .. } => collect_calls_expression(rhe, calls),
+        LogCall { args, .. } => {
+            for arg in args {
+                if let LogArgument::LogExp(exp) = arg {
+                    collect_calls_expression(exp, calls);
+                }
+            }
+        }
+        Block { stmts, .. } => {
+            for stmt in stmts {
+                collect_calls_statement(stmt, calls);
+            }
+        }
+        Assert { arg, .. } => collect_calls_expression(arg, calls),
+    }
+}
+
+fn collect_calls_expression(expr: &Expression, calls: &mut HashSet<String>) {
+    use Expression::*;
+    match expr {
+        InfixOp { lhe, rhe, .. } => {
+            collect_calls_expression(lhe, calls);
+            collect_calls_expression(rhe, calls);
+        }
+        PrefixOp { rhe, .. } | ParallelOp { rhe, .. } => collect_calls_expression(rhe, calls),
+        InlineSwitchOp { cond, if_true, if_false, .. } => {
+            collect_calls_expression(cond, calls);
+            collect_calls_expression(if_true, calls);
+            collect_calls_expression(if_false, calls);
+        }
+        Variable { access, .. } => collect_calls_access(access, calls),
+        Number(..) => {}
+        Call { id, args, .. } => {
+            calls.insert(id.clone());
+            for arg in args {
+                collect_calls_expression(arg, calls);
+            }
+        }
+        AnonymousComp { id, params, signals, .. } => {
+            calls.insert(id.clone());
+            for arg in params.iter().chain(signals.iter()) {
+                collect_calls_expression(arg, calls);
+            }
+        }
+        ArrayInLine { values, .. } | Tuple { values, .. } => {
+            for value in values {
+                collect_calls_expression(value, calls);
+            }
+        }
+        UniformArray { value, dimension, .. } => {
+            collect_calls_expression(value, calls);
+            collect_calls_expression(dimension, calls);
+        }
+    }
+}
+
+fn collect_calls_access(access: &[Access], calls: &mut HashSet<String>) {
+    for acc in access {
+        if let Access::ArrayAccess(index) = acc {
+            collect_calls_expression(index, calls);
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use super::include_logic::IncludesGraph;
+use json::JsonValue;
+use program_structure::dot::escape_dot;
+use program_structure::output_directory::{FileSystem, OutputDirectory};
+use program_structure::output_error::OutputResult;
+use std::path::Path;
+
+fn display(path: &Path) -> String {
+    format!("{}", path.display())
+}
+
+fn library_as_string(library: &Option<std::path::PathBuf>) -> String {
+    match library {
+        Some(library) => format!("-l {}", library.display()),
+        None => "relative".to_string(),
+    }
+}
+
+pub fn produce_dot(graph: &IncludesGraph) -> String {
+    let mut dot = String::new();
+    dot.push_str("digraph includes {\n");
+    dot.push_str("    node [shape=box, fontname=\"monospace\"];\n");
+    for (id, node) in graph.get_nodes().iter().enumerate() {
+        let mut lines = vec![display(&node.canonical_path)];
+        if !node.templates.is_empty() {
+            lines.push(format!("templates: {}", node.templates.join(", ")));
+        }
+        if !node.functions.is_empty() {
+            lines.push(format!("functions: {}", node.functions.join(", ")));
+        }
+        if node.custom_gates_pragma {
+            lines.push("pragma custom_templates".to_string());
+        }
+        let label: Vec<String> = lines.iter().map(|l| escape_dot(l)).collect();
+        dot.push_str(&format!("    n{} [label=\"{}\\l\"];\n", id, label.join("\\l")));
+    }
+    let mut missing = 0;
+    for edge in graph.get_edges() {
+        let target = match graph.get_node_by_path(&edge.resolution.path) {
+            Some(id) => format!("n{}", id),
+            None => {
+                missing += 1;
+                format!("m{}", missing)
+            }
+        };
+        let mut label = format!("{} ({})", edge.include, library_as_string(&edge.resolution.library));
+        if let Some(alias) = &edge.alias {
+            label.push_str(&format!(" as {}", alias));
+        }
+        if !edge.resolution.shadowed.is_empty() {
+            label.push_str(&format!(", shadows {}", edge.resolution.shadowed.len()));
+        }
+        dot.push_str(&format!("    n{} -> {} [label=\"{}\"", edge.from, target, escape_dot(&label)));
+        if !graph.is_edge_used(edge) {
+            dot.push_str(", style=\"dashed\", color=\"gray\"");
+        }
+        if !edge.resolution.shadowed.is_empty() {
+            dot.push_str(", color=\"red\"");
+        }
+        dot.push_str("];\n");
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+pub fn produce_json(graph: &IncludesGraph) -> JsonValue {
+    let mut files = JsonValue::new_array();
+    for (id, node) in graph.get_nodes().iter().enumerate() {
+        let file = json::object! {
+            "id" => id,
+            "path" => display(&node.canonical_path),
+            "custom_templates_pragma" => node.custom_gates_pragma,
+            "declares_custom_templates" => node.custom_gates_usage,
+            "templates" => node.templates.clone(),
+            "functions" => node.functions.clone(),
+        };
+        files.push(file).unwrap();
+    }
+    let mut includes = JsonValue::new_array();
+    for edge in graph.get_edges() {
+        let library: JsonValue = match &edge.resolution.library {
+            Some(library) => display(library).into(),
+            None => JsonValue::Null,
+        };
+        let to: JsonValue = match graph.get_node_by_path(&edge.resolution.path) {
+            Some(id) => id.into(),
+            None => JsonValue::Null,
+        };
+        let alias: JsonValue = match &edge.alias {
+            Some(alias) => alias.as_str().into(),
+            None => JsonValue::Null,
+        };
+        let shadowed: Vec<String> = edge.resolution.shadowed.iter().map(|p| display(p)).collect();
+        let include = json::object! {
+            "from" => edge.from,
+            "to" => to,
+            "include" => edge.include.as_str(),
+            "alias" => alias,
+            "resolved" => display(&edge.resolution.path),
+            "library" => library,
+            "shadowed" => shadowed,
+            "used" => graph.is_edge_used(edge),
+        };
+        includes.push(include).unwrap();
+    }
+    let mut definitions = JsonValue::new_object();
+    for (name, is_template, defined_in) in graph.get_definitions_origin() {
+        let nodes = graph.get_nodes();
+        let taken_from = display(&nodes[defined_in[0]].canonical_path);
+        let also_in: Vec<String> =
+            defined_in[1..].iter().map(|id| display(&nodes[*id].canonical_path)).collect();
+        definitions[name] = json::object! {
+            "kind" => if is_template { "template" } else { "function" },
+            "file" => taken_from,
+            "also_defined_in" => also_in,
+        };
+    }
+    json::object! {
+        "files" => files,
+        "includes" => includes,
+        "definitions" => definitions,
+    }
+}
+
+pub fn port_includes_dot(graph: &IncludesGraph, file: &str) -> OutputResult<()> {
+    FileSystem.write_file(file, produce_dot(graph).as_bytes())
+}
+
+pub fn port_includes_json(graph: &IncludesGraph, file: &str) -> OutputResult<()> {
+    FileSystem.write_file(file, produce_json(graph).pretty(4).as_bytes())
+}
@@ -8,8 +8,11 @@ extern crate lalrpop_util;
 lalrpop_mod!(pub lang);
 
 mod include_logic;
+mod include_porting;
 mod parser_logic;
 mod syntax_sugar_remover;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod test_project;
 
 use include_logic::{FileStack, IncludesGraph};
 use program_structure::ast::{produce_compiler_version_report, produce_report, produce_report_with_message, produce_version_warning_report, Expression};
@@ -25,6 +28,11 @@ use std::str::FromStr;
 
 pub type Version = (usize, usize, usize);
 
+pub struct IncludesOutput {
+    pub dot: String,
+    pub json: String,
+}
+
 pub fn find_file(
     crr_file: PathBuf,
     ext_link_libraries: Vec<PathBuf>,
@@ -56,16 +64,51 @@ pub fn find_file(
     (found, path, src, crr_str_file, reports)
 }
 
+type ParserResult = Result<(ProgramArchive, ReportCollection), (FileLibrary, ReportCollection)>;
+
 pub fn run_parser(
     file: String,
     version: &str,
     link_libraries: Vec<PathBuf>,
-) -> Result<(ProgramArchive, ReportCollection), (FileLibrary, ReportCollection)> {
+    includes_output: Option<IncludesOutput>,
+) -> ParserResult {
+    let mut includes_graph = IncludesGraph::new();
+    let result = parse_project(file, version, link_libraries, &mut includes_graph);
+    match includes_output {
+        Option::None => result,
+        Option::Some(output) => {
+            let dot = include_porting::port_includes_dot(&includes_graph, &output.dot);
+            let json = include_porting::port_includes_json(&includes_graph, &output.json);
+            let errors: Vec<_> = vec![dot, json].into_iter().filter_map(Result::err).collect();
+            if errors.is_empty() {
+                result
+            } else {
+                let mut output_reports = errors.into_iter().map(|err| err.into_report()).collect();
+                match result {
+                    Result::Ok((program_archive, mut warnings)) => {
+                        warnings.append(&mut output_reports);
+                        Result::Err((program_archive.file_library, warnings))
+                    }
+                    Result::Err((file_library, mut reports)) => {
+                        reports.append(&mut output_reports);
+                        Result::Err((file_library, reports))
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn parse_project(
+    file: String,
+    version: &str,
+    link_libraries: Vec<PathBuf>,
+    includes_graph: &mut IncludesGraph,
+) -> ParserResult {
     let mut file_library = FileLibrary::new();
     let mut definitions = Vec::new();
     let mut main_components = Vec::new();
     let mut file_stack = FileStack::new(PathBuf::from(file));
-    let mut includes_graph = IncludesGraph::new();
     let mut warnings = Vec::new();
     let mut link_libraries2 = link_libraries.clone();
     let mut ext_link_libraries = vec![Path::new("").to_path_buf()];
@@ -79,17 +122,17 @@ pub fn run_parser(
         let file_id = file_library.add_file(path.clone(), src.clone());
         let program =
             parser_logic::parse_file(&src, file_id).map_err(|e| (file_library.clone(), e))?;
+        includes_graph.add_node(crr_str_file, file_id, &program);
         if let Some(main) = program.main_component {
             main_components.push((file_id, main, program.custom_gates));
         }
-        includes_graph.add_node(crr_str_file, program.custom_gates, program.custom_gates_declared);
         let includes = program.includes;
         definitions.push((file_id, program.definitions));
         for include in includes {
-            let path_include =
-                FileStack::add_include(&mut file_stack, include.clone(), &link_libraries.clone())
+            let resolution =
+                FileStack::add_include(&mut file_stack, include.path.clone(), &link_libraries.clone())
                     .map_err(|e| (file_library.clone(), vec![e]))?;
-            includes_graph.add_edge(path_include).map_err(|e| (file_library.clone(), vec![e]))?;
+            includes_graph.add_edge(&include, resolution);
         }
         warnings.append(
             &mut check_number_version(
@@ -128,6 +171,13 @@ pub fn run_parser(
                 ReportCode::CustomGatesPragmaError
             )
         ).collect();
+        let namespaces = match includes_graph.get_namespaces() {
+            Ok(namespaces) => namespaces,
+            Err(mut namespace_errors) => {
+                errors.append(&mut namespace_errors);
+                vec![]
+            }
+        };
         if errors.len() > 0 {
             warnings.append(& mut errors);
             Err((file_library, warnings))
@@ -138,6 +188,7 @@ pub fn run_parser(
                 main_id,
                 main_component,
                 definitions,
+                namespaces,
                 custom_gates,
             );
             match result_program_archive {
@@ -260,4 +311,74 @@ fn check_custom_gates_version(
         }
     }
     Ok(())
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_project::Project;
+
+    fn parse(project: &Project) -> ParserResult {
+        let main = project.path("main.circom").to_str().unwrap().to_string();
+        run_parser(main, "2.1.6", vec![], None)
+    }
+
+    fn error_codes(result: ParserResult) -> Vec<String> {
+        match result {
+            Ok(_) => panic!("the project should not parse"),
+            Err((_, reports)) => reports.iter().filter(|r| r.is_error()).map(|r| r.get_code().to_string()).collect(),
+        }
+    }
+
+    const SQUARE: &str = "pragma circom 2.1.6;
+        function square(x) { return x * x; }
+        template Square() { signal input in; signal output out; out <== in * in; var s = square(2); }";
+    const CUBE: &str = "pragma circom 2.1.6;
+        template Square() { signal input in; signal output out; signal t; t <== in * in; out <== t * in; }";
+
+    #[test]
+    fn aliases_qualify_the_definitions_of_the_included_files() {
+        let main = "pragma circom 2.1.6;
+            include \"square.circom\" as A;
+            include \"cube.circom\" as B;
+            template Main() {
+                signal input in; signal output out;
+                component a = A.Square();
+                component b = B.Square();
+                a.in <== in; b.in <== a.out; out <== b.out;
+            }
+            component main = Main();";
+        let project = Project::new("parser_aliases", &[("main.circom", main), ("square.circom", SQUARE), ("cube.circom", CUBE)]);
+        let (program, _) = parse(&project).ok().expect("the project should parse");
+        for template in ["Main", "A.Square", "B.Square"] {
+            assert!(program.contains_template(template), "missing template {}", template);
+        }
+        assert!(!program.contains_template("Square"));
+        // Calls inside the included file refer to its own namespace
+        assert!(program.contains_function("A.square"));
+        assert!(!program.contains_function("square"));
+        let mut calls = std::collections::HashSet::new();
+        include_logic::collect_calls_statement(program.get_template_data("A.Square").get_body(), &mut calls);
+        assert!(calls.contains("A.square") && !calls.contains("square"), "A.Square calls {:?}", calls);
+    }
+
+    #[test]
+    fn calls_through_an_unknown_alias_are_reported() {
+        let main = "pragma circom 2.1.6;
+            include \"square.circom\" as A;
+            template Main() { signal input in; component c = C.Square(); c.in <== in; }
+            component main = Main();";
+        let project = Project::new("parser_unknown_alias", &[("main.circom", main), ("square.circom", SQUARE)]);
+        assert_eq!(error_codes(parse(&project)), vec!["T2052"]);
+    }
+
+    #[test]
+    fn one_alias_for_two_files_is_reported() {
+        let main = "pragma circom 2.1.6;
+            include \"square.circom\" as A;
+            include \"cube.circom\" as A;
+            template Main() { signal input in; component c = A.Square(); c.in <== in; }
+            component main = Main();";
+        let project = Project::new("parser_alias_conflict", &[("main.circom", main), ("square.circom", SQUARE), ("cube.circom", CUBE)]);
+        assert_eq!(error_codes(parse(&project)), vec!["P1016"]);
+    }
+}
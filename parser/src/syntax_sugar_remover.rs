@@ -426,7 +426,7 @@ pub fn remove_anonymous_from_expression(
             if template.is_none(){
                 return Result::Err(anonymous_general_error(meta.clone(),format!("The template {} does not exist", id)));
             }
-            let id_anon_temp = id.to_string() + "_" + &file_lib.get_line(meta.start, meta.get_file_id()).unwrap().to_string() + "_" + &meta.start.to_string();
+            let id_anon_temp = id.replace('.', "_") + "_" + &file_lib.get_line(meta.start, meta.get_file_id()).unwrap().to_string() + "_" + &meta.start.to_string();
             
             // in case we are not inside a loop, we can automatically convert into a component
             if var_access.is_none(){
@@ -0,0 +1,44 @@
+// Projects written in the temporary directory for the tests of this crate
+// and, with the feature test-utils, of the crates that depend on it
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Folder with the files of a project, removed when it is dropped, also when
+// the test that uses it fails
+pub struct Project {
+    root: PathBuf,
+}
+
+impl Project {
+    // The name tells apart the projects of the tests that run at the same time
+    pub fn new(name: &str, files: &[(&str, &str)]) -> Project {
+        let root = std::env::temp_dir().join(format!("circom_{}_{}", name, std::process::id()));
+        fs::create_dir_all(&root).unwrap();
+        let project = Project { root };
+        for (file, contents) in files {
+            project.write(file, contents.as_bytes());
+        }
+        project
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn path<P: AsRef<Path>>(&self, file: P) -> PathBuf {
+        self.root.join(file)
+    }
+
+    // Creates the folders of the file if they do not exist
+    pub fn write<P: AsRef<Path>>(&self, file: P, contents: &[u8]) {
+        let path = self.path(file);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+}
+
+impl Drop for Project {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.root);
+    }
+}
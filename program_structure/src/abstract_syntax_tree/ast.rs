@@ -20,6 +20,17 @@ pub fn build_main_component(public: Vec<String>, call: Expression) -> MainCompon
 
 pub type Version = (usize, usize, usize);
 
+// include "path"; or include "path" as Alias;
+#[derive(Clone)]
+pub struct Include {
+    pub meta: Meta,
+    pub path: String,
+    pub alias: Option<String>,
+}
+pub fn build_include(meta: Meta, path: String, alias: Option<String>) -> Include {
+    Include { meta, path, alias }
+}
+
 #[derive(Clone)]
 pub struct Meta {
     pub elem_id: usize,
@@ -87,7 +98,7 @@ pub struct AST {
     pub compiler_version: Option<Version>,
     pub custom_gates: bool,
     pub custom_gates_declared: bool,
-    pub includes: Vec<String>,
+    pub includes: Vec<Include>,
     pub definitions: Vec<Definition>,
     pub main_component: Option<MainComponent>,
 }
@@ -96,7 +107,7 @@ impl AST {
     pub fn new(
         meta: Meta,
         pragmas: Vec<Pragma>,
-        includes: Vec<String>,
+        includes: Vec<Include>,
         definitions: Vec<Definition>,
         main_component: Option<MainComponent>,
     ) -> (AST,Vec<Report>) {
@@ -14,6 +14,7 @@ pub enum ReportCode {
     UnrecognizedPragma,
     ExpectedIdentifier,
     IncludeNotFound,
+    IncludeAliasConflict,
     IllegalExpression,
     MultiplePragma,
     NoCompilerVersionWarning, 
@@ -50,6 +51,7 @@ pub enum ReportCode {
     NonCompatibleBranchTypes,
     NonEqualTypesInExpression,
     NonExistentSymbol,
+    UnknownNamespace,
     MainComponentWithTags,
     TemplateCallAsArgument,
     TemplateWrongNumberOfArguments,
@@ -94,8 +96,13 @@ pub enum ReportCode {
     UnconstrainedIOSignal,
     UnusedInput,
     UnusedOutput,
+    // Prime dependence codes
+    PrimeDependentConstant,
+    PrimeDependentComparison,
 
     ErrorWat2Wasm,
+    IllFormedIr,
+    InvalidCompilerOption,
     CustomGateIntermediateSignalWarning,
     CustomGateConstraintError,
     CustomGateSubComponentError,
@@ -133,6 +140,7 @@ impl fmt::Display for ReportCode {
             MultiplePragma => "P1013",
             IncludeNotFound => "P1014",
             ExpectedIdentifier => "P1015",
+            IncludeAliasConflict => "P1016",
             UndefinedFunction => "T2001",
             UndefinedTemplate => "T2002",
             UninitializedSymbolInExpression => "T2003",
@@ -191,6 +199,7 @@ impl fmt::Display for ReportCode {
             UnreachableTags => "T2049",
             UnreachableSignals => "T2050",
             MainComponentWithTags => "T2051",
+            UnknownNamespace => "T2052",
             RuntimeError => "T3001",
             RuntimeWarning => "T3002",
             UnknownDimension => "T20460",
@@ -205,7 +214,11 @@ impl fmt::Display for ReportCode {
             UnconstrainedIOSignal => "CA02",
             UnusedInput => "CA03",
             UnusedOutput => "CA04",
+            PrimeDependentConstant => "PD01",
+            PrimeDependentComparison => "PD02",
             ErrorWat2Wasm => "W01",
+            IllFormedIr => "IR01",
+            InvalidCompilerOption => "O01",
             CustomGateIntermediateSignalWarning => "CG01",
             CustomGateConstraintError => "CG02",
             CustomGateSubComponentError => "CG03",
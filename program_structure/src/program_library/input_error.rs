@@ -0,0 +1,49 @@
+use super::error_code::ReportCode;
+use super::error_definition::Report;
+use std::fmt;
+use std::io;
+
+// Error produced when a file written by the compiler (for instance an r1cs
+// or a sym file) can not be read back. Malformed contents are reported with
+// an io::Error of kind InvalidData.
+#[derive(Debug)]
+pub struct InputError {
+    pub file: String,
+    pub part: Option<String>,
+    pub cause: io::Error,
+}
+
+pub type InputResult<T> = Result<T, InputError>;
+
+impl InputError {
+    pub fn new(file: &str, cause: io::Error) -> InputError {
+        InputError { file: file.to_string(), part: None, cause }
+    }
+
+    pub fn invalid(file: &str, reason: String) -> InputError {
+        InputError::new(file, io::Error::new(io::ErrorKind::InvalidData, reason))
+    }
+
+    pub fn in_part(mut self, part: &str) -> InputError {
+        self.part = Some(part.to_string());
+        self
+    }
+
+    pub fn into_report(self) -> Report {
+        let mut report = Report::error(format!("Could not read {}", self.file), ReportCode::FileOs);
+        if let Some(part) = &self.part {
+            report.add_note(format!("The error happened while reading the {}", part));
+        }
+        report.add_note(format!("Cause: {}", self.cause));
+        report
+    }
+}
+
+impl fmt::Display for InputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.part {
+            Some(part) => write!(f, "could not read the {} of {}: {}", part, self.file, self.cause),
+            None => write!(f, "could not read {}: {}", self.file, self.cause),
+        }
+    }
+}
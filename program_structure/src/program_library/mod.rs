@@ -3,6 +3,9 @@ pub mod error_code;
 pub mod error_definition;
 pub mod file_definition;
 pub mod function_data;
+pub mod input_error;
+pub mod output_directory;
+pub mod output_error;
 pub mod program_archive;
 pub mod program_merger;
 pub mod template_data;
@@ -0,0 +1,112 @@
+use super::output_error::{OutputError, OutputResult};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+// Destination of a single output file. The writers of the compiler produce
+// their files front to back, so a sink may be a pipe or a socket.
+pub trait OutputSink: Write {}
+impl<T: Write> OutputSink for T {}
+
+// Place where the compiler leaves the files it produces. Paths are given
+// exactly as the user wrote them (for instance "out/main_js/main.wat").
+pub trait OutputDirectory {
+    fn create_file(&mut self, path: &str) -> io::Result<Box<dyn OutputSink + '_>>;
+    // Leaves an empty folder in the given path, removing its previous contents
+    fn create_folder(&mut self, path: &str) -> io::Result<()>;
+
+    // Creates the file with the given contents
+    fn write_file(&mut self, path: &str, contents: &[u8]) -> OutputResult<()> {
+        let error = |err| OutputError::new(path, err);
+        let mut writer = self.create_file(path).map_err(error)?;
+        writer.write_all(contents).map_err(error)?;
+        writer.flush().map_err(error)
+    }
+}
+
+pub struct FileSystem;
+
+impl OutputDirectory for FileSystem {
+    fn create_file(&mut self, path: &str) -> io::Result<Box<dyn OutputSink + '_>> {
+        let file = File::create(path)?;
+        Result::Ok(Box::new(BufWriter::new(file)))
+    }
+
+    fn create_folder(&mut self, path: &str) -> io::Result<()> {
+        if Path::new(path).is_dir() {
+            std::fs::remove_dir_all(path)?;
+        }
+        std::fs::create_dir(path)
+    }
+}
+
+// Keeps every produced file in memory, indexed by its path
+#[derive(Default)]
+pub struct MemoryDirectory {
+    files: BTreeMap<String, Vec<u8>>,
+}
+
+impl MemoryDirectory {
+    pub fn new() -> MemoryDirectory {
+        MemoryDirectory::default()
+    }
+
+    pub fn get_file(&self, path: &str) -> Option<&[u8]> {
+        self.files.get(path).map(|contents| contents.as_slice())
+    }
+
+    pub fn get_files(&self) -> &BTreeMap<String, Vec<u8>> {
+        &self.files
+    }
+
+    pub fn remove_file(&mut self, path: &str) -> Option<Vec<u8>> {
+        self.files.remove(path)
+    }
+
+    pub fn into_files(self) -> BTreeMap<String, Vec<u8>> {
+        self.files
+    }
+}
+
+impl OutputDirectory for MemoryDirectory {
+    fn create_file(&mut self, path: &str) -> io::Result<Box<dyn OutputSink + '_>> {
+        let contents = self.files.entry(path.to_string()).or_default();
+        contents.clear();
+        Result::Ok(Box::new(contents))
+    }
+
+    fn create_folder(&mut self, path: &str) -> io::Result<()> {
+        let prefix = format!("{}/", path.trim_end_matches('/'));
+        self.files.retain(|file, _| !file.starts_with(&prefix));
+        Result::Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn memory_directory_append_and_rewrite() {
+        let mut directory = MemoryDirectory::new();
+        {
+            let mut writer = directory.create_file("out/a.bin").unwrap();
+            writer.write_all(&[1, 2, 3]).unwrap();
+            writer.write_all(&[4]).unwrap();
+        }
+        assert_eq!(directory.get_file("out/a.bin"), Some(&[1, 2, 3, 4][..]));
+        directory.create_file("out/a.bin").unwrap().write_all(&[5]).unwrap();
+        assert_eq!(directory.get_file("out/a.bin"), Some(&[5][..]));
+    }
+
+    #[test]
+    fn memory_directory_create_folder_clears_contents() {
+        let mut directory = MemoryDirectory::new();
+        directory.create_file("out/a.bin").unwrap();
+        directory.create_file("out_js/b.bin").unwrap();
+        directory.create_folder("out").unwrap();
+        assert!(directory.get_file("out/a.bin").is_none());
+        assert!(directory.get_file("out_js/b.bin").is_some());
+    }
+}
@@ -0,0 +1,45 @@
+use super::error_code::ReportCode;
+use super::error_definition::Report;
+use std::fmt;
+use std::io;
+
+// Error produced when one of the output files of the compiler can not be
+// written. The part indicates where the writing failed (for instance the
+// section of the r1cs file or the generated file of a folder).
+#[derive(Debug)]
+pub struct OutputError {
+    pub file: String,
+    pub part: Option<String>,
+    pub cause: io::Error,
+}
+
+pub type OutputResult<T> = Result<T, OutputError>;
+
+impl OutputError {
+    pub fn new(file: &str, cause: io::Error) -> OutputError {
+        OutputError { file: file.to_string(), part: None, cause }
+    }
+
+    pub fn in_part(mut self, part: &str) -> OutputError {
+        self.part = Some(part.to_string());
+        self
+    }
+
+    pub fn into_report(self) -> Report {
+        let mut report = Report::error(format!("Could not write {}", self.file), ReportCode::FileOs);
+        if let Some(part) = &self.part {
+            report.add_note(format!("The error happened while writing the {}", part));
+        }
+        report.add_note(format!("Cause: {}", self.cause));
+        report
+    }
+}
+
+impl fmt::Display for OutputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.part {
+            Some(part) => write!(f, "could not write the {} of {}: {}", part, self.file, self.cause),
+            None => write!(f, "could not write {}: {}", self.file, self.cause),
+        }
+    }
+}
@@ -1,7 +1,7 @@
 use super::ast::{Definition, Expression, MainComponent};
 use super::file_definition::{FileID, FileLibrary};
 use super::function_data::{FunctionData, FunctionInfo};
-use super::program_merger::Merger;
+use super::program_merger::{Merger, Namespace};
 use super::template_data::{TemplateData, TemplateInfo};
 use crate::abstract_syntax_tree::ast::FillMeta;
 use std::collections::HashSet;
@@ -28,13 +28,19 @@ impl ProgramArchive {
         file_id_main: FileID,
         main_component: MainComponent,
         program_contents: Contents,
+        namespaces: Vec<Namespace>,
         custom_gates: bool,
     ) -> Result<ProgramArchive, (FileLibrary, Vec<Report>)> {
         let mut merger = Merger::new();
         let mut reports = vec![];
-        for (file_id, definitions) in program_contents {
-            if let Err(mut errs) = merger.add_definitions(file_id, definitions) {
-                reports.append(&mut errs);
+        for namespace in &namespaces {
+            for (file_id, definitions) in &program_contents {
+                if !namespace.files.contains(file_id) {
+                    continue;
+                }
+                if let Err(mut errs) = merger.add_definitions(*file_id, definitions.clone(), namespace) {
+                    reports.append(&mut errs);
+                }
             }
         }
         let (mut fresh_id, functions, templates) = merger.decompose();
@@ -1,9 +1,29 @@
-use super::ast::Definition;
+use super::ast::{Access, Definition, Expression, LogArgument, Meta, Statement};
 use super::error_code::ReportCode;
 use super::error_definition::Report;
 use super::file_definition::FileID;
 use super::function_data::{FunctionData, FunctionInfo};
 use super::template_data::{TemplateData, TemplateInfo};
+use std::collections::HashSet;
+
+// Set of files whose definitions share the same prefix. The namespace of
+// the main file has no alias and its definitions keep their names; the rest
+// are introduced by an `include "..." as alias;` and their definitions are
+// renamed to `alias.name`.
+pub struct Namespace {
+    pub alias: Option<String>,
+    pub files: Vec<FileID>,
+    pub visible_aliases: HashSet<String>,
+}
+
+impl Namespace {
+    pub fn qualify(&self, name: &str) -> String {
+        match &self.alias {
+            Some(alias) => format!("{}.{}", alias, name),
+            None => name.to_string(),
+        }
+    }
+}
 
 pub struct Merger {
     fresh_id: usize,
@@ -25,11 +45,13 @@ impl Merger {
         Merger::default()
     }
 
-    pub fn add_definitions(&mut self, file_id: FileID, definitions: Vec<Definition>)  -> Result<(), Vec<Report>> {
+    pub fn add_definitions(&mut self, file_id: FileID, definitions: Vec<Definition>, namespace: &Namespace)  -> Result<(), Vec<Report>> {
         let mut reports = vec![];
         for definition in definitions {
             let (name, meta) = match definition {
-                Definition::Template { name, args, arg_location, body, meta, parallel, is_custom_gate } => {
+                Definition::Template { name, args, arg_location, mut body, meta, parallel, is_custom_gate } => {
+                    let name = namespace.qualify(&name);
+                    qualify_statement(&mut body, file_id, namespace, &mut reports);
                     if self.contains_function(&name) || self.contains_template(&name) {
                         (Option::Some(name), meta)
                     } else {
@@ -48,7 +70,9 @@ impl Merger {
                         (Option::None, meta)
                     }
                 }
-                Definition::Function { name, body, args, arg_location, meta } => {
+                Definition::Function { name, mut body, args, arg_location, meta } => {
+                    let name = namespace.qualify(&name);
+                    qualify_statement(&mut body, file_id, namespace, &mut reports);
                     if self.contains_function(&name) || self.contains_template(&name) {
                         (Option::Some(name), meta)
                     } else {
@@ -106,3 +130,124 @@ impl Merger {
         (self.fresh_id, self.function_info, self.template_info)
     }
 }
+
+// Calls without an alias refer to definitions of the same namespace, calls
+// with an alias must use one of the aliases declared in the namespace.
+fn qualify_callee(id: &mut String, meta: &Meta, file_id: FileID, namespace: &Namespace, reports: &mut Vec<Report>) {
+    match id.split_once('.') {
+        Some((alias, _)) => {
+            if !namespace.visible_aliases.contains(alias) {
+                let mut report = Report::error(
+                    format!("Unknown namespace {}", alias),
+                    ReportCode::UnknownNamespace,
+                );
+                report.add_primary(
+                    meta.file_location(),
+                    file_id,
+                    format!("{} is not an alias of an include of this file", alias),
+                );
+                reports.push(report);
+            }
+        }
+        None => *id = namespace.qualify(id),
+    }
+}
+
+fn qualify_statement(stmt: &mut Statement, file_id: FileID, namespace: &Namespace, reports: &mut Vec<Report>) {
+    use Statement::*;
+    match stmt {
+        IfThenElse { cond, if_case, else_case, .. } => {
+            qualify_expression(cond, file_id, namespace, reports);
+            qualify_statement(if_case, file_id, namespace, reports);
+            if let Some(else_case) = else_case {
+                qualify_statement(else_case, file_id, namespace, reports);
+            }
+        }
+        While { cond, stmt, .. } => {
+            qualify_expression(cond, file_id, namespace, reports);
+            qualify_statement(stmt, file_id, namespace, reports);
+        }
+        Return { value, .. } => qualify_expression(value, file_id, namespace, reports),
+        InitializationBlock { initializations, .. } => {
+            for init in initializations {
+                qualify_statement(init, file_id, namespace, reports);
+            }
+        }
+        Declaration { dimensions, .. } => {
+            for dim in dimensions {
+                qualify_expression(dim, file_id, namespace, reports);
+            }
+        }
+        Substitution { access, rhe, .. } => {
+            qualify_access(access, file_id, namespace, reports);
+            qualify_expression(rhe, file_id, namespace, reports);
+        }
+        MultSubstitution { lhe, rhe, .. } | ConstraintEquality { lhe, rhe, .. } => {
+            qualify_expression(lhe, file_id, namespace, reports);
+            qualify_expression(rhe, file_id, namespace, reports);
+        }
+        UnderscoreSubstitution { rhe, .. } => qualify_expression(rhe, file_id, namespace, reports),
+        LogCall { args, .. } => {
+            for arg in args {
+                if let LogArgument::LogExp(exp) = arg {
+                    qualify_expression(exp, file_id, namespace, reports);
+                }
+            }
+        }
+        Block { stmts, .. } => {
+            for stmt in stmts {
+                qualify_statement(stmt, file_id, namespace, reports);
+            }
+        }
+        Assert { arg, .. } => qualify_expression(arg, file_id, namespace, reports),
+    }
+}
+
+fn qualify_expression(expr: &mut Expression, file_id: FileID, namespace: &Namespace, reports: &mut Vec<Report>) {
+    use Expression::*;
+    match expr {
+        InfixOp { lhe, rhe, .. } => {
+            qualify_expression(lhe, file_id, namespace, reports);
+            qualify_expression(rhe, file_id, namespace, reports);
+        }
+        PrefixOp { rhe, .. } | ParallelOp { rhe, .. } => {
+            qualify_expression(rhe, file_id, namespace, reports)
+        }
+        InlineSwitchOp { cond, if_true, if_false, .. } => {
+            qualify_expression(cond, file_id, namespace, reports);
+            qualify_expression(if_true, file_id, namespace, reports);
+            qualify_expression(if_false, file_id, namespace, reports);
+        }
+        Variable { access, .. } => qualify_access(access, file_id, namespace, reports),
+        Number(..) => {}
+        Call { meta, id, args } => {
+            qualify_callee(id, meta, file_id, namespace, reports);
+            for arg in args {
+                qualify_expression(arg, file_id, namespace, reports);
+            }
+        }
+        AnonymousComp { meta, id, params, signals, .. } => {
+            qualify_callee(id, meta, file_id, namespace, reports);
+            for arg in params.iter_mut().chain(signals.iter_mut()) {
+                qualify_expression(arg, file_id, namespace, reports);
+            }
+        }
+        ArrayInLine { values, .. } | Tuple { values, .. } => {
+            for value in values {
+                qualify_expression(value, file_id, namespace, reports);
+            }
+        }
+        UniformArray { value, dimension, .. } => {
+            qualify_expression(value, file_id, namespace, reports);
+            qualify_expression(dimension, file_id, namespace, reports);
+        }
+    }
+}
+
+fn qualify_access(access: &mut [Access], file_id: FileID, namespace: &Namespace, reports: &mut Vec<Report>) {
+    for acc in access {
+        if let Access::ArrayAccess(index) = acc {
+            qualify_expression(index, file_id, namespace, reports);
+        }
+    }
+}
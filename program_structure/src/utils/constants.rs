@@ -1,4 +1,5 @@
 use num_bigint::BigInt;
+use num_traits::Zero;
 
 const P_BN128: &str =
     "21888242871839275222246405745257275088548364400416034343698204186575808495617";
@@ -11,6 +12,78 @@ const P_PALLAS: &str = "28948022309329048855892746252171976963363056481941560715
 const P_VESTA : &str = "28948022309329048855892746252171976963363056481941647379679742748393362948097";
 //const P_STR: &str = "21888242871839275222246405745257275088548364400416034343698204186575808495617";
 
+const PRIME_NAMES: [&str; 6] = ["bn128", "bls12381", "goldilocks", "grumpkin", "pallas", "vesta"];
+
+pub fn is_named_prime(possible_prime: &str) -> bool {
+    PRIME_NAMES.contains(&possible_prime)
+}
+
+pub fn is_supported_prime(possible_prime: &str) -> bool {
+    parse_prime(possible_prime).is_ok()
+}
+
+// The modulus of a prime given by name, or written in decimal or in
+// hexadecimal with the 0x prefix. The field code keeps 32 bit integers
+// as shorts, so the prime must be above 2^32.
+pub fn parse_prime(possible_prime: &str) -> Result<BigInt, String> {
+    let named = match possible_prime {
+        "bn128" => Some(P_BN128),
+        "bls12381" => Some(P_BLS12381),
+        "goldilocks" => Some(P_GOLDILOCKS),
+        "grumpkin" => Some(P_GRUMPKIN),
+        "pallas" => Some(P_PALLAS),
+        "vesta" => Some(P_VESTA),
+        _ => None,
+    };
+    if let Some(decimal) = named {
+        return Ok(BigInt::parse_bytes(decimal.as_bytes(), 10).expect("can not parse p"));
+    }
+    let parsed = match possible_prime.strip_prefix("0x").or_else(|| possible_prime.strip_prefix("0X")) {
+        Some(hexadecimal) => BigInt::parse_bytes(hexadecimal.as_bytes(), 16),
+        None if possible_prime.bytes().all(|c| c.is_ascii_digit()) => BigInt::parse_bytes(possible_prime.as_bytes(), 10),
+        None => None,
+    };
+    let p = parsed.ok_or_else(|| format!("{} is neither the name of a curve nor a number", possible_prime))?;
+    if p.bits() <= 32 {
+        Err(format!("the prime {} must be greater than 2^32", possible_prime))
+    } else if !is_probably_prime(&p) {
+        Err(format!("{} is not a prime number", possible_prime))
+    } else {
+        Ok(p)
+    }
+}
+
+// Miller-Rabin test over the first prime bases, which is exact below 2^81
+// and fails for a composite with probability below 4^-20 otherwise
+fn is_probably_prime(n: &BigInt) -> bool {
+    const BASES: [u32; 20] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71];
+    let one = BigInt::from(1);
+    let n_minus_one = n - &one;
+    let mut d = n_minus_one.clone();
+    let mut s = 0;
+    while (&d % 2u32).is_zero() {
+        d >>= 1;
+        s += 1;
+    }
+    if s == 0 {
+        return false;
+    }
+    'bases: for base in BASES.iter() {
+        let mut x = BigInt::from(*base).modpow(&d, n);
+        if x == one || x == n_minus_one {
+            continue;
+        }
+        for _ in 1..s {
+            x = (&x * &x) % n;
+            if x == n_minus_one {
+                continue 'bases;
+            }
+        }
+        return false;
+    }
+    true
+}
+
 pub struct UsefulConstants {
     p: BigInt,
 }
@@ -23,18 +96,30 @@ impl Clone for UsefulConstants {
 
 impl UsefulConstants {
     pub fn new(possible_prime: &String) -> UsefulConstants {
-        let prime_to_use = if possible_prime.eq("bn128") {P_BN128} 
-          else if possible_prime.eq("bls12381") { P_BLS12381} 
-          else if possible_prime.eq("goldilocks") { P_GOLDILOCKS} 
-          else if possible_prime.eq("grumpkin") { P_GRUMPKIN} 
-          else if possible_prime.eq("pallas") { P_PALLAS} 
-          else if possible_prime.eq("vesta") { P_VESTA} 
-          else {unreachable!()};
-
-        UsefulConstants { p: BigInt::parse_bytes(prime_to_use.as_bytes(), 10).expect("can not parse p") }
+        UsefulConstants { p: parse_prime(possible_prime).expect("can not parse p") }
     }
     
     pub fn get_p(&self) -> &BigInt {
         &self.p
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn primes_given_by_value() {
+        let bn128 = parse_prime("bn128").unwrap();
+        assert_eq!(parse_prime(P_BN128), Ok(bn128.clone()));
+        assert_eq!(parse_prime("0x30644e72e131a029b85045b68181585d2833e84879b9709143e1f593f0000001"), Ok(bn128));
+        assert!(parse_prime("18446744069414584321").is_ok());
+        assert!(parse_prime("6309603491").is_ok());
+        // 2^64 + 1 = 274177 * 67280421310721
+        assert!(parse_prime("18446744073709551617").is_err());
+        assert!(parse_prime("4294967291").is_err());
+        assert!(parse_prime("0x").is_err());
+        assert!(parse_prime("-7").is_err());
+        assert!(parse_prime("bn254").is_err());
+    }
+}
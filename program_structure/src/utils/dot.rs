@@ -0,0 +1,4 @@
+// Escapes a text to be used as a quoted label of a graph in DOT format
+pub fn escape_dot(label: &str) -> String {
+    label.replace('\\', "\\\\").replace('"', "\\\"")
+}
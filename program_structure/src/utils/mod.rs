@@ -1,3 +1,4 @@
 pub mod constants;
+pub mod dot;
 pub mod environment;
 pub mod memory_slice;
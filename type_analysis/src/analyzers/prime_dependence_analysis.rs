@@ -0,0 +1,244 @@
+use num_bigint::BigInt;
+use num_traits::Zero;
+use program_structure::ast::{
+    Access, Expression, ExpressionInfixOpcode, ExpressionPrefixOpcode, LogArgument, Meta,
+    Statement,
+};
+use program_structure::constants::parse_prime;
+use program_structure::error_code::ReportCode;
+use program_structure::error_definition::{Report, ReportCollection};
+use program_structure::file_definition::{self, FileID};
+use program_structure::program_archive::ProgramArchive;
+
+// Looks for the constructs of the program whose behaviour is not the same
+// for all the given primes: constants that are not elements of some of the
+// fields, and comparisons with a constant that is positive for some of the
+// primes and negative for others (that is, above p/2 for some of them).
+pub fn prime_dependence_analysis(program_archive: &ProgramArchive, primes: &[String]) -> ReportCollection {
+    let primes: Vec<(&str, BigInt)> = primes
+        .iter()
+        .map(|prime| (prime.as_str(), parse_prime(prime).expect("can not parse p")))
+        .collect();
+    let mut reports = ReportCollection::new();
+    for template in program_archive.get_templates().values() {
+        let file_id = template.get_file_id();
+        visit_statement(template.get_body(), file_id, &primes, &mut reports);
+    }
+    for function in program_archive.get_functions().values() {
+        let file_id = function.get_file_id();
+        visit_statement(function.get_body(), file_id, &primes, &mut reports);
+    }
+    visit_expression(
+        program_archive.get_main_expression(),
+        *program_archive.get_file_id_main(),
+        &primes,
+        &mut reports,
+    );
+    reports
+}
+
+fn visit_statement(stmt: &Statement, file_id: FileID, primes: &[(&str, BigInt)], reports: &mut ReportCollection) {
+    use Statement::*;
+    match stmt {
+        IfThenElse { cond, if_case, else_case, .. } => {
+            visit_expression(cond, file_id, primes, reports);
+            visit_statement(if_case, file_id, primes, reports);
+            if let Option::Some(else_block) = else_case {
+                visit_statement(else_block, file_id, primes, reports);
+            }
+        }
+        While { cond, stmt, .. } => {
+            visit_expression(cond, file_id, primes, reports);
+            visit_statement(stmt, file_id, primes, reports);
+        }
+        Return { value, .. } => visit_expression(value, file_id, primes, reports),
+        InitializationBlock { initializations, .. } => {
+            for init in initializations {
+                visit_statement(init, file_id, primes, reports);
+            }
+        }
+        Declaration { dimensions, .. } => {
+            for dimension in dimensions {
+                visit_expression(dimension, file_id, primes, reports);
+            }
+        }
+        Substitution { access, rhe, .. } => {
+            visit_accesses(access, file_id, primes, reports);
+            visit_expression(rhe, file_id, primes, reports);
+        }
+        MultSubstitution { lhe, rhe, .. } => {
+            visit_expression(lhe, file_id, primes, reports);
+            visit_expression(rhe, file_id, primes, reports);
+        }
+        UnderscoreSubstitution { rhe, .. } => visit_expression(rhe, file_id, primes, reports),
+        ConstraintEquality { lhe, rhe, .. } => {
+            visit_expression(lhe, file_id, primes, reports);
+            visit_expression(rhe, file_id, primes, reports);
+        }
+        LogCall { args, .. } => {
+            for arg in args {
+                if let LogArgument::LogExp(arg) = arg {
+                    visit_expression(arg, file_id, primes, reports);
+                }
+            }
+        }
+        Block { stmts, .. } => {
+            for stmt in stmts {
+                visit_statement(stmt, file_id, primes, reports);
+            }
+        }
+        Assert { arg, .. } => visit_expression(arg, file_id, primes, reports),
+    }
+}
+
+fn visit_accesses(access: &[Access], file_id: FileID, primes: &[(&str, BigInt)], reports: &mut ReportCollection) {
+    for acc in access {
+        if let Access::ArrayAccess(index) = acc {
+            visit_expression(index, file_id, primes, reports);
+        }
+    }
+}
+
+fn visit_expression(expr: &Expression, file_id: FileID, primes: &[(&str, BigInt)], reports: &mut ReportCollection) {
+    use Expression::*;
+    match expr {
+        InfixOp { meta, lhe, infix_op, rhe } => {
+            if is_comparison(*infix_op) {
+                for operand in [lhe, rhe] {
+                    if let Some(value) = constant_value(operand) {
+                        check_sign(meta, &value, file_id, primes, reports);
+                    }
+                }
+            }
+            visit_expression(lhe, file_id, primes, reports);
+            visit_expression(rhe, file_id, primes, reports);
+        }
+        PrefixOp { rhe, .. } => visit_expression(rhe, file_id, primes, reports),
+        InlineSwitchOp { cond, if_true, if_false, .. } => {
+            visit_expression(cond, file_id, primes, reports);
+            visit_expression(if_true, file_id, primes, reports);
+            visit_expression(if_false, file_id, primes, reports);
+        }
+        ParallelOp { rhe, .. } => visit_expression(rhe, file_id, primes, reports),
+        Variable { access, .. } => visit_accesses(access, file_id, primes, reports),
+        Number(meta, value) => check_overflow(meta, value, file_id, primes, reports),
+        Call { args, .. } => {
+            for arg in args {
+                visit_expression(arg, file_id, primes, reports);
+            }
+        }
+        AnonymousComp { params, signals, .. } => {
+            for arg in params.iter().chain(signals.iter()) {
+                visit_expression(arg, file_id, primes, reports);
+            }
+        }
+        ArrayInLine { values, .. } | Tuple { values, .. } => {
+            for value in values {
+                visit_expression(value, file_id, primes, reports);
+            }
+        }
+        UniformArray { value, dimension, .. } => {
+            visit_expression(value, file_id, primes, reports);
+            visit_expression(dimension, file_id, primes, reports);
+        }
+    }
+}
+
+fn is_comparison(op: ExpressionInfixOpcode) -> bool {
+    use ExpressionInfixOpcode::*;
+    matches!(op, Lesser | LesserEq | Greater | GreaterEq)
+}
+
+// Constants written as a number, possibly negated
+fn constant_value(expr: &Expression) -> Option<BigInt> {
+    match expr {
+        Expression::Number(_, value) => Some(value.clone()),
+        Expression::PrefixOp { prefix_op: ExpressionPrefixOpcode::Sub, rhe, .. } => {
+            constant_value(rhe).map(|value| -value)
+        }
+        _ => None,
+    }
+}
+
+fn check_overflow(meta: &Meta, value: &BigInt, file_id: FileID, primes: &[(&str, BigInt)], reports: &mut ReportCollection) {
+    let overflowed: Vec<&str> =
+        primes.iter().filter(|(_, p)| value >= p).map(|(name, _)| *name).collect();
+    if overflowed.is_empty() {
+        return;
+    }
+    let mut report = Report::warning(
+        format!("The constant {} is not an element of the field of {}", value, overflowed.join(", ")),
+        ReportCode::PrimeDependentConstant,
+    );
+    report.add_primary(
+        file_definition::generate_file_location(meta.get_start(), meta.get_end()),
+        file_id,
+        "This constant is reduced modulo the prime".to_string(),
+    );
+    reports.push(report);
+}
+
+fn check_sign(meta: &Meta, value: &BigInt, file_id: FileID, primes: &[(&str, BigInt)], reports: &mut ReportCollection) {
+    // Comparisons take the values above p/2 as negative
+    let is_negative = |p: &BigInt| {
+        let mut reduced = value % p;
+        if reduced < BigInt::zero() {
+            reduced += p;
+        }
+        reduced > p / 2
+    };
+    let negative: Vec<&str> =
+        primes.iter().filter(|(_, p)| is_negative(p)).map(|(name, _)| *name).collect();
+    if negative.is_empty() || negative.len() == primes.len() {
+        return;
+    }
+    let mut report = Report::warning(
+        format!("The comparison with {} depends on the prime", value),
+        ReportCode::PrimeDependentComparison,
+    );
+    report.add_primary(
+        file_definition::generate_file_location(meta.get_start(), meta.get_end()),
+        file_id,
+        format!("The constant is negative in the field of {}", negative.join(", ")),
+    );
+    reports.push(report);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num_traits::One;
+
+    fn primes() -> Vec<(&'static str, BigInt)> {
+        ["goldilocks", "bn128"]
+            .iter()
+            .map(|prime| (*prime, parse_prime(prime).unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn constants_above_the_smaller_prime_are_reported() {
+        let primes = primes();
+        let mut reports = ReportCollection::new();
+        let small = Expression::Number(Meta::new(0, 1), BigInt::from(7));
+        visit_expression(&small, 0, &primes, &mut reports);
+        assert!(reports.is_empty());
+        let big = Expression::Number(Meta::new(0, 1), BigInt::one() << 100);
+        visit_expression(&big, 0, &primes, &mut reports);
+        assert_eq!(reports.len(), 1);
+    }
+
+    #[test]
+    fn comparisons_between_the_halves_are_reported() {
+        let primes = primes();
+        let compare = |value: BigInt| {
+            let mut reports = ReportCollection::new();
+            check_sign(&Meta::new(0, 1), &value, 0, &primes, &mut reports);
+            reports.len()
+        };
+        assert_eq!(compare(BigInt::from(-1)), 0);
+        assert_eq!(compare(BigInt::from(1000)), 0);
+        assert_eq!(compare(BigInt::one() << 63), 1);
+        assert_eq!(compare(-(BigInt::one() << 63)), 1);
+    }
+}
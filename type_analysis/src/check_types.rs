@@ -154,3 +154,9 @@ fn template_semantic_analyses(
         warnings.append(&mut template_substitution_analysis(template_data));
     }
 }
+
+// Warnings about the constructs that behave differently for each of the
+// primes the program is compiled for
+pub fn check_prime_dependence(program_archive: &ProgramArchive, primes: &[String]) -> ReportCollection {
+    prime_dependence_analysis(program_archive, primes)
+}